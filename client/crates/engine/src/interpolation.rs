@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use crate::FrameInterpolation;
+
+/// Easing applied to [`FrameInterpolation`] before blending transforms.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InterpolationEasing {
+    #[default]
+    Linear,
+    Smoothstep,
+}
+
+impl InterpolationEasing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            InterpolationEasing::Linear => t,
+            InterpolationEasing::Smoothstep => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Tags an entity for render interpolation and holds its last two
+/// fixed-step transforms. Simulation code calls [`Interpolated::push`] each
+/// fixed step; the rendered [`Transform`] is blended between the two.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Interpolated {
+    pub previous: Transform,
+    pub current: Transform,
+}
+
+impl Interpolated {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+        }
+    }
+
+    /// Record the transform produced by the latest fixed step.
+    pub fn push(&mut self, next: Transform) {
+        self.previous = self.current;
+        self.current = next;
+    }
+
+    pub fn blend(&self, t: f32) -> Transform {
+        Transform {
+            translation: self.previous.translation.lerp(self.current.translation, t),
+            rotation: self.previous.rotation.slerp(self.current.rotation, t),
+            scale: self.previous.scale.lerp(self.current.scale, t),
+        }
+    }
+}
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameInterpolation>()
+            .init_resource::<InterpolationEasing>()
+            .add_systems(
+                PostUpdate,
+                apply_frame_interpolation.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Writes the eased blend of each [`Interpolated`] entity into its [`Transform`].
+pub fn apply_frame_interpolation(
+    interpolation: Res<FrameInterpolation>,
+    easing: Res<InterpolationEasing>,
+    mut query: Query<(&Interpolated, &mut Transform)>,
+) {
+    let t = easing.apply(interpolation.0);
+    for (steps, mut transform) in &mut query {
+        *transform = steps.blend(t);
+    }
+}
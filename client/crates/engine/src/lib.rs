@@ -11,16 +11,19 @@ use gloo_timers::future::TimeoutFuture;
 #[cfg(not(target_arch = "wasm32"))]
 use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use platform_api::{
-    AppState, CapabilityFlags, GameModule, ModuleContext, ModuleMetadata, discover_local_modules,
+    AppState, CapabilityFlags, GameModule, ModuleContext, ModuleMetadata, disabled_capabilities,
+    discover_local_modules,
 };
 #[cfg(target_arch = "wasm32")]
 use platform_api::ModuleManifest;
 #[cfg(not(target_arch = "wasm32"))]
-use std::path::Path;
+use platform_api::modules_dir;
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::mpsc;
 #[cfg(not(target_arch = "wasm32"))]
 use std::sync::mpsc::Receiver;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
 use thiserror::Error;
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::spawn_local;
@@ -28,6 +31,7 @@ use wasm_bindgen_futures::spawn_local;
 pub mod core;
 #[cfg(feature = "flight")]
 pub mod flight;
+pub mod interpolation;
 pub mod motion;
 pub mod net;
 #[cfg(feature = "vehicle")]
@@ -37,6 +41,7 @@ use crate::net::NetClientPlugin;
 use core::CorePlugin;
 #[cfg(feature = "flight")]
 use flight::FlightPlugin;
+use interpolation::InterpolationPlugin;
 use motion::{Controller, MotionPlugin, Player, PlayerCamera};
 use netcode::NetPlugin as NetworkPlugin;
 #[cfg(feature = "vehicle")]
@@ -55,6 +60,31 @@ const LOBBY_KEYS: [KeyCode; 9] = [
     KeyCode::Key9,
 ];
 
+/// Number of modules the number keys address at once.
+pub const LOBBY_PAGE_SIZE: usize = LOBBY_KEYS.len();
+
+/// Which block of [`LOBBY_PAGE_SIZE`] modules the number keys select.
+/// Tab or PageDown moves to the next page and PageUp to the previous one.
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LobbyPage(pub usize);
+
+impl LobbyPage {
+    /// Number of pages needed for `modules` modules; always at least one.
+    pub fn count(modules: usize) -> usize {
+        modules.div_ceil(LOBBY_PAGE_SIZE).max(1)
+    }
+
+    /// Index of the module selected by the `key`-th number key (0-based).
+    pub fn module_for_key(self, key: usize) -> usize {
+        self.0 * LOBBY_PAGE_SIZE + key
+    }
+
+    /// Digit shown on the pad of `module`, if it's on this page.
+    pub fn hotkey_for(self, module: usize) -> Option<usize> {
+        (module / LOBBY_PAGE_SIZE == self.0).then_some(module % LOBBY_PAGE_SIZE + 1)
+    }
+}
+
 /// Stores metadata for all registered game modules.
 #[derive(Resource, Default)]
 pub struct ModuleRegistry {
@@ -62,6 +92,43 @@ pub struct ModuleRegistry {
     pub modules: Vec<ModuleMetadata>,
 }
 
+/// Modules that appeared, disappeared or changed version when the registry
+/// was rebuilt, keyed by module id.
+#[derive(Event, Clone, Default)]
+pub struct ModulesChanged {
+    pub added: Vec<ModuleMetadata>,
+    pub removed: Vec<ModuleMetadata>,
+    /// New metadata for modules whose version changed.
+    pub changed: Vec<ModuleMetadata>,
+}
+
+impl ModulesChanged {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two registry snapshots by module id.
+pub fn diff_modules(old: &[ModuleMetadata], new: &[ModuleMetadata]) -> ModulesChanged {
+    let find = |list: &[ModuleMetadata], id: &str| list.iter().find(|m| m.id == id).cloned();
+    let mut diff = ModulesChanged::default();
+    for module in new {
+        match find(old, &module.id) {
+            None => diff.added.push(module.clone()),
+            Some(prev) if prev.version != module.version || prev.state != module.state => {
+                diff.changed.push(module.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|m| find(new, &m.id).is_none())
+        .cloned()
+        .collect();
+    diff
+}
+
 /// Stores the interpolation factor between fixed simulation steps for smooth rendering.
 #[derive(Resource, Default)]
 pub struct FrameInterpolation(pub f32);
@@ -72,6 +139,11 @@ enum EngineError {
     ModuleEnter(AnyError),
     #[error("module exit failed: {0}")]
     ModuleExit(AnyError),
+    #[error("module {module} unavailable, missing capabilities: {missing:?}")]
+    CapabilityUnavailable {
+        module: &'static str,
+        missing: CapabilityFlags,
+    },
     #[cfg(not(target_arch = "wasm32"))]
     #[error("watcher error: {0}")]
     Watcher(#[from] notify::Error),
@@ -84,13 +156,17 @@ impl Plugin for EnginePlugin {
         app.add_plugins(NetworkPlugin)
             .add_plugins(CorePlugin)
             .add_plugins(MotionPlugin)
+            .add_plugins(InterpolationPlugin)
             .init_resource::<ModuleRegistry>()
             .init_resource::<FrameInterpolation>()
+            .init_resource::<LobbyPage>()
+            .add_event::<ModulesChanged>()
             .add_state::<AppState>()
             .add_systems(Startup, discover_modules)
             .add_systems(OnEnter(AppState::Lobby), setup_lobby)
             .add_systems(OnExit(AppState::Lobby), cleanup_lobby)
             .add_systems(Update, lobby_keyboard.run_if(in_state(AppState::Lobby)))
+            .add_systems(Update, lobby_paging.run_if(in_state(AppState::Lobby)))
             .add_systems(FixedUpdate, pad_trigger.run_if(in_state(AppState::Lobby)))
             .add_systems(Update, doc_button_system.run_if(in_state(AppState::Lobby)))
             .add_systems(Update, exit_to_lobby)
@@ -104,7 +180,9 @@ impl Plugin for EnginePlugin {
         app.add_systems(
             Update,
             update_lobby_pads
-                .run_if(resource_changed::<ModuleRegistry>())
+                .run_if(
+                    resource_changed::<ModuleRegistry>().or_else(resource_changed::<LobbyPage>()),
+                )
                 .run_if(in_state(AppState::Lobby)),
         );
 
@@ -138,6 +216,10 @@ pub struct DocButton {
 #[derive(Component)]
 pub struct NoModulesSign;
 
+/// Quad above a lobby pad showing the module's icon.
+#[derive(Component)]
+pub struct ModuleIcon;
+
 #[derive(Component)]
 pub struct LeaderboardScreen;
 
@@ -161,6 +243,7 @@ pub fn setup_lobby(
     mut materials: ResMut<Assets<StandardMaterial>>,
     registry: Res<ModuleRegistry>,
     asset_server: Option<Res<AssetServer>>,
+    page: Option<Res<LobbyPage>>,
     mut windows: Query<&mut Window>,
 ) {
     if let Ok(mut window) = windows.get_single_mut() {
@@ -170,6 +253,7 @@ pub fn setup_lobby(
         warn!("no window available");
         return;
     }
+    let page = page.map_or_else(LobbyPage::default, |p| *p);
 
     commands
         .spawn((
@@ -359,10 +443,13 @@ pub fn setup_lobby(
             LobbyEntity,
         ));
     } else {
+        let icon_mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(0.8))));
         for (i, info) in registry.modules.iter().enumerate() {
             if !info.capabilities.contains(CapabilityFlags::LOBBY_PAD) {
                 continue;
             }
+            let icon = pad_icon_material(info, asset_server.as_deref(), &mut materials);
+            let label = pad_label(info, page.hotkey_for(i));
             commands
                 .spawn((
                     PbrBundle {
@@ -380,22 +467,17 @@ pub fn setup_lobby(
                     LobbyEntity,
                 ))
                 .with_children(|parent| {
-                    let label = if LOBBY_KEYS.get(i).is_some() {
-                        format!(
-                            "[{}] {} v{}\nPlayers: {}\nPing: 0ms",
-                            i + 1,
-                            info.name,
-                            info.version,
-                            info.max_players
-                        )
-                    } else {
-                        format!(
-                            "{} v{}\nPlayers: {}\nPing: 0ms",
-                            info.name,
-                            info.version,
-                            info.max_players
-                        )
-                    };
+                    if let Some(material) = icon {
+                        parent.spawn((
+                            PbrBundle {
+                                mesh: icon_mesh.clone(),
+                                material,
+                                transform: Transform::from_xyz(0.0, 1.5, 0.0),
+                                ..default()
+                            },
+                            ModuleIcon,
+                        ));
+                    }
                     parent.spawn(Text2dBundle {
                         text: Text::from_section(
                             label,
@@ -413,6 +495,32 @@ pub fn setup_lobby(
     }
 }
 
+/// The module's icon, loaded through the asset server from the manifest's
+/// `icon_path`. Falls back to [`ModuleMetadata::icon`] without a path or server.
+pub fn module_icon(info: &ModuleMetadata, asset_server: Option<&AssetServer>) -> Handle<Image> {
+    match (&info.icon_path, asset_server) {
+        (Some(path), Some(server)) => server.load(path.clone()),
+        _ => info.icon.clone(),
+    }
+}
+
+/// Material for a pad's icon quad, or `None` if the module has no icon.
+fn pad_icon_material(
+    info: &ModuleMetadata,
+    asset_server: Option<&AssetServer>,
+    materials: &mut Assets<StandardMaterial>,
+) -> Option<Handle<StandardMaterial>> {
+    let icon = module_icon(info, asset_server);
+    (icon != Handle::default()).then(|| {
+        materials.add(StandardMaterial {
+            base_color_texture: Some(icon),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        })
+    })
+}
+
 fn cleanup_lobby(mut commands: Commands, q: Query<Entity, With<LobbyEntity>>) {
     for e in &q {
         commands.entity(e).despawn_recursive();
@@ -422,17 +530,53 @@ fn cleanup_lobby(mut commands: Commands, q: Query<Entity, With<LobbyEntity>>) {
 pub fn lobby_keyboard(
     keys: Res<Input<KeyCode>>,
     registry: Res<ModuleRegistry>,
+    page: Option<Res<LobbyPage>>,
     mut next_state: ResMut<NextState<AppState>>,
 ) {
-    for (i, info) in registry.modules.iter().enumerate() {
-        if let Some(&key) = LOBBY_KEYS.get(i) {
-            if keys.just_pressed(key) {
-                next_state.set(info.state.clone());
-            }
+    let page = page.map_or_else(LobbyPage::default, |p| *p);
+    for (i, &key) in LOBBY_KEYS.iter().enumerate() {
+        if !keys.just_pressed(key) {
+            continue;
+        }
+        if let Some(info) = registry.modules.get(page.module_for_key(i)) {
+            next_state.set(info.state.clone());
         }
     }
 }
 
+/// Cycle the [`LobbyPage`] with Tab/PageDown and PageUp, keeping it in range
+/// as modules come and go.
+pub fn lobby_paging(
+    keys: Res<Input<KeyCode>>,
+    registry: Res<ModuleRegistry>,
+    mut page: ResMut<LobbyPage>,
+) {
+    let pages = LobbyPage::count(registry.modules.len());
+    let current = page.0.min(pages - 1);
+    let next = if keys.any_just_pressed([KeyCode::Tab, KeyCode::PageDown]) {
+        (current + 1) % pages
+    } else if keys.just_pressed(KeyCode::PageUp) {
+        (current + pages - 1) % pages
+    } else {
+        current
+    };
+    // Only write on change so pads aren't rebuilt every frame.
+    if next != page.0 {
+        page.0 = next;
+    }
+}
+
+fn pad_label(info: &ModuleMetadata, hotkey: Option<usize>) -> String {
+    let label = format!(
+        "{} v{}\nPlayers: {}\nPing: 0ms",
+        info.name, info.version, info.max_players
+    );
+    match hotkey {
+        Some(key) => format!("[{key}] {label}"),
+        None => label,
+    }
+}
+
 fn exit_to_lobby(
     keys: Res<Input<KeyCode>>,
     mut next_state: ResMut<NextState<AppState>>,
@@ -536,8 +680,32 @@ pub fn register_module<M: GameModule + Default + 'static>(app: &mut App) {
     app.add_systems(OnExit(state), exit_module::<M>);
 }
 
+/// Capabilities `info` declares that the engine has disabled. An empty result
+/// means the module can be entered; the lobby uses this to flag unavailable
+/// modules.
+pub fn missing_capabilities(world: &World, info: &ModuleMetadata) -> CapabilityFlags {
+    info.capabilities & disabled_capabilities(world)
+}
+
 /// System wrapper that forwards state entry to the module.
+///
+/// Modules whose declared capabilities aren't wired are not entered; the
+/// engine returns to the lobby instead.
 fn enter_module<M: GameModule>(world: &mut World) {
+    let missing = missing_capabilities(world, &M::metadata());
+    if !missing.is_empty() {
+        log::error!(
+            "{}",
+            EngineError::CapabilityUnavailable {
+                module: M::ID,
+                missing,
+            }
+        );
+        world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::Lobby);
+        return;
+    }
     let mut ctx = ModuleContext::new(world);
     if let Err(e) = M::enter(&mut ctx) {
         log::error!("{}", EngineError::ModuleEnter(e));
@@ -546,6 +714,10 @@ fn enter_module<M: GameModule>(world: &mut World) {
 
 /// System wrapper that forwards state exit to the module.
 fn exit_module<M: GameModule>(world: &mut World) {
+    // Modules refused by `enter_module` were never entered.
+    if !missing_capabilities(world, &M::metadata()).is_empty() {
+        return;
+    }
     let mut ctx = ModuleContext::new(world);
     if let Err(e) = M::exit(&mut ctx) {
         log::error!("{}", EngineError::ModuleExit(e));
@@ -579,30 +751,14 @@ pub fn discover_modules(
                 Ok(manifests) => manifests
                     .into_iter()
                     .filter_map(|manifest| {
-                        let state = match manifest.state.as_str() {
-                            "Lobby" => AppState::Lobby,
-                            "DuckHunt" => AppState::DuckHunt,
-                            other => {
-                                log::error!(
-                                    "unknown module state '{}' for module '{}', skipping",
-                                    other,
-                                    manifest.id
-                                );
+                        let state = match manifest.state.parse::<AppState>() {
+                            Ok(state) => state,
+                            Err(e) => {
+                                log::error!("{e}, skipping module '{}'", manifest.id);
                                 return None;
                             }
                         };
-                        let mut caps = CapabilityFlags::default();
-                        for cap in manifest.capabilities {
-                            match cap.as_str() {
-                                "LOBBY_PAD" => caps |= CapabilityFlags::LOBBY_PAD,
-                                "NeedsPhysics" => caps |= CapabilityFlags::NEEDS_PHYSICS,
-                                "UsesHitscan" => caps |= CapabilityFlags::USES_HITSCAN,
-                                "NeedsNav" => caps |= CapabilityFlags::NEEDS_NAV,
-                                "UsesVehicles" => caps |= CapabilityFlags::USES_VEHICLES,
-                                "UsesFlight" => caps |= CapabilityFlags::USES_FLIGHT,
-                                _ => {}
-                            }
-                        }
+                        let caps = CapabilityFlags::from_manifest(&manifest.capabilities);
                         Some(ModuleMetadata {
                             id: manifest.id,
                             name: manifest.name,
@@ -612,6 +768,7 @@ pub fn discover_modules(
                             capabilities: caps,
                             max_players: manifest.max_players,
                             icon: Handle::default(),
+                            icon_path: manifest.icon_path,
                         })
                     })
                     .collect::<Vec<_>>(),
@@ -642,27 +799,117 @@ fn apply_discovered_modules(
     }
 }
 
+/// How long the module directory must stay quiet before modules are
+/// rediscovered. Read when [`hotload_modules`] sets up the watcher.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ModuleWatchDebounce(pub Duration);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ModuleWatchDebounce {
+    fn default() -> Self {
+        Self(Duration::from_millis(250))
+    }
+}
+
+/// Collapses a burst of filesystem events into a single trigger that fires
+/// once no event has arrived for the debounce window.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct Debouncer {
+    window: Duration,
+    last_event: Option<Instant>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_event: None,
+        }
+    }
+
+    /// Record an event observed at `now`.
+    pub fn event(&mut self, now: Instant) {
+        self.last_event = Some(now);
+    }
+
+    /// Whether the pending burst has settled by `now`. Returns `true` once per
+    /// burst.
+    pub fn ready(&mut self, now: Instant) -> bool {
+        match self.last_event {
+            Some(last) if now.duration_since(last) >= self.window => {
+                self.last_event = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Resource)]
 struct ModuleWatcher {
     receiver: std::sync::Mutex<Receiver<notify::Result<notify::Event>>>,
     #[allow(dead_code)]
     watcher: RecommendedWatcher,
+    debouncer: Debouncer,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn process_module_events(watcher: Res<ModuleWatcher>, mut registry: ResMut<ModuleRegistry>) {
-    let mut changed = false;
+fn process_module_events(world: &mut World) {
+    let now = Instant::now();
+    let Some(mut watcher) = world.get_resource_mut::<ModuleWatcher>() else {
+        return;
+    };
+    let mut received = false;
     if let Ok(rx) = watcher.receiver.lock() {
         while let Ok(_event) = rx.try_recv() {
-            changed = true;
+            received = true;
         }
     }
-    if changed {
-        registry.modules = discover_local_modules();
+    if received {
+        watcher.debouncer.event(now);
+    }
+    if watcher.debouncer.ready(now) {
+        update_module_registry(world, discover_local_modules());
     }
 }
 
+/// Replace the registered modules with `modules` and run the lifecycle for
+/// the difference.
+///
+/// If the active module was removed or changed, the engine returns to the
+/// lobby so the module's `exit` hook runs and its systems stop driving the
+/// world; added modules are entered as usual once selected. The diff is also
+/// sent as a [`ModulesChanged`] event.
+pub fn update_module_registry(world: &mut World, modules: Vec<ModuleMetadata>) -> ModulesChanged {
+    let Some(mut registry) = world.get_resource_mut::<ModuleRegistry>() else {
+        return ModulesChanged::default();
+    };
+    let diff = diff_modules(&registry.modules, &modules);
+    registry.modules = modules;
+
+    let active = world
+        .get_resource::<State<AppState>>()
+        .map(|s| s.get().clone());
+    if let Some(active) = active.filter(|s| *s != AppState::Lobby) {
+        let mut replaced = diff.removed.iter().chain(&diff.changed);
+        if replaced.any(|m| m.state == active) {
+            world
+                .resource_mut::<NextState<AppState>>()
+                .set(AppState::Lobby);
+        }
+    }
+    if !diff.is_empty() {
+        if let Some(mut events) = world.get_resource_mut::<Events<ModulesChanged>>() {
+            events.send(diff.clone());
+        }
+    }
+    diff
+}
+
 pub fn hotload_modules(app: &mut App) {
     #[cfg(not(target_arch = "wasm32"))]
     {
@@ -675,14 +922,21 @@ pub fn hotload_modules(app: &mut App) {
                 return;
             }
         };
-        let modules_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../assets/modules");
+        // Watch the same directory `discover_local_modules` reads.
+        let modules_dir = modules_dir();
         if let Err(e) = watcher.watch(&modules_dir, RecursiveMode::Recursive) {
             log::error!("{}", EngineError::Watcher(e));
             return;
         }
+        let debounce = app
+            .world
+            .get_resource::<ModuleWatchDebounce>()
+            .copied()
+            .unwrap_or_default();
         app.insert_resource(ModuleWatcher {
             receiver: std::sync::Mutex::new(rx),
             watcher,
+            debouncer: Debouncer::new(debounce.0),
         });
         app.add_systems(Update, process_module_events);
     }
@@ -720,11 +974,13 @@ pub fn update_lobby_pads(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     asset_server: Option<Res<AssetServer>>,
+    page: Option<Res<LobbyPage>>,
     pads: Query<Entity, Or<(With<LobbyPad>, With<DocPad>, With<NoModulesSign>)>>,
 ) {
     for entity in pads.iter() {
         commands.entity(entity).despawn_recursive();
     }
+    let page = page.map_or_else(LobbyPage::default, |p| *p);
 
     let pad_mesh = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
     let pad_material = materials.add(Color::rgb(0.8, 0.2, 0.2).into());
@@ -785,10 +1041,13 @@ pub fn update_lobby_pads(
         return;
     }
 
+    let icon_mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(0.8))));
     for (i, info) in registry.modules.iter().enumerate() {
         if !info.capabilities.contains(CapabilityFlags::LOBBY_PAD) {
             continue;
         }
+        let icon = pad_icon_material(info, asset_server.as_deref(), &mut materials);
+        let label = pad_label(info, page.hotkey_for(i));
         commands
             .spawn((
                 PbrBundle {
@@ -806,14 +1065,20 @@ pub fn update_lobby_pads(
                 LobbyEntity,
             ))
             .with_children(|parent| {
+                if let Some(material) = icon {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: icon_mesh.clone(),
+                            material,
+                            transform: Transform::from_xyz(0.0, 1.5, 0.0),
+                            ..default()
+                        },
+                        ModuleIcon,
+                    ));
+                }
                 parent.spawn(Text2dBundle {
                     text: Text::from_section(
-                        format!(
-                            "{} v{}\nPlayers: {}\nPing: 0ms",
-                            info.name,
-                            info.version,
-                            info.max_players
-                        ),
+                        label,
                         TextStyle {
                             font: font.clone(),
                             font_size: 20.0,
@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+use engine::{ModuleRegistry, missing_capabilities, register_module};
+use log::Level;
+use logtest::Logger;
+use platform_api::{
+    AppState, CapabilityFlags, GameModule, ModuleContext, ModuleMetadata, PhysicsEnabled,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENTERED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct PhysicsModule;
+
+impl Plugin for PhysicsModule {
+    fn build(&self, _app: &mut App) {}
+}
+
+impl GameModule for PhysicsModule {
+    const ID: &'static str = "physics_module";
+
+    fn metadata() -> ModuleMetadata {
+        ModuleMetadata {
+            id: Self::ID.to_string(),
+            name: "Physics".to_string(),
+            version: "0.1.0".to_string(),
+            author: "Test".to_string(),
+            state: AppState::DuckHunt,
+            capabilities: CapabilityFlags::NEEDS_PHYSICS,
+            max_players: 4,
+            icon: Handle::default(),
+            icon_path: None,
+        }
+    }
+
+    fn enter(_ctx: &mut ModuleContext) -> anyhow::Result<()> {
+        ENTERED.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn module_with_disabled_capability_is_not_entered() {
+    let mut logger = Logger::start();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_state::<AppState>();
+    app.init_resource::<ModuleRegistry>();
+    app.insert_resource(PhysicsEnabled(false));
+
+    register_module::<PhysicsModule>(&mut app);
+    let info = PhysicsModule::metadata();
+    assert_eq!(
+        missing_capabilities(&app.world, &info),
+        CapabilityFlags::NEEDS_PHYSICS
+    );
+
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::DuckHunt);
+    app.update();
+    app.update();
+
+    assert!(!ENTERED.load(Ordering::SeqCst));
+    assert_eq!(
+        *app.world.resource::<State<AppState>>().get(),
+        AppState::Lobby
+    );
+    assert!(logger.any(|r| r.level() == Level::Error && r.args().contains("unavailable")));
+
+    app.insert_resource(PhysicsEnabled(true));
+    assert!(missing_capabilities(&app.world, &info).is_empty());
+}
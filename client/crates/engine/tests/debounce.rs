@@ -0,0 +1,39 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use engine::{Debouncer, ModuleWatchDebounce};
+use std::time::{Duration, Instant};
+
+#[test]
+fn burst_of_events_triggers_once() {
+    let window = Duration::from_millis(250);
+    let mut debouncer = Debouncer::new(window);
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+
+    let mut triggers = 0;
+    for ms in [0, 40, 80, 120] {
+        debouncer.event(at(ms));
+        triggers += debouncer.ready(at(ms)) as u32;
+    }
+    for ms in [200, 300, 369, 370, 400, 1000] {
+        triggers += debouncer.ready(at(ms)) as u32;
+    }
+    assert_eq!(triggers, 1);
+}
+
+#[test]
+fn later_burst_triggers_again() {
+    let mut debouncer = Debouncer::new(Duration::from_millis(10));
+    let start = Instant::now();
+    debouncer.event(start);
+    assert!(debouncer.ready(start + Duration::from_millis(10)));
+    assert!(!debouncer.ready(start + Duration::from_millis(20)));
+
+    debouncer.event(start + Duration::from_millis(30));
+    assert!(debouncer.ready(start + Duration::from_millis(45)));
+}
+
+#[test]
+fn default_window_is_250ms() {
+    assert_eq!(ModuleWatchDebounce::default().0, Duration::from_millis(250));
+}
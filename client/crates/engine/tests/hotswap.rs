@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+use engine::{
+    ModuleRegistry, ModulesChanged, diff_modules, register_module, update_module_registry,
+};
+use platform_api::{AppState, CapabilityFlags, GameModule, ModuleContext, ModuleMetadata};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static EXITS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+struct SwappedModule;
+
+impl Plugin for SwappedModule {
+    fn build(&self, _app: &mut App) {}
+}
+
+impl GameModule for SwappedModule {
+    const ID: &'static str = "swapped";
+
+    fn metadata() -> ModuleMetadata {
+        metadata(Self::ID, "1.0.0")
+    }
+
+    fn exit(_ctx: &mut ModuleContext) -> anyhow::Result<()> {
+        EXITS.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+fn metadata(id: &str, version: &str) -> ModuleMetadata {
+    ModuleMetadata {
+        id: id.to_string(),
+        name: id.to_string(),
+        version: version.to_string(),
+        author: "Test".to_string(),
+        state: AppState::DuckHunt,
+        capabilities: CapabilityFlags::LOBBY_PAD,
+        max_players: 4,
+        icon: Handle::default(),
+        icon_path: None,
+    }
+}
+
+#[test]
+fn diff_reports_added_removed_and_changed() {
+    let old = [metadata("a", "1.0.0"), metadata("b", "1.0.0")];
+    let new = [metadata("b", "1.1.0"), metadata("c", "1.0.0")];
+    let diff = diff_modules(&old, &new);
+    let ids = |list: &[ModuleMetadata]| list.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+    assert_eq!(ids(&diff.added), ["c"]);
+    assert_eq!(ids(&diff.removed), ["a"]);
+    assert_eq!(ids(&diff.changed), ["b"]);
+    assert!(diff_modules(&new, &new).is_empty());
+}
+
+#[test]
+fn removing_active_module_exits_it() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_state::<AppState>();
+    app.add_event::<ModulesChanged>();
+    app.init_resource::<ModuleRegistry>();
+    register_module::<SwappedModule>(&mut app);
+
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::DuckHunt);
+    app.update();
+    assert_eq!(EXITS.load(Ordering::SeqCst), 0);
+
+    let diff = update_module_registry(&mut app.world, Vec::new());
+    assert_eq!(diff.removed.len(), 1);
+    app.update();
+
+    assert_eq!(EXITS.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        *app.world.resource::<State<AppState>>().get(),
+        AppState::Lobby
+    );
+    assert!(app.world.resource::<ModuleRegistry>().modules.is_empty());
+    let events = app.world.resource::<Events<ModulesChanged>>();
+    assert_eq!(events.len(), 1);
+}
@@ -0,0 +1,60 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use engine::{ModuleIcon, ModuleRegistry, module_icon, setup_lobby};
+use platform_api::{AppState, CapabilityFlags, ModuleMetadata};
+
+fn metadata(icon_path: Option<&str>) -> ModuleMetadata {
+    ModuleMetadata {
+        id: "iconic".to_string(),
+        name: "Iconic".to_string(),
+        version: "1.0.0".to_string(),
+        author: "Test".to_string(),
+        state: AppState::DuckHunt,
+        capabilities: CapabilityFlags::LOBBY_PAD,
+        max_players: 4,
+        icon: Handle::default(),
+        icon_path: icon_path.map(str::to_string),
+    }
+}
+
+/// Headless app whose asset server hands out handles without rendering.
+fn app_with_asset_server() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()));
+    app.init_asset::<Image>();
+    app.init_asset::<Mesh>();
+    app.init_asset::<StandardMaterial>();
+    app.init_resource::<ModuleRegistry>();
+    app.world.spawn(Window::default());
+    app
+}
+
+#[test]
+fn icon_path_loads_through_asset_server() {
+    let app = app_with_asset_server();
+    let server = app.world.resource::<AssetServer>();
+
+    let icon = module_icon(&metadata(Some("modules/iconic/icon.png")), Some(server));
+    assert_ne!(icon, Handle::default());
+
+    assert_eq!(
+        module_icon(&metadata(None), Some(server)),
+        Handle::default()
+    );
+    let without_server = module_icon(&metadata(Some("modules/iconic/icon.png")), None);
+    assert_eq!(without_server, Handle::default());
+}
+
+#[test]
+fn lobby_pads_show_manifest_icons() {
+    let mut app = app_with_asset_server();
+    app.world
+        .resource_mut::<ModuleRegistry>()
+        .modules
+        .extend([metadata(Some("modules/iconic/icon.png")), metadata(None)]);
+
+    app.world.run_system_once(setup_lobby);
+
+    let icons = app.world.query::<&ModuleIcon>().iter(&app.world).count();
+    assert_eq!(icons, 1);
+}
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use engine::FrameInterpolation;
+use engine::interpolation::{Interpolated, InterpolationEasing, InterpolationPlugin};
+
+fn interpolated_x(easing: InterpolationEasing, t: f32) -> f32 {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_plugins(InterpolationPlugin);
+    app.insert_resource(FrameInterpolation(t));
+    app.insert_resource(easing);
+
+    let mut steps = Interpolated::new(Transform::from_xyz(0.0, 0.0, 0.0));
+    steps.push(Transform::from_xyz(2.0, 0.0, 0.0));
+    let entity = app.world.spawn((steps, Transform::default())).id();
+
+    app.world.run_schedule(PostUpdate);
+    app.world.get::<Transform>(entity).unwrap().translation.x
+}
+
+#[test]
+fn linear_blend_at_half() {
+    assert!((interpolated_x(InterpolationEasing::Linear, 0.5) - 1.0).abs() < 1e-6);
+    assert!((interpolated_x(InterpolationEasing::Linear, 0.25) - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn smoothstep_blend_at_half() {
+    assert!((interpolated_x(InterpolationEasing::Smoothstep, 0.5) - 1.0).abs() < 1e-6);
+    // smoothstep(0.25) = 0.15625
+    assert!((interpolated_x(InterpolationEasing::Smoothstep, 0.25) - 0.3125).abs() < 1e-6);
+}
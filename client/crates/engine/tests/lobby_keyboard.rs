@@ -25,6 +25,7 @@ fn lobby_keyboard_supports_more_than_five_modules() {
             capabilities: CapabilityFlags::empty(),
             max_players: 0,
             icon: Handle::default(),
+            icon_path: None,
         });
     }
     app.insert_resource(registry);
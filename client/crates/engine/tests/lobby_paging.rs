@@ -0,0 +1,100 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use engine::{LOBBY_PAGE_SIZE, LobbyPage, ModuleRegistry, lobby_keyboard, lobby_paging};
+use platform_api::{AppState, CapabilityFlags, ModuleMetadata};
+
+fn registry(count: usize, duck_hunt: usize) -> ModuleRegistry {
+    let mut registry = ModuleRegistry::default();
+    for i in 0..count {
+        registry.modules.push(ModuleMetadata {
+            id: format!("m{i}"),
+            name: format!("Mod {i}"),
+            version: "1.0.0".into(),
+            author: "Test".into(),
+            state: if i == duck_hunt {
+                AppState::DuckHunt
+            } else {
+                AppState::Lobby
+            },
+            capabilities: CapabilityFlags::empty(),
+            max_players: 0,
+            icon: Handle::default(),
+            icon_path: None,
+        });
+    }
+    registry
+}
+
+fn app(count: usize, duck_hunt: usize) -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.add_state::<AppState>();
+    app.insert_resource(Input::<KeyCode>::default());
+    app.init_resource::<LobbyPage>();
+    app.insert_resource(registry(count, duck_hunt));
+    app
+}
+
+fn press(app: &mut App, key: KeyCode) {
+    let mut input = app.world.resource_mut::<Input<KeyCode>>();
+    input.clear();
+    input.release_all();
+    input.press(key);
+}
+
+#[test]
+fn keys_map_to_modules_across_pages() {
+    assert_eq!(LobbyPage::count(0), 1);
+    assert_eq!(LobbyPage::count(LOBBY_PAGE_SIZE), 1);
+    assert_eq!(LobbyPage::count(LOBBY_PAGE_SIZE + 1), 2);
+
+    assert_eq!(LobbyPage(0).module_for_key(8), 8);
+    assert_eq!(LobbyPage(1).module_for_key(0), 9);
+    assert_eq!(LobbyPage(0).hotkey_for(8), Some(9));
+    assert_eq!(LobbyPage(0).hotkey_for(9), None);
+    assert_eq!(LobbyPage(1).hotkey_for(9), Some(1));
+    assert_eq!(LobbyPage(1).hotkey_for(8), None);
+}
+
+#[test]
+fn tenth_module_is_selectable_on_second_page() {
+    let mut app = app(12, 9);
+
+    press(&mut app, KeyCode::Key1);
+    app.world.run_system_once(lobby_keyboard);
+    assert_eq!(
+        app.world.resource::<NextState<AppState>>().0,
+        Some(AppState::Lobby)
+    );
+
+    press(&mut app, KeyCode::Tab);
+    app.world.run_system_once(lobby_paging);
+    assert_eq!(*app.world.resource::<LobbyPage>(), LobbyPage(1));
+
+    press(&mut app, KeyCode::Key1);
+    app.world.run_system_once(lobby_keyboard);
+    assert_eq!(
+        app.world.resource::<NextState<AppState>>().0,
+        Some(AppState::DuckHunt)
+    );
+}
+
+#[test]
+fn paging_wraps_and_stays_in_range() {
+    let mut app = app(12, 0);
+
+    press(&mut app, KeyCode::PageUp);
+    app.world.run_system_once(lobby_paging);
+    assert_eq!(*app.world.resource::<LobbyPage>(), LobbyPage(1));
+
+    press(&mut app, KeyCode::PageDown);
+    app.world.run_system_once(lobby_paging);
+    assert_eq!(*app.world.resource::<LobbyPage>(), LobbyPage(0));
+
+    // Dropping to a single page pulls the selection back onto it.
+    app.world.insert_resource(LobbyPage(1));
+    app.world.insert_resource(registry(3, 0));
+    press(&mut app, KeyCode::Space);
+    app.world.run_system_once(lobby_paging);
+    assert_eq!(*app.world.resource::<LobbyPage>(), LobbyPage(0));
+}
@@ -24,6 +24,7 @@ impl GameModule for FailingModule {
             capabilities: CapabilityFlags::empty(),
             max_players: 4,
             icon: Handle::default(),
+            icon_path: None,
         }
     }
 
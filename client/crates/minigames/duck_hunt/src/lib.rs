@@ -10,14 +10,26 @@ use net::{
 use platform_api::{
     AppState, CapabilityFlags, GameModule, ModuleContext, ModuleMetadata, ServerApp,
 };
-use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand::{SeedableRng, rngs::StdRng};
 use serde::{Deserialize, Serialize};
 
+#[path = "../../../../../crates/minigames/duck_hunt/paths.rs"]
+pub mod paths;
+
+use paths::{Spline, duck_path, duck_points};
+
 const DUCK_RADIUS: f32 = 0.5;
+/// How long the HUD ammo counter stays highlighted after a dry fire.
+const AMMO_FLASH_SECS: f32 = 0.3;
+const AMMO_FLASH_COLOR: Color = Color::RED;
 
 #[derive(Resource, Default)]
 struct Score(pub u32);
 
+/// Points from the most recent hit, shown next to the score.
+#[derive(Resource, Default)]
+struct LastAward(pub u32);
+
 #[derive(Resource, Default)]
 struct RoundTimer(pub Timer);
 
@@ -30,6 +42,21 @@ struct Weapon {
     max_ammo: u32,
 }
 
+/// Seconds left on the HUD's out-of-ammo highlight.
+#[derive(Resource, Default)]
+struct AmmoFlash(f32);
+
+/// Sound effects triggered by gameplay, behind a trait so the host can plug
+/// in its audio backend and tests can record what played.
+pub trait SoundEffects: Send + Sync + 'static {
+    /// The trigger was pulled with an empty magazine.
+    fn dry_fire(&self);
+}
+
+/// Optional [`SoundEffects`] backend; without it gameplay is silent.
+#[derive(Resource)]
+pub struct Sfx(pub Box<dyn SoundEffects>);
+
 #[derive(Resource)]
 struct HudProfile {
     font: Handle<Font>,
@@ -37,8 +64,22 @@ struct HudProfile {
     color: Color,
 }
 
+/// Draws duck paths from the server's round seed so spawns line up with
+/// the server's `spawn_wave`.
 #[derive(Resource)]
-struct SpawnRng(pub StdRng);
+struct SpawnRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl SpawnRng {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
 
 #[derive(Component)]
 struct Duck {
@@ -46,24 +87,6 @@ struct Duck {
     t: f32,
 }
 
-#[derive(Clone)]
-struct Spline {
-    points: Vec<Vec3>,
-    duration: f32,
-}
-
-impl Spline {
-    fn sample(&self, segment: usize, t: f32) -> Vec3 {
-        if self.points.len() < 2 {
-            return Vec3::ZERO;
-        }
-        let seg = segment.min(self.points.len() - 2);
-        let start = self.points[seg];
-        let end = self.points[seg + 1];
-        start.lerp(end, t.clamp(0.0, 1.0))
-    }
-}
-
 #[derive(Component)]
 struct HudText;
 
@@ -107,16 +130,18 @@ fn setup(world: &mut World) {
     world.spawn((Camera3dBundle::default(), DuckHuntEntity));
 
     world.insert_resource(Score(0));
+    world.insert_resource(LastAward::default());
     world.insert_resource(RoundTimer(Timer::from_seconds(90.0, TimerMode::Once)));
     world.insert_resource(TargetSpawnTimer(Timer::from_seconds(
         2.0,
         TimerMode::Repeating,
     )));
-    world.insert_resource(SpawnRng(StdRng::seed_from_u64(0)));
+    world.insert_resource(SpawnRng::new(0));
     world.insert_resource(Weapon {
         ammo: 6,
         max_ammo: 6,
     });
+    world.insert_resource(AmmoFlash::default());
 
     let Some(asset_server) = world.get_resource::<AssetServer>() else {
         return;
@@ -158,10 +183,12 @@ fn cleanup(world: &mut World) {
     }
 
     world.remove_resource::<Score>();
+    world.remove_resource::<LastAward>();
     world.remove_resource::<RoundTimer>();
     world.remove_resource::<TargetSpawnTimer>();
     world.remove_resource::<SpawnRng>();
     world.remove_resource::<Weapon>();
+    world.remove_resource::<AmmoFlash>();
     world.remove_resource::<HudProfile>();
 }
 
@@ -178,6 +205,7 @@ impl GameModule for DuckHuntPlugin {
             capabilities: CapabilityFlags::LOBBY_PAD,
             max_players: 4,
             icon: Handle::default(),
+            icon_path: None,
         }
     }
 
@@ -211,29 +239,16 @@ fn spawn_ducks(
         }));
         let material = materials.add(Color::rgb(0.2, 0.8, 0.2).into());
 
-        let start_y = rng.0.gen_range(0.5..2.0);
-        let end_y = rng.0.gen_range(0.5..2.5);
-        let left_to_right = rng.0.gen_bool(0.5);
-        let (start, end) = if left_to_right {
-            (Vec3::new(-5.0, start_y, 0.0), Vec3::new(5.0, end_y, 0.0))
-        } else {
-            (Vec3::new(5.0, start_y, 0.0), Vec3::new(-5.0, end_y, 0.0))
-        };
+        let spline = duck_path(&mut rng.rng);
 
         commands.spawn((
             PbrBundle {
                 mesh,
                 material,
-                transform: Transform::from_translation(start),
+                transform: Transform::from_translation(spline.sample(0, 0.0)),
                 ..default()
             },
-            Duck {
-                spline: Spline {
-                    points: vec![start, end],
-                    duration: 5.0,
-                },
-                t: 0.0,
-            },
+            Duck { spline, t: 0.0 },
             DuckHuntEntity,
         ));
     }
@@ -261,20 +276,34 @@ fn move_ducks(
 fn fire_weapon(
     buttons: Res<Input<MouseButton>>,
     keys: Res<Input<KeyCode>>,
-    q: Query<(Entity, &Transform), With<Duck>>,
+    q: Query<(Entity, &Transform, &Duck)>,
     camera: Query<&Transform, With<Camera3d>>,
     time: Res<Time>,
     mut weapon: ResMut<Weapon>,
+    mut flash: ResMut<AmmoFlash>,
+    mut score: ResMut<Score>,
+    mut award: ResMut<LastAward>,
     mut commands: Commands,
     mut writer: EventWriter<InputFrame>,
     frame: Res<CurrentFrame>,
     analytics: Option<Res<Analytics>>,
+    sfx: Option<Res<Sfx>>,
 ) {
     if keys.just_pressed(KeyCode::R) {
         weapon.ammo = weapon.max_ammo;
     }
 
-    if buttons.just_pressed(MouseButton::Left) && weapon.ammo > 0 {
+    if buttons.just_pressed(MouseButton::Left) && weapon.ammo == 0 {
+        flash.0 = AMMO_FLASH_SECS;
+        if let Some(sfx) = sfx.as_ref() {
+            sfx.0.dry_fire();
+        }
+        if let Some(a) = analytics.as_ref() {
+            a.dispatch(Event::Error {
+                message: "out_of_ammo".into(),
+            });
+        }
+    } else if buttons.just_pressed(MouseButton::Left) {
         weapon.ammo -= 1;
         if let Some(a) = analytics.as_ref() {
             a.dispatch(Event::ShotFired);
@@ -294,10 +323,14 @@ fn fire_weapon(
                 });
             }
 
-            if let Some((entity, _)) = q.iter().find(|(_, transform)| {
+            if let Some((entity, _, duck)) = q.iter().find(|(_, transform, _)| {
                 ray_sphere_intersect(origin, direction, transform.translation, DUCK_RADIUS)
             }) {
                 commands.entity(entity).despawn_recursive();
+                // Predict the server's award until the next snapshot.
+                let points = duck_points(duck.spline.speed());
+                score.0 += points;
+                award.0 = points;
                 if let Some(a) = analytics.as_ref() {
                     a.dispatch(Event::TargetHit);
                 }
@@ -318,18 +351,36 @@ fn ray_sphere_intersect(origin: Vec3, dir: Vec3, center: Vec3, radius: f32) -> b
 }
 
 fn update_hud(
+    time: Res<Time>,
     score: Res<Score>,
+    award: Res<LastAward>,
     timer: Res<RoundTimer>,
     weapon: Res<Weapon>,
+    mut flash: ResMut<AmmoFlash>,
     mut q: Query<&mut Text, With<HudText>>,
 ) {
-    if score.is_changed() || timer.is_changed() || weapon.is_changed() {
+    // Keep redrawing while the highlight fades so it switches back off.
+    let flashing = flash.0 > 0.0;
+    if flashing {
+        flash.0 = (flash.0 - time.delta_seconds()).max(0.0);
+    }
+    let changed = score.is_changed() || award.is_changed() || timer.is_changed();
+    if changed || weapon.is_changed() || flashing {
         for mut text in &mut q {
             let remaining = timer.0.remaining_secs().ceil() as u32;
+            let last = match award.0 {
+                0 => String::new(),
+                points => format!(" (+{points})"),
+            };
             text.sections[0].value = format!(
-                "Score: {}\nTime: {remaining}\nAmmo: {}",
+                "Score: {}{last}\nTime: {remaining}\nAmmo: {}",
                 score.0, weapon.ammo
             );
+            text.sections[0].style.color = if flash.0 > 0.0 {
+                AMMO_FLASH_COLOR
+            } else {
+                Color::WHITE
+            };
         }
     }
 }
@@ -342,7 +393,11 @@ fn apply_state_snapshots(
     for snap in reader.read() {
         if let Ok(state) = postcard::from_bytes::<GameState>(&snap.data) {
             score.0 = state.scores.get(0).copied().unwrap_or(0);
-            rng.0 = StdRng::seed_from_u64(state.seed);
+            // Only restart the sequence when the round's seed changes, or
+            // every snapshot would replay the first duck.
+            if rng.seed != state.seed {
+                *rng = SpawnRng::new(state.seed);
+            }
         }
     }
 }
@@ -369,12 +424,63 @@ fn log_connection_events(mut events: EventReader<ConnectionEvent>) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::paths::SplineKind;
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    struct CountingSfx(Arc<AtomicUsize>);
+
+    impl SoundEffects for CountingSfx {
+        fn dry_fire(&self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn dry_fire_keeps_ammo_at_zero_and_reports() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_event::<InputFrame>();
+        app.init_resource::<Input<MouseButton>>();
+        app.init_resource::<Input<KeyCode>>();
+        app.init_resource::<CurrentFrame>();
+        app.init_resource::<AmmoFlash>();
+        app.init_resource::<Score>();
+        app.init_resource::<LastAward>();
+        app.insert_resource(Weapon {
+            ammo: 0,
+            max_ammo: 6,
+        });
+        let analytics = Analytics::new(true, None, None, None);
+        app.insert_resource(analytics.clone());
+        let clicks = Arc::new(AtomicUsize::new(0));
+        app.insert_resource(Sfx(Box::new(CountingSfx(Arc::clone(&clicks)))));
+        app.add_systems(Update, fire_weapon);
+
+        app.world
+            .resource_mut::<Input<MouseButton>>()
+            .press(MouseButton::Left);
+        app.update();
+
+        assert_eq!(app.world.resource::<Weapon>().ammo, 0);
+        assert!(app.world.resource::<AmmoFlash>().0 > 0.0);
+        assert_eq!(clicks.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            analytics.events(),
+            vec![Event::Error {
+                message: "out_of_ammo".into()
+            }]
+        );
+    }
 
     #[test]
     fn sample_with_fewer_than_two_points_returns_zero() {
         let spline = Spline {
             points: vec![Vec3::new(1.0, 2.0, 3.0)],
             duration: 1.0,
+            kind: SplineKind::Linear,
         };
         assert_eq!(sample_at(&spline, 0.5), Vec3::ZERO);
     }
@@ -400,6 +506,7 @@ mod tests {
                 Vec3::new(2.0, 1.0, 0.0),
             ],
             duration: 1.0,
+            kind: SplineKind::Linear,
         };
         let checks = [
             (0.0, Vec3::new(0.0, 0.0, 0.0)),
@@ -414,4 +521,45 @@ mod tests {
             assert!(sample_at(&spline, t).distance(expected) < 1e-5);
         }
     }
+
+    fn zigzag(kind: SplineKind) -> Spline {
+        Spline {
+            points: vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(2.0, 1.0, 0.0),
+            ],
+            duration: 1.0,
+            kind,
+        }
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_control_points() {
+        let spline = zigzag(SplineKind::CatmullRom);
+        for segment in 0..3 {
+            let start = spline.sample(segment, 0.0);
+            let end = spline.sample(segment, 1.0);
+            assert!(start.distance(spline.points[segment]) < 1e-5);
+            assert!(end.distance(spline.points[segment + 1]) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn catmull_rom_curves_away_from_linear_midpoints() {
+        let linear = zigzag(SplineKind::Linear);
+        let curve = zigzag(SplineKind::CatmullRom);
+        // The outer segments bend towards their neighbours; the middle one is
+        // symmetric and meets the straight line at its midpoint.
+        let checks = [
+            (0, Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.5, -0.0625, 0.0)),
+            (1, Vec3::new(1.0, 0.5, 0.0), Vec3::new(1.0, 0.5, 0.0)),
+            (2, Vec3::new(1.5, 1.0, 0.0), Vec3::new(1.5, 1.0625, 0.0)),
+        ];
+        for (segment, straight, curved) in checks {
+            assert!(linear.sample(segment, 0.5).distance(straight) < 1e-5);
+            assert!(curve.sample(segment, 0.5).distance(curved) < 1e-5);
+        }
+    }
 }
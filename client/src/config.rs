@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use netcode::client::RTCIceServer;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -70,6 +71,19 @@ impl Default for RuntimeConfig {
 }
 
 impl RuntimeConfig {
+    /// ICE servers from `/config.json` in the form the connector expects.
+    pub fn rtc_ice_servers(&self) -> Vec<RTCIceServer> {
+        self.ice_servers
+            .iter()
+            .map(|server| RTCIceServer {
+                urls: server.urls.clone(),
+                username: server.username.clone().unwrap_or_default(),
+                credential: server.credential.clone().unwrap_or_default(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub async fn load() -> Self {
         use wasm_bindgen::JsCast;
@@ -223,5 +237,23 @@ mod tests {
         handle.join().unwrap();
         assert_eq!(cfg, RuntimeConfig::default());
     }
+
+    #[test]
+    fn ice_servers_feed_rtc_configuration() {
+        let cfg: RuntimeConfig = serde_json::from_str(
+            r#"{"signal_url":"sig","api_base_url":"api","ice_servers":[
+                {"urls":"stun:stun.example.com"},
+                {"urls":["turn:turn.example.com"],"username":"u","credential":"c"}
+            ]}"#,
+        )
+        .unwrap();
+        let servers = cfg.rtc_ice_servers();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].urls, vec!["stun:stun.example.com".to_string()]);
+        assert!(servers[0].username.is_empty());
+        assert_eq!(servers[1].urls, vec!["turn:turn.example.com".to_string()]);
+        assert_eq!(servers[1].username, "u");
+        assert_eq!(servers[1].credential, "c");
+    }
 }
 
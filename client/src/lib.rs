@@ -1 +1,2 @@
 pub mod entitlements;
+pub mod telemetry;
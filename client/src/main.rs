@@ -1,6 +1,6 @@
 #![cfg_attr(target_arch = "wasm32", feature(web_worker))]
 
-use analytics::{Analytics, Event};
+use analytics::Event;
 use bevy::prelude::*;
 use duck_hunt::DuckHuntPlugin;
 use engine::{AppExt, EnginePlugin};
@@ -8,6 +8,7 @@ mod entitlements;
 mod lobby;
 mod net;
 mod config;
+mod telemetry;
 use entitlements::{claim_entitlement, fetch_entitlements, ensure_session};
 use config::RuntimeConfig;
 use null_module::NullModule;
@@ -23,8 +24,8 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
     let config = future::block_on(RuntimeConfig::load());
-    let enabled = config.analytics_enabled && !config.analytics_opt_out;
-    let analytics = Analytics::new(enabled, None, None, None);
+    let analytics =
+        telemetry::analytics_resource(config.analytics_enabled, config.analytics_opt_out);
     analytics.dispatch(Event::SessionStart);
     analytics.dispatch(Event::LevelStart { level: 1 });
     let mut entitlements: HashSet<String> =
@@ -57,8 +58,8 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen(start)]
 pub async fn main() -> Result<(), JsValue> {
     let config = RuntimeConfig::load().await;
-    let enabled = config.analytics_enabled && !config.analytics_opt_out;
-    let analytics = Analytics::new(enabled, None, None, None);
+    let analytics =
+        telemetry::analytics_resource(config.analytics_enabled, config.analytics_opt_out);
     analytics.dispatch(Event::SessionStart);
     analytics.dispatch(Event::LevelStart { level: 1 });
     let mut entitlements: HashSet<String> =
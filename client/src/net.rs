@@ -9,6 +9,10 @@ use platform_api::AppState;
 #[cfg(target_arch = "wasm32")]
 use futures_lite::future;
 #[cfg(target_arch = "wasm32")]
+use gloo_timers::future::TimeoutFuture;
+#[cfg(target_arch = "wasm32")]
+use netcode::reconnect::{Backoff, reconnect};
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::spawn_local;
 
 #[cfg(target_arch = "wasm32")]
@@ -26,8 +30,9 @@ fn start_connection(mut commands: Commands, config: Res<RuntimeConfig>) {
         return;
     }
     let signal_url = config.signal_url.clone();
+    let ice_servers = config.rtc_ice_servers();
     let task = AsyncComputeTaskPool::get().spawn_local(async move {
-        match ClientConnector::new().await {
+        match ClientConnector::with_ice_servers(ice_servers).await {
             Ok(conn) => match conn.signal(&signal_url).await {
                 Ok(_) => Ok(conn),
                 Err(e) => Err(e.to_string()),
@@ -59,6 +64,42 @@ fn finish_connection_task(
     }
 }
 
+/// Replace a connector whose channel closed with a freshly signaled one,
+/// backing off between attempts. Connectors closed on purpose were already
+/// taken out of [`ConnectorResource`] and are left alone.
+#[cfg(target_arch = "wasm32")]
+fn reconnect_on_close(
+    mut commands: Commands,
+    mut events: EventReader<ConnectionEvent>,
+    connector: Option<ResMut<ConnectorResource>>,
+    task: Option<Res<ConnectorTask>>,
+) {
+    let closed = events
+        .read()
+        .filter(|event| matches!(event, ConnectionEvent::Closed))
+        .count()
+        > 0;
+    let Some(mut connector) = connector else {
+        return;
+    };
+    if !closed || task.is_some() {
+        return;
+    }
+    let Some(signal_url) = connector.0.as_ref().and_then(ClientConnector::signal_url) else {
+        return;
+    };
+    let Some(conn) = connector.0.take() else {
+        return;
+    };
+    let task = AsyncComputeTaskPool::get().spawn_local(async move {
+        let sleep = |delay: std::time::Duration| TimeoutFuture::new(delay.as_millis() as u32);
+        reconnect(&conn, &signal_url, &Backoff::default(), sleep)
+            .await
+            .map_err(|e| e.to_string())
+    });
+    commands.insert_resource(ConnectorTask(task));
+}
+
 #[cfg(target_arch = "wasm32")]
 fn close_connector(connector: &mut ResMut<ConnectorResource>) {
     if let Some(conn) = connector.0.take() {
@@ -93,7 +134,7 @@ impl Plugin for ClientNetPlugin {
     fn build(&self, app: &mut App) {
         #[cfg(target_arch = "wasm32")]
         app.add_systems(Startup, start_connection)
-            .add_systems(Update, finish_connection_task)
+            .add_systems(Update, (finish_connection_task, reconnect_on_close))
             .add_systems(Update, (cleanup_on_exit, cleanup_on_state_change));
     }
 }
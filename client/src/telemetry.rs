@@ -0,0 +1,13 @@
+use analytics::Analytics;
+
+/// Build the analytics resource for the client.
+///
+/// When analytics are disabled or the player opted out this returns
+/// [`Analytics::disabled`], so dispatches from client systems do nothing.
+pub fn analytics_resource(enabled: bool, opt_out: bool) -> Analytics {
+    if enabled && !opt_out {
+        Analytics::new(true, None, None, None)
+    } else {
+        Analytics::disabled()
+    }
+}
@@ -0,0 +1,20 @@
+use analytics::{Analytics, Event};
+use bevy::prelude::*;
+use client::telemetry::analytics_resource;
+
+fn fire(analytics: Res<Analytics>) {
+    analytics.dispatch(Event::ShotFired);
+}
+
+#[test]
+fn opted_out_analytics_are_inert() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.insert_resource(analytics_resource(true, true));
+    app.add_systems(Update, fire);
+    app.update();
+
+    let analytics = app.world.resource::<Analytics>();
+    assert!(!analytics.is_enabled());
+    assert!(analytics.events().is_empty());
+}
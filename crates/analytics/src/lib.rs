@@ -2,15 +2,24 @@
 //!
 //! Up to `DEFAULT_MAX_EVENTS` events are retained in memory. Set the
 //! `ARENA_ANALYTICS_MAX_EVENTS` environment variable to change this limit.
+//! `ARENA_ANALYTICS_FLUSH_SECS` and `ARENA_ANALYTICS_ROLLUP_SECS` tune how
+//! often events are written to and rolled up in the database.
+
+#[cfg(feature = "posthog")]
+mod posthog;
+mod sink;
+
+pub use sink::AnalyticsSink;
 
 use std::{
+    collections::{HashSet, VecDeque},
     net::SocketAddr,
     sync::{Arc, Mutex},
 };
 
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use sea_orm::{
-    DatabaseConnection, DbBackend, Set, Statement,
+    DatabaseConnection, DbBackend, QueryOrder, Set, Statement,
     entity::prelude::*,
     sea_query::{Alias, Expr, Func, OnConflict, PostgresQueryBuilder, Query, SimpleExpr},
 };
@@ -21,25 +30,82 @@ use uuid::Uuid;
 #[cfg(feature = "bevy-resource")]
 use bevy_ecs::system::Resource;
 #[cfg(feature = "otlp")]
-use opentelemetry::{KeyValue, global, metrics::Counter};
+use opentelemetry::global;
+#[cfg(feature = "posthog")]
+use posthog::PosthogSink;
 #[cfg(feature = "prometheus")]
 use prometheus::{IntCounterVec, opts};
-#[cfg(feature = "posthog")]
-use reqwest::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "otlp")]
+use sink::OtlpSink;
+#[cfg(feature = "prometheus")]
+use sink::PrometheusSink;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 const DEFAULT_MAX_EVENTS: usize = 10_000;
 const MAX_EVENTS_ENV_VAR: &str = "ARENA_ANALYTICS_MAX_EVENTS";
+const FLUSH_SECS_ENV_VAR: &str = "ARENA_ANALYTICS_FLUSH_SECS";
+const ROLLUP_SECS_ENV_VAR: &str = "ARENA_ANALYTICS_ROLLUP_SECS";
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_ROLLUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often the background tasks flush events to and roll them up in the
+/// database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Intervals {
+    pub flush: Duration,
+    pub rollup: Duration,
+}
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+impl Default for Intervals {
+    fn default() -> Self {
+        Self {
+            flush: DEFAULT_FLUSH_INTERVAL,
+            rollup: DEFAULT_ROLLUP_INTERVAL,
+        }
+    }
+}
+
+impl Intervals {
+    /// Read `ARENA_ANALYTICS_FLUSH_SECS` and `ARENA_ANALYTICS_ROLLUP_SECS`.
+    pub fn from_env() -> Self {
+        Self {
+            flush: parse_secs(
+                std::env::var(FLUSH_SECS_ENV_VAR).ok(),
+                DEFAULT_FLUSH_INTERVAL,
+            ),
+            rollup: parse_secs(
+                std::env::var(ROLLUP_SECS_ENV_VAR).ok(),
+                DEFAULT_ROLLUP_INTERVAL,
+            ),
+        }
+    }
+}
+
+/// Zero or unparsable values fall back to `default`; a zero period would
+/// make `tokio::time::interval` panic.
+fn parse_secs(value: Option<String>, default: Duration) -> Duration {
+    value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(default)
+}
+
+/// Callback invoked with every dispatched event; see [`Analytics::add_observer`].
+pub type Observer = Box<dyn Fn(&Event) + Send + Sync>;
+
+/// Serialized as an object tagged with [`Event::name`] under `"event"`, plus
+/// any fields, e.g. `{"event":"level_start","level":3}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
 pub enum Event {
     WsConnected,
     MailTestQueued,
     PurchaseCompleted { sku: String, user: String },
     EntitlementChecked,
     RunVerificationFailed,
+    ReplayTrimmed,
 
     SessionStart,
     LevelStart { level: u32 },
@@ -71,34 +137,78 @@ pub enum Event {
     EntitlementGranted,
 }
 
+/// An event plus the player and session it is attributed to, if known.
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    event: Event,
+    player_id: Option<String>,
+    session_id: Option<Uuid>,
+}
+
+impl From<Event> for Record {
+    fn from(event: Event) -> Self {
+        Self {
+            event,
+            player_id: None,
+            session_id: None,
+        }
+    }
+}
+
+/// Ring buffer of the most recent `max_len` records, oldest first.
 struct ColumnarStore {
-    events: Vec<Event>,
+    records: VecDeque<Record>,
     max_len: usize,
 }
 
 impl ColumnarStore {
     fn new(max_len: usize) -> Self {
         Self {
-            events: Vec::new(),
+            records: VecDeque::new(),
             max_len,
         }
     }
 
-    fn push(&mut self, event: Event) {
-        if self.events.len() >= self.max_len {
-            self.events.remove(0);
+    /// Append a record, returning how many were dropped to stay in bounds.
+    fn push(&mut self, record: impl Into<Record>) -> u64 {
+        if self.max_len == 0 {
+            return 1;
         }
-        self.events.push(event);
+        let mut dropped = 0;
+        if self.records.len() >= self.max_len {
+            self.records.pop_front();
+            dropped = 1;
+        }
+        self.records.push_back(record.into());
+        dropped
     }
 
     fn events(&self) -> Vec<Event> {
-        self.events.clone()
+        self.records.iter().map(|r| r.event.clone()).collect()
     }
 
     fn take_events(&mut self) -> Vec<Event> {
-        let events = self.events.clone();
-        self.events.clear();
-        events
+        self.records.drain(..).map(|r| r.event).collect()
+    }
+
+    fn take_records(&mut self) -> Vec<Record> {
+        self.records.drain(..).collect()
+    }
+
+    /// Put records taken by a failed flush back in front of newer ones.
+    ///
+    /// Still bounded by `max_len`: the oldest records are dropped first and
+    /// the number dropped is returned.
+    fn requeue(&mut self, records: Vec<Record>) -> u64 {
+        let mut dropped = 0;
+        for record in records.into_iter().rev() {
+            if self.records.len() >= self.max_len {
+                dropped += 1;
+            } else {
+                self.records.push_front(record);
+            }
+        }
+        dropped
     }
 }
 
@@ -115,6 +225,7 @@ impl Event {
             Event::PurchaseCompleted { .. } => "purchase_completed",
             Event::EntitlementChecked => "entitlement_checked",
             Event::RunVerificationFailed => "run_verification_failed",
+            Event::ReplayTrimmed => "replay_trimmed",
             Event::SessionStart => "session_start",
             Event::LevelStart { .. } => "level_start",
             Event::StoreOpen => "store_open",
@@ -140,6 +251,17 @@ impl Event {
             Event::EntitlementGranted => "entitlement_granted",
         }
     }
+
+    /// Structured fields of variants that carry data, as stored in
+    /// `analytics_events.payload_json`.
+    pub fn payload(&self) -> Option<JsonValue> {
+        match self {
+            Event::PurchaseCompleted { sku, user } => Some(json!({ "sku": sku, "user": user })),
+            Event::LevelStart { level } => Some(json!({ "level": level })),
+            Event::Error { message } => Some(json!({ "message": message })),
+            _ => None,
+        }
+    }
 }
 
 #[cfg_attr(feature = "bevy-resource", derive(Resource))]
@@ -147,13 +269,19 @@ impl Event {
 pub struct Analytics {
     enabled: bool,
     store: Arc<Mutex<ColumnarStore>>,
+    sinks: Arc<Mutex<Vec<Arc<dyn AnalyticsSink>>>>,
+    /// Event names allowed through `dispatch`; `None` allows all.
+    filter: Option<Arc<HashSet<&'static str>>>,
     db: Option<DatabaseConnection>,
+    last_flush_error: Arc<Mutex<Option<String>>>,
+    /// Events evicted from the store before they could be flushed or read.
+    events_lost: Arc<AtomicU64>,
     #[cfg(feature = "prometheus")]
     counter: IntCounterVec,
     #[cfg(feature = "posthog")]
-    posthog: Option<(Client, String, String)>,
+    posthog: Option<PosthogSink>,
     #[cfg(feature = "otlp")]
-    otel: Option<(Counter<u64>, Arc<AtomicU64>)>,
+    otel_calls: Option<Arc<AtomicU64>>,
 }
 
 impl Analytics {
@@ -163,6 +291,24 @@ impl Analytics {
         posthog_key: Option<String>,
         metrics_addr: Option<SocketAddr>,
         max_events: usize,
+    ) -> Self {
+        Self::with_intervals(
+            enabled,
+            db,
+            posthog_key,
+            metrics_addr,
+            max_events,
+            Intervals::default(),
+        )
+    }
+
+    pub fn with_intervals(
+        enabled: bool,
+        db: Option<DatabaseConnection>,
+        posthog_key: Option<String>,
+        metrics_addr: Option<SocketAddr>,
+        max_events: usize,
+        intervals: Intervals,
     ) -> Self {
         let store = Arc::new(Mutex::new(ColumnarStore::new(max_events)));
 
@@ -180,34 +326,51 @@ impl Analytics {
         #[cfg(feature = "posthog")]
         let posthog = posthog_key.map(|key| {
             let endpoint = std::env::var("POSTHOG_ENDPOINT")
-                .unwrap_or_else(|_| "https://app.posthog.com/capture/".to_string());
-            (Client::new(), key, endpoint)
+                .unwrap_or_else(|_| posthog::DEFAULT_ENDPOINT.to_string());
+            PosthogSink::spawn(key, endpoint)
         });
         #[cfg(not(feature = "posthog"))]
         let _ = posthog_key;
 
         #[cfg(feature = "otlp")]
-        let otel = if metrics_addr.is_some() {
+        let otel = metrics_addr.map(|_| {
             let meter = global::meter("analytics");
-            let counter = meter.u64_counter("analytics_events").init();
-            let calls = Arc::new(AtomicU64::new(0));
-            Some((counter, calls))
-        } else {
-            None
-        };
+            OtlpSink {
+                counter: meter.u64_counter("analytics_events").init(),
+                calls: Arc::new(AtomicU64::new(0)),
+            }
+        });
         #[cfg(not(feature = "otlp"))]
         let _ = metrics_addr;
 
+        #[cfg(feature = "otlp")]
+        let otel_calls = otel.as_ref().map(|otel| otel.calls.clone());
+        let built_in: Vec<Option<Arc<dyn AnalyticsSink>>> = vec![
+            #[cfg(feature = "prometheus")]
+            Some(Arc::new(PrometheusSink(counter.clone()))),
+            #[cfg(feature = "posthog")]
+            posthog
+                .clone()
+                .map(|p| Arc::new(p) as Arc<dyn AnalyticsSink>),
+            #[cfg(feature = "otlp")]
+            otel.map(|o| Arc::new(o) as Arc<dyn AnalyticsSink>),
+        ];
+        let sinks: Vec<_> = built_in.into_iter().flatten().collect();
+
         let analytics = Self {
             enabled,
             store,
+            sinks: Arc::new(Mutex::new(sinks)),
+            filter: None,
             db,
+            last_flush_error: Arc::default(),
+            events_lost: Arc::default(),
             #[cfg(feature = "prometheus")]
             counter,
             #[cfg(feature = "posthog")]
             posthog,
             #[cfg(feature = "otlp")]
-            otel,
+            otel_calls,
         };
 
         if analytics.db.is_some() {
@@ -215,7 +378,7 @@ impl Analytics {
             {
                 let this = analytics.clone();
                 tokio::spawn(async move {
-                    let mut ticker = interval(Duration::from_secs(5));
+                    let mut ticker = interval(intervals.flush);
                     loop {
                         ticker.tick().await;
                         let _ = this.flush_to_db().await;
@@ -227,7 +390,7 @@ impl Analytics {
             {
                 let this = analytics.clone();
                 tokio::spawn(async move {
-                    let mut ticker = interval(Duration::from_secs(60 * 60));
+                    let mut ticker = interval(intervals.rollup);
                     loop {
                         ticker.tick().await;
                         let _ = this.rollup().await;
@@ -249,72 +412,136 @@ impl Analytics {
             .ok()
             .and_then(|v| v.parse().ok())
             .unwrap_or(DEFAULT_MAX_EVENTS);
-        Self::with_max_events(enabled, db, posthog_key, metrics_addr, max_events)
+        Self::with_intervals(
+            enabled,
+            db,
+            posthog_key,
+            metrics_addr,
+            max_events,
+            Intervals::from_env(),
+        )
+    }
+
+    /// A handle that records nothing, registers no metrics and spawns no
+    /// background tasks. Use it when analytics are opted out entirely.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            store: Arc::new(Mutex::new(ColumnarStore::new(0))),
+            sinks: Arc::default(),
+            filter: None,
+            db: None,
+            last_flush_error: Arc::default(),
+            events_lost: Arc::default(),
+            #[cfg(feature = "prometheus")]
+            counter: IntCounterVec::new(
+                opts!("analytics_events_total", "count of analytics events"),
+                &["event"],
+            )
+            .expect("metric can be created"),
+            #[cfg(feature = "posthog")]
+            posthog: None,
+            #[cfg(feature = "otlp")]
+            otel_calls: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Only dispatch events whose [`Event::name`] is in `allowed`. An empty
+    /// set means "allow all", the same as not setting a filter.
+    pub fn with_filter(mut self, allowed: HashSet<&'static str>) -> Self {
+        self.filter = (!allowed.is_empty()).then(|| Arc::new(allowed));
+        self
+    }
+
+    /// Register a callback run for every dispatched event, in dispatch order.
+    ///
+    /// Observers are shared by all clones of this handle, so embedders can
+    /// route events to custom sinks without a dedicated cargo feature.
+    pub fn add_observer(&self, observer: Observer) {
+        self.add_sink(Box::new(observer));
+    }
+
+    /// Fan dispatched events out to `sink` as well as the built-in ones.
+    /// Sinks are shared by all clones of this handle.
+    pub fn add_sink(&self, sink: Box<dyn AnalyticsSink>) {
+        self.sinks.lock().unwrap().push(Arc::from(sink));
     }
 
     pub fn dispatch(&self, event: Event) {
+        self.dispatch_with_context(event, None, None);
+    }
+
+    /// Dispatch `event` attributed to a player and/or session, which are
+    /// persisted alongside it in `analytics_events`.
+    pub fn dispatch_with_context(
+        &self,
+        event: Event,
+        player_id: Option<String>,
+        session_id: Option<Uuid>,
+    ) {
         if !self.enabled {
             return;
         }
         let name = event.name();
-        self.store.lock().unwrap().push(event.clone());
-
-        #[cfg(feature = "prometheus")]
-        self.counter.with_label_values(&[name]).inc();
-
-        #[cfg(feature = "posthog")]
-        if let Some((client, key, endpoint)) = &self.posthog {
-            let payload = serde_json::json!({
-                "api_key": key,
-                "event": name,
-                "distinct_id": "server",
-            });
-            let client = client.clone();
-            let endpoint = endpoint.clone();
-            tokio::spawn(async move {
-                let _ = client.post(endpoint).json(&payload).send().await;
-            });
+        if self
+            .filter
+            .as_ref()
+            .is_some_and(|allowed| !allowed.contains(name))
+        {
+            return;
         }
-
-        #[cfg(feature = "otlp")]
-        if let Some((counter, calls)) = &self.otel {
-            counter.add(1, &[KeyValue::new("event", name)]);
-            calls.fetch_add(1, Ordering::Relaxed);
+        // Snapshot the list so sinks run without any lock held and may
+        // themselves dispatch or register sinks.
+        let sinks = self.sinks.lock().unwrap().clone();
+        for sink in &sinks {
+            sink.record_with_context(&event, player_id.as_deref(), session_id);
         }
+
+        let dropped = self.store.lock().unwrap().push(Record {
+            event,
+            player_id,
+            session_id,
+        });
+        self.events_lost.fetch_add(dropped, Ordering::Relaxed);
     }
 
     async fn flush_to_db(&self) -> Result<(), DbErr> {
         if !self.enabled {
             return Ok(());
         }
-        let events = self.store.lock().unwrap().take_events();
-        if events.is_empty() {
+        let records = self.store.lock().unwrap().take_records();
+        if records.is_empty() {
             return Ok(());
         }
         if let Some(db) = &self.db {
-            let mut models = Vec::with_capacity(events.len());
-            for event in events {
-                let payload = match &event {
-                    Event::Error { message } => Some(json!({ "message": message })),
-                    Event::PurchaseCompleted { sku, user } => {
-                        Some(json!({ "sku": sku, "user": user }))
-                    }
-                    _ => None,
-                };
-                models.push(events::ActiveModel {
-                    ts: Set(Utc::now()),
-                    player_id: Set(None),
-                    session_id: Set(None),
-                    kind: Set(event.name().to_string()),
-                    payload_json: Set(payload),
-                    ..Default::default()
-                });
+            let models = records.iter().map(events::ActiveModel::from_record);
+            if let Err(e) = events::Entity::insert_many(models).exec(db).await {
+                *self.last_flush_error.lock().unwrap() = Some(e.to_string());
+                let dropped = self.store.lock().unwrap().requeue(records);
+                self.events_lost.fetch_add(dropped, Ordering::Relaxed);
+                return Err(e);
             }
-            events::Entity::insert_many(models).exec(db).await?;
+            *self.last_flush_error.lock().unwrap() = None;
         }
         Ok(())
     }
 
+    /// Total events dropped because the store was full, e.g. while database
+    /// flushes keep failing.
+    pub fn events_lost(&self) -> u64 {
+        self.events_lost.load(Ordering::Relaxed)
+    }
+
+    /// Error from the most recent database flush, or `None` if it succeeded
+    /// (or none has run yet).
+    pub fn last_flush_error(&self) -> Option<String> {
+        self.last_flush_error.lock().unwrap().clone()
+    }
+
     async fn rollup(&self) -> Result<(), DbErr> {
         if !self.enabled {
             return Ok(());
@@ -374,6 +601,30 @@ impl Analytics {
         Ok(())
     }
 
+    /// Hourly rollup values for `kind` with buckets in `[from, to)`, oldest
+    /// first. Returns an empty list when no database is configured.
+    pub async fn query_rollups(
+        &self,
+        kind: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, f64)>, DbErr> {
+        let Some(db) = &self.db else {
+            return Ok(Vec::new());
+        };
+        let rows = rollups::Entity::find()
+            .filter(rollups::Column::Kind.eq(kind))
+            .filter(rollups::Column::BucketStart.gte(from))
+            .filter(rollups::Column::BucketStart.lt(to))
+            .order_by_asc(rollups::Column::BucketStart)
+            .all(db)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.bucket_start, row.value))
+            .collect())
+    }
+
     pub fn events(&self) -> Vec<Event> {
         self.store.lock().unwrap().events()
     }
@@ -387,17 +638,23 @@ impl Analytics {
         self.counter.with_label_values(&[name]).get()
     }
 
+    /// Number of events PostHog failed to accept (errors or non-2xx replies).
+    #[cfg(feature = "posthog")]
+    pub fn posthog_dropped(&self) -> u64 {
+        self.posthog.as_ref().map(PosthogSink::dropped).unwrap_or(0)
+    }
+
     #[cfg(feature = "otlp")]
     pub fn otlp_count(&self) -> u64 {
-        self.otel
+        self.otel_calls
             .as_ref()
-            .map(|(_, c)| c.load(Ordering::Relaxed))
+            .map(|c| c.load(Ordering::Relaxed))
             .unwrap_or(0)
     }
 }
 
 mod events {
-    use super::{JsonValue, Uuid};
+    use super::{JsonValue, Record, Set, Utc, Uuid};
     use sea_orm::entity::prelude::*;
 
     #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
@@ -416,6 +673,20 @@ mod events {
     pub enum Relation {}
 
     impl ActiveModelBehavior for ActiveModel {}
+
+    impl ActiveModel {
+        /// Row for `record`, including its [`Event::payload`](super::Event::payload).
+        pub(super) fn from_record(record: &Record) -> Self {
+            Self {
+                ts: Set(Utc::now()),
+                player_id: Set(record.player_id.clone()),
+                session_id: Set(record.session_id),
+                kind: Set(record.event.name().to_string()),
+                payload_json: Set(record.event.payload()),
+                ..Default::default()
+            }
+        }
+    }
 }
 
 mod rollups {
@@ -454,6 +725,18 @@ mod tests {
         unsafe { std::env::remove_var(key) }
     }
 
+    /// Build a PostHog-enabled handle pointed at `endpoint`. The endpoint is
+    /// read from the environment, so concurrent tests take turns.
+    #[cfg(feature = "posthog")]
+    fn posthog_analytics(endpoint: String) -> Analytics {
+        static ENV_LOCK: Mutex<()> = Mutex::new(());
+        let _guard = ENV_LOCK.lock().unwrap();
+        set_var("POSTHOG_ENDPOINT", endpoint);
+        let analytics = Analytics::new(true, None, Some("test_key".into()), None);
+        remove_var("POSTHOG_ENDPOINT");
+        analytics
+    }
+
     #[cfg(feature = "prometheus")]
     #[test]
     fn store_and_prometheus() {
@@ -482,6 +765,304 @@ mod tests {
         remove_var(MAX_EVENTS_ENV_VAR);
     }
 
+    #[test]
+    fn ring_buffer_keeps_newest_in_order() {
+        let mut store = ColumnarStore::new(3);
+        for level in 0..5 {
+            store.push(Event::LevelStart { level });
+        }
+        let expected: Vec<Event> = (2..5).map(|level| Event::LevelStart { level }).collect();
+        assert_eq!(store.events(), expected);
+        assert_eq!(store.take_events(), expected);
+        assert!(store.events().is_empty());
+    }
+
+    #[test]
+    fn disabled_is_inert() {
+        let analytics = Analytics::disabled();
+        analytics.dispatch(Event::ShotFired);
+        assert!(!analytics.is_enabled());
+        assert!(analytics.events().is_empty());
+        assert!(analytics.flush().is_empty());
+    }
+
+    #[test]
+    fn filtered_events_are_dropped() {
+        let analytics = Analytics::with_max_events(true, None, None, None, 4)
+            .with_filter(HashSet::from(["shot_fired"]));
+        analytics.dispatch(Event::ShotFired);
+        analytics.dispatch(Event::FrameDropped);
+        analytics.dispatch(Event::TickOverrun);
+
+        assert_eq!(analytics.events(), vec![Event::ShotFired]);
+        #[cfg(feature = "prometheus")]
+        assert_eq!(analytics.counter_value("frame_dropped"), 0);
+        // `flush_to_db` writes exactly what the store hands it.
+        let records = analytics.store.lock().unwrap().take_records();
+        assert_eq!(records, vec![Record::from(Event::ShotFired)]);
+    }
+
+    #[test]
+    fn empty_filter_allows_all() {
+        let analytics =
+            Analytics::with_max_events(true, None, None, None, 4).with_filter(HashSet::new());
+        analytics.dispatch(Event::FrameDropped);
+        assert_eq!(analytics.events(), vec![Event::FrameDropped]);
+    }
+
+    #[test]
+    fn observers_receive_events_in_order() {
+        let analytics = Analytics::with_max_events(true, None, None, None, 4);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let spy = seen.clone();
+        analytics.add_observer(Box::new(move |event| {
+            spy.lock().unwrap().push(event.clone());
+        }));
+
+        analytics.dispatch(Event::ShotFired);
+        analytics.clone().dispatch(Event::TargetHit);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![Event::ShotFired, Event::TargetHit]
+        );
+    }
+
+    fn stored_payload(event: Event) -> Option<JsonValue> {
+        events::ActiveModel::from_record(&event.into())
+            .payload_json
+            .unwrap()
+    }
+
+    #[test]
+    fn context_is_stored_with_event() {
+        let analytics = Analytics::with_max_events(true, None, None, None, 4);
+        let session = Uuid::new_v4();
+        analytics.dispatch_with_context(Event::SessionStart, Some("p1".into()), Some(session));
+        analytics.dispatch(Event::WsConnected);
+
+        let rows: Vec<_> = analytics
+            .store
+            .lock()
+            .unwrap()
+            .take_records()
+            .iter()
+            .map(events::ActiveModel::from_record)
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].kind, Set("session_start".to_string()));
+        assert_eq!(rows[0].player_id, Set(Some("p1".to_string())));
+        assert_eq!(rows[0].session_id, Set(Some(session)));
+        assert_eq!(rows[1].player_id, Set(None));
+        assert_eq!(rows[1].session_id, Set(None));
+    }
+
+    #[test]
+    fn level_start_payload_is_stored() {
+        assert_eq!(
+            stored_payload(Event::LevelStart { level: 3 }),
+            Some(json!({ "level": 3 }))
+        );
+    }
+
+    #[test]
+    fn error_payload_is_stored() {
+        assert_eq!(
+            stored_payload(Event::Error {
+                message: "boom".into()
+            }),
+            Some(json!({ "message": "boom" }))
+        );
+    }
+
+    #[test]
+    fn purchase_completed_payload_is_stored() {
+        assert_eq!(
+            stored_payload(Event::PurchaseCompleted {
+                sku: "basic".into(),
+                user: "u1".into()
+            }),
+            Some(json!({ "sku": "basic", "user": "u1" }))
+        );
+        assert_eq!(stored_payload(Event::ShotFired), None);
+    }
+
+    #[tokio::test]
+    async fn query_rollups_reads_back_window() {
+        use sea_orm::{ConnectionTrait, Database, EntityTrait, Schema};
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(db.get_database_backend());
+        db.execute(
+            db.get_database_backend()
+                .build(&schema.create_table_from_entity(rollups::Entity)),
+        )
+        .await
+        .unwrap();
+        let hour = |h| {
+            "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + chrono::Duration::hours(h)
+        };
+        let rows = [
+            (2, "shot_fired", 5.0),
+            (0, "shot_fired", 3.0),
+            (1, "death", 1.0),
+            (3, "shot_fired", 9.0),
+        ]
+        .map(|(h, kind, value)| rollups::ActiveModel {
+            bucket_start: Set(hour(h)),
+            kind: Set(kind.to_string()),
+            value: Set(value),
+        });
+        rollups::Entity::insert_many(rows).exec(&db).await.unwrap();
+
+        let analytics = Analytics::with_max_events(true, Some(db), None, None, 4);
+        let rollups = analytics
+            .query_rollups("shot_fired", hour(0), hour(3))
+            .await
+            .unwrap();
+        assert_eq!(rollups, vec![(hour(0), 3.0), (hour(2), 5.0)]);
+    }
+
+    #[tokio::test]
+    async fn failed_flush_reports_error_and_keeps_events() {
+        use sea_orm::Database;
+
+        // No `analytics_events` table, so the insert fails.
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let analytics = Analytics::with_max_events(true, Some(db), None, None, 4);
+        analytics.dispatch(Event::ShotFired);
+        analytics.dispatch(Event::TargetHit);
+
+        assert!(analytics.flush_to_db().await.is_err());
+        assert!(analytics.last_flush_error().is_some());
+        assert_eq!(analytics.events(), vec![Event::ShotFired, Event::TargetHit]);
+    }
+
+    #[tokio::test]
+    async fn stalled_flush_counts_lost_events() {
+        use sea_orm::Database;
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let analytics = Analytics::with_max_events(true, Some(db), None, None, 3);
+        analytics.dispatch(Event::ShotFired);
+        analytics.dispatch(Event::TargetHit);
+        analytics.dispatch(Event::Death);
+        assert!(analytics.flush_to_db().await.is_err());
+        assert_eq!(analytics.events_lost(), 0);
+
+        // The store is full again; the next arrival evicts the oldest.
+        analytics.dispatch(Event::Respawn);
+        assert_eq!(analytics.events_lost(), 1);
+        assert_eq!(
+            analytics.events(),
+            vec![Event::TargetHit, Event::Death, Event::Respawn]
+        );
+
+        // Events arriving mid-flush leave less room for the requeued ones.
+        let taken = analytics.store.lock().unwrap().take_records();
+        analytics.dispatch(Event::ShotFired);
+        analytics.dispatch(Event::ShotFired);
+        let dropped = analytics.store.lock().unwrap().requeue(taken);
+        assert_eq!(dropped, 2);
+        assert_eq!(
+            analytics.events(),
+            vec![Event::Respawn, Event::ShotFired, Event::ShotFired]
+        );
+    }
+
+    #[tokio::test]
+    async fn query_rollups_without_db_is_empty() {
+        let analytics = Analytics::with_max_events(true, None, None, None, 4);
+        let now = Utc::now();
+        assert!(
+            analytics
+                .query_rollups("death", now, now)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn interval_parsing_falls_back_to_default() {
+        let default = Duration::from_secs(5);
+        assert_eq!(parse_secs(None, default), default);
+        assert_eq!(parse_secs(Some("0".into()), default), default);
+        assert_eq!(parse_secs(Some("soon".into()), default), default);
+        assert_eq!(parse_secs(Some("-3".into()), default), default);
+        assert_eq!(
+            parse_secs(Some(" 30 ".into()), default),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn custom_sink_receives_each_event() {
+        struct CountingSink(Arc<AtomicU64>);
+
+        impl AnalyticsSink for CountingSink {
+            fn record(&self, _: &Event) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let analytics = Analytics::with_max_events(true, None, None, None, 4);
+        let count = Arc::new(AtomicU64::new(0));
+        analytics.add_sink(Box::new(CountingSink(count.clone())));
+        analytics.dispatch(Event::ShotFired);
+        analytics.dispatch(Event::Death);
+        analytics.dispatch(Event::Respawn);
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn events_round_trip_through_json() {
+        let all = [
+            Event::WsConnected,
+            Event::MailTestQueued,
+            Event::PurchaseCompleted {
+                sku: "basic".into(),
+                user: "u1".into(),
+            },
+            Event::EntitlementChecked,
+            Event::RunVerificationFailed,
+            Event::ReplayTrimmed,
+            Event::SessionStart,
+            Event::LevelStart { level: 3 },
+            Event::StoreOpen,
+            Event::Error {
+                message: "boom".into(),
+            },
+            Event::PlayerJoined,
+            Event::PlayerJumped,
+            Event::PlayerDied,
+            Event::ShotFired,
+            Event::TargetHit,
+            Event::DamageTaken,
+            Event::Death,
+            Event::Respawn,
+            Event::LeaderboardSubmit,
+            Event::ItemPurchased,
+            Event::CurrencyEarned,
+            Event::CurrencySpent,
+            Event::FrameDropped,
+            Event::HighLatency,
+            Event::TickOverrun,
+            Event::StoreViewed,
+            Event::PurchaseInitiated,
+            Event::PurchaseSucceeded,
+            Event::EntitlementGranted,
+        ];
+        for event in all {
+            let value = serde_json::to_value(&event).unwrap();
+            assert_eq!(value["event"], event.name());
+            assert_eq!(serde_json::from_value::<Event>(value).unwrap(), event);
+        }
+        assert_eq!(
+            serde_json::to_value(Event::LevelStart { level: 3 }).unwrap(),
+            json!({ "event": "level_start", "level": 3 })
+        );
+    }
+
     #[test]
     fn flush_clears_events() {
         let analytics = Analytics::with_max_events(true, None, None, None, 2);
@@ -493,23 +1074,76 @@ mod tests {
 
     #[cfg(feature = "posthog")]
     #[tokio::test]
-    async fn posthog_sink() {
+    async fn posthog_sink_batches_events() {
         use httpmock::{Method::POST, MockServer};
         use std::time::Duration;
 
         let server = MockServer::start();
         let mock = server.mock(|when, then| {
-            when.method(POST).path("/capture/");
+            when.method(POST)
+                .path("/batch/")
+                .body_contains(r#""api_key":"test_key""#)
+                .body_contains("shot_fired")
+                .body_contains("level_start")
+                .body_contains(r#""level":2"#);
             then.status(200);
         });
 
-        set_var("POSTHOG_ENDPOINT", server.url("/capture/"));
-
-        let analytics = Analytics::new(true, None, Some("test_key".into()), None);
+        let analytics = posthog_analytics(server.url("/batch/"));
         analytics.dispatch(Event::ShotFired);
+        analytics.dispatch(Event::LevelStart { level: 2 });
+        // Dropping the last handle flushes whatever is still queued.
+        drop(analytics);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        mock.assert_hits(1);
+    }
+
+    #[cfg(feature = "posthog")]
+    #[tokio::test]
+    async fn posthog_distinct_id_follows_player() {
+        use httpmock::{Method::POST, MockServer};
+        use std::time::Duration;
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/players/batch/")
+                .body_contains(r#""distinct_id":"player-7""#)
+                .body_contains(r#""distinct_id":"server""#);
+            then.status(200);
+        });
+
+        let analytics = posthog_analytics(server.url("/players/batch/"));
+        analytics.dispatch_with_context(Event::SessionStart, Some("player-7".into()), None);
+        analytics.dispatch(Event::WsConnected);
+        drop(analytics);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        mock.assert_hits(1);
+    }
+
+    #[cfg(feature = "posthog")]
+    #[tokio::test]
+    async fn posthog_failures_count_as_dropped() {
+        use httpmock::{Method::POST, MockServer};
+        use std::time::Duration;
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/failing/batch/");
+            then.status(500);
+        });
+
+        let analytics = posthog_analytics(server.url("/failing/batch/"));
+        // A full batch is sent straight away.
+        for _ in 0..posthog::MAX_BATCH {
+            analytics.dispatch(Event::ShotFired);
+        }
 
-        tokio::time::sleep(Duration::from_millis(50)).await;
-        mock.assert();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        mock.assert_hits(1);
+        assert_eq!(analytics.posthog_dropped(), posthog::MAX_BATCH as u64);
     }
 
     #[cfg(feature = "otlp")]
@@ -0,0 +1,92 @@
+//! Batched delivery of analytics events to PostHog.
+//!
+//! Events are queued and posted to the `/batch/` endpoint every
+//! [`BATCH_INTERVAL`] or once [`MAX_BATCH`] events are waiting, whichever
+//! comes first.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::Utc;
+use reqwest::Client;
+use serde_json::{Value as JsonValue, json};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant, interval_at};
+use uuid::Uuid;
+
+use crate::{AnalyticsSink, Event};
+
+pub(crate) const DEFAULT_ENDPOINT: &str = "https://app.posthog.com/batch/";
+const BATCH_INTERVAL: Duration = Duration::from_secs(2);
+pub(crate) const MAX_BATCH: usize = 100;
+
+#[derive(Clone)]
+pub(crate) struct PosthogSink {
+    tx: mpsc::UnboundedSender<JsonValue>,
+    /// Events lost to failed or non-2xx batch requests.
+    dropped: Arc<AtomicU64>,
+}
+
+impl PosthogSink {
+    /// Start the background batching task. Must be called within a Tokio
+    /// runtime.
+    pub(crate) fn spawn(api_key: String, endpoint: String) -> Self {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let dropped = Arc::new(AtomicU64::new(0));
+        let task_dropped = dropped.clone();
+        tokio::spawn(async move {
+            let client = Client::new();
+            let mut batch = Vec::new();
+            let mut ticker = interval_at(Instant::now() + BATCH_INTERVAL, BATCH_INTERVAL);
+            loop {
+                let closed = tokio::select! {
+                    entry = rx.recv() => match entry {
+                        Some(entry) => {
+                            batch.push(entry);
+                            if batch.len() < MAX_BATCH {
+                                continue;
+                            }
+                            false
+                        }
+                        None => true,
+                    },
+                    _ = ticker.tick() => false,
+                };
+                if !batch.is_empty() {
+                    let len = batch.len() as u64;
+                    let body = json!({ "api_key": api_key, "batch": std::mem::take(&mut batch) });
+                    match client.post(&endpoint).json(&body).send().await {
+                        Ok(resp) if resp.status().is_success() => {}
+                        _ => {
+                            task_dropped.fetch_add(len, Ordering::Relaxed);
+                        }
+                    }
+                }
+                if closed {
+                    break;
+                }
+            }
+        });
+        Self { tx, dropped }
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl AnalyticsSink for PosthogSink {
+    fn record(&self, event: &Event) {
+        self.record_with_context(event, None, None);
+    }
+
+    fn record_with_context(&self, event: &Event, player_id: Option<&str>, _: Option<Uuid>) {
+        // PostHog groups events per identity; unattributed ones share one.
+        let _ = self.tx.send(json!({
+            "event": event.name(),
+            "distinct_id": player_id.unwrap_or("server"),
+            "properties": event.payload().unwrap_or_else(|| json!({})),
+            "timestamp": Utc::now().to_rfc3339(),
+        }));
+    }
+}
@@ -0,0 +1,70 @@
+//! Destinations that dispatched events fan out to.
+//!
+//! The prometheus, OTLP and PostHog integrations are [`AnalyticsSink`]s
+//! registered by the constructors; embedders add their own with
+//! [`Analytics::add_sink`](crate::Analytics::add_sink).
+
+use uuid::Uuid;
+
+use crate::Event;
+
+#[cfg(feature = "otlp")]
+use opentelemetry::{KeyValue, metrics::Counter};
+#[cfg(feature = "prometheus")]
+use prometheus::IntCounterVec;
+#[cfg(feature = "otlp")]
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+pub trait AnalyticsSink: Send + Sync {
+    fn record(&self, event: &Event);
+
+    /// Like [`record`](Self::record), with the player and session the event
+    /// was dispatched for. Sinks that don't track identity can ignore this.
+    fn record_with_context(
+        &self,
+        event: &Event,
+        player_id: Option<&str>,
+        session_id: Option<Uuid>,
+    ) {
+        let _ = (player_id, session_id);
+        self.record(event);
+    }
+}
+
+impl<F> AnalyticsSink for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn record(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Counts events per name in the `analytics_events_total` metric.
+#[cfg(feature = "prometheus")]
+pub(crate) struct PrometheusSink(pub(crate) IntCounterVec);
+
+#[cfg(feature = "prometheus")]
+impl AnalyticsSink for PrometheusSink {
+    fn record(&self, event: &Event) {
+        self.0.with_label_values(&[event.name()]).inc();
+    }
+}
+
+/// Adds each event to the OTLP `analytics_events` counter.
+#[cfg(feature = "otlp")]
+pub(crate) struct OtlpSink {
+    pub(crate) counter: Counter<u64>,
+    pub(crate) calls: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "otlp")]
+impl AnalyticsSink for OtlpSink {
+    fn record(&self, event: &Event) {
+        self.counter.add(1, &[KeyValue::new("event", event.name())]);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+    }
+}
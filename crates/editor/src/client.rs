@@ -1,4 +1,11 @@
-use crate::level::Level;
+use crate::{
+    csg::build_csg,
+    level::{Level, PathDef},
+    navmesh::{NavMesh, bake_navmesh},
+};
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, io::ErrorKind};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::{JsCast, prelude::*};
@@ -10,6 +17,13 @@ use web_sys::{
     FileSystemWritableFileStream, IdbDatabase, IdbTransactionMode, StorageManager,
 };
 
+/// Default for [`EditorClient::spline_samples`].
+pub const DEFAULT_SPLINE_SAMPLES: usize = 8;
+/// Default for [`EditorClient::max_history`].
+pub const DEFAULT_MAX_HISTORY: usize = 50;
+/// Default for [`EditorClient::local_dir`].
+pub const DEFAULT_LOCAL_DIR: &str = "editor-levels";
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum EditorMode {
     FirstPerson,
@@ -27,10 +41,13 @@ pub struct EditorClient {
     pub mode: EditorMode,
     /// Whether transform gizmos are visible.
     pub gizmos: bool,
-    /// Optional grid snapping increment.
-    pub grid_snap: Option<f32>,
+    /// Optional grid snapping increments along x, y and z. A step of zero
+    /// leaves that axis unsnapped.
+    pub grid_snap: Option<[f32; 3]>,
     /// Control points for the in-progress spline tool.
     pub spline: Vec<[f32; 3]>,
+    /// Points sampled per spline segment when baking.
+    pub spline_samples: usize,
     /// Points defining editor volumes.
     pub volumes: Vec<[f32; 3]>,
     /// Whether the baked navigation mesh is visible.
@@ -39,6 +56,16 @@ pub struct EditorClient {
     pub undo_stack: Vec<Level>,
     /// History stack for redo operations.
     pub redo_stack: Vec<Level>,
+    /// Most undo states kept; the oldest is dropped once exceeded.
+    pub max_history: usize,
+    /// Directory levels are stored in locally on native targets.
+    pub local_dir: PathBuf,
+}
+
+impl Default for EditorClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EditorClient {
@@ -49,10 +76,13 @@ impl EditorClient {
             gizmos: true,
             grid_snap: None,
             spline: Vec::new(),
+            spline_samples: DEFAULT_SPLINE_SAMPLES,
             volumes: Vec::new(),
             navmesh_visible: false,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            max_history: DEFAULT_MAX_HISTORY,
+            local_dir: PathBuf::from(DEFAULT_LOCAL_DIR),
         }
     }
 
@@ -65,17 +95,29 @@ impl EditorClient {
         self.gizmos = !self.gizmos;
     }
 
-    /// Enable grid snapping with the provided step size or disable it with `None`.
+    /// Enable grid snapping with the same step on every axis or disable it with
+    /// `None`.
     pub fn set_grid_snap(&mut self, step: Option<f32>) {
-        self.grid_snap = step;
+        self.grid_snap = step.map(|s| [s; 3]);
+    }
+
+    /// Enable grid snapping with separate x, y and z steps or disable it with
+    /// `None`.
+    pub fn set_grid_snap_axes(&mut self, steps: Option<[f32; 3]>) {
+        self.grid_snap = steps;
     }
 
     /// Apply grid snapping to the provided coordinate if enabled.
+    #[deprecated(note = "use `snap_point`, which honours per-axis steps")]
     pub fn snap_value(&self, v: f32) -> f32 {
-        if let Some(step) = self.grid_snap {
-            (v / step).round() * step
-        } else {
-            v
+        snap(v, self.grid_snap.map_or(0.0, |steps| steps[0]))
+    }
+
+    /// Snap each component of `point` to its axis' grid step, if enabled.
+    pub fn snap_point(&self, point: [f32; 3]) -> [f32; 3] {
+        match self.grid_snap {
+            Some(steps) => [0, 1, 2].map(|i| snap(point[i], steps[i])),
+            None => point,
         }
     }
 
@@ -89,6 +131,25 @@ impl EditorClient {
         self.spline.clear();
     }
 
+    /// Sample the active spline into a [`PathDef`] on the level and clear it.
+    ///
+    /// The path is a Catmull-Rom curve through every control point with
+    /// [`Self::spline_samples`] points per segment. Returns `false`, leaving
+    /// the spline in place, if it has fewer than two points.
+    pub fn bake_spline(&mut self, level: &mut Level) -> bool {
+        if self.spline.len() < 2 {
+            return false;
+        }
+        self.snapshot(level);
+        let points = sample_catmull_rom(&self.spline, self.spline_samples.max(1));
+        level.paths.push(PathDef {
+            name: format!("path_{}", level.paths.len()),
+            points,
+        });
+        self.clear_spline();
+        true
+    }
+
     /// Add a point to the active volume being edited.
     pub fn add_volume_point(&mut self, point: [f32; 3]) {
         self.volumes.push(point);
@@ -104,12 +165,44 @@ impl EditorClient {
         self.navmesh_visible = !self.navmesh_visible;
     }
 
+    /// Rebuild the level's triangle mesh from its CSG brushes. On error the
+    /// level is left unchanged.
+    pub fn rebuild_csg(&mut self, level: &mut Level) -> anyhow::Result<()> {
+        let mesh = build_csg(level)?;
+        self.snapshot(level);
+        level.mesh = Some(mesh);
+        Ok(())
+    }
+
+    /// Rebake the level's navigation mesh from its brushes and show it.
+    pub fn bake_navmesh(&mut self, level: &mut Level) {
+        self.snapshot(level);
+        level.navmesh = Some(bake_navmesh(level));
+        self.navmesh_visible = true;
+    }
+
+    /// The navigation mesh to draw, if the overlay is on and one is baked.
+    pub fn visible_navmesh<'a>(&self, level: &'a Level) -> Option<&'a NavMesh> {
+        level.navmesh.as_ref().filter(|_| self.navmesh_visible)
+    }
+
     /// Capture the current level state for undo operations.
     pub fn snapshot(&mut self, level: &Level) {
-        self.undo_stack.push(level.clone());
+        self.push_undo(level.clone());
         self.redo_stack.clear();
     }
 
+    /// Number of states available to undo and redo, respectively.
+    pub fn history_depth(&self) -> (usize, usize) {
+        (self.undo_stack.len(), self.redo_stack.len())
+    }
+
+    fn push_undo(&mut self, level: Level) {
+        self.undo_stack.push(level);
+        let excess = self.undo_stack.len().saturating_sub(self.max_history);
+        self.undo_stack.drain(..excess);
+    }
+
     /// Undo to the previous captured level state.
     pub fn undo(&mut self, level: &mut Level) -> bool {
         if let Some(prev) = self.undo_stack.pop() {
@@ -124,7 +217,7 @@ impl EditorClient {
     /// Redo the last undone level state.
     pub fn redo(&mut self, level: &mut Level) -> bool {
         if let Some(next) = self.redo_stack.pop() {
-            self.undo_stack.push(level.clone());
+            self.push_undo(level.clone());
             *level = next;
             true
         } else {
@@ -132,7 +225,8 @@ impl EditorClient {
         }
     }
 
-    /// Persist the level locally using OPFS or IndexedDB.
+    /// Persist the level locally using OPFS or IndexedDB, or as a JSON file in
+    /// [`Self::local_dir`] on native targets.
     pub async fn store_level_locally(&self, level: &Level) -> Result<(), String> {
         #[cfg(target_arch = "wasm32")]
         {
@@ -143,8 +237,9 @@ impl EditorClient {
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let _ = level;
-            Ok(())
+            let data = serde_json::to_string(level).map_err(|e| e.to_string())?;
+            fs::create_dir_all(&self.local_dir).map_err(|e| e.to_string())?;
+            fs::write(self.local_path(&level.id), data).map_err(|e| e.to_string())
         }
     }
 
@@ -162,10 +257,51 @@ impl EditorClient {
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let _ = id;
-            Ok(None)
+            match fs::read_to_string(self.local_path(id)) {
+                Ok(data) => serde_json::from_str(&data)
+                    .map(Some)
+                    .map_err(|e| e.to_string()),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn local_path(&self, id: &str) -> PathBuf {
+        self.local_dir.join(format!("{id}.json"))
+    }
+}
+
+fn snap(v: f32, step: f32) -> f32 {
+    if step > 0.0 {
+        (v / step).round() * step
+    } else {
+        v
+    }
+}
+
+fn sample_catmull_rom(points: &[[f32; 3]], samples: usize) -> Vec<[f32; 3]> {
+    let last = points.len() - 1;
+    let mut out = Vec::with_capacity(last * samples + 1);
+    for k in 0..last {
+        // End segments reuse their endpoint as the missing neighbour.
+        let p0 = points[k.saturating_sub(1)];
+        let (p1, p2) = (points[k], points[k + 1]);
+        let p3 = points[(k + 2).min(last)];
+        for s in 0..samples {
+            let t = s as f32 / samples as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            out.push([0, 1, 2].map(|i| {
+                0.5 * (2.0 * p1[i]
+                    + (p2[i] - p0[i]) * t
+                    + (2.0 * p0[i] - 5.0 * p1[i] + 4.0 * p2[i] - p3[i]) * t2
+                    + (3.0 * p1[i] - p0[i] - 3.0 * p2[i] + p3[i]) * t3)
+            }));
         }
     }
+    out.push(points[last]);
+    out
 }
 
 // --- wasm helpers ---
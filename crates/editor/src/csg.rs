@@ -0,0 +1,202 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+use crate::level::{BrushShape, CsgOp, Level};
+
+/// Number of sides used to approximate cylinder brushes.
+pub const CYLINDER_SEGMENTS: u32 = 16;
+
+/// Triangle mesh with counter-clockwise, outward-facing winding.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// Enclosed volume, assuming the mesh is closed.
+    pub fn volume(&self) -> f32 {
+        self.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| self.vertices[i as usize]);
+                dot(a, cross(b, c)) / 6.0
+            })
+            .sum()
+    }
+
+    /// Axis-aligned bounds as `(min, max)`, or `None` for an empty mesh.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = *self.vertices.first()?;
+        Some(self.vertices.iter().fold((first, first), |(lo, hi), v| {
+            (
+                [0, 1, 2].map(|i| lo[i].min(v[i])),
+                [0, 1, 2].map(|i| hi[i].max(v[i])),
+            )
+        }))
+    }
+
+    fn append(&mut self, vertices: &[[f32; 3]], triangles: impl IntoIterator<Item = [u32; 3]>) {
+        let offset = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(vertices);
+        self.triangles
+            .extend(triangles.into_iter().map(|t| t.map(|i| i + offset)));
+    }
+}
+
+type Aabb = ([f32; 3], [f32; 3]);
+
+/// Apply the level's brushes in order and mesh the resulting solid.
+///
+/// Box brushes support every [`CsgOp`]. Cylinders can only be added; carving
+/// or intersecting a cylinder is rejected rather than approximated.
+pub fn build_csg(level: &Level) -> Result<Mesh> {
+    let mut boxes: Vec<Aabb> = Vec::new();
+    let mut cylinders: Vec<BrushShape> = Vec::new();
+    for (i, brush) in level.brushes.iter().enumerate() {
+        let Some(shape) = brush.shape else {
+            continue;
+        };
+        let overlaps_cylinder =
+            |b: Aabb| cylinders.iter().any(|c| overlap(c.bounds(), b).is_some());
+        match (brush.op, shape) {
+            (CsgOp::Add, BrushShape::Box { min, max }) => boxes = union(&boxes, (min, max)),
+            (CsgOp::Add, cylinder @ BrushShape::Cylinder { .. }) => cylinders.push(cylinder),
+            (CsgOp::Subtract, BrushShape::Box { min, max }) if !overlaps_cylinder((min, max)) => {
+                boxes = subtract(&boxes, (min, max))
+            }
+            (CsgOp::Intersect, BrushShape::Box { min, max }) if cylinders.is_empty() => {
+                boxes = intersect(&boxes, (min, max))
+            }
+            (op, _) => bail!("brush {i}: {op:?} is not supported on cylinders"),
+        }
+    }
+
+    let mut mesh = Mesh::default();
+    for b in boxes {
+        append_box(&mut mesh, b);
+    }
+    for c in cylinders {
+        append_cylinder(&mut mesh, c);
+    }
+    Ok(mesh)
+}
+
+/// Overlap of two boxes, if it has positive volume.
+fn overlap(a: Aabb, b: Aabb) -> Option<Aabb> {
+    let min = [0, 1, 2].map(|i| a.0[i].max(b.0[i]));
+    let max = [0, 1, 2].map(|i| a.1[i].min(b.1[i]));
+    (0..3).all(|i| min[i] < max[i]).then_some((min, max))
+}
+
+/// Split `a` into at most six disjoint boxes covering `a` minus `b`.
+fn subtract_box(a: Aabb, b: Aabb) -> Vec<Aabb> {
+    let Some(cut) = overlap(a, b) else {
+        return vec![a];
+    };
+    let mut pieces = Vec::new();
+    let mut rest = a;
+    for axis in 0..3 {
+        if rest.0[axis] < cut.0[axis] {
+            let mut below = rest;
+            below.1[axis] = cut.0[axis];
+            pieces.push(below);
+        }
+        if cut.1[axis] < rest.1[axis] {
+            let mut above = rest;
+            above.0[axis] = cut.1[axis];
+            pieces.push(above);
+        }
+        rest.0[axis] = cut.0[axis];
+        rest.1[axis] = cut.1[axis];
+    }
+    pieces
+}
+
+fn subtract(solid: &[Aabb], b: Aabb) -> Vec<Aabb> {
+    solid.iter().flat_map(|&a| subtract_box(a, b)).collect()
+}
+
+fn intersect(solid: &[Aabb], b: Aabb) -> Vec<Aabb> {
+    solid.iter().filter_map(|&a| overlap(a, b)).collect()
+}
+
+/// Add `b` while keeping the boxes disjoint, so volumes add up.
+fn union(solid: &[Aabb], b: Aabb) -> Vec<Aabb> {
+    let mut pieces = vec![b];
+    for &a in solid {
+        pieces = subtract(&pieces, a);
+    }
+    solid.iter().copied().chain(pieces).collect()
+}
+
+fn append_box(mesh: &mut Mesh, (min, max): Aabb) {
+    // Corner `i` takes max on axis `k` when bit `k` of `i` is set.
+    let corners: Vec<[f32; 3]> = (0..8)
+        .map(|i| [0, 1, 2].map(|k| if i >> k & 1 == 1 { max[k] } else { min[k] }))
+        .collect();
+    let faces = [
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+    ];
+    mesh.append(
+        &corners,
+        faces
+            .into_iter()
+            .flat_map(|[a, b, c, d]| [[a, b, c], [a, c, d]]),
+    );
+}
+
+fn append_cylinder(mesh: &mut Mesh, cylinder: BrushShape) {
+    let BrushShape::Cylinder {
+        base,
+        radius,
+        height,
+    } = cylinder
+    else {
+        return;
+    };
+    let n = CYLINDER_SEGMENTS;
+    // Bottom ring, top ring, then the two cap centres.
+    let mut vertices = Vec::with_capacity(2 * n as usize + 2);
+    for y in [base[1], base[1] + height] {
+        for i in 0..n {
+            let angle = TAU * i as f32 / n as f32;
+            vertices.push([
+                base[0] + radius * angle.cos(),
+                y,
+                base[2] + radius * angle.sin(),
+            ]);
+        }
+    }
+    vertices.push(base);
+    vertices.push([base[0], base[1] + height, base[2]]);
+    let (bottom, top) = (2 * n, 2 * n + 1);
+    let triangles = (0..n).flat_map(|i| {
+        let j = (i + 1) % n;
+        [
+            [i, n + j, j],
+            [i, n + i, n + j],
+            [bottom, i, j],
+            [top, n + j, n + i],
+        ]
+    });
+    mesh.append(&vertices, triangles);
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
@@ -1,7 +1,9 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use bevy_ecs::prelude::Resource;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+
+use crate::{csg::Mesh, navmesh::NavMesh};
 use std::path::Path;
 use std::{cmp::Ordering, fs};
 
@@ -18,6 +20,9 @@ pub struct Level {
     /// Areas where players can spawn when the level loads.
     #[serde(default)]
     pub spawn_zones: Vec<SpawnZone>,
+    /// Number of teams the level is built for, numbered from zero.
+    #[serde(default)]
+    pub teams: u32,
     /// Estimated number of entities the level will spawn at runtime.
     #[serde(default)]
     pub entity_count: usize,
@@ -30,6 +35,15 @@ pub struct Level {
     /// Surfaces that block visibility and are used for occlusion.
     #[serde(default)]
     pub occluders: Vec<Occluder>,
+    /// Walkable areas baked from the brushes by [`crate::bake_navmesh`].
+    #[serde(default)]
+    pub navmesh: Option<NavMesh>,
+    /// Triangle mesh built from the brushes by [`crate::build_csg`].
+    #[serde(default)]
+    pub mesh: Option<Mesh>,
+    /// Named paths baked from the spline tool, e.g. duck flight paths.
+    #[serde(default)]
+    pub paths: Vec<PathDef>,
 }
 
 impl Level {
@@ -40,10 +54,14 @@ impl Level {
             references: Vec::new(),
             brushes: Vec::new(),
             spawn_zones: Vec::new(),
+            teams: 0,
             entity_count: 0,
             assets: Vec::new(),
             portals: Vec::new(),
             occluders: Vec::new(),
+            navmesh: None,
+            mesh: None,
+            paths: Vec::new(),
         }
     }
 
@@ -69,6 +87,14 @@ impl Level {
         self.occluders.push(occ);
     }
 
+    /// First spawn zone reserved for `team`, falling back to a shared zone.
+    pub fn spawn_for(&self, team: u32) -> Option<&SpawnZone> {
+        self.spawn_zones
+            .iter()
+            .find(|z| z.team == Some(team))
+            .or_else(|| self.spawn_zones.iter().find(|z| z.team.is_none()))
+    }
+
     /// Register an asset by its original name and hashed identifier.
     pub fn add_asset(&mut self, name: impl Into<String>, hash: impl Into<String>) {
         self.assets.push(HashedAsset {
@@ -84,6 +110,16 @@ pub struct SpawnZone {
     pub x: f32,
     pub y: f32,
     pub radius: f32,
+    /// Team served by this zone; `None` means any team may use it.
+    #[serde(default)]
+    pub team: Option<u32>,
+}
+
+/// Polyline sampled from an editor spline.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PathDef {
+    pub name: String,
+    pub points: Vec<[f32; 3]>,
 }
 
 /// Describes a basic CSG brush with an operation and optional UV coordinates.
@@ -92,13 +128,89 @@ pub struct Brush {
     pub op: CsgOp,
     #[serde(default)]
     pub uv: Option<Uv>,
+    /// Solid the brush adds or removes; brushes without one have no geometry.
+    #[serde(default)]
+    pub shape: Option<BrushShape>,
+}
+
+impl Brush {
+    /// An axis-aligned box brush spanning `min` to `max`.
+    pub fn cuboid(op: CsgOp, min: [f32; 3], max: [f32; 3]) -> Self {
+        Self {
+            op,
+            uv: None,
+            shape: Some(BrushShape::Box { min, max }),
+        }
+    }
+
+    /// An upright cylinder brush standing on `base`.
+    pub fn cylinder(op: CsgOp, base: [f32; 3], radius: f32, height: f32) -> Self {
+        Self {
+            op,
+            uv: None,
+            shape: Some(BrushShape::Cylinder {
+                base,
+                radius,
+                height,
+            }),
+        }
+    }
+}
+
+/// Brush primitives, in world units with `y` up.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum BrushShape {
+    Box {
+        min: [f32; 3],
+        max: [f32; 3],
+    },
+    /// Cylinder along `y` whose bottom cap is centred on `base`.
+    Cylinder {
+        base: [f32; 3],
+        radius: f32,
+        height: f32,
+    },
+}
+
+impl BrushShape {
+    /// Axis-aligned bounding box as `(min, max)`.
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        match *self {
+            Self::Box { min, max } => (min, max),
+            Self::Cylinder {
+                base,
+                radius,
+                height,
+            } => (
+                [base[0] - radius, base[1], base[2] - radius],
+                [base[0] + radius, base[1] + height, base[2] + radius],
+            ),
+        }
+    }
+
+    /// Whether `p` lies inside the solid, boundaries included.
+    pub fn contains(&self, p: [f32; 3]) -> bool {
+        match self {
+            Self::Box { min, max } => (0..3).all(|i| p[i] >= min[i] && p[i] <= max[i]),
+            Self::Cylinder {
+                base,
+                radius,
+                height,
+            } => {
+                let (dx, dz) = (p[0] - base[0], p[2] - base[2]);
+                p[1] >= base[1] && p[1] <= base[1] + height && dx * dx + dz * dz <= radius * radius
+            }
+        }
+    }
 }
 
 /// Boolean operation applied by a brush.
-#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CsgOp {
     Add,
     Subtract,
+    /// Keep only the solid inside the brush.
+    Intersect,
 }
 
 /// Simplified UV parameters assigned during texturing.
@@ -125,13 +237,17 @@ pub struct HashedAsset {
     pub hash: String,
 }
 
-/// Persist the level to the assets directory.
-pub fn export_level(level: &Level) -> Result<()> {
+/// Format version stamped into exported levels. Version 1 is the unversioned
+/// format written before the field existed; it has the same fields.
+pub const LEVEL_FORMAT_VERSION: u32 = 2;
+
+/// Serialize the level to deterministic TOML tagged with [`LEVEL_FORMAT_VERSION`].
+pub fn serialize_level(level: &Level) -> Result<String> {
     let mut lvl = level.clone();
     lvl.references.sort();
     lvl.assets.sort_by(|a, b| a.name.cmp(&b.name));
     lvl.brushes
-        .sort_by(|a, b| format!("{:?}{:?}", a.op, a.uv).cmp(&format!("{:?}{:?}", b.op, b.uv)));
+        .sort_by_cached_key(|b| format!("{:?}{:?}{:?}", b.op, b.uv, b.shape));
     lvl.spawn_zones.sort_by(|a, b| {
         a.x.partial_cmp(&b.x)
             .unwrap_or(Ordering::Equal)
@@ -139,14 +255,52 @@ pub fn export_level(level: &Level) -> Result<()> {
     });
     lvl.portals.sort_by(|a, b| a.id.cmp(&b.id));
     lvl.occluders.sort_by(|a, b| a.id.cmp(&b.id));
-    let dir = Path::new("assets").join("levels").join(&lvl.id);
+    lvl.paths.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut table = toml::Table::try_from(&lvl)?;
+    table.insert(
+        "version".into(),
+        toml::Value::Integer(LEVEL_FORMAT_VERSION.into()),
+    );
+    Ok(toml::to_string_pretty(&table)?)
+}
+
+/// Parse a level written by [`serialize_level`], migrating older versions.
+pub fn parse_level(toml: &str) -> Result<Level> {
+    let mut table: toml::Table = toml.parse()?;
+    let version = match table.remove("version") {
+        None => 1,
+        Some(toml::Value::Integer(v)) => v,
+        Some(other) => bail!("level format version must be an integer, got {other}"),
+    };
+    match version {
+        // Version 1 only lacked the version field.
+        1 | 2 => Ok(table.try_into()?),
+        v if v > i64::from(LEVEL_FORMAT_VERSION) => bail!(
+            "level format version {v} is newer than the supported version {LEVEL_FORMAT_VERSION}"
+        ),
+        v => bail!("unknown level format version {v}"),
+    }
+}
+
+/// Persist the level to the assets directory.
+pub fn export_level(level: &Level) -> Result<()> {
+    export_level_in(level, Path::new("assets"))
+}
+
+/// Persist the level to `levels/<id>/level.toml` under `assets`.
+pub fn export_level_in(level: &Level, assets: &Path) -> Result<()> {
+    let dir = assets.join("levels").join(&level.id);
     fs::create_dir_all(&dir)?;
     let path = dir.join("level.toml");
-    let toml = toml::to_string_pretty(&lvl)?;
-    fs::write(path, toml)?;
+    fs::write(path, serialize_level(level)?)?;
     Ok(())
 }
 
+/// Load a level previously written by [`export_level`].
+pub fn import_level(path: &Path) -> Result<Level> {
+    parse_level(&fs::read_to_string(path)?)
+}
+
 /// Export an additional binary referenced by the level.
 pub fn export_binary(level_id: &str, _name: &str, data: &[u8]) -> Result<String> {
     let dir = Path::new("assets").join("levels").join(level_id);
@@ -1,13 +1,21 @@
 pub mod client;
+pub mod csg;
 pub mod level;
+pub mod navmesh;
 pub mod server;
 
-pub use client::{EditorClient, EditorMode};
+pub use client::{
+    DEFAULT_LOCAL_DIR, DEFAULT_MAX_HISTORY, DEFAULT_SPLINE_SAMPLES, EditorClient, EditorMode,
+};
+pub use csg::{Mesh, build_csg};
 pub use level::{
-    Brush, CsgOp, HashedAsset, Level, Occluder, Portal, SpawnZone, Uv, export_binary,
-    export_level,
+    Brush, BrushShape, CsgOp, HashedAsset, LEVEL_FORMAT_VERSION, Level, Occluder, PathDef, Portal,
+    SpawnZone, Uv, export_binary, export_level, export_level_in, import_level, parse_level,
+    serialize_level,
 };
+pub use navmesh::{NavCell, NavMesh, bake_navmesh};
 pub use server::{
-    AssetRegistry, EditorServer, EditorSession, play_in_editor, stop_play_in_editor,
-    validate_gameplay, validate_level, validate_performance, validate_structural,
+    AssetRegistry, EditorServer, EditorSession, IssueLocation, PlaySnapshotRegistry, Severity,
+    ValidationIssue, play_in_editor, stop_play_in_editor, validate_gameplay, validate_level,
+    validate_performance, validate_structural,
 };
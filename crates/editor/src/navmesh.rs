@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+use crate::level::{BrushShape, CsgOp, Level};
+
+/// Edge length of a navigation cell in world units.
+pub const NAVMESH_CELL_SIZE: f32 = 1.0;
+/// Largest height difference between neighbouring cells an agent can step.
+pub const NAVMESH_MAX_STEP: f32 = 0.5;
+/// Clearance an agent needs above a walkable surface.
+pub const NAVMESH_AGENT_HEIGHT: f32 = 2.0;
+
+/// Offset used to probe just above or below a surface.
+const EPSILON: f32 = 1e-3;
+
+/// Grid-based navigation mesh over the level's walkable surfaces.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct NavMesh {
+    pub cell_size: f32,
+    pub cells: Vec<NavCell>,
+}
+
+/// A walkable square whose centre is `((x + 0.5) * size, height, (z + 0.5) * size)`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct NavCell {
+    pub x: i32,
+    pub z: i32,
+    pub height: f32,
+    /// Cells sharing a region are reachable from each other.
+    pub region: u32,
+}
+
+impl NavMesh {
+    /// Walkable area covered by the mesh.
+    pub fn area(&self) -> f32 {
+        self.cells.len() as f32 * self.cell_size * self.cell_size
+    }
+
+    /// Number of disconnected walkable regions.
+    pub fn region_count(&self) -> usize {
+        self.cells
+            .iter()
+            .map(|c| c.region as usize + 1)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Bake a navigation mesh from the level's box brushes.
+///
+/// Space is solid where an additive brush covers it and no subtractive brush
+/// does. Each grid column keeps its highest surface that has solid below and
+/// [`NAVMESH_AGENT_HEIGHT`] of clearance above. Brushes are treated as
+/// axis-aligned, so the result is exact for axis-aligned geometry.
+pub fn bake_navmesh(level: &Level) -> NavMesh {
+    let shapes = |op: CsgOp| -> Vec<BrushShape> {
+        level
+            .brushes
+            .iter()
+            .filter(|b| b.op == op)
+            .filter_map(|b| b.shape)
+            .collect()
+    };
+    let added = shapes(CsgOp::Add);
+    let removed = shapes(CsgOp::Subtract);
+    let solid =
+        |p: [f32; 3]| added.iter().any(|s| s.contains(p)) && !removed.iter().any(|s| s.contains(p));
+
+    // Solid space can only start (floors) or end (ceilings) at brush faces.
+    let mut floors = Vec::new();
+    let mut ceilings = Vec::new();
+    for (min, max) in added.iter().map(BrushShape::bounds) {
+        floors.push(max[1]);
+        ceilings.push(min[1]);
+    }
+    for (min, max) in removed.iter().map(BrushShape::bounds) {
+        floors.push(min[1]);
+        ceilings.push(max[1]);
+    }
+    floors.sort_by(|a, b| b.total_cmp(a));
+    floors.dedup();
+    let walkable = |cx: f32, h: f32, cz: f32| {
+        solid([cx, h - EPSILON, cz])
+            && !solid([cx, h + EPSILON, cz])
+            && !ceilings
+                .iter()
+                .any(|&c| c > h && c < h + NAVMESH_AGENT_HEIGHT && solid([cx, c + EPSILON, cz]))
+    };
+
+    let size = NAVMESH_CELL_SIZE;
+    let (mut lo, mut hi) = ([i32::MAX; 2], [i32::MIN; 2]);
+    for (min, max) in added.iter().map(BrushShape::bounds) {
+        lo = [lo[0].min(cell(min[0], size)), lo[1].min(cell(min[2], size))];
+        hi = [hi[0].max(cell(max[0], size)), hi[1].max(cell(max[2], size))];
+    }
+
+    let mut cells = Vec::new();
+    for x in lo[0]..=hi[0] {
+        for z in lo[1]..=hi[1] {
+            let (cx, cz) = ((x as f32 + 0.5) * size, (z as f32 + 0.5) * size);
+            let floor = floors.iter().copied().find(|&h| walkable(cx, h, cz));
+            if let Some(height) = floor {
+                cells.push(NavCell {
+                    x,
+                    z,
+                    height,
+                    region: 0,
+                });
+            }
+        }
+    }
+    assign_regions(&mut cells);
+    NavMesh {
+        cell_size: size,
+        cells,
+    }
+}
+
+fn cell(v: f32, size: f32) -> i32 {
+    (v / size).floor() as i32
+}
+
+/// Flood-fill 4-connected cells whose heights differ by at most
+/// [`NAVMESH_MAX_STEP`] into numbered regions.
+fn assign_regions(cells: &mut [NavCell]) {
+    let index: HashMap<(i32, i32), usize> = cells
+        .iter()
+        .enumerate()
+        .map(|(i, c)| ((c.x, c.z), i))
+        .collect();
+    let mut assigned = vec![false; cells.len()];
+    let mut next = 0;
+    for start in 0..cells.len() {
+        if assigned[start] {
+            continue;
+        }
+        assigned[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            cells[i].region = next;
+            let NavCell { x, z, height, .. } = cells[i];
+            for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let Some(&j) = index.get(&(x + dx, z + dz)) else {
+                    continue;
+                };
+                if !assigned[j] && (cells[j].height - height).abs() <= NAVMESH_MAX_STEP {
+                    assigned[j] = true;
+                    queue.push_back(j);
+                }
+            }
+        }
+        next += 1;
+    }
+}
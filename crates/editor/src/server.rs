@@ -1,4 +1,4 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{Result, bail};
 use bevy_app::{AppExit, MainScheduleOrder};
 use bevy_ecs::prelude::Resource;
 use bevy_ecs::{prelude::*, schedule::Schedules};
@@ -12,79 +12,260 @@ pub struct EditorServer;
 /// Tracking a running editor play session.
 pub struct EditorSession {
     pub app: ServerApp,
+    /// The level as authored when play started.
+    pub snapshot: Level,
+    /// The editor world as it was when play started.
+    world_snapshot: WorldSnapshot,
+}
+
+/// Puts one captured component or resource type back into a world.
+type Restore = Box<dyn FnOnce(&mut World)>;
+
+/// Component and resource types whose editor state is restored when play
+/// stops. Worlds can't be cloned wholesale, so only registered types survive
+/// a playtest unchanged; entities themselves are always restored.
+#[derive(Resource, Default)]
+pub struct PlaySnapshotRegistry {
+    captures: Vec<fn(&mut World) -> Restore>,
+}
+
+impl PlaySnapshotRegistry {
+    /// Restore every `C` to its value when play started.
+    pub fn register_component<C: Component + Clone>(&mut self) -> &mut Self {
+        self.captures.push(capture_component::<C>);
+        self
+    }
+
+    /// Restore resource `R` to its value when play started, or remove it if
+    /// it didn't exist yet.
+    pub fn register_resource<R: Resource + Clone>(&mut self) -> &mut Self {
+        self.captures.push(capture_resource::<R>);
+        self
+    }
+}
+
+fn capture_component<C: Component + Clone>(world: &mut World) -> Restore {
+    let saved: Vec<(Entity, C)> = world
+        .query::<(Entity, &C)>()
+        .iter(world)
+        .map(|(entity, component)| (entity, component.clone()))
+        .collect();
+    Box::new(move |world| {
+        let current: Vec<Entity> = world
+            .query_filtered::<Entity, With<C>>()
+            .iter(world)
+            .collect();
+        for entity in current {
+            world.entity_mut(entity).remove::<C>();
+        }
+        for (entity, component) in saved {
+            if let Some(mut entity) = world.get_entity_mut(entity) {
+                entity.insert(component);
+            }
+        }
+    })
+}
+
+fn capture_resource<R: Resource + Clone>(world: &mut World) -> Restore {
+    let saved = world.get_resource::<R>().cloned();
+    Box::new(move |world| match saved {
+        Some(resource) => world.insert_resource(resource),
+        None => {
+            world.remove_resource::<R>();
+        }
+    })
+}
+
+/// The editor world's entities and registered state when play started.
+struct WorldSnapshot {
+    entities: Vec<Entity>,
+    restores: Vec<Restore>,
+}
+
+impl WorldSnapshot {
+    fn capture(world: &mut World) -> Self {
+        let captures = world
+            .get_resource::<PlaySnapshotRegistry>()
+            .map(|registry| registry.captures.clone())
+            .unwrap_or_default();
+        Self {
+            entities: world.iter_entities().map(|entity| entity.id()).collect(),
+            restores: captures.into_iter().map(|capture| capture(world)).collect(),
+        }
+    }
+
+    /// Despawn entities spawned during play, respawn those despawned with
+    /// their old ids, then put registered state back.
+    fn restore(self, world: &mut World) {
+        let kept: HashSet<Entity> = self.entities.iter().copied().collect();
+        let spawned: Vec<Entity> = world
+            .iter_entities()
+            .map(|entity| entity.id())
+            .filter(|entity| !kept.contains(entity))
+            .collect();
+        for entity in spawned {
+            world.despawn(entity);
+        }
+        for entity in self.entities {
+            world.get_or_spawn(entity);
+        }
+        for restore in self.restores {
+            restore(world);
+        }
+    }
 }
 
 /// Simple registry of asset identifiers available to the editor.
 #[derive(Resource, Default)]
 pub struct AssetRegistry(pub HashSet<String>);
 
+/// How serious a [`ValidationIssue`] is. Errors block play-in-editor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Part of the level a [`ValidationIssue`] refers to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IssueLocation {
+    /// Index into [`Level::spawn_zones`].
+    SpawnZone(usize),
+    /// Index into [`Level::paths`].
+    Path(usize),
+    /// A world-space coordinate.
+    Point([f32; 3]),
+}
+
+/// A single diagnostic produced by level validation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<IssueLocation>,
+}
+
+impl ValidationIssue {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    pub fn at(mut self, location: IssueLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
 /// Perform structural validation on the level definition.
-pub fn validate_structural(level: &Level) -> Result<()> {
+pub fn validate_structural(level: &Level) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
     if level.id.trim().is_empty() {
-        bail!("level id cannot be empty");
+        issues.push(ValidationIssue::error("level id cannot be empty"));
     }
     if level.name.trim().is_empty() {
-        bail!("level name cannot be empty");
+        issues.push(ValidationIssue::error("level name cannot be empty"));
     }
     if level.spawn_zones.is_empty() {
-        bail!("level must define at least one spawn zone");
+        issues.push(ValidationIssue::error("no spawn zone defined"));
     }
     const WORLD_BOUND: f32 = 1000.0;
     for (i, z) in level.spawn_zones.iter().enumerate() {
+        let at = IssueLocation::SpawnZone(i);
         if z.radius <= 0.0 {
-            bail!("spawn zone {} has non-positive radius", i);
+            issues.push(
+                ValidationIssue::error(format!("spawn zone {i} has non-positive radius")).at(at),
+            );
         }
         if z.x.abs() > WORLD_BOUND || z.y.abs() > WORLD_BOUND {
-            bail!("spawn zone {} out of bounds", i);
+            issues.push(ValidationIssue::error(format!("spawn zone {i} out of bounds")).at(at));
         }
     }
-    Ok(())
+    for (i, a) in level.spawn_zones.iter().enumerate() {
+        for (j, b) in level.spawn_zones.iter().enumerate().skip(i + 1) {
+            if (a.x - b.x).hypot(a.y - b.y) < a.radius + b.radius {
+                issues.push(
+                    ValidationIssue::warning(format!("spawn zones {i} and {j} overlap"))
+                        .at(IssueLocation::SpawnZone(j)),
+                );
+            }
+        }
+    }
+    for team in (0..level.teams).filter(|&t| level.spawn_for(t).is_none()) {
+        issues.push(ValidationIssue::error(format!(
+            "team {team} has no spawn zone"
+        )));
+    }
+    for (i, path) in level.paths.iter().enumerate() {
+        if path.points.len() < 2 {
+            issues.push(
+                ValidationIssue::error(format!("path {} has fewer than 2 points", path.name))
+                    .at(IssueLocation::Path(i)),
+            );
+        }
+    }
+    issues
 }
 
 /// Validate gameplay related concerns such as asset references.
-pub fn validate_gameplay(ctx: &mut ModuleContext, level: &Level) -> Result<()> {
-    if !level.references.is_empty() {
-        let registry = ctx
-            .world()
-            .get_resource::<AssetRegistry>()
-            .context("asset registry missing")?;
-        for r in &level.references {
-            if !registry.0.contains(r) {
-                bail!("missing asset reference: {}", r);
-            }
-        }
+pub fn validate_gameplay(ctx: &mut ModuleContext, level: &Level) -> Vec<ValidationIssue> {
+    if level.references.is_empty() {
+        return Vec::new();
     }
-    Ok(())
+    let Some(registry) = ctx.world().get_resource::<AssetRegistry>() else {
+        return vec![ValidationIssue::error("asset registry missing")];
+    };
+    level
+        .references
+        .iter()
+        .filter(|r| !registry.0.contains(*r))
+        .map(|r| ValidationIssue::error(format!("missing asset reference: {r}")))
+        .collect()
 }
 
 /// Validate performance budgets for the level.
-pub fn validate_performance(level: &Level) -> Result<()> {
+pub fn validate_performance(level: &Level) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
     if level.id.len() > 64 || level.name.len() > 64 {
-        bail!("level metadata too long");
+        issues.push(ValidationIssue::error("level metadata too long"));
     }
     const ENTITY_LIMIT: usize = 1000;
     if level.entity_count > ENTITY_LIMIT {
-        bail!(
+        issues.push(ValidationIssue::error(format!(
             "level exceeds entity budget: {}/{}",
-            level.entity_count,
-            ENTITY_LIMIT
-        );
+            level.entity_count, ENTITY_LIMIT
+        )));
     }
-    Ok(())
+    issues
 }
 
 /// Validate the provided level using server-side rules.
-pub fn validate_level(ctx: &mut ModuleContext, level: &Level) -> Result<()> {
-    validate_structural(level)?;
-    validate_gameplay(ctx, level)?;
-    validate_performance(level)?;
+pub fn validate_level(ctx: &mut ModuleContext, level: &Level) -> Vec<ValidationIssue> {
+    let mut issues = validate_structural(level);
+    issues.extend(validate_gameplay(ctx, level));
+    issues.extend(validate_performance(level));
 
     // Ensure no other level is currently active
     if ctx.world().contains_resource::<Level>() {
-        bail!("a level is already active");
+        issues.push(ValidationIssue::error("a level is already active"));
     }
 
-    Ok(())
+    issues
 }
 
 /// Hook for playing the level inside the editor environment.
@@ -93,11 +274,19 @@ pub fn play_in_editor<M: GameModule + Default>(
     ctx: &mut ModuleContext,
     level: &Level,
 ) -> Result<()> {
-    // Run validation before attempting to play the level
-    validate_level(ctx, level)?;
+    // Run validation before attempting to play the level; warnings don't block.
+    let errors: Vec<String> = validate_level(ctx, level)
+        .into_iter()
+        .filter(ValidationIssue::is_error)
+        .map(|issue| issue.message)
+        .collect();
+    if !errors.is_empty() {
+        bail!("level failed validation: {}", errors.join("; "));
+    }
 
     // Stop any existing session and reclaim its world so we can reload.
     stop_play_in_editor(ctx);
+    let world_snapshot = WorldSnapshot::capture(ctx.world());
 
     // Move the editor world into a new server app so modules can operate on the
     // same entities and resources (spawn points, etc.).
@@ -129,18 +318,32 @@ pub fn play_in_editor<M: GameModule + Default>(
 
     // Store the running session in the editor context so it can be stopped or
     // reloaded later.
-    ctx.world().insert_non_send_resource(EditorSession { app });
+    ctx.world().insert_non_send_resource(EditorSession {
+        app,
+        snapshot: level.clone(),
+        world_snapshot,
+    });
     Ok(())
 }
 
 /// Stop the currently running editor session, returning control of the world to
-/// the caller.
-pub fn stop_play_in_editor(ctx: &mut ModuleContext) {
-    if let Some(mut session) = ctx.world().remove_non_send_resource::<EditorSession>() {
-        let mut world = World::new();
-        std::mem::swap(&mut session.app.world, &mut world);
-        std::mem::swap(ctx.world(), &mut world);
-    }
+/// the caller with its entities and [`PlaySnapshotRegistry`] types as they were
+/// when play started.
+///
+/// Returns the level as it was when play started, so editing resumes from the
+/// authored state rather than whatever the playtest left behind.
+pub fn stop_play_in_editor(ctx: &mut ModuleContext) -> Option<Level> {
+    let snapshot = ctx
+        .world()
+        .remove_non_send_resource::<EditorSession>()
+        .map(|mut session| {
+            let mut world = World::new();
+            std::mem::swap(&mut session.app.world, &mut world);
+            std::mem::swap(ctx.world(), &mut world);
+            session.world_snapshot.restore(ctx.world());
+            session.snapshot
+        });
     // Ensure any level resource from the play session is cleared.
     ctx.world().remove_resource::<Level>();
+    snapshot
 }
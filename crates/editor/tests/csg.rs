@@ -0,0 +1,83 @@
+use editor::{Brush, CsgOp, EditorClient, Level, Mesh, build_csg};
+
+fn level(brushes: impl IntoIterator<Item = Brush>) -> Level {
+    let mut level = Level::new("csg", "CSG");
+    for brush in brushes {
+        level.add_brush(brush);
+    }
+    level
+}
+
+fn assert_volume(mesh: &Mesh, expected: f32) {
+    let volume = mesh.volume();
+    assert!(
+        (volume - expected).abs() < 1e-4,
+        "volume {volume} != {expected}"
+    );
+}
+
+fn cube(op: CsgOp, min: [f32; 3], size: f32) -> Brush {
+    Brush::cuboid(op, min, min.map(|v| v + size))
+}
+
+#[test]
+fn box_minus_overlapping_box_keeps_the_rest() {
+    let level = level([
+        cube(CsgOp::Add, [0.0; 3], 4.0),
+        cube(CsgOp::Subtract, [2.0, 0.0, 0.0], 4.0),
+    ]);
+    let mesh = build_csg(&level).unwrap();
+    assert_volume(&mesh, 32.0);
+    assert_eq!(mesh.bounds(), Some(([0.0; 3], [2.0, 4.0, 4.0])));
+    assert_eq!(mesh.vertices.len(), 8);
+    assert_eq!(mesh.triangles.len(), 12);
+}
+
+#[test]
+fn carving_a_room_hollows_the_box() {
+    let level = level([
+        cube(CsgOp::Add, [0.0; 3], 4.0),
+        cube(CsgOp::Subtract, [1.0; 3], 2.0),
+    ]);
+    let mesh = build_csg(&level).unwrap();
+    assert_volume(&mesh, 56.0);
+    assert_eq!(mesh.bounds(), Some(([0.0; 3], [4.0; 3])));
+}
+
+#[test]
+fn union_and_intersect_count_overlap_once() {
+    let union = level([
+        cube(CsgOp::Add, [0.0; 3], 2.0),
+        cube(CsgOp::Add, [1.0; 3], 2.0),
+    ]);
+    assert_volume(&build_csg(&union).unwrap(), 15.0);
+
+    let intersect = level([
+        cube(CsgOp::Add, [0.0; 3], 2.0),
+        cube(CsgOp::Intersect, [1.0; 3], 2.0),
+    ]);
+    let mesh = build_csg(&intersect).unwrap();
+    assert_volume(&mesh, 1.0);
+    assert_eq!(mesh.bounds(), Some(([1.0; 3], [2.0; 3])));
+}
+
+#[test]
+fn cylinder_is_meshed_but_not_carved() {
+    let mut level = level([Brush::cylinder(CsgOp::Add, [0.0; 3], 1.0, 2.0)]);
+    let volume = build_csg(&level).unwrap().volume();
+    // A 16-gon prism slightly undershoots pi * r^2 * h.
+    assert!(volume > 6.0 && volume < std::f32::consts::PI * 2.0);
+
+    level.add_brush(cube(CsgOp::Subtract, [0.0; 3], 1.0));
+    assert!(build_csg(&level).is_err());
+}
+
+#[test]
+fn client_stores_rebuilt_mesh() {
+    let mut level = level([cube(CsgOp::Add, [0.0; 3], 1.0)]);
+    let mut client = EditorClient::new();
+    client.rebuild_csg(&mut level).unwrap();
+    assert_volume(level.mesh.as_ref().unwrap(), 1.0);
+    assert!(client.undo(&mut level));
+    assert!(level.mesh.is_none());
+}
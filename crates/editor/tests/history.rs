@@ -0,0 +1,49 @@
+use editor::{DEFAULT_MAX_HISTORY, EditorClient, Level};
+
+fn named(n: usize) -> Level {
+    Level::new(format!("lvl_{n}"), "History")
+}
+
+#[test]
+fn undo_stack_is_clamped_to_max_history() {
+    let mut client = EditorClient::new();
+    client.max_history = 3;
+    for n in 0..5 {
+        client.snapshot(&named(n));
+    }
+    assert_eq!(client.history_depth(), (3, 0));
+    let ids: Vec<_> = client.undo_stack.iter().map(|l| l.id.as_str()).collect();
+    assert_eq!(ids, ["lvl_2", "lvl_3", "lvl_4"]);
+}
+
+#[test]
+fn redo_works_within_retained_window() {
+    let mut client = EditorClient::new();
+    client.max_history = 2;
+    let mut level = named(0);
+    for n in 1..=4 {
+        client.snapshot(&level);
+        level = named(n);
+    }
+
+    assert!(client.undo(&mut level));
+    assert!(client.undo(&mut level));
+    assert_eq!(level.id, "lvl_2");
+    assert!(!client.undo(&mut level));
+    assert_eq!(client.history_depth(), (0, 2));
+
+    assert!(client.redo(&mut level));
+    assert!(client.redo(&mut level));
+    assert_eq!(level.id, "lvl_4");
+    assert_eq!(client.history_depth(), (2, 0));
+}
+
+#[test]
+fn default_history_depth() {
+    let mut client = EditorClient::new();
+    for n in 0..DEFAULT_MAX_HISTORY + 10 {
+        client.snapshot(&named(n));
+    }
+    assert_eq!(client.history_depth(), (DEFAULT_MAX_HISTORY, 0));
+    assert_eq!(client.undo_stack[0].id, "lvl_10");
+}
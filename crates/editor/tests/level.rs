@@ -0,0 +1,56 @@
+use editor::{LEVEL_FORMAT_VERSION, Level, SpawnZone, parse_level, serialize_level};
+
+fn level() -> Level {
+    let mut level = Level::new("versioned", "Versioned");
+    level.spawn_zones.push(SpawnZone {
+        x: 1.0,
+        y: 2.0,
+        radius: 3.0,
+        team: None,
+    });
+    level.references.push("crate".into());
+    level
+}
+
+#[test]
+fn current_version_round_trips() {
+    let toml = serialize_level(&level()).unwrap();
+    assert!(toml.contains(&format!("version = {LEVEL_FORMAT_VERSION}")));
+
+    let parsed = parse_level(&toml).unwrap();
+    assert_eq!(parsed.id, "versioned");
+    assert_eq!(parsed.spawn_zones.len(), 1);
+    assert_eq!(parsed.spawn_zones[0].radius, 3.0);
+    assert_eq!(parsed.references, ["crate"]);
+}
+
+#[test]
+fn unversioned_level_is_migrated() {
+    let toml = "id = \"legacy\"\nname = \"Legacy\"\n";
+    let parsed = parse_level(toml).unwrap();
+    assert_eq!(parsed.id, "legacy");
+    assert!(parsed.spawn_zones.is_empty());
+}
+
+#[test]
+fn future_version_is_rejected() {
+    let toml = "version = 99\nid = \"future\"\nname = \"Future\"\n";
+    let err = parse_level(toml).unwrap_err();
+    assert!(err.to_string().contains("newer than the supported version"));
+}
+
+#[test]
+fn spawn_lookup_prefers_team_zone_over_shared() {
+    let mut level = level();
+    level.spawn_zones.push(SpawnZone {
+        x: 10.0,
+        y: 0.0,
+        radius: 1.0,
+        team: Some(1),
+    });
+    assert_eq!(level.spawn_for(1).unwrap().x, 10.0);
+    assert_eq!(level.spawn_for(0).unwrap().x, 1.0);
+
+    level.spawn_zones.remove(0);
+    assert!(level.spawn_for(0).is_none());
+}
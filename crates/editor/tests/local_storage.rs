@@ -0,0 +1,55 @@
+use editor::{EditorClient, Level, SpawnZone};
+
+fn client_in(dir: &tempfile::TempDir) -> EditorClient {
+    let mut client = EditorClient::new();
+    client.local_dir = dir.path().join("levels");
+    client
+}
+
+#[tokio::test]
+async fn level_round_trips_through_local_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = client_in(&dir);
+    let mut level = Level::new("autosave", "Autosave");
+    level.spawn_zones.push(SpawnZone {
+        x: 1.0,
+        y: 2.0,
+        radius: 3.0,
+        team: Some(0),
+    });
+
+    client.store_level_locally(&level).await.unwrap();
+    assert!(dir.path().join("levels/autosave.json").exists());
+
+    let loaded = client
+        .load_level_locally("autosave")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(loaded.name, "Autosave");
+    assert_eq!(loaded.spawn_zones[0].team, Some(0));
+}
+
+#[tokio::test]
+async fn storing_again_overwrites() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = client_in(&dir);
+    let mut level = Level::new("autosave", "First");
+    client.store_level_locally(&level).await.unwrap();
+    level.name = "Second".into();
+    client.store_level_locally(&level).await.unwrap();
+
+    let loaded = client
+        .load_level_locally("autosave")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(loaded.name, "Second");
+}
+
+#[tokio::test]
+async fn missing_level_loads_as_none() {
+    let dir = tempfile::tempdir().unwrap();
+    let client = client_in(&dir);
+    assert!(client.load_level_locally("nope").await.unwrap().is_none());
+}
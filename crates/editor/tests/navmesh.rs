@@ -0,0 +1,58 @@
+use editor::{Brush, CsgOp, EditorClient, Level, bake_navmesh};
+
+fn floor(size: f32) -> Brush {
+    Brush::cuboid(CsgOp::Add, [0.0, -1.0, 0.0], [size, 0.0, size])
+}
+
+#[test]
+fn flat_plane_is_fully_walkable() {
+    let mut level = Level::new("plane", "Plane");
+    level.add_brush(floor(10.0));
+
+    let mesh = bake_navmesh(&level);
+    assert_eq!(mesh.area(), 100.0);
+    assert_eq!(mesh.region_count(), 1);
+    assert!(mesh.cells.iter().all(|c| c.height == 0.0));
+}
+
+#[test]
+fn wall_splits_plane_into_regions() {
+    let mut level = Level::new("walled", "Walled");
+    level.add_brush(floor(10.0));
+    level.add_brush(Brush::cuboid(CsgOp::Add, [4.0, 0.0, 0.0], [6.0, 3.0, 10.0]));
+
+    let mesh = bake_navmesh(&level);
+    // The wall top is walkable but too high to step onto from the floor.
+    assert_eq!(mesh.area(), 100.0);
+    assert_eq!(mesh.region_count(), 3);
+    assert_eq!(mesh.cells.iter().filter(|c| c.height == 3.0).count(), 20);
+}
+
+#[test]
+fn carved_pit_lowers_the_floor() {
+    let mut level = Level::new("pit", "Pit");
+    level.add_brush(Brush::cuboid(CsgOp::Add, [0.0, -2.0, 0.0], [4.0, 0.0, 4.0]));
+    level.add_brush(Brush::cuboid(
+        CsgOp::Subtract,
+        [1.0, -0.3, 1.0],
+        [3.0, 0.0, 3.0],
+    ));
+
+    let mesh = bake_navmesh(&level);
+    assert_eq!(mesh.area(), 16.0);
+    assert_eq!(mesh.cells.iter().filter(|c| c.height == -0.3).count(), 4);
+    assert_eq!(mesh.region_count(), 1);
+}
+
+#[test]
+fn client_shows_baked_navmesh() {
+    let mut level = Level::new("plane", "Plane");
+    level.add_brush(floor(2.0));
+    let mut client = EditorClient::new();
+    assert!(client.visible_navmesh(&level).is_none());
+
+    client.bake_navmesh(&mut level);
+    assert_eq!(client.visible_navmesh(&level).unwrap().area(), 4.0);
+    client.toggle_navmesh();
+    assert!(client.visible_navmesh(&level).is_none());
+}
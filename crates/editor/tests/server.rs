@@ -1,13 +1,8 @@
 use bevy_ecs::prelude::*;
 use editor::{
-    export_level,
-    play_in_editor,
-    stop_play_in_editor,
+    AssetRegistry, EditorSession, Level, PlaySnapshotRegistry, SpawnZone, ValidationIssue,
+    export_level_in, import_level, play_in_editor, serialize_level, stop_play_in_editor,
     validate_level,
-    AssetRegistry,
-    EditorSession,
-    Level,
-    SpawnZone,
 };
 use null_module::NullModule;
 use platform_api::ModuleContext;
@@ -20,7 +15,11 @@ fn invalid_level_is_rejected() {
     let mut world = World::new();
     let mut ctx = ModuleContext::new(&mut world);
     let bad = Level::new("", "");
-    assert!(validate_level(&mut ctx, &bad).is_err());
+    assert!(
+        validate_level(&mut ctx, &bad)
+            .iter()
+            .any(ValidationIssue::is_error)
+    );
 }
 
 #[test]
@@ -32,6 +31,7 @@ fn play_in_editor_starts_session() {
         x: 0.0,
         y: 0.0,
         radius: 5.0,
+        team: None,
     });
 
     play_in_editor::<NullModule>(&mut ctx, &level).expect("should start editor session");
@@ -57,14 +57,21 @@ fn round_trip_export_play_modify_replay() {
     let mut ctx = ModuleContext::new(&mut world);
 
     // initial editor setup
+    let mut registry = PlaySnapshotRegistry::default();
+    registry.register_component::<TestComponent>();
+    ctx.world().insert_resource(registry);
     let entity = ctx.world().spawn(TestComponent(1)).id();
+    let assets = tempfile::tempdir().unwrap();
+    let exported = assets.path().join("levels/roundtrip/level.toml");
     let mut level = Level::new("roundtrip", "Round Trip");
     level.spawn_zones.push(SpawnZone {
         x: 0.0,
         y: 0.0,
         radius: 5.0,
+        team: None,
     });
-    export_level(&level).unwrap();
+    export_level_in(&level, assets.path()).unwrap();
+    assert_eq!(import_level(&exported).unwrap().name, "Round Trip");
 
     play_in_editor::<NullModule>(&mut ctx, &level).unwrap();
 
@@ -78,15 +85,16 @@ fn round_trip_export_play_modify_replay() {
         comp.0 = 2;
     }
 
-    // stop and modify again in editor
+    // stop, which undoes the play change, and modify again in editor
     stop_play_in_editor(&mut ctx);
     {
         let mut comp = ctx.world().get_mut::<TestComponent>(entity).unwrap();
-        assert_eq!(comp.0, 2);
+        assert_eq!(comp.0, 1);
         comp.0 = 3;
     }
     level.name = "Round Trip 2".into();
-    export_level(&level).unwrap();
+    export_level_in(&level, assets.path()).unwrap();
+    assert_eq!(import_level(&exported).unwrap().name, "Round Trip 2");
 
     // replay
     play_in_editor::<NullModule>(&mut ctx, &level).unwrap();
@@ -102,6 +110,92 @@ fn round_trip_export_play_modify_replay() {
     }
 }
 
+#[test]
+fn stopping_play_restores_authored_level() {
+    let mut world = World::new();
+    let mut ctx = ModuleContext::new(&mut world);
+    let mut level = Level::new("restore", "Restore");
+    level.spawn_zones.push(SpawnZone {
+        x: 0.0,
+        y: 0.0,
+        radius: 5.0,
+        team: None,
+    });
+    level.entity_count = 12;
+    let authored = serialize_level(&level).unwrap();
+
+    play_in_editor::<NullModule>(&mut ctx, &level).unwrap();
+    {
+        let mut session = ctx
+            .world()
+            .get_non_send_resource_mut::<EditorSession>()
+            .unwrap();
+        let mut played = session.app.world.resource_mut::<Level>();
+        played.spawn_zones.clear();
+        played.entity_count = 3;
+        played.name = "Mutated".into();
+    }
+
+    let restored = stop_play_in_editor(&mut ctx).expect("session snapshot");
+    assert_eq!(serialize_level(&restored).unwrap(), authored);
+    assert!(!ctx.world().contains_resource::<Level>());
+    assert!(stop_play_in_editor(&mut ctx).is_none());
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+struct Score(u32);
+
+#[test]
+fn stopping_play_restores_editor_world() {
+    let mut world = World::new();
+    let mut ctx = ModuleContext::new(&mut world);
+    let mut registry = PlaySnapshotRegistry::default();
+    registry
+        .register_component::<TestComponent>()
+        .register_resource::<Score>();
+    ctx.world().insert_resource(registry);
+    ctx.world().insert_resource(Score(1));
+    let kept = ctx.world().spawn(TestComponent(1)).id();
+    let despawned = ctx.world().spawn(TestComponent(5)).id();
+    let mut level = Level::new("restore-world", "Restore World");
+    level.spawn_zones.push(SpawnZone {
+        x: 0.0,
+        y: 0.0,
+        radius: 5.0,
+        team: None,
+    });
+
+    play_in_editor::<NullModule>(&mut ctx, &level).unwrap();
+    {
+        let mut session = ctx
+            .world()
+            .get_non_send_resource_mut::<EditorSession>()
+            .unwrap();
+        let world = &mut session.app.world;
+        assert!(world.despawn(despawned));
+        // Likely reuses the despawned entity's slot.
+        world.spawn(TestComponent(9));
+        world.get_mut::<TestComponent>(kept).unwrap().0 = 2;
+        world.resource_mut::<Score>().0 = 7;
+    }
+
+    stop_play_in_editor(&mut ctx);
+    let world = ctx.world();
+    assert_eq!(world.get::<TestComponent>(kept), Some(&TestComponent(1)));
+    assert_eq!(
+        world.get::<TestComponent>(despawned),
+        Some(&TestComponent(5))
+    );
+    let mut values: Vec<i32> = world
+        .query::<&TestComponent>()
+        .iter(world)
+        .map(|c| c.0)
+        .collect();
+    values.sort();
+    assert_eq!(values, vec![1, 5]);
+    assert_eq!(world.resource::<Score>(), &Score(1));
+}
+
 #[test]
 fn missing_reference_is_rejected() {
     let mut world = World::new();
@@ -109,7 +203,11 @@ fn missing_reference_is_rejected() {
     let mut ctx = ModuleContext::new(&mut world);
     let mut level = Level::new("lvl", "Lvl");
     level.references.push("missing_asset".into());
-    assert!(validate_level(&mut ctx, &level).is_err());
+    assert!(
+        validate_level(&mut ctx, &level)
+            .iter()
+            .any(ValidationIssue::is_error)
+    );
 }
 
 #[test]
@@ -121,8 +219,13 @@ fn illegal_spawn_zone_is_rejected() {
         x: 2000.0,
         y: 0.0,
         radius: 10.0,
+        team: None,
     });
-    assert!(validate_level(&mut ctx, &level).is_err());
+    assert!(
+        validate_level(&mut ctx, &level)
+            .iter()
+            .any(ValidationIssue::is_error)
+    );
 }
 
 #[test]
@@ -134,7 +237,12 @@ fn perf_budget_is_enforced() {
         x: 0.0,
         y: 0.0,
         radius: 10.0,
+        team: None,
     });
     level.entity_count = 2000;
-    assert!(validate_level(&mut ctx, &level).is_err());
+    assert!(
+        validate_level(&mut ctx, &level)
+            .iter()
+            .any(ValidationIssue::is_error)
+    );
 }
@@ -0,0 +1,32 @@
+use editor::EditorClient;
+
+#[test]
+fn snapping_is_off_by_default() {
+    let client = EditorClient::new();
+    assert_eq!(client.snap_point([0.3, 1.7, -2.2]), [0.3, 1.7, -2.2]);
+}
+
+#[test]
+fn each_axis_uses_its_own_step() {
+    let mut client = EditorClient::new();
+    client.set_grid_snap_axes(Some([1.0, 0.25, 2.0]));
+    assert_eq!(client.snap_point([0.6, 1.1, -2.9]), [1.0, 1.0, -2.0]);
+}
+
+#[test]
+fn zero_step_leaves_axis_free() {
+    let mut client = EditorClient::new();
+    client.set_grid_snap_axes(Some([1.0, 0.0, 1.0]));
+    assert_eq!(client.snap_point([0.4, 1.37, 2.6]), [0.0, 1.37, 3.0]);
+}
+
+#[test]
+fn uniform_snap_applies_to_all_axes() {
+    let mut client = EditorClient::new();
+    client.set_grid_snap(Some(0.5));
+    assert_eq!(client.grid_snap, Some([0.5; 3]));
+    assert_eq!(client.snap_point([0.3, 0.7, 1.1]), [0.5, 0.5, 1.0]);
+
+    client.set_grid_snap(None);
+    assert_eq!(client.snap_point([0.3, 0.7, 1.1]), [0.3, 0.7, 1.1]);
+}
@@ -0,0 +1,33 @@
+use editor::{EditorClient, Level};
+
+#[test]
+fn baking_spline_adds_sampled_path() {
+    let mut level = Level::new("ducks", "Ducks");
+    let mut client = EditorClient::new();
+    client.spline_samples = 4;
+    let control = [[0.0, 1.0, 0.0], [4.0, 3.0, 0.0], [8.0, 1.0, 2.0]];
+    for point in control {
+        client.add_spline_point(point);
+    }
+
+    assert!(client.bake_spline(&mut level));
+    assert!(client.spline.is_empty());
+    assert_eq!(level.paths.len(), 1);
+    let path = &level.paths[0];
+    assert_eq!(path.name, "path_0");
+    assert_eq!(path.points.len(), 2 * 4 + 1);
+    // The curve passes through every control point.
+    assert_eq!(path.points[0], control[0]);
+    assert_eq!(path.points[4], control[1]);
+    assert_eq!(path.points[8], control[2]);
+}
+
+#[test]
+fn single_point_spline_is_not_baked() {
+    let mut level = Level::new("ducks", "Ducks");
+    let mut client = EditorClient::new();
+    client.add_spline_point([0.0; 3]);
+    assert!(!client.bake_spline(&mut level));
+    assert!(level.paths.is_empty());
+    assert_eq!(client.spline.len(), 1);
+}
@@ -0,0 +1,117 @@
+use bevy_ecs::prelude::*;
+use editor::{
+    AssetRegistry, IssueLocation, Level, PathDef, Severity, SpawnZone, ValidationIssue,
+    validate_level,
+};
+use platform_api::ModuleContext;
+
+fn valid_level() -> Level {
+    let mut level = Level::new("lvl", "Lvl");
+    level.spawn_zones.push(SpawnZone {
+        x: 0.0,
+        y: 0.0,
+        radius: 5.0,
+        team: None,
+    });
+    level
+}
+
+fn issues(level: &Level) -> Vec<ValidationIssue> {
+    let mut world = World::new();
+    world.insert_resource(AssetRegistry::default());
+    validate_level(&mut ModuleContext::new(&mut world), level)
+}
+
+#[test]
+fn valid_level_has_no_issues() {
+    assert_eq!(issues(&valid_level()), []);
+}
+
+#[test]
+fn missing_spawn_zone_is_an_error() {
+    let mut level = valid_level();
+    level.spawn_zones.clear();
+    assert_eq!(
+        issues(&level),
+        [ValidationIssue::error("no spawn zone defined")]
+    );
+}
+
+#[test]
+fn overlapping_spawn_zones_warn() {
+    let mut level = valid_level();
+    level.spawn_zones.push(SpawnZone {
+        x: 3.0,
+        y: 0.0,
+        radius: 5.0,
+        team: None,
+    });
+    let issues = issues(&level);
+    assert_eq!(
+        issues,
+        [ValidationIssue::warning("spawn zones 0 and 1 overlap").at(IssueLocation::SpawnZone(1))]
+    );
+    assert_eq!(issues[0].severity, Severity::Warning);
+}
+
+#[test]
+fn bad_spawn_zone_is_located() {
+    let mut level = valid_level();
+    level.spawn_zones[0].radius = 0.0;
+    assert_eq!(
+        issues(&level),
+        [
+            ValidationIssue::error("spawn zone 0 has non-positive radius")
+                .at(IssueLocation::SpawnZone(0))
+        ]
+    );
+}
+
+#[test]
+fn short_path_is_an_error() {
+    let mut level = valid_level();
+    level.paths.push(PathDef {
+        name: "flight".into(),
+        points: vec![[0.0; 3]],
+    });
+    assert_eq!(
+        issues(&level),
+        [
+            ValidationIssue::error("path flight has fewer than 2 points")
+                .at(IssueLocation::Path(0))
+        ]
+    );
+}
+
+#[test]
+fn missing_reference_and_budget_are_reported_together() {
+    let mut level = valid_level();
+    level.references.push("missing_asset".into());
+    level.entity_count = 2000;
+    assert_eq!(
+        issues(&level),
+        [
+            ValidationIssue::error("missing asset reference: missing_asset"),
+            ValidationIssue::error("level exceeds entity budget: 2000/1000"),
+        ]
+    );
+}
+
+#[test]
+fn declared_team_without_zone_is_an_error() {
+    let mut level = valid_level();
+    level.teams = 2;
+    level.spawn_zones[0].team = Some(0);
+    assert_eq!(
+        issues(&level),
+        [ValidationIssue::error("team 1 has no spawn zone")]
+    );
+
+    level.spawn_zones.push(SpawnZone {
+        x: 50.0,
+        y: 0.0,
+        radius: 5.0,
+        team: Some(1),
+    });
+    assert_eq!(issues(&level), []);
+}
@@ -1,6 +1,10 @@
 use bevy::prelude::*;
 use bevy::ecs::schedule::{Schedule, ScheduleLabel};
 use bevy_rapier3d::prelude::{NoUserData, RapierPhysicsPlugin};
+pub use platform_api::{
+    AudioEnabled, FlightEnabled, HitscanEnabled, NavigationEnabled, NetworkingEnabled,
+    PhysicsEnabled, VehiclesEnabled,
+};
 
 #[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Network;
@@ -75,21 +79,8 @@ pub fn lobby_scene(app: &mut App) {
 /// - [`CapabilityFlags::NEEDS_NAV`]     &rarr; enables navigation/path finding.
 /// - [`CapabilityFlags::USES_VEHICLES`] &rarr; enables vehicle dynamics.
 /// - [`CapabilityFlags::USES_FLIGHT`]   &rarr; enables flight dynamics.
-#[derive(Resource, Default)]
-pub struct PhysicsEnabled(pub bool);
-
-#[derive(Resource, Default)]
-pub struct HitscanEnabled(pub bool);
-
-#[derive(Resource, Default)]
-pub struct NavigationEnabled(pub bool);
-
-#[derive(Resource, Default)]
-pub struct VehiclesEnabled(pub bool);
-
-#[derive(Resource, Default)]
-pub struct FlightEnabled(pub bool);
-
+/// - [`CapabilityFlags::USES_AUDIO`]    &rarr; enables audio playback.
+/// - [`CapabilityFlags::NEEDS_NETWORKING`] &rarr; enables authoritative networking.
 pub fn auto_wire(app: &mut App, capabilities: platform_api::CapabilityFlags) {
     use platform_api::CapabilityFlags;
 
@@ -119,4 +110,10 @@ pub fn auto_wire(app: &mut App, capabilities: platform_api::CapabilityFlags) {
     app.insert_resource(FlightEnabled(
         capabilities.contains(CapabilityFlags::USES_FLIGHT),
     ));
+    app.insert_resource(AudioEnabled(
+        capabilities.contains(CapabilityFlags::USES_AUDIO),
+    ));
+    app.insert_resource(NetworkingEnabled(
+        capabilities.contains(CapabilityFlags::NEEDS_NETWORKING),
+    ));
 }
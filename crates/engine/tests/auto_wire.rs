@@ -0,0 +1,20 @@
+use arena_engine::{AudioEnabled, NetworkingEnabled, PhysicsEnabled, auto_wire};
+use bevy::prelude::*;
+use platform_api::CapabilityFlags;
+
+#[test]
+fn auto_wire_toggles_audio_and_networking() {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    auto_wire(
+        &mut app,
+        CapabilityFlags::USES_AUDIO | CapabilityFlags::NEEDS_NETWORKING,
+    );
+    assert!(app.world.resource::<AudioEnabled>().0);
+    assert!(app.world.resource::<NetworkingEnabled>().0);
+    assert!(!app.world.resource::<PhysicsEnabled>().0);
+
+    auto_wire(&mut app, CapabilityFlags::USES_AUDIO);
+    assert!(app.world.resource::<AudioEnabled>().0);
+    assert!(!app.world.resource::<NetworkingEnabled>().0);
+}
@@ -10,7 +10,7 @@ pub mod runs {
     #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
     #[sea_orm(table_name = "runs")]
     pub struct Model {
-        #[sea_orm(primary_key)]
+        #[sea_orm(primary_key, auto_increment = false)]
         pub id: Uuid,
         pub leaderboard: Uuid,
         pub player_id: String,
@@ -18,6 +18,8 @@ pub mod runs {
         pub created_at: DateTimeUtc,
         pub flagged: bool,
         pub replay_index: i64,
+        /// Points of the events trimmed from the stored replay.
+        pub trimmed_points: i32,
     }
 
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -41,7 +43,7 @@ pub mod scores {
     #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
     #[sea_orm(table_name = "scores")]
     pub struct Model {
-        #[sea_orm(primary_key)]
+        #[sea_orm(primary_key, auto_increment = false)]
         pub id: Uuid,
         pub run: Uuid,
         pub leaderboard: Uuid,
@@ -76,7 +78,7 @@ pub mod purchases {
     #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
     #[sea_orm(table_name = "purchases")]
     pub struct Model {
-        #[sea_orm(primary_key)]
+        #[sea_orm(primary_key, auto_increment = false)]
         pub id: Uuid,
         pub player_id: String,
         pub sku: String,
@@ -1,42 +1,108 @@
 pub mod db;
 pub mod models;
+#[cfg(test)]
+mod tests;
 
-use std::io;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Read, Write};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use chrono::{Duration, Utc};
 use db::{purchases, runs, scores};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use models::{LeaderboardWindow, Run, Score};
 use sea_orm::{
     ActiveModelTrait, ActiveValue::Set, ColumnTrait, Database, DatabaseConnection, EntityTrait,
-    JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait,
+    JoinType, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, RelationTrait, Select,
+    sea_query::Expr,
 };
 use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
-const WINDOWS: [LeaderboardWindow; 3] = [
+const WINDOWS: [LeaderboardWindow; 4] = [
     LeaderboardWindow::Daily,
     LeaderboardWindow::Weekly,
+    LeaderboardWindow::Monthly,
     LeaderboardWindow::AllTime,
 ];
 
+/// Snapshots kept per leaderboard for resuming subscribers.
+const HISTORY_LEN: usize = 64;
+
+/// Prefix marking a gzip-compressed replay; files without it are stored raw.
+const COMPRESSED_MAGIC: &[u8; 4] = b"ARZ1";
+
+/// Shortens an encoded replay to at most the given bytes, keeping it
+/// decodable, or returns `None` if it can't.
+pub type ReplayTrimmer = fn(&[u8], usize) -> Option<TrimmedReplay>;
+
+/// A replay cut down by a [`ReplayTrimmer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrimmedReplay {
+    pub replay: Vec<u8>,
+    /// Points scored by the dropped events, so the kept tail plus these
+    /// still adds up to the submitted score.
+    pub dropped_points: i32,
+}
+
 #[derive(Clone)]
 pub struct LeaderboardService {
     db: DatabaseConnection,
     replay_dir: PathBuf,
     tx: broadcast::Sender<LeaderboardSnapshot>,
+    history: Arc<Mutex<SnapshotHistory>>,
     max: usize,
+    /// Scores above this ceiling are auto-flagged as suspected cheats.
+    max_points: Option<i32>,
+    /// Stored replays are trimmed to at most this many bytes.
+    max_replay_bytes: Option<usize>,
+    /// Knows the replay format; without it over-cap replays are rejected.
+    replay_trimmer: Option<ReplayTrimmer>,
+    /// Gzip replays on write. Reads handle both forms regardless.
+    compress_replays: bool,
+}
+
+/// Details about a stored submission that callers may want to report.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SubmitOutcome {
+    pub replay_trimmed: bool,
+}
+
+/// A stored replay opened for streaming.
+pub struct ReplayFile {
+    pub file: tokio::fs::File,
+    /// The file is positioned past the header of a compressed replay, so
+    /// the remaining bytes are a plain gzip stream.
+    pub gzip: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct LeaderboardSnapshot {
+    /// Increases with every broadcast, across all leaderboards.
+    pub seq: u64,
     pub leaderboard: Uuid,
     pub window: LeaderboardWindow,
     pub scores: Vec<Score>,
 }
 
+#[derive(Default)]
+struct SnapshotHistory {
+    last_seq: u64,
+    boards: HashMap<Uuid, BoardHistory>,
+}
+
+#[derive(Default)]
+struct BoardHistory {
+    snapshots: VecDeque<LeaderboardSnapshot>,
+    /// Highest seq evicted from `snapshots`; resuming from before it would
+    /// miss updates.
+    evicted_seq: u64,
+}
+
 impl LeaderboardService {
     pub async fn new(database_url: &str, replay_dir: PathBuf) -> Result<Self> {
         let db = Database::connect(database_url).await?;
@@ -50,28 +116,104 @@ impl LeaderboardService {
             .ok()
             .and_then(|v| v.parse().ok())
             .unwrap_or(100);
+        let max_points = std::env::var("ARENA_LEADERBOARD_MAX_POINTS")
+            .ok()
+            .and_then(|v| v.parse().ok());
+        let max_replay_bytes = std::env::var("ARENA_LEADERBOARD_MAX_REPLAY_BYTES")
+            .ok()
+            .and_then(|v| v.parse().ok());
+        let compress_replays = std::env::var("ARENA_REPLAY_COMPRESS")
+            .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+            .unwrap_or(false);
         Ok(Self {
             db,
             replay_dir,
             tx,
+            history: Arc::default(),
             max,
+            max_points,
+            max_replay_bytes,
+            replay_trimmer: None,
+            compress_replays,
         })
     }
 
+    /// Set the plausibility ceiling; `None` disables auto-flagging.
+    pub fn with_max_points(mut self, max_points: Option<i32>) -> Self {
+        self.max_points = max_points;
+        self
+    }
+
+    /// Returns `true` if `points` exceed the configured plausibility ceiling.
+    pub fn is_implausible(&self, points: i32) -> bool {
+        self.max_points.is_some_and(|max| points > max)
+    }
+
+    /// Set the stored replay cap in bytes; `None` keeps replays unbounded.
+    pub fn with_max_replay_bytes(mut self, max_replay_bytes: Option<usize>) -> Self {
+        self.max_replay_bytes = max_replay_bytes;
+        self
+    }
+
+    /// Trim over-cap replays with `trimmer` instead of rejecting them.
+    pub fn with_replay_trimmer(mut self, trimmer: ReplayTrimmer) -> Self {
+        self.replay_trimmer = Some(trimmer);
+        self
+    }
+
+    /// Toggle gzip compression of newly written replays.
+    pub fn with_compress_replays(mut self, compress_replays: bool) -> Self {
+        self.compress_replays = compress_replays;
+        self
+    }
+
+    /// Shorten `replay` to `max_replay_bytes` with the replay trimmer.
+    ///
+    /// Returns the points dropped with the trimmed events if the replay was
+    /// over the cap, or an `InvalidInput` error if it is over the cap and
+    /// can't be trimmed.
+    pub fn trim_replay(&self, replay: &mut Vec<u8>) -> io::Result<Option<i32>> {
+        let Some(max) = self.max_replay_bytes.filter(|&max| replay.len() > max) else {
+            return Ok(None);
+        };
+        match self.replay_trimmer.and_then(|trim| trim(replay, max)) {
+            Some(trimmed) if trimmed.replay.len() <= max => {
+                *replay = trimmed.replay;
+                Ok(Some(trimmed.dropped_points))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("replay exceeds {max} bytes"),
+            )),
+        }
+    }
+
     pub async fn submit_score(
         &self,
         leaderboard: Uuid,
         score: Score,
         mut run: Run,
-        replay: Vec<u8>,
-    ) -> io::Result<()> {
+        mut replay: Vec<u8>,
+    ) -> io::Result<SubmitOutcome> {
+        if self.is_implausible(score.points) {
+            run.flagged = true;
+        }
+        let trimmed_points = self.trim_replay(&mut replay)?;
         if !replay.is_empty() {
             let filename = format!("{}", run.id);
-            let path = self.replay_dir.join(&filename);
-            tokio::fs::write(&path, &replay).await?;
+            self.write_replay(&filename, &replay).await?;
             run.replay_path = filename;
         }
 
+        let mut changed = Vec::with_capacity(WINDOWS.len());
+        for window in WINDOWS {
+            if window == LeaderboardWindow::AllTime
+                || (!run.flagged && self.could_enter(leaderboard, window, score.points).await)
+            {
+                changed.push(window);
+            }
+        }
+
         let run_model = runs::ActiveModel {
             id: Set(run.id),
             leaderboard: Set(leaderboard),
@@ -80,6 +222,7 @@ impl LeaderboardService {
             created_at: Set(run.created_at),
             flagged: Set(run.flagged),
             replay_index: Set(run.replay_index),
+            trimmed_points: Set(trimmed_points.unwrap_or(0)),
         };
         run_model.insert(&self.db).await.map_err(to_io_error)?;
 
@@ -94,37 +237,135 @@ impl LeaderboardService {
         };
         score_model.insert(&self.db).await.map_err(to_io_error)?;
 
-        for window in WINDOWS {
+        self.broadcast(leaderboard, changed).await;
+        Ok(SubmitOutcome {
+            replay_trimmed: trimmed_points.is_some(),
+        })
+    }
+
+    /// Send fresh snapshots of `windows` to live subscribers.
+    async fn broadcast(
+        &self,
+        leaderboard: Uuid,
+        windows: impl IntoIterator<Item = LeaderboardWindow>,
+    ) {
+        for window in windows {
             let scores = self.get_scores(leaderboard, window).await;
-            let _ = self.tx.send(LeaderboardSnapshot {
+            let mut history = self.history.lock().unwrap();
+            history.last_seq += 1;
+            let snapshot = LeaderboardSnapshot {
+                seq: history.last_seq,
                 leaderboard,
                 window,
                 scores,
-            });
+            };
+            let board = history.boards.entry(leaderboard).or_default();
+            if board.snapshots.len() == HISTORY_LEN {
+                board.evicted_seq = board.snapshots.pop_front().map_or(0, |s| s.seq);
+            }
+            board.snapshots.push_back(snapshot.clone());
+            // Sent under the lock so subscribers see seqs in order.
+            let _ = self.tx.send(snapshot);
+        }
+    }
+
+    /// Buffered snapshots of `leaderboard` newer than `since`, oldest first.
+    ///
+    /// `None` means some newer snapshots were already evicted, or `since`
+    /// comes from before a restart, and the caller must fetch the full board
+    /// instead.
+    pub fn snapshots_since(
+        &self,
+        leaderboard: Uuid,
+        since: u64,
+    ) -> Option<Vec<LeaderboardSnapshot>> {
+        let history = self.history.lock().unwrap();
+        if since > history.last_seq {
+            return None;
+        }
+        let Some(board) = history.boards.get(&leaderboard) else {
+            return Some(Vec::new());
+        };
+        if since < board.evicted_seq {
+            return None;
         }
+        Some(
+            board
+                .snapshots
+                .iter()
+                .filter(|snapshot| snapshot.seq > since)
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Hide (or restore) a run's scores and push the updated boards to
+    /// subscribers.
+    pub async fn flag_run(&self, run_id: Uuid, flagged: bool) -> Result<()> {
+        let run = runs::Entity::find_by_id(run_id)
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| anyhow!("unknown run {run_id}"))?;
+        let leaderboard = run.leaderboard;
+        let mut run: runs::ActiveModel = run.into();
+        run.flagged = Set(flagged);
+        run.update(&self.db).await?;
+        self.broadcast(leaderboard, WINDOWS).await;
         Ok(())
     }
 
-    pub async fn get_scores(&self, leaderboard: Uuid, window: LeaderboardWindow) -> Vec<Score> {
-        let now = Utc::now();
-        let mut query = scores::Entity::find()
-            .filter(scores::Column::Leaderboard.eq(leaderboard))
-            .join(JoinType::InnerJoin, scores::Relation::Runs.def())
-            .filter(runs::Column::Flagged.eq(false))
+    /// Whether `points` would show on the window's board: it has room, or
+    /// the score beats the lowest entry shown. Ties lose to older scores.
+    async fn could_enter(&self, leaderboard: Uuid, window: LeaderboardWindow, points: i32) -> bool {
+        let Some(last) = self.max.checked_sub(1) else {
+            return false;
+        };
+        match visible_scores(leaderboard, window)
             .order_by_desc(scores::Column::Points)
-            .limit(self.max as u64);
+            .offset(last as u64)
+            .one(&self.db)
+            .await
+        {
+            Ok(Some(cutoff)) => points > cutoff.points,
+            _ => true,
+        }
+    }
 
-        match window {
-            LeaderboardWindow::Daily => {
-                query = query.filter(scores::Column::CreatedAt.gte(now - Duration::days(1)));
-            }
-            LeaderboardWindow::Weekly => {
-                query = query.filter(scores::Column::CreatedAt.gte(now - Duration::weeks(1)));
-            }
-            LeaderboardWindow::AllTime => {}
+    /// Write via a temporary file and rename so a crash never leaves a
+    /// truncated replay at the final path.
+    async fn write_replay(&self, filename: &str, replay: &[u8]) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.replay_dir).await?;
+        let path = self.replay_dir.join(filename);
+        let tmp = self.replay_dir.join(format!("{filename}.tmp"));
+        if self.compress_replays {
+            tokio::fs::write(&tmp, compress_replay(replay)?).await?;
+        } else {
+            tokio::fs::write(&tmp, replay).await?;
         }
+        tokio::fs::rename(&tmp, &path).await
+    }
 
-        query
+    pub async fn get_scores(&self, leaderboard: Uuid, window: LeaderboardWindow) -> Vec<Score> {
+        self.get_scores_paged(leaderboard, window, 0, self.max as u64)
+            .await
+    }
+
+    /// One page of the board, best first. `limit` is clamped to the
+    /// configured maximum; ties are ordered by submission time so pages are
+    /// stable.
+    pub async fn get_scores_paged(
+        &self,
+        leaderboard: Uuid,
+        window: LeaderboardWindow,
+        offset: u64,
+        limit: u64,
+    ) -> Vec<Score> {
+        visible_scores(leaderboard, window)
+            .order_by_desc(scores::Column::Points)
+            .order_by_asc(scores::Column::CreatedAt)
+            .order_by_asc(scores::Column::Id)
+            .offset(offset)
+            .limit(limit.min(self.max as u64))
             .all(&self.db)
             .await
             .unwrap_or_default()
@@ -141,6 +382,28 @@ impl LeaderboardService {
             .collect()
     }
 
+    /// 1-based rank of the player's best score in the window; equal points
+    /// share a rank. `None` if the player has no visible score there.
+    pub async fn get_rank(
+        &self,
+        leaderboard: Uuid,
+        window: LeaderboardWindow,
+        player_id: Uuid,
+    ) -> Option<u64> {
+        let best = visible_scores(leaderboard, window)
+            .filter(scores::Column::PlayerId.eq(player_id.to_string()))
+            .order_by_desc(scores::Column::Points)
+            .one(&self.db)
+            .await
+            .ok()??;
+        let ahead = visible_scores(leaderboard, window)
+            .filter(scores::Column::Points.gt(best.points))
+            .count(&self.db)
+            .await
+            .ok()?;
+        Some(ahead + 1)
+    }
+
     pub async fn record_purchase(&self, user_id: Uuid, sku: &str) -> Result<Uuid> {
         let id = Uuid::new_v4();
         let purchase = purchases::ActiveModel {
@@ -160,17 +423,115 @@ impl LeaderboardService {
     pub async fn get_replay(&self, run_id: Uuid) -> Option<Vec<u8>> {
         if let Ok(Some(run)) = runs::Entity::find_by_id(run_id).one(&self.db).await {
             let path = self.replay_dir.join(run.replay_path);
-            return tokio::fs::read(path).await.ok();
+            let data = tokio::fs::read(path).await.ok()?;
+            return decompress_replay(data).ok();
         }
         None
     }
 
-    pub async fn verify_run(&self, _run_id: Uuid) -> bool {
-        // Updating verification status is left as future work.
-        false
+    /// Open the run's replay without reading it into memory.
+    pub async fn open_replay(&self, run_id: Uuid) -> Option<ReplayFile> {
+        let run = runs::Entity::find_by_id(run_id)
+            .one(&self.db)
+            .await
+            .ok()??;
+        if run.replay_path.is_empty() {
+            return None;
+        }
+        let mut file = tokio::fs::File::open(self.replay_dir.join(run.replay_path))
+            .await
+            .ok()?;
+        let mut magic = [0; COMPRESSED_MAGIC.len()];
+        let gzip = file.read_exact(&mut magic).await.is_ok() && magic == *COMPRESSED_MAGIC;
+        if !gzip {
+            file.rewind().await.ok()?;
+        }
+        Some(ReplayFile { file, gzip })
+    }
+
+    /// Re-score the run's stored replay with `score_replay` and mark its
+    /// scores verified if the result matches the submitted points. A matching
+    /// replay also clears a suspected-cheat flag, so auto-flagged runs show
+    /// again once verified.
+    ///
+    /// Runs without a replay and replays that no longer decode are left
+    /// unverified, and flagged, and return `false`.
+    pub async fn verify_run(
+        &self,
+        run_id: Uuid,
+        score_replay: impl Fn(&[u8]) -> Option<i32>,
+    ) -> bool {
+        let Ok(Some(run)) = runs::Entity::find_by_id(run_id).one(&self.db).await else {
+            return false;
+        };
+        if run.replay_path.is_empty() {
+            return false;
+        }
+        let Some(replay) = self.get_replay(run_id).await else {
+            return false;
+        };
+        let Ok(Some(score)) = scores::Entity::find()
+            .filter(scores::Column::Run.eq(run_id))
+            .one(&self.db)
+            .await
+        else {
+            return false;
+        };
+        // A trimmed replay only scores its kept tail; the dropped events
+        // were scored when the run was submitted.
+        if score_replay(&replay).map(|points| points + run.trimmed_points) != Some(score.points) {
+            return false;
+        }
+        let verified = scores::Entity::update_many()
+            .col_expr(scores::Column::Verified, Expr::value(true))
+            .filter(scores::Column::Run.eq(run_id))
+            .exec(&self.db)
+            .await
+            .is_ok();
+        if !verified {
+            return false;
+        }
+        !run.flagged || self.flag_run(run_id, false).await.is_ok()
+    }
+}
+
+/// Scores on `leaderboard` from unflagged runs inside `window`.
+fn visible_scores(leaderboard: Uuid, window: LeaderboardWindow) -> Select<scores::Entity> {
+    let query = scores::Entity::find()
+        .filter(scores::Column::Leaderboard.eq(leaderboard))
+        .join(JoinType::InnerJoin, scores::Relation::Runs.def())
+        .filter(runs::Column::Flagged.eq(false));
+    let now = Utc::now();
+    match window {
+        LeaderboardWindow::Daily => {
+            query.filter(scores::Column::CreatedAt.gte(now - Duration::days(1)))
+        }
+        LeaderboardWindow::Weekly => {
+            query.filter(scores::Column::CreatedAt.gte(now - Duration::weeks(1)))
+        }
+        LeaderboardWindow::Monthly => {
+            query.filter(scores::Column::CreatedAt.gte(now - Duration::days(30)))
+        }
+        LeaderboardWindow::AllTime => query,
     }
 }
 
+fn compress_replay(replay: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(COMPRESSED_MAGIC.to_vec(), Compression::default());
+    encoder.write_all(replay)?;
+    encoder.finish()
+}
+
+/// Inflate a stored replay, passing legacy uncompressed files through.
+fn decompress_replay(data: Vec<u8>) -> io::Result<Vec<u8>> {
+    let Some(body) = data.strip_prefix(COMPRESSED_MAGIC) else {
+        return Ok(data);
+    };
+    let mut replay = Vec::new();
+    GzDecoder::new(body).read_to_end(&mut replay)?;
+    Ok(replay)
+}
+
 fn to_io_error<E: std::error::Error + Send + Sync + 'static>(e: E) -> io::Error {
     io::Error::new(io::ErrorKind::Other, e)
 }
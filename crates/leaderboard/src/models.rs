@@ -4,8 +4,13 @@ use uuid::Uuid;
 
 #[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum LeaderboardWindow {
+    #[serde(alias = "daily")]
     Daily,
+    #[serde(alias = "weekly")]
     Weekly,
+    #[serde(alias = "monthly")]
+    Monthly,
+    #[serde(alias = "all_time")]
     AllTime,
 }
 
@@ -36,6 +41,7 @@ impl LeaderboardWindow {
         match self {
             LeaderboardWindow::Daily => "daily",
             LeaderboardWindow::Weekly => "weekly",
+            LeaderboardWindow::Monthly => "monthly",
             LeaderboardWindow::AllTime => "all_time",
         }
     }
@@ -44,6 +50,7 @@ impl LeaderboardWindow {
         match s {
             "daily" => LeaderboardWindow::Daily,
             "weekly" => LeaderboardWindow::Weekly,
+            "monthly" => LeaderboardWindow::Monthly,
             _ => LeaderboardWindow::AllTime,
         }
     }
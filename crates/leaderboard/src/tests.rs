@@ -0,0 +1,487 @@
+use super::*;
+use chrono::Utc;
+use sea_orm::ConnectionTrait;
+use tempfile::TempDir;
+
+/// A service backed by an in-memory SQLite database and a temporary replay
+/// directory, which must outlive the service.
+async fn service() -> (LeaderboardService, TempDir) {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared(
+        "CREATE TABLE runs (id BLOB PRIMARY KEY, leaderboard BLOB NOT NULL, \
+         player_id TEXT NOT NULL, replay_path TEXT NOT NULL, created_at TEXT NOT NULL, \
+         flagged BOOLEAN NOT NULL, replay_index INTEGER NOT NULL, \
+         trimmed_points INTEGER NOT NULL); \
+         CREATE TABLE scores (id BLOB PRIMARY KEY, run BLOB NOT NULL, leaderboard BLOB NOT NULL, \
+         player_id TEXT NOT NULL, points INTEGER NOT NULL, created_at TEXT NOT NULL, \
+         verified BOOLEAN NOT NULL);",
+    )
+    .await
+    .unwrap();
+    let dir = TempDir::new().unwrap();
+    let service = LeaderboardService::with_db(db, dir.path().to_path_buf())
+        .await
+        .unwrap()
+        .with_max_points(None)
+        .with_max_replay_bytes(None);
+    (service, dir)
+}
+
+fn run_and_score(leaderboard: Uuid, player_id: Uuid, points: i32) -> (Run, Score) {
+    let run = Run {
+        id: Uuid::new_v4(),
+        leaderboard,
+        player_id,
+        replay_path: String::new(),
+        created_at: Utc::now(),
+        flagged: false,
+        replay_index: 0,
+    };
+    let score = Score {
+        id: Uuid::new_v4(),
+        run: run.id,
+        player_id,
+        points,
+        verified: false,
+        created_at: Utc::now(),
+        window: LeaderboardWindow::AllTime,
+    };
+    (run, score)
+}
+
+/// Test replays are just the score as little-endian bytes.
+fn score_replay(replay: &[u8]) -> Option<i32> {
+    Some(i32::from_le_bytes(replay.try_into().ok()?))
+}
+
+async fn verified(service: &LeaderboardService, run_id: Uuid) -> bool {
+    scores::Entity::find()
+        .filter(scores::Column::Run.eq(run_id))
+        .one(&service.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .verified
+}
+
+#[tokio::test]
+async fn verify_run_marks_matching_replay_verified() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 7);
+    let run_id = run.id;
+    service
+        .submit_score(leaderboard, score, run, 7i32.to_le_bytes().to_vec())
+        .await
+        .unwrap();
+
+    assert!(!verified(&service, run_id).await);
+    assert!(service.verify_run(run_id, score_replay).await);
+    assert!(verified(&service, run_id).await);
+}
+
+#[tokio::test]
+async fn verify_run_rejects_mismatched_or_missing_runs() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 7);
+    let run_id = run.id;
+    service
+        .submit_score(leaderboard, score, run, 3i32.to_le_bytes().to_vec())
+        .await
+        .unwrap();
+
+    assert!(!service.verify_run(run_id, score_replay).await);
+    assert!(!verified(&service, run_id).await);
+    assert!(!service.verify_run(Uuid::new_v4(), score_replay).await);
+}
+
+#[tokio::test]
+async fn verify_run_unflags_only_matching_replays() {
+    let (service, _dir) = service().await;
+    let service = service.with_max_points(Some(5));
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 7);
+    let run_id = run.id;
+    service
+        .submit_score(leaderboard, score, run, 7i32.to_le_bytes().to_vec())
+        .await
+        .unwrap();
+    let (forged, score) = run_and_score(leaderboard, Uuid::new_v4(), 9);
+    let forged_id = forged.id;
+    service
+        .submit_score(leaderboard, score, forged, 3i32.to_le_bytes().to_vec())
+        .await
+        .unwrap();
+    assert!(
+        service
+            .get_scores(leaderboard, LeaderboardWindow::AllTime)
+            .await
+            .is_empty()
+    );
+
+    assert!(!service.verify_run(forged_id, score_replay).await);
+    assert!(service.verify_run(run_id, score_replay).await);
+    let shown = service
+        .get_scores(leaderboard, LeaderboardWindow::AllTime)
+        .await;
+    assert_eq!(
+        shown.iter().map(|s| s.run).collect::<Vec<_>>(),
+        vec![run_id]
+    );
+    assert!(shown[0].verified);
+}
+
+async fn submit_points(service: &LeaderboardService, leaderboard: Uuid, points: &[i32]) {
+    for &p in points {
+        let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), p);
+        service
+            .submit_score(leaderboard, score, run, Vec::new())
+            .await
+            .unwrap();
+    }
+}
+
+fn points(scores: &[Score]) -> Vec<i32> {
+    scores.iter().map(|s| s.points).collect()
+}
+
+#[tokio::test]
+async fn get_scores_paged_applies_offset() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    submit_points(&service, leaderboard, &[10, 50, 30, 20, 40]).await;
+
+    let page = service
+        .get_scores_paged(leaderboard, LeaderboardWindow::AllTime, 1, 2)
+        .await;
+    assert_eq!(points(&page), vec![40, 30]);
+    let tail = service
+        .get_scores_paged(leaderboard, LeaderboardWindow::AllTime, 4, 10)
+        .await;
+    assert_eq!(points(&tail), vec![10]);
+}
+
+#[tokio::test]
+async fn get_scores_paged_clamps_limit_to_max() {
+    let (mut service, _dir) = service().await;
+    service.max = 3;
+    let leaderboard = Uuid::new_v4();
+    submit_points(&service, leaderboard, &[1, 2, 3, 4, 5]).await;
+
+    let page = service
+        .get_scores_paged(leaderboard, LeaderboardWindow::AllTime, 0, 100)
+        .await;
+    assert_eq!(points(&page), vec![5, 4, 3]);
+    assert_eq!(
+        points(
+            &service
+                .get_scores(leaderboard, LeaderboardWindow::AllTime)
+                .await
+        ),
+        vec![5, 4, 3]
+    );
+}
+
+#[tokio::test]
+async fn get_rank_counts_strictly_better_scores() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    submit_points(&service, leaderboard, &[90, 70, 70]).await;
+    let player = Uuid::new_v4();
+    for p in [10, 70] {
+        let (run, score) = run_and_score(leaderboard, player, p);
+        service
+            .submit_score(leaderboard, score, run, Vec::new())
+            .await
+            .unwrap();
+    }
+    let window = LeaderboardWindow::AllTime;
+
+    // Best score 70 ties two others behind a 90.
+    assert_eq!(service.get_rank(leaderboard, window, player).await, Some(2));
+    let leader = service.get_scores(leaderboard, window).await[0].player_id;
+    assert_eq!(service.get_rank(leaderboard, window, leader).await, Some(1));
+    assert_eq!(
+        service.get_rank(leaderboard, window, Uuid::new_v4()).await,
+        None
+    );
+}
+
+#[tokio::test]
+async fn monthly_window_excludes_scores_older_than_30_days() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    let (run, mut score) = run_and_score(leaderboard, Uuid::new_v4(), 5);
+    score.created_at = Utc::now() - chrono::Duration::days(31);
+    service
+        .submit_score(leaderboard, score, run, Vec::new())
+        .await
+        .unwrap();
+    submit_points(&service, leaderboard, &[3]).await;
+
+    let monthly = service
+        .get_scores(leaderboard, LeaderboardWindow::Monthly)
+        .await;
+    assert_eq!(points(&monthly), vec![3]);
+    let all_time = service
+        .get_scores(leaderboard, LeaderboardWindow::AllTime)
+        .await;
+    assert_eq!(points(&all_time), vec![5, 3]);
+}
+
+#[test]
+fn window_query_accepts_lowercase_names() {
+    let window: LeaderboardWindow = serde_json::from_str("\"monthly\"").unwrap();
+    assert!(window == LeaderboardWindow::Monthly);
+    assert!(serde_json::from_str::<LeaderboardWindow>("\"Monthly\"").unwrap() == window);
+}
+
+#[tokio::test]
+async fn replay_write_leaves_no_temp_files() {
+    let (service, dir) = service().await;
+    // The directory may disappear underneath a running server.
+    std::fs::remove_dir(dir.path()).unwrap();
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 1);
+    let run_id = run.id;
+    service
+        .submit_score(leaderboard, score, run, vec![1, 2, 3])
+        .await
+        .unwrap();
+
+    let names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(names, vec![run_id.to_string()]);
+    assert_eq!(service.get_replay(run_id).await, Some(vec![1, 2, 3]));
+}
+
+#[tokio::test]
+async fn compressed_replay_round_trips() {
+    let (service, dir) = service().await;
+    let service = service.with_compress_replays(true);
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 1);
+    let run_id = run.id;
+    let replay = vec![7u8; 4096];
+    service
+        .submit_score(leaderboard, score, run, replay.clone())
+        .await
+        .unwrap();
+
+    let stored = std::fs::read(dir.path().join(run_id.to_string())).unwrap();
+    assert!(stored.starts_with(COMPRESSED_MAGIC));
+    assert!(stored.len() < replay.len());
+    assert_eq!(service.get_replay(run_id).await, Some(replay));
+}
+
+#[tokio::test]
+async fn legacy_uncompressed_replay_is_read_as_is() {
+    let (service, dir) = service().await;
+    let service = service.with_compress_replays(true);
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 1);
+    let run_id = run.id;
+    service
+        .submit_score(leaderboard, score, run, vec![1])
+        .await
+        .unwrap();
+    std::fs::write(dir.path().join(run_id.to_string()), [1, 2, 3]).unwrap();
+
+    assert_eq!(service.get_replay(run_id).await, Some(vec![1, 2, 3]));
+}
+
+async fn submit_and_collect(
+    service: &LeaderboardService,
+    leaderboard: Uuid,
+    points: i32,
+) -> Vec<&'static str> {
+    let mut rx = service.subscribe();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), points);
+    service
+        .submit_score(leaderboard, score, run, Vec::new())
+        .await
+        .unwrap();
+    let mut windows = Vec::new();
+    while let Ok(snapshot) = rx.try_recv() {
+        windows.push(snapshot.window.as_str());
+    }
+    windows
+}
+
+#[tokio::test]
+async fn low_score_on_full_board_only_broadcasts_all_time() {
+    let (mut service, _dir) = service().await;
+    service.max = 2;
+    let leaderboard = Uuid::new_v4();
+    assert_eq!(
+        submit_and_collect(&service, leaderboard, 10).await,
+        ["daily", "weekly", "monthly", "all_time"]
+    );
+    submit_and_collect(&service, leaderboard, 20).await;
+
+    assert_eq!(
+        submit_and_collect(&service, leaderboard, 10).await,
+        ["all_time"]
+    );
+    assert_eq!(
+        submit_and_collect(&service, leaderboard, 15).await,
+        ["daily", "weekly", "monthly", "all_time"]
+    );
+}
+
+#[tokio::test]
+async fn flagged_run_disappears_from_scores() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 5);
+    let run_id = run.id;
+    service
+        .submit_score(leaderboard, score, run, Vec::new())
+        .await
+        .unwrap();
+    let mut rx = service.subscribe();
+
+    service.flag_run(run_id, true).await.unwrap();
+    assert!(
+        service
+            .get_scores(leaderboard, LeaderboardWindow::AllTime)
+            .await
+            .is_empty()
+    );
+    let snapshot = rx.try_recv().unwrap();
+    assert!(snapshot.scores.is_empty());
+
+    service.flag_run(run_id, false).await.unwrap();
+    assert_eq!(
+        service
+            .get_scores(leaderboard, LeaderboardWindow::AllTime)
+            .await
+            .len(),
+        1
+    );
+    assert!(service.flag_run(Uuid::new_v4(), true).await.is_err());
+}
+
+#[tokio::test]
+async fn score_over_ceiling_is_flagged_and_hidden() {
+    let (service, _dir) = service().await;
+    let service = service.with_max_points(Some(10));
+    let leaderboard = Uuid::new_v4();
+    submit_points(&service, leaderboard, &[10, 11]).await;
+
+    let shown = service
+        .get_scores(leaderboard, LeaderboardWindow::AllTime)
+        .await;
+    assert_eq!(points(&shown), vec![10]);
+    let flagged: Vec<bool> = runs::Entity::find()
+        .order_by_asc(runs::Column::Flagged)
+        .all(&service.db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|run| run.flagged)
+        .collect();
+    assert_eq!(flagged, vec![false, true]);
+}
+
+async fn read_replay_file(replay: ReplayFile) -> Vec<u8> {
+    let mut file = replay.file;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).await.unwrap();
+    bytes
+}
+
+#[tokio::test]
+async fn opened_replay_streams_written_bytes() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 1);
+    let run_id = run.id;
+    let replay: Vec<u8> = (0..=255).collect();
+    service
+        .submit_score(leaderboard, score, run, replay.clone())
+        .await
+        .unwrap();
+
+    let opened = service.open_replay(run_id).await.unwrap();
+    assert!(!opened.gzip);
+    assert_eq!(read_replay_file(opened).await, replay);
+    assert!(service.open_replay(Uuid::new_v4()).await.is_none());
+}
+
+#[tokio::test]
+async fn opened_compressed_replay_is_a_gzip_stream() {
+    let (service, _dir) = service().await;
+    let service = service.with_compress_replays(true);
+    let leaderboard = Uuid::new_v4();
+    let (run, score) = run_and_score(leaderboard, Uuid::new_v4(), 1);
+    let run_id = run.id;
+    let replay: Vec<u8> = (0..=255).collect();
+    service
+        .submit_score(leaderboard, score, run, replay.clone())
+        .await
+        .unwrap();
+
+    let opened = service.open_replay(run_id).await.unwrap();
+    assert!(opened.gzip);
+    let gzipped = read_replay_file(opened).await;
+    let mut inflated = Vec::new();
+    GzDecoder::new(&gzipped[..])
+        .read_to_end(&mut inflated)
+        .unwrap();
+    assert_eq!(inflated, replay);
+}
+
+#[tokio::test]
+async fn resume_returns_only_newer_snapshots() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    let mut rx = service.subscribe();
+    submit_and_collect(&service, leaderboard, 1).await;
+    let mut since = 0;
+    while let Ok(snapshot) = rx.try_recv() {
+        since = snapshot.seq;
+    }
+    submit_and_collect(&service, Uuid::new_v4(), 1).await;
+    submit_and_collect(&service, leaderboard, 2).await;
+
+    let missed = service.snapshots_since(leaderboard, since).unwrap();
+    assert_eq!(missed.len(), WINDOWS.len());
+    assert!(missed.iter().all(|snapshot| snapshot.seq > since));
+    assert!(
+        missed
+            .iter()
+            .all(|snapshot| snapshot.leaderboard == leaderboard)
+    );
+    assert!(missed.windows(2).all(|pair| pair[0].seq < pair[1].seq));
+    assert_eq!(missed[WINDOWS.len() - 1].scores[0].points, 2);
+
+    let latest = missed[WINDOWS.len() - 1].seq;
+    assert!(
+        service
+            .snapshots_since(leaderboard, latest)
+            .unwrap()
+            .is_empty()
+    );
+    // A token from before a restart can't be resumed.
+    assert!(service.snapshots_since(leaderboard, latest + 100).is_none());
+}
+
+#[tokio::test]
+async fn resume_from_evicted_snapshot_needs_full_fetch() {
+    let (service, _dir) = service().await;
+    let leaderboard = Uuid::new_v4();
+    for points in 0..=(HISTORY_LEN / WINDOWS.len()) as i32 {
+        submit_and_collect(&service, leaderboard, points).await;
+    }
+
+    assert!(service.snapshots_since(leaderboard, 0).is_none());
+    assert!(
+        service
+            .snapshots_since(leaderboard, WINDOWS.len() as u64)
+            .is_some()
+    );
+}
@@ -0,0 +1,117 @@
+//! Duck flight paths shared by the client spawner and the server's
+//! `spawn_wave`, so both sides agree on where every duck is for a seed.
+//!
+//! This file is compiled into both crates, so it only depends on `glam` and
+//! `rand`.
+
+use glam::Vec3;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+/// Seconds a duck takes to cross the screen.
+pub const DUCK_FLIGHT_SECS: f32 = 5.0;
+
+/// Each full step of this many units per second adds a point to a duck's
+/// value.
+const POINTS_SPEED_STEP: f32 = 2.0;
+
+/// Points awarded for hitting a duck flying at `speed` units per second.
+/// Stationary and slow ducks are worth one point; faster ones more.
+pub fn duck_points(speed: f32) -> u32 {
+    1 + (speed.max(0.0) / POINTS_SPEED_STEP) as u32
+}
+
+/// How a [`Spline`] interpolates between its control points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplineKind {
+    /// Straight segments between adjacent points.
+    #[default]
+    Linear,
+    /// A smooth curve through every point, shaped by its neighbours.
+    CatmullRom,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spline {
+    pub points: Vec<Vec3>,
+    pub duration: f32,
+    pub kind: SplineKind,
+}
+
+impl Spline {
+    pub fn sample(&self, segment: usize, t: f32) -> Vec3 {
+        match self.kind {
+            SplineKind::Linear => self.sample_linear(segment, t),
+            SplineKind::CatmullRom => self.sample_catmull_rom(segment, t),
+        }
+    }
+
+    pub fn sample_linear(&self, segment: usize, t: f32) -> Vec3 {
+        if self.points.len() < 2 {
+            return Vec3::ZERO;
+        }
+        let seg = segment.min(self.points.len() - 2);
+        let start = self.points[seg];
+        let end = self.points[seg + 1];
+        start.lerp(end, t.clamp(0.0, 1.0))
+    }
+
+    /// Uniform Catmull-Rom between `points[segment]` and
+    /// `points[segment + 1]`. The end points are repeated so the curve still
+    /// starts and ends on the first and last points.
+    pub fn sample_catmull_rom(&self, segment: usize, t: f32) -> Vec3 {
+        if self.points.len() < 2 {
+            return Vec3::ZERO;
+        }
+        let last = self.points.len() - 1;
+        let seg = segment.min(last - 1);
+        let p0 = self.points[seg.saturating_sub(1)];
+        let p1 = self.points[seg];
+        let p2 = self.points[seg + 1];
+        let p3 = self.points[(seg + 2).min(last)];
+        let t = t.clamp(0.0, 1.0);
+        let t2 = t * t;
+        let t3 = t2 * t;
+        0.5 * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    /// Total distance along the control points.
+    pub fn length(&self) -> f32 {
+        self.points.windows(2).map(|w| w[0].distance(w[1])).sum()
+    }
+
+    /// Average speed over the whole flight.
+    pub fn speed(&self) -> f32 {
+        if self.duration > 0.0 {
+            self.length() / self.duration
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The next duck path drawn from `rng`: a straight flight across the screen
+/// in a random direction and height.
+pub fn duck_path(rng: &mut StdRng) -> Spline {
+    let start_y = rng.gen_range(0.5..2.0);
+    let end_y = rng.gen_range(0.5..2.5);
+    let left_to_right = rng.gen_bool(0.5);
+    let (start, end) = if left_to_right {
+        (Vec3::new(-5.0, start_y, 0.0), Vec3::new(5.0, end_y, 0.0))
+    } else {
+        (Vec3::new(5.0, start_y, 0.0), Vec3::new(-5.0, end_y, 0.0))
+    };
+    Spline {
+        points: vec![start, end],
+        duration: DUCK_FLIGHT_SECS,
+        kind: SplineKind::Linear,
+    }
+}
+
+/// The first `count` duck paths for `seed`.
+pub fn duck_paths(seed: u64, count: usize) -> Vec<Spline> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| duck_path(&mut rng)).collect()
+}
@@ -5,11 +5,12 @@ use leaderboard::{
     LeaderboardService,
     models::{LeaderboardWindow, Run, Score},
 };
-use rand::{Rng, SeedableRng, rngs::StdRng};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use uuid::Uuid;
 
+use crate::paths::{duck_paths, duck_points};
+
 #[cfg(test)]
 use migration::{Migrator, MigratorTrait, sea_orm::Database};
 
@@ -26,6 +27,9 @@ pub mod net {
         pub latency: Duration,
         pub ducks: Vec<DuckState>,
         pub snapshot_txs: Vec<Sender<ServerMessage>>,
+        /// Furthest back a shot may rewind the ducks; older shots are
+        /// rejected.
+        pub max_rewind: Duration,
     }
 
     impl Server {
@@ -43,6 +47,10 @@ pub mod net {
             self.latency
         }
 
+        pub fn max_rewind(&self) -> Duration {
+            self.max_rewind
+        }
+
         pub fn ducks(&self) -> &[DuckState] {
             &self.ducks
         }
@@ -53,12 +61,17 @@ pub use net::Server;
 
 const DUCK_RADIUS: f32 = 0.5;
 
+/// Default bound on lag compensation for [`Server::max_rewind`].
+pub const DEFAULT_MAX_REWIND: Duration = Duration::from_millis(500);
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct DuckState {
     pub position: Vec3,
     pub velocity: Vec3,
     pub path: Vec<Vec3>,
     pub path_index: usize,
+    /// Awarded for a hit; faster ducks are worth more.
+    pub points: u32,
 }
 
 pub fn spawn_duck(server: &mut Server, position: Vec3, velocity: Vec3) {
@@ -68,6 +81,7 @@ pub fn spawn_duck(server: &mut Server, position: Vec3, velocity: Vec3) {
         velocity,
         path,
         path_index: 0,
+        points: duck_points(velocity.length()),
     };
     server.ducks.push(state.clone());
     // send initial state to clients
@@ -88,6 +102,7 @@ pub fn spawn_duck_path(server: &mut Server, path: Vec<Vec3>, speed: f32) {
         velocity,
         path,
         path_index: 0,
+        points: duck_points(velocity.length()),
     };
     server.ducks.push(state.clone());
     server.broadcast(&state);
@@ -98,13 +113,12 @@ pub fn replicate(server: &Server, state: &DuckState) {
 }
 
 /// Spawn a deterministic wave of ducks using the provided `seed`.
-/// This allows tests and replays to reproduce identical spawns.
+/// This allows tests and replays to reproduce identical spawns, and the
+/// paths match the ones the client spawns for the same seed.
 pub fn spawn_wave(server: &mut Server, seed: u64, count: usize) {
-    let mut rng = StdRng::seed_from_u64(seed);
-    for _ in 0..count {
-        let position = Vec3::new(rng.gen_range(-5.0..5.0), rng.gen_range(0.5..2.5), 0.0);
-        let velocity = Vec3::new(rng.gen_range(-1.0..1.0), 0.0, 0.0);
-        spawn_duck(server, position, velocity);
+    for spline in duck_paths(seed, count) {
+        let speed = spline.speed();
+        spawn_duck_path(server, spline.points, speed);
     }
 }
 
@@ -135,19 +149,29 @@ pub fn advance_ducks(server: &mut Server, dt: f32) {
     }
 }
 
-pub fn validate_hit(server: &Server, origin: Vec3, direction: Vec3, shot_time: Duration) -> bool {
-    let rewind = shot_time + server.latency();
+/// Check a shot against the ducks' rewound positions. Returns the hit
+/// duck's point value, or `None` on a miss.
+///
+/// The rewind is capped at [`Server::max_rewind`], and shots claiming to be
+/// older than that are rejected outright so a client can't rewind ducks
+/// arbitrarily far.
+pub fn validate_hit(
+    server: &Server,
+    origin: Vec3,
+    direction: Vec3,
+    shot_time: Duration,
+) -> Option<u32> {
+    if shot_time > server.max_rewind() {
+        return None;
+    }
+    let rewind = (shot_time + server.latency()).min(server.max_rewind());
     let rewind_secs = rewind.as_secs_f32();
     let dir = direction.normalize();
 
-    for duck in server.ducks() {
+    server.ducks().iter().find_map(|duck| {
         let center = duck.position - duck.velocity * rewind_secs;
-        if ray_sphere_intersect(origin, dir, center, DUCK_RADIUS) {
-            return true;
-        }
-    }
-
-    false
+        ray_sphere_intersect(origin, dir, center, DUCK_RADIUS).then_some(duck.points)
+    })
 }
 
 pub fn serialize_replay(origin: Vec3, direction: Vec3, time: f32) -> Vec<u8> {
@@ -175,11 +199,11 @@ pub async fn handle_shot(
     direction: Vec3,
     shot_time: Duration,
     replay: Vec<u8>,
-) -> bool {
+) -> Option<u32> {
     if let Some(a) = analytics {
         a.dispatch(Event::ShotFired);
     }
-    if validate_hit(server, origin, direction, shot_time) {
+    if let Some(points) = validate_hit(server, origin, direction, shot_time) {
         if let Some(a) = analytics {
             a.dispatch(Event::TargetHit);
             a.dispatch(Event::DamageTaken);
@@ -200,7 +224,7 @@ pub async fn handle_shot(
             id: Uuid::new_v4(),
             run: run_id,
             player_id,
-            points: 1,
+            points: points as i32,
             verified: true,
             created_at: Utc::now(),
             window: LeaderboardWindow::AllTime,
@@ -211,9 +235,9 @@ pub async fn handle_shot(
         if let Some(a) = analytics {
             a.dispatch(Event::LeaderboardSubmit);
         }
-        return true;
+        return Some(points);
     }
-    false
+    None
 }
 
 fn ray_sphere_intersect(origin: Vec3, dir: Vec3, center: Vec3, radius: f32) -> bool {
@@ -241,12 +265,14 @@ mod tests {
                 velocity: Vec3::ZERO,
                 path: Vec::new(),
                 path_index: 0,
+                points: 1,
             }],
             snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
         };
 
         let hit = validate_hit(&server, Vec3::ZERO, Vec3::Z, Duration::from_secs_f32(0.0));
-        assert!(hit);
+        assert!(hit.is_some());
     }
 
     #[test]
@@ -258,12 +284,53 @@ mod tests {
                 velocity: Vec3::new(10.0, 0.0, 0.0),
                 path: Vec::new(),
                 path_index: 0,
+                points: 1,
             }],
             snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
         };
 
         let hit = validate_hit(&server, Vec3::ZERO, Vec3::Z, Duration::from_secs_f32(0.0));
-        assert!(hit);
+        assert!(hit.is_some());
+    }
+
+    #[test]
+    fn shot_within_rewind_window_hits() {
+        let server = Server {
+            latency: Duration::from_secs_f32(0.0),
+            ducks: vec![DuckState {
+                position: Vec3::new(3.0, 0.0, 5.0),
+                velocity: Vec3::new(10.0, 0.0, 0.0),
+                path: Vec::new(),
+                path_index: 0,
+                points: 1,
+            }],
+            snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
+        };
+
+        let hit = validate_hit(&server, Vec3::ZERO, Vec3::Z, Duration::from_millis(300));
+        assert!(hit.is_some());
+    }
+
+    #[test]
+    fn shot_claiming_excessive_rewind_is_rejected() {
+        let server = Server {
+            latency: Duration::from_secs_f32(0.0),
+            ducks: vec![DuckState {
+                position: Vec3::new(10.0, 0.0, 5.0),
+                velocity: Vec3::new(10.0, 0.0, 0.0),
+                path: Vec::new(),
+                path_index: 0,
+                points: 1,
+            }],
+            snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
+        };
+
+        // Rewinding a full second would line the duck up with the shot.
+        let hit = validate_hit(&server, Vec3::ZERO, Vec3::Z, Duration::from_secs(1));
+        assert!(hit.is_none());
     }
 
     #[test]
@@ -275,12 +342,31 @@ mod tests {
                 velocity: Vec3::ZERO,
                 path: Vec::new(),
                 path_index: 0,
+                points: 1,
             }],
             snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
         };
 
         let hit = validate_hit(&server, Vec3::ZERO, Vec3::X, Duration::from_secs_f32(0.0));
-        assert!(!hit);
+        assert!(hit.is_none());
+    }
+
+    #[test]
+    fn fast_duck_is_worth_more_than_slow_duck() {
+        let hit_points = |velocity: Vec3| {
+            let mut server = Server {
+                latency: Duration::from_secs_f32(0.0),
+                ducks: Vec::new(),
+                snapshot_txs: Vec::new(),
+                max_rewind: DEFAULT_MAX_REWIND,
+            };
+            spawn_duck(&mut server, Vec3::new(0.0, 0.0, 5.0), velocity);
+            validate_hit(&server, Vec3::ZERO, Vec3::Z, Duration::from_secs_f32(0.0))
+        };
+        let slow = hit_points(Vec3::new(0.5, 0.0, 0.0)).expect("slow duck hit");
+        let fast = hit_points(Vec3::new(8.0, 0.0, 0.0)).expect("fast duck hit");
+        assert!(fast > slow);
     }
 
     #[test]
@@ -292,8 +378,10 @@ mod tests {
                 velocity: Vec3::new(1.0, 0.0, 0.0),
                 path: Vec::new(),
                 path_index: 0,
+                points: 1,
             }],
             snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
         };
         advance_ducks(&mut server, 1.0);
         assert_eq!(server.ducks[0].position, Vec3::new(1.0, 0.0, 0.0));
@@ -314,8 +402,10 @@ mod tests {
                 velocity: Vec3::ZERO,
                 path: Vec::new(),
                 path_index: 0,
+                points: 1,
             }],
             snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
         };
         let leaderboard_id = Uuid::new_v4();
         let player_id = Uuid::new_v4();
@@ -332,7 +422,7 @@ mod tests {
             replay.clone(),
         )
         .await;
-        assert!(hit);
+        assert_eq!(hit, Some(1));
         let scores = service
             .get_scores(leaderboard_id, LeaderboardWindow::AllTime)
             .await;
@@ -357,8 +447,10 @@ mod tests {
                 velocity: Vec3::ZERO,
                 path: Vec::new(),
                 path_index: 0,
+                points: 1,
             }],
             snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
         };
         let leaderboard_id = Uuid::new_v4();
         let player_id = Uuid::new_v4();
@@ -376,7 +468,7 @@ mod tests {
             replay,
         )
         .await;
-        assert!(hit);
+        assert_eq!(hit, Some(1));
         assert_eq!(
             analytics.events(),
             vec![
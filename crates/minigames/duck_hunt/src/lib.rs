@@ -4,6 +4,8 @@ use platform_api::{
     AppState, CapabilityFlags, GameModule, ModuleContext, ModuleMetadata, ServerApp,
 };
 
+#[path = "../paths.rs"]
+pub mod paths;
 #[path = "../server.rs"]
 pub mod server;
 
@@ -13,6 +15,25 @@ pub struct Score(pub u32);
 #[derive(Resource, Debug)]
 pub struct Multiplier(pub u32);
 
+/// Seconds without a hit before the multiplier drops back to 1.
+pub const DEFAULT_MULTIPLIER_DECAY: f32 = 3.0;
+
+/// Time since the last hit, and how long the multiplier survives without one.
+#[derive(Resource, Debug)]
+pub struct MultiplierDecay {
+    pub since_hit: f32,
+    pub window: f32,
+}
+
+impl Default for MultiplierDecay {
+    fn default() -> Self {
+        Self {
+            since_hit: 0.0,
+            window: DEFAULT_MULTIPLIER_DECAY,
+        }
+    }
+}
+
 #[derive(Resource, Debug)]
 pub struct Ammo(pub u32);
 
@@ -53,6 +74,7 @@ fn setup(world: &mut World) {
     });
     world.insert_resource(Score::default());
     world.insert_resource(Multiplier(1));
+    world.insert_resource(MultiplierDecay::default());
     world.insert_resource(Ammo(0));
     world.insert_resource(RoundTimer { remaining: 0.0 });
     world.insert_resource(Rtt(0.0));
@@ -62,6 +84,7 @@ fn cleanup(world: &mut World) {
     world.remove_resource::<HudProfile>();
     world.remove_resource::<Score>();
     world.remove_resource::<Multiplier>();
+    world.remove_resource::<MultiplierDecay>();
     world.remove_resource::<Ammo>();
     world.remove_resource::<RoundTimer>();
     world.remove_resource::<Rtt>();
@@ -78,6 +101,9 @@ pub fn award_score(world: &mut World, points: u32) {
         let mut score = world.get_resource_or_insert_with(Score::default);
         score.0 += points * mult_value;
     }
+    world
+        .get_resource_or_insert_with(MultiplierDecay::default)
+        .since_hit = 0.0;
     let score_val = world.get_resource::<Score>().map(|s| s.0).unwrap_or(0);
     if let Some(mut hud) = world.get_resource_mut::<HudProfile>() {
         hud.score = score_val;
@@ -85,9 +111,15 @@ pub fn award_score(world: &mut World, points: u32) {
     }
 }
 
-pub fn start_round(world: &mut World, duration: f32, ammo: u32) {
+/// Start a round lasting `duration` seconds. The multiplier resets after
+/// `decay_window` seconds without a hit.
+pub fn start_round(world: &mut World, duration: f32, ammo: u32, decay_window: f32) {
     world.insert_resource(RoundTimer { remaining: duration });
     world.insert_resource(Multiplier(1));
+    world.insert_resource(MultiplierDecay {
+        since_hit: 0.0,
+        window: decay_window,
+    });
     world.insert_resource(Ammo(ammo));
     if let Some(mut hud) = world.get_resource_mut::<HudProfile>() {
         hud.timer = duration;
@@ -96,6 +128,30 @@ pub fn start_round(world: &mut World, duration: f32, ammo: u32) {
     }
 }
 
+/// Advance the time since the last hit, dropping the multiplier back to 1
+/// once it exceeds the decay window.
+pub fn tick_multiplier_decay(world: &mut World, dt: f32) {
+    let expired = {
+        let Some(mut decay) = world.get_resource_mut::<MultiplierDecay>() else {
+            return;
+        };
+        decay.since_hit += dt;
+        decay.since_hit >= decay.window
+    };
+    if !expired {
+        return;
+    }
+    if let Some(mut mult) = world.get_resource_mut::<Multiplier>() {
+        if mult.0 == 1 {
+            return;
+        }
+        mult.0 = 1;
+    }
+    if let Some(mut hud) = world.get_resource_mut::<HudProfile>() {
+        hud.multiplier = 1;
+    }
+}
+
 pub fn tick_round(world: &mut World, dt: f32) {
     let mut finished = false;
     if let Some(mut timer) = world.get_resource_mut::<RoundTimer>() {
@@ -141,6 +197,7 @@ impl GameModule for DuckHuntModule {
             capabilities: CapabilityFlags::LOBBY_PAD,
             max_players: 4,
             icon: Handle::default(),
+            icon_path: None,
         }
     }
 
@@ -1,6 +1,9 @@
 use duck_hunt_server::{
-    server::{replicate, spawn_duck, spawn_wave, Server, DuckState},
+    paths::duck_paths,
+    server::{advance_ducks, replicate, spawn_duck, spawn_wave, Server, DuckState, DEFAULT_MAX_REWIND},
     award_score,
+    start_round,
+    tick_multiplier_decay,
     DuckHuntModule,
     Score,
     Multiplier,
@@ -14,15 +17,31 @@ use std::time::Duration;
 
 #[test]
 fn spawns_are_deterministic() {
-    let mut server_a = Server { latency: Duration::from_secs(0), ducks: vec![], snapshot_txs: Vec::new() };
+    let mut server_a = Server { latency: Duration::from_secs(0), ducks: vec![], snapshot_txs: Vec::new(), max_rewind: DEFAULT_MAX_REWIND };
     spawn_wave(&mut server_a, 42, 3);
     let ducks_a = server_a.ducks.clone();
 
-    let mut server_b = Server { latency: Duration::from_secs(0), ducks: vec![], snapshot_txs: Vec::new() };
+    let mut server_b = Server { latency: Duration::from_secs(0), ducks: vec![], snapshot_txs: Vec::new(), max_rewind: DEFAULT_MAX_REWIND };
     spawn_wave(&mut server_b, 42, 3);
     assert_eq!(ducks_a, server_b.ducks);
 }
 
+#[test]
+fn server_ducks_follow_client_paths() {
+    let mut server = Server { latency: Duration::from_secs(0), ducks: vec![], snapshot_txs: Vec::new(), max_rewind: DEFAULT_MAX_REWIND };
+    spawn_wave(&mut server, 7, 4);
+    // The client samples these same splines for seed 7.
+    let paths = duck_paths(7, 4);
+    assert_eq!(server.ducks.len(), paths.len());
+    for second in 0..4 {
+        for (duck, spline) in server.ducks.iter().zip(&paths) {
+            let expected = spline.sample(0, second as f32 / spline.duration);
+            assert!(duck.position.distance(expected) < 1e-3);
+        }
+        advance_ducks(&mut server, 1.0);
+    }
+}
+
 #[test]
 fn scoring_accumulates_with_multiplier() {
     let mut world = World::new();
@@ -36,10 +55,31 @@ fn scoring_accumulates_with_multiplier() {
     assert_eq!(mult.0, 3); // multiplier advanced twice
 }
 
+#[test]
+fn multiplier_decays_without_hits() {
+    let mut world = World::new();
+    let mut ctx = ModuleContext::new(&mut world);
+    DuckHuntModule::enter(&mut ctx).unwrap();
+    start_round(&mut world, 90.0, 6, 2.0);
+    award_score(&mut world, 1);
+    award_score(&mut world, 1);
+    assert_eq!(world.get_resource::<Multiplier>().unwrap().0, 3);
+
+    tick_multiplier_decay(&mut world, 1.5);
+    assert_eq!(world.get_resource::<Multiplier>().unwrap().0, 3);
+    // A hit restarts the window.
+    award_score(&mut world, 1);
+    tick_multiplier_decay(&mut world, 1.5);
+    assert_eq!(world.get_resource::<Multiplier>().unwrap().0, 4);
+
+    tick_multiplier_decay(&mut world, 1.0);
+    assert_eq!(world.get_resource::<Multiplier>().unwrap().0, 1);
+}
+
 #[tokio::test]
 async fn replication_broadcasts_state() {
     let (tx, mut rx) = mpsc::channel(1);
-    let mut server = Server { latency: Duration::from_secs(0), ducks: vec![], snapshot_txs: vec![tx] };
+    let mut server = Server { latency: Duration::from_secs(0), ducks: vec![], snapshot_txs: vec![tx], max_rewind: DEFAULT_MAX_REWIND };
     spawn_duck(&mut server, Vec3::ZERO, Vec3::X);
     // initial broadcast from spawn
     rx.try_recv().expect("baseline missing");
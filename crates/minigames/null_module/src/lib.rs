@@ -24,6 +24,7 @@ impl GameModule for NullModule {
             capabilities: CapabilityFlags::empty(),
             max_players: 1,
             icon: Handle::default(),
+            icon_path: None,
         }
     }
 
@@ -0,0 +1,58 @@
+use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
+
+/// Delivery guarantees of a WebRTC data channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelReliability {
+    /// Unordered and never retransmitted; for snapshots and input frames
+    /// where a newer message supersedes a lost one.
+    Unreliable,
+    /// Ordered and retransmitted until delivered; for control messages that
+    /// must not be lost.
+    Reliable,
+}
+
+impl ChannelReliability {
+    /// Label the channel is negotiated under, so the server can tell the
+    /// channels apart.
+    pub fn label(self) -> &'static str {
+        match self {
+            ChannelReliability::Unreliable => "gamedata",
+            ChannelReliability::Reliable => "control",
+        }
+    }
+
+    /// Options to open a channel with this reliability.
+    pub fn init(self) -> RTCDataChannelInit {
+        match self {
+            ChannelReliability::Unreliable => RTCDataChannelInit {
+                ordered: Some(false),
+                max_retransmits: Some(0),
+                ..Default::default()
+            },
+            ChannelReliability::Reliable => RTCDataChannelInit {
+                ordered: Some(true),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreliable_channel_is_unordered_without_retransmits() {
+        let init = ChannelReliability::Unreliable.init();
+        assert_eq!(init.ordered, Some(false));
+        assert_eq!(init.max_retransmits, Some(0));
+    }
+
+    #[test]
+    fn reliable_channel_is_ordered_with_unlimited_retransmits() {
+        let init = ChannelReliability::Reliable.init();
+        assert_eq!(init.ordered, Some(true));
+        assert_eq!(init.max_retransmits, None);
+        assert_eq!(init.max_packet_life_time, None);
+    }
+}
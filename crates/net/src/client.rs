@@ -1,5 +1,6 @@
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -8,13 +9,16 @@ use bytes::Bytes;
 use wasm_bindgen_futures::spawn_local;
 use webrtc::api::media_engine::MediaEngine;
 use webrtc::api::APIBuilder;
-use webrtc::data_channel::data_channel_init::RTCDataChannelInit;
 use webrtc::data_channel::data_channel_message::DataChannelMessage;
 use webrtc::data_channel::RTCDataChannel;
 use webrtc::peer_connection::configuration::RTCConfiguration;
 use webrtc::peer_connection::RTCPeerConnection;
 
-use crate::message::{apply_delta, ClientMessage, InputFrame, ServerMessage, Snapshot};
+pub use webrtc::ice_transport::ice_server::RTCIceServer;
+
+use crate::CurrentRtt;
+use crate::channel::ChannelReliability;
+use crate::message::{apply_delta, ClientMessage, EntityMask, InputFrame, ServerMessage, Snapshot};
 
 #[async_trait]
 pub trait DataSender: Send + Sync {
@@ -29,10 +33,15 @@ impl DataSender for RTCDataChannel {
 }
 
 static DATA_CHANNEL: Mutex<Option<Arc<dyn DataSender>>> = Mutex::new(None);
+static RELIABLE_CHANNEL: Mutex<Option<Arc<dyn DataSender>>> = Mutex::new(None);
 const SNAPSHOT_QUEUE_CAPACITY: usize = 64;
 static SNAPSHOT_QUEUE: Mutex<VecDeque<Snapshot>> = Mutex::new(VecDeque::new());
 static LAST_SNAPSHOT: Mutex<Option<Snapshot>> = Mutex::new(None);
 static CONNECTION_EVENTS: Mutex<VecDeque<ConnectionEvent>> = Mutex::new(VecDeque::new());
+/// Send timestamps echoed back by the server, awaiting [`apply_pongs`].
+static PONGS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+/// How often [`send_pings`] probes the round-trip time.
+const PING_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Events describing the state of the underlying connection.
 #[derive(Debug, Clone, Event)]
@@ -45,26 +54,61 @@ pub enum ConnectionEvent {
 /// Handles the client side of the WebRTC connection.
 pub struct ClientConnector {
     pc: RTCPeerConnection,
-    _dc: Arc<RTCDataChannel>,
+    reliable: Arc<RTCDataChannel>,
+    /// Servers the connection was built with, reused when reconnecting.
+    ice_servers: Vec<RTCIceServer>,
+    /// Signaling endpoint from the last [`ClientConnector::signal`], reused
+    /// when reconnecting.
+    signal_url: Mutex<Option<String>>,
 }
 
 impl ClientConnector {
-    /// Create a new connector with a single unreliable data channel.
+    /// Create a new connector with an unreliable channel for snapshots and
+    /// input, and a reliable one for control messages.
     pub async fn new() -> Result<Self> {
+        Self::with_ice_servers(Vec::new()).await
+    }
+
+    /// Like [`ClientConnector::new`], gathering candidates through the given
+    /// STUN/TURN servers, e.g. those served in `/config.json`.
+    pub async fn with_ice_servers(ice_servers: Vec<RTCIceServer>) -> Result<Self> {
         let mut m = MediaEngine::default();
         m.register_default_codecs()?;
         let api = APIBuilder::new().with_media_engine(m).build();
-        let pc = api.new_peer_connection(RTCConfiguration::default()).await?;
-        let cfg = RTCDataChannelInit {
-            ordered: Some(false),
-            max_retransmits: Some(0),
+        let config = RTCConfiguration {
+            ice_servers: ice_servers.clone(),
             ..Default::default()
         };
-        let dc = pc.create_data_channel("gamedata", Some(cfg)).await?;
-        setup_channel(&dc);
-        let dc_trait: Arc<dyn DataSender> = dc.clone();
+        let pc = api.new_peer_connection(config).await?;
+        let unreliable = open_channel(&pc, ChannelReliability::Unreliable).await?;
+        setup_channel(&unreliable);
+        let reliable = open_channel(&pc, ChannelReliability::Reliable).await?;
+        handle_server_messages(&reliable);
+        let dc_trait: Arc<dyn DataSender> = unreliable.clone();
         *DATA_CHANNEL.lock().unwrap_or_else(|e| e.into_inner()) = Some(dc_trait);
-        Ok(Self { pc, _dc: dc })
+        let dc_trait: Arc<dyn DataSender> = reliable.clone();
+        *RELIABLE_CHANNEL.lock().unwrap_or_else(|e| e.into_inner()) = Some(dc_trait);
+        Ok(Self {
+            pc,
+            reliable,
+            ice_servers,
+            signal_url: Mutex::new(None),
+        })
+    }
+
+    /// Signaling endpoint to reconnect to, once [`ClientConnector::signal`]
+    /// has run.
+    pub fn signal_url(&self) -> Option<String> {
+        self.signal_url
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// ICE servers the connection gathers candidates through, reused when
+    /// reconnecting.
+    pub fn ice_servers(&self) -> &[RTCIceServer] {
+        &self.ice_servers
     }
 
     /// Perform signaling over a WebSocket endpoint, exchanging an SDP offer and answer.
@@ -76,6 +120,7 @@ impl ClientConnector {
         use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
         use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 
+        *self.signal_url.lock().unwrap_or_else(|e| e.into_inner()) = Some(url.to_string());
         let offer = self.pc.create_offer(None).await?;
         self.pc.set_local_description(offer.clone()).await?;
 
@@ -106,38 +151,75 @@ impl ClientConnector {
         Ok(())
     }
 
-    /// Close the underlying connection.
+    /// Tell the server this client is leaving, then close the underlying
+    /// connection.
     pub async fn close(self) -> Result<()> {
+        if let Ok(bytes) = postcard::to_allocvec(&ClientMessage::Disconnect) {
+            // Best-effort: the server still notices a silent close later.
+            let _ = self.reliable.send(&Bytes::from(bytes)).await;
+        }
         self.pc.close().await?;
         Ok(())
     }
 }
 
+async fn open_channel(
+    pc: &RTCPeerConnection,
+    reliability: ChannelReliability,
+) -> Result<Arc<RTCDataChannel>> {
+    Ok(pc
+        .create_data_channel(reliability.label(), Some(reliability.init()))
+        .await?)
+}
+
+/// A closed peer connection can't be renegotiated, so each attempt builds a
+/// new connector with fresh channels and signals it.
+#[cfg(target_arch = "wasm32")]
+#[async_trait(?Send)]
+impl crate::reconnect::Reconnect for ClientConnector {
+    type Connection = ClientConnector;
+
+    async fn reconnect(&self, url: &str) -> Result<ClientConnector> {
+        // Best-effort: the old connection is already dead.
+        let _ = self.pc.close().await;
+        let connector = ClientConnector::with_ice_servers(self.ice_servers().to_vec()).await?;
+        if let Err(e) = connector.signal(url).await {
+            let _ = connector.pc.close().await;
+            return Err(e);
+        }
+        Ok(connector)
+    }
+}
+
+/// Queue a connection state change for [`apply_connection_events`].
+pub(crate) fn push_connection_event(event: ConnectionEvent) {
+    CONNECTION_EVENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push_back(event);
+}
+
 fn setup_channel(dc: &Arc<RTCDataChannel>) {
     dc.on_open(Box::new(|| {
-        CONNECTION_EVENTS
-            .lock()
-            .unwrap_or_else(|e| e.into_inner())
-            .push_back(ConnectionEvent::Open);
+        push_connection_event(ConnectionEvent::Open);
         Box::pin(async {})
     }));
 
     dc.on_close(Box::new(|| {
-        CONNECTION_EVENTS
-            .lock()
-            .unwrap_or_else(|e| e.into_inner())
-            .push_back(ConnectionEvent::Closed);
+        push_connection_event(ConnectionEvent::Closed);
         Box::pin(async {})
     }));
 
     dc.on_error(Box::new(|e| {
-        CONNECTION_EVENTS
-            .lock()
-            .unwrap_or_else(|e| e.into_inner())
-            .push_back(ConnectionEvent::Error(e.to_string()));
+        push_connection_event(ConnectionEvent::Error(e.to_string()));
         Box::pin(async {})
     }));
 
+    handle_server_messages(dc);
+}
+
+/// Decode [`ServerMessage`]s arriving on `dc`.
+fn handle_server_messages(dc: &Arc<RTCDataChannel>) {
     dc.on_message(Box::new(|msg: DataChannelMessage| {
         if !msg.is_string {
             if let Ok(msg) = postcard::from_bytes::<ServerMessage>(&msg.data) {
@@ -169,6 +251,9 @@ fn setup_channel(dc: &Arc<RTCDataChannel>) {
                             }
                         }
                     }
+                    ServerMessage::Pong(sent) => {
+                        PONGS.lock().unwrap_or_else(|e| e.into_inner()).push(sent);
+                    }
                 }
             }
         }
@@ -203,23 +288,59 @@ pub fn send_input_frames(mut reader: EventReader<InputFrame>) {
     }
 }
 
-/// Update the server with a new interest mask describing which entities this
-/// client cares about. The mask is sent over the data channel.
-pub fn set_interest_mask(mask: u64) {
-    if let Some(dc) = DATA_CHANNEL
+/// Send `bytes` over the reliable control channel, which retransmits until
+/// delivered and preserves order.
+pub fn send_reliable(bytes: Vec<u8>) {
+    if let Some(dc) = RELIABLE_CHANNEL
         .lock()
         .unwrap_or_else(|e| e.into_inner())
         .clone()
     {
-        let msg = ClientMessage::Interest(mask);
-        if let Ok(bytes) = postcard::to_allocvec(&msg) {
-            spawn_local(async move {
-                send_bytes(dc, bytes).await;
-            });
-        }
+        spawn_local(async move {
+            send_bytes(dc, bytes).await;
+        });
+    }
+}
+
+/// Update the server with a new interest mask describing which entities this
+/// client cares about. The mask is sent over the reliable channel so a lost
+/// update can't leave the server filtering on a stale mask.
+pub fn set_interest_mask(mask: EntityMask) {
+    let msg = ClientMessage::Interest(mask);
+    if let Ok(bytes) = postcard::to_allocvec(&msg) {
+        send_reliable(bytes);
+    }
+}
+
+/// Probe the round-trip time over the reliable channel every
+/// [`PING_INTERVAL`], stamping each ping with the app's elapsed time.
+pub fn send_pings(time: Res<Time>, mut last_sent: Local<Option<Duration>>) {
+    let now = time.elapsed();
+    if last_sent.is_some_and(|last| now - last < PING_INTERVAL) {
+        return;
+    }
+    *last_sent = Some(now);
+    let msg = ClientMessage::Ping(now.as_millis() as u64);
+    if let Ok(bytes) = postcard::to_allocvec(&msg) {
+        send_reliable(bytes);
     }
 }
 
+/// Update [`CurrentRtt`] from pongs received since the last frame.
+pub fn apply_pongs(time: Res<Time>, mut rtt: ResMut<CurrentRtt>) {
+    let now = time.elapsed().as_millis() as u64;
+    let mut pongs = PONGS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(sent) = pongs.drain(..).max() {
+        rtt.0 = rtt_between(sent, now);
+    }
+}
+
+/// Round-trip time of a ping sent at `sent_ms` whose pong arrived at
+/// `received_ms`, both on the same clock.
+fn rtt_between(sent_ms: u64, received_ms: u64) -> Duration {
+    Duration::from_millis(received_ms.saturating_sub(sent_ms))
+}
+
 /// Apply incoming [`Snapshot`] messages by emitting events into the world.
 ///
 /// Snapshots are stored in a bounded queue. If the queue is full when a new
@@ -281,6 +402,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rtt_is_time_between_ping_and_pong() {
+        assert_eq!(rtt_between(1_000, 1_042), Duration::from_millis(42));
+        // A pong stamped after the local clock reset never goes negative.
+        assert_eq!(rtt_between(1_000, 10), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn connector_opens_unreliable_and_reliable_channels() {
+        let connector = ClientConnector::new().await.unwrap();
+        // Inputs go out over the unreliable channel `send_input_frames` reads.
+        assert!(
+            DATA_CHANNEL
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_some()
+        );
+        assert_eq!(
+            connector.reliable.label(),
+            ChannelReliability::Reliable.label()
+        );
+        assert!(connector.reliable.ordered());
+        connector.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn connector_accepts_ice_servers() {
+        let connector = ClientConnector::with_ice_servers(vec![RTCIceServer {
+            urls: vec!["stun:stun.example.com:3478".into()],
+            ..Default::default()
+        }])
+        .await
+        .unwrap();
+        connector.close().await.unwrap();
+    }
+
     #[tokio::test]
     async fn logs_error_when_send_fails() {
         let buf = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
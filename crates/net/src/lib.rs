@@ -1,5 +1,7 @@
+pub mod channel;
 pub mod client;
 pub mod message;
+pub mod reconnect;
 pub mod server;
 
 use bevy::prelude::*;
@@ -8,6 +10,11 @@ use bevy::prelude::*;
 #[derive(Resource, Default)]
 pub struct CurrentFrame(pub u32);
 
+/// Latest measured round-trip time to the server; zero until the first
+/// pong arrives.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq)]
+pub struct CurrentRtt(pub std::time::Duration);
+
 fn advance_frame(mut frame: ResMut<CurrentFrame>) {
     frame.0 = frame.0.wrapping_add(1);
 }
@@ -18,6 +25,7 @@ pub struct NetPlugin;
 impl Plugin for NetPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CurrentFrame::default())
+            .insert_resource(CurrentRtt::default())
             .add_event::<message::InputFrame>()
             .add_event::<message::Snapshot>()
             .add_event::<client::ConnectionEvent>()
@@ -28,6 +36,8 @@ impl Plugin for NetPlugin {
                     client::send_input_frames,
                     client::apply_snapshots,
                     client::apply_connection_events,
+                    client::send_pings,
+                    client::apply_pongs,
                 ),
             );
     }
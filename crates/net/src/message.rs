@@ -17,7 +17,96 @@ pub enum ClientMessage {
     /// An input frame for the given simulation step.
     Input(InputFrame),
     /// Update the client's interest mask for snapshot filtering.
-    Interest(u64),
+    Interest(EntityMask),
+    /// Round-trip probe carrying the client's send time in milliseconds,
+    /// echoed back in [`ServerMessage::Pong`].
+    Ping(u64),
+    /// The client is leaving; the server frees its slot right away instead
+    /// of waiting for the channel to close.
+    Disconnect,
+}
+
+/// Growable set of entity indices, used for interest and change masks.
+///
+/// Indices past the stored chunks all take the value of `rest`, so
+/// [`EntityMask::all`] covers any number of entities.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityMask {
+    chunks: Vec<u64>,
+    rest: bool,
+}
+
+impl EntityMask {
+    /// A mask containing no entities.
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// A mask containing every entity.
+    pub fn all() -> Self {
+        Self {
+            chunks: Vec::new(),
+            rest: true,
+        }
+    }
+
+    /// A mask containing exactly `indices`.
+    pub fn from_indices(indices: impl IntoIterator<Item = usize>) -> Self {
+        let mut mask = Self::none();
+        for index in indices {
+            mask.insert(index);
+        }
+        mask
+    }
+
+    /// Add `index` to the mask.
+    pub fn insert(&mut self, index: usize) {
+        let (chunk, bit) = (index / 64, index % 64);
+        if chunk >= self.chunks.len() {
+            if self.rest {
+                return;
+            }
+            self.chunks.resize(chunk + 1, 0);
+        }
+        self.chunks[chunk] |= 1 << bit;
+    }
+
+    /// Returns `true` if `index` is in the mask.
+    pub fn contains(&self, index: usize) -> bool {
+        self.chunk(index / 64) & (1 << (index % 64)) != 0
+    }
+
+    /// Returns `true` if the masks share at least one entity.
+    pub fn intersects(&self, other: &Self) -> bool {
+        (self.rest && other.rest)
+            || (0..self.chunks.len().max(other.chunks.len()))
+                .any(|i| self.chunk(i) & other.chunk(i) != 0)
+    }
+
+    /// Returns `true` if every entity in `self` is also in `other`.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        (!self.rest || other.rest)
+            && (0..self.chunks.len().max(other.chunks.len()))
+                .all(|i| self.chunk(i) & !other.chunk(i) == 0)
+    }
+
+    fn chunk(&self, i: usize) -> u64 {
+        match self.chunks.get(i) {
+            Some(&chunk) => chunk,
+            None if self.rest => u64::MAX,
+            None => 0,
+        }
+    }
+}
+
+impl From<u64> for EntityMask {
+    /// A mask over the first 64 entities, one bit each.
+    fn from(bits: u64) -> Self {
+        Self {
+            chunks: vec![bits],
+            rest: false,
+        }
+    }
 }
 
 /// Full state snapshot from the server.
@@ -45,6 +134,8 @@ pub enum ServerMessage {
     Baseline(Snapshot),
     /// Delta-compressed snapshot relative to the last baseline.
     Delta(SnapshotDelta),
+    /// Echo of a [`ClientMessage::Ping`] timestamp.
+    Pong(u64),
 }
 
 /// Create a [`SnapshotDelta`] by XOR'ing the bytes of `base` and `current`.
@@ -122,6 +213,30 @@ mod tests {
         assert!(delta_compress(&base, &current).is_err());
     }
 
+    #[test]
+    fn entity_mask_tracks_indices_past_64() {
+        let mask = EntityMask::from_indices([3, 70, 199]);
+        assert!(mask.contains(70));
+        assert!(mask.contains(199));
+        assert!(!mask.contains(64 + 3));
+        assert!(!mask.contains(1000));
+        assert!(mask.intersects(&EntityMask::from_indices([70])));
+        assert!(!mask.intersects(&EntityMask::from_indices([71])));
+        assert!(EntityMask::from_indices([70, 199]).is_subset(&mask));
+        assert!(!EntityMask::from_indices([70, 71]).is_subset(&mask));
+    }
+
+    #[test]
+    fn entity_mask_all_and_none() {
+        let some = EntityMask::from_indices([150]);
+        assert!(EntityMask::all().contains(150));
+        assert!(EntityMask::all().intersects(&some));
+        assert!(!EntityMask::none().intersects(&some));
+        assert!(some.is_subset(&EntityMask::all()));
+        assert!(!EntityMask::all().is_subset(&some));
+        assert_eq!(EntityMask::from(1 << 1), EntityMask::from_indices([1]));
+    }
+
     #[test]
     fn apply_delta_mismatched_lengths() {
         let base = Snapshot {
@@ -0,0 +1,171 @@
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Exponential backoff between reconnect attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay before the first attempt.
+    pub initial: Duration,
+    /// Upper bound on any single delay.
+    pub max: Duration,
+    /// Multiplier applied to the delay after each failed attempt.
+    pub factor: u32,
+    /// Give up after this many failed attempts; `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(250),
+            max: Duration::from_secs(10),
+            factor: 2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the zero-based `attempt`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.initial
+            .saturating_mul(self.factor.saturating_pow(attempt))
+            .min(self.max)
+    }
+}
+
+/// Something that can set up a fresh connection against a signaling URL
+/// after its own connection closed.
+#[async_trait(?Send)]
+pub trait Reconnect {
+    /// The connection a successful attempt produces.
+    type Connection;
+
+    async fn reconnect(&self, url: &str) -> Result<Self::Connection>;
+}
+
+/// Retry `connector.reconnect(url)` until it succeeds, waiting with `sleep`
+/// for the backoff delay before each attempt, and return the new connection.
+///
+/// The new connection reports [`ConnectionEvent::Open`] itself once its
+/// channel opens. Returns the last error if `backoff.max_attempts` is
+/// exhausted.
+///
+/// [`ConnectionEvent::Open`]: crate::client::ConnectionEvent::Open
+pub async fn reconnect<R, S, F>(
+    connector: &R,
+    url: &str,
+    backoff: &Backoff,
+    sleep: S,
+) -> Result<R::Connection>
+where
+    R: Reconnect + ?Sized,
+    S: Fn(Duration) -> F,
+    F: Future<Output = ()>,
+{
+    let mut attempt = 0;
+    loop {
+        sleep(backoff.delay(attempt)).await;
+        match connector.reconnect(url).await {
+            Ok(connection) => return Ok(connection),
+            Err(e) => {
+                attempt += 1;
+                bevy::log::warn!("reconnect attempt {attempt} to {url} failed: {e}");
+                if backoff.max_attempts.is_some_and(|max| attempt >= max) {
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::Mutex;
+
+    /// Fails until `failures` attempts have been made, recording each URL.
+    struct FlakyConnector {
+        failures: usize,
+        attempts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait(?Send)]
+    impl Reconnect for FlakyConnector {
+        type Connection = ();
+
+        async fn reconnect(&self, url: &str) -> Result<()> {
+            let mut attempts = self.attempts.lock().unwrap();
+            attempts.push(url.to_string());
+            if attempts.len() <= self.failures {
+                Err(anyhow!("channel closed"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn backoff() -> Backoff {
+        Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            factor: 2,
+            max_attempts: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_with_increasing_capped_delays() {
+        let connector = FlakyConnector {
+            failures: 4,
+            attempts: Mutex::new(Vec::new()),
+        };
+        let delays = Mutex::new(Vec::new());
+        reconnect(&connector, "ws://arena/ws", &backoff(), |delay| {
+            delays.lock().unwrap().push(delay);
+            std::future::ready(())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(connector.attempts.lock().unwrap().len(), 5);
+        assert!(
+            connector
+                .attempts
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|url| url == "ws://arena/ws")
+        );
+        let ms: Vec<u128> = delays
+            .lock()
+            .unwrap()
+            .iter()
+            .map(Duration::as_millis)
+            .collect();
+        assert_eq!(ms, vec![100, 200, 400, 500, 500]);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let connector = FlakyConnector {
+            failures: usize::MAX,
+            attempts: Mutex::new(Vec::new()),
+        };
+        let backoff = Backoff {
+            max_attempts: Some(3),
+            ..backoff()
+        };
+        let result = reconnect(&connector, "ws://arena/ws", &backoff, |_| {
+            std::future::ready(())
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(connector.attempts.lock().unwrap().len(), 3);
+    }
+}
@@ -16,42 +16,53 @@ use webrtc::data_channel::data_channel_message::DataChannelMessage;
 use webrtc::peer_connection::RTCPeerConnection;
 use webrtc::peer_connection::configuration::RTCConfiguration;
 
-use crate::message::{ClientMessage, InputFrame, ServerMessage};
+use crate::channel::ChannelReliability;
+use crate::message::{ClientMessage, EntityMask, InputFrame, ServerMessage};
 
 static DECODE_FAILURES: AtomicUsize = AtomicUsize::new(0);
 
 /// Handles the server side of the WebRTC connection.
 pub struct ServerConnector {
-    /// Underlying peer connection.
-    pub pc: RTCPeerConnection,
+    /// Underlying peer connection, shared so signaling can keep adding ICE
+    /// candidates after the connector has joined a room.
+    pub pc: Arc<RTCPeerConnection>,
     /// Incoming input frames from the client.
     pub input_rx: Receiver<InputFrame>,
     /// Channel used to send snapshots to the client.
     pub snapshot_tx: Sender<ServerMessage>,
     /// Incoming interest mask updates from the client.
-    pub interest_rx: Receiver<u64>,
+    pub interest_rx: Receiver<EntityMask>,
+    /// Signalled when the client sends [`ClientMessage::Disconnect`].
+    pub disconnect_rx: Receiver<()>,
 }
 
 impl ServerConnector {
-    /// Create a new server connector accepting unreliable data channels.
+    /// Create a new server connector accepting the client's unreliable and
+    /// reliable data channels.
     pub async fn new() -> Result<Self> {
         let mut m = MediaEngine::default();
         m.register_default_codecs()?;
         let api = APIBuilder::new().with_media_engine(m).build();
-        let pc = api.new_peer_connection(RTCConfiguration::default()).await?;
+        let pc = Arc::new(api.new_peer_connection(RTCConfiguration::default()).await?);
         let (snapshot_tx, snapshot_rx) = mpsc::channel(32);
         let (input_tx, input_rx) = mpsc::channel(32);
         let (interest_tx, interest_rx) = mpsc::channel(8);
+        let (disconnect_tx, disconnect_rx) = mpsc::channel(1);
 
         let snapshot_rx = Arc::new(Mutex::new(snapshot_rx));
         pc.on_data_channel(Box::new(move |dc: Arc<RTCDataChannel>| {
             let input_tx = input_tx.clone();
             let interest_tx = interest_tx.clone();
+            let disconnect_tx = disconnect_tx.clone();
             let snapshot_rx = Arc::clone(&snapshot_rx);
             Box::pin(async move {
+                // Weak so the handler doesn't keep its own channel alive.
+                let reply_dc = Arc::downgrade(&dc);
                 dc.on_message(Box::new(move |msg: DataChannelMessage| {
                     let input_tx = input_tx.clone();
                     let interest_tx = interest_tx.clone();
+                    let disconnect_tx = disconnect_tx.clone();
+                    let reply_dc = reply_dc.clone();
                     Box::pin(async move {
                         if !msg.is_string {
                             match postcard::from_bytes::<ClientMessage>(&msg.data) {
@@ -61,6 +72,15 @@ impl ServerConnector {
                                 Ok(ClientMessage::Interest(mask)) => {
                                     let _ = interest_tx.send(mask).await;
                                 }
+                                Ok(ClientMessage::Disconnect) => {
+                                    let _ = disconnect_tx.try_send(());
+                                }
+                                Ok(ClientMessage::Ping(sent)) => {
+                                    let pong = postcard::to_allocvec(&ServerMessage::Pong(sent));
+                                    if let (Some(dc), Ok(bytes)) = (reply_dc.upgrade(), pong) {
+                                        let _ = dc.send(&Bytes::from(bytes)).await;
+                                    }
+                                }
                                 Err(e) => {
                                     let count = DECODE_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
                                     if count <= 5 || count % 100 == 0 {
@@ -72,6 +92,11 @@ impl ServerConnector {
                     })
                 }));
 
+                // Snapshots go out unreliably; the control channel only
+                // carries client messages.
+                if dc.label() != ChannelReliability::Unreliable.label() {
+                    return;
+                }
                 let dc_open = Arc::clone(&dc);
                 dc.on_open(Box::new(move || {
                     let dc = Arc::clone(&dc_open);
@@ -95,6 +120,7 @@ impl ServerConnector {
             input_rx,
             snapshot_tx,
             interest_rx,
+            disconnect_rx,
         })
     }
 
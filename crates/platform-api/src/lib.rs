@@ -4,7 +4,11 @@ use bevy::prelude::*;
 use bitflags::bitflags;
 use serde::Deserialize;
 #[cfg(not(target_arch = "wasm32"))]
-use std::{fs, path::Path};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+use std::{fmt, str::FromStr};
 
 #[derive(States, Default, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum AppState {
@@ -13,6 +17,48 @@ pub enum AppState {
     DuckHunt,
 }
 
+impl AppState {
+    /// Every state, in declaration order.
+    pub const ALL: [AppState; 2] = [AppState::Lobby, AppState::DuckHunt];
+
+    /// Name used for the state in module manifests.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AppState::Lobby => "Lobby",
+            AppState::DuckHunt => "DuckHunt",
+        }
+    }
+}
+
+/// Returned when a manifest names a state that isn't an [`AppState`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAppState(pub String);
+
+impl fmt::Display for UnknownAppState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let valid: Vec<_> = AppState::ALL.iter().map(AppState::name).collect();
+        write!(
+            f,
+            "unknown module state '{}', expected one of: {}",
+            self.0,
+            valid.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownAppState {}
+
+impl FromStr for AppState {
+    type Err = UnknownAppState;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AppState::ALL
+            .into_iter()
+            .find(|state| state.name() == s)
+            .ok_or_else(|| UnknownAppState(s.to_string()))
+    }
+}
+
 bitflags! {
     #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct CapabilityFlags: u32 {
@@ -22,7 +68,87 @@ bitflags! {
         const NEEDS_NAV = 0b1000;
         const USES_VEHICLES = 0b1_0000;
         const USES_FLIGHT = 0b10_0000;
+        const USES_AUDIO = 0b100_0000;
+        const NEEDS_NETWORKING = 0b1000_0000;
+    }
+}
+
+impl CapabilityFlags {
+    /// Flag for a capability as spelled in `module.toml`, e.g. `"NeedsPhysics"`.
+    pub fn from_manifest_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "LOBBY_PAD" => Self::LOBBY_PAD,
+            "NeedsPhysics" => Self::NEEDS_PHYSICS,
+            "UsesHitscan" => Self::USES_HITSCAN,
+            "NeedsNav" => Self::NEEDS_NAV,
+            "UsesVehicles" => Self::USES_VEHICLES,
+            "UsesFlight" => Self::USES_FLIGHT,
+            "UsesAudio" => Self::USES_AUDIO,
+            "NeedsNetworking" => Self::NEEDS_NETWORKING,
+            _ => return None,
+        })
+    }
+
+    /// Combine manifest capability names, ignoring unknown ones.
+    pub fn from_manifest<S: AsRef<str>>(names: &[S]) -> Self {
+        names
+            .iter()
+            .filter_map(|name| Self::from_manifest_name(name.as_ref()))
+            .fold(Self::empty(), |caps, flag| caps | flag)
+    }
+}
+
+/// Whether the Rapier physics subsystem is wired up.
+#[derive(Resource, Default)]
+pub struct PhysicsEnabled(pub bool);
+
+/// Whether the hitscan subsystem is wired up.
+#[derive(Resource, Default)]
+pub struct HitscanEnabled(pub bool);
+
+/// Whether navigation/path finding is wired up.
+#[derive(Resource, Default)]
+pub struct NavigationEnabled(pub bool);
+
+/// Whether vehicle dynamics are wired up.
+#[derive(Resource, Default)]
+pub struct VehiclesEnabled(pub bool);
+
+/// Whether flight dynamics are wired up.
+#[derive(Resource, Default)]
+pub struct FlightEnabled(pub bool);
+
+/// Whether audio playback is wired up.
+#[derive(Resource, Default)]
+pub struct AudioEnabled(pub bool);
+
+/// Whether authoritative networking is wired up.
+#[derive(Resource, Default)]
+pub struct NetworkingEnabled(pub bool);
+
+/// Capabilities whose subsystem has been explicitly disabled in `world`.
+///
+/// A subsystem without its `*Enabled` resource is assumed to be available, so
+/// engines that don't use capability wiring are unaffected.
+pub fn disabled_capabilities(world: &World) -> CapabilityFlags {
+    fn disabled<R: Resource>(
+        world: &World,
+        flag: CapabilityFlags,
+        enabled: fn(&R) -> bool,
+    ) -> CapabilityFlags {
+        match world.get_resource::<R>() {
+            Some(r) if !enabled(r) => flag,
+            _ => CapabilityFlags::empty(),
+        }
     }
+    use CapabilityFlags as F;
+    disabled::<PhysicsEnabled>(world, F::NEEDS_PHYSICS, |r| r.0)
+        | disabled::<HitscanEnabled>(world, F::USES_HITSCAN, |r| r.0)
+        | disabled::<NavigationEnabled>(world, F::NEEDS_NAV, |r| r.0)
+        | disabled::<VehiclesEnabled>(world, F::USES_VEHICLES, |r| r.0)
+        | disabled::<FlightEnabled>(world, F::USES_FLIGHT, |r| r.0)
+        | disabled::<AudioEnabled>(world, F::USES_AUDIO, |r| r.0)
+        | disabled::<NetworkingEnabled>(world, F::NEEDS_NETWORKING, |r| r.0)
 }
 
 /// Describes a game module and its capabilities.
@@ -44,6 +170,8 @@ pub struct ModuleMetadata {
     pub max_players: u32,
     /// Icon representing the module.
     pub icon: Handle<Image>,
+    /// Asset path the icon is loaded from, relative to the asset root.
+    pub icon_path: Option<String>,
 }
 
 /// Context handed to module hooks giving access to the Bevy [`World`] and other
@@ -83,6 +211,23 @@ impl<'a> ModuleContext<'a> {
         self.world.get_resource_mut::<A>()
     }
 
+    /// Queue an event for systems reading `E`. Returns `false`, dropping the
+    /// event, if `E` hasn't been registered with `add_event`.
+    pub fn send_event<E: Event>(&mut self, event: E) -> bool {
+        match self.world.get_resource_mut::<Events<E>>() {
+            Some(mut events) => {
+                events.send(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Spawn an entity with the given components.
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        self.world.spawn(bundle).id()
+    }
+
     /// Access a UI-related resource.
     pub fn ui<U: Resource>(&mut self) -> Option<Mut<'_, U>> {
         self.world.get_resource_mut::<U>()
@@ -128,11 +273,31 @@ pub struct ModuleManifest {
     pub capabilities: Vec<String>,
     #[serde(default)]
     pub max_players: u32,
+    #[serde(default)]
+    pub icon_path: Option<String>,
+}
+
+/// Environment variable naming the directory local modules are discovered in.
+#[cfg(not(target_arch = "wasm32"))]
+pub const MODULES_DIR_ENV: &str = "ARENA_MODULES_DIR";
+
+/// Directory holding local module manifests: [`MODULES_DIR_ENV`] when set,
+/// otherwise `assets/modules` in the source tree.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn modules_dir() -> PathBuf {
+    env::var_os(MODULES_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/modules"))
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn discover_local_modules() -> Vec<ModuleMetadata> {
-    let modules_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets/modules");
+    discover_modules_in(&modules_dir())
+}
+
+/// Load every `<module>/module.toml` manifest under `modules_dir`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn discover_modules_in(modules_dir: &Path) -> Vec<ModuleMetadata> {
     let Ok(entries) = fs::read_dir(modules_dir) else {
         return Vec::new();
     };
@@ -148,23 +313,14 @@ pub fn discover_local_modules() -> Vec<ModuleMetadata> {
         let Ok(manifest) = toml::from_str::<ModuleManifest>(&contents) else {
             continue;
         };
-        let state = match manifest.state.as_str() {
-            "Lobby" => AppState::Lobby,
-            "DuckHunt" => AppState::DuckHunt,
-            _ => continue,
-        };
-        let mut caps = CapabilityFlags::default();
-        for cap in manifest.capabilities {
-            match cap.as_str() {
-                "LOBBY_PAD" => caps |= CapabilityFlags::LOBBY_PAD,
-                "NeedsPhysics" => caps |= CapabilityFlags::NEEDS_PHYSICS,
-                "UsesHitscan" => caps |= CapabilityFlags::USES_HITSCAN,
-                "NeedsNav" => caps |= CapabilityFlags::NEEDS_NAV,
-                "UsesVehicles" => caps |= CapabilityFlags::USES_VEHICLES,
-                "UsesFlight" => caps |= CapabilityFlags::USES_FLIGHT,
-                _ => {}
+        let state = match manifest.state.parse::<AppState>() {
+            Ok(state) => state,
+            Err(e) => {
+                log::error!("{e}, skipping module '{}'", manifest.id);
+                continue;
             }
-        }
+        };
+        let caps = CapabilityFlags::from_manifest(&manifest.capabilities);
         mods.push(ModuleMetadata {
             id: manifest.id,
             name: manifest.name,
@@ -174,6 +330,7 @@ pub fn discover_local_modules() -> Vec<ModuleMetadata> {
             capabilities: caps,
             max_players: manifest.max_players,
             icon: Handle::default(),
+            icon_path: manifest.icon_path,
         });
     }
     mods
@@ -0,0 +1,19 @@
+use platform_api::{AppState, UnknownAppState};
+
+#[test]
+fn parses_every_known_state() {
+    for state in AppState::ALL {
+        assert_eq!(state.name().parse::<AppState>(), Ok(state));
+    }
+    assert_eq!("DuckHunt".parse::<AppState>(), Ok(AppState::DuckHunt));
+}
+
+#[test]
+fn rejects_unknown_state_listing_valid_ones() {
+    let err = "Racing".parse::<AppState>().unwrap_err();
+    assert_eq!(err, UnknownAppState("Racing".into()));
+    assert_eq!(
+        err.to_string(),
+        "unknown module state 'Racing', expected one of: Lobby, DuckHunt"
+    );
+}
@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+use platform_api::{
+    AudioEnabled, CapabilityFlags, NetworkingEnabled, PhysicsEnabled, disabled_capabilities,
+};
+
+#[test]
+fn parses_manifest_capability_names() {
+    assert_eq!(
+        CapabilityFlags::from_manifest_name("UsesAudio"),
+        Some(CapabilityFlags::USES_AUDIO)
+    );
+    assert_eq!(
+        CapabilityFlags::from_manifest_name("NeedsNetworking"),
+        Some(CapabilityFlags::NEEDS_NETWORKING)
+    );
+    assert_eq!(CapabilityFlags::from_manifest_name("usesaudio"), None);
+
+    let caps =
+        CapabilityFlags::from_manifest(&["LOBBY_PAD", "UsesAudio", "NeedsNetworking", "Bogus"]);
+    assert_eq!(
+        caps,
+        CapabilityFlags::LOBBY_PAD
+            | CapabilityFlags::USES_AUDIO
+            | CapabilityFlags::NEEDS_NETWORKING
+    );
+}
+
+#[test]
+fn only_explicitly_disabled_subsystems_are_reported() {
+    let mut world = World::new();
+    assert!(disabled_capabilities(&world).is_empty());
+
+    world.insert_resource(PhysicsEnabled(true));
+    world.insert_resource(AudioEnabled(false));
+    world.insert_resource(NetworkingEnabled(false));
+    assert_eq!(
+        disabled_capabilities(&world),
+        CapabilityFlags::USES_AUDIO | CapabilityFlags::NEEDS_NETWORKING
+    );
+}
@@ -0,0 +1,39 @@
+use bevy::prelude::*;
+use platform_api::ModuleContext;
+
+#[derive(Event, Debug, PartialEq)]
+struct RoundStarted(u32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Marker(&'static str);
+
+#[test]
+fn send_event_queues_registered_event() {
+    let mut world = World::new();
+    world.init_resource::<Events<RoundStarted>>();
+    let mut ctx = ModuleContext::new(&mut world);
+
+    assert!(ctx.send_event(RoundStarted(3)));
+
+    let events = world.resource::<Events<RoundStarted>>();
+    let mut reader = events.get_reader();
+    assert_eq!(reader.read(events).collect::<Vec<_>>(), [&RoundStarted(3)]);
+}
+
+#[test]
+fn send_event_without_registration_is_dropped() {
+    let mut world = World::new();
+    let mut ctx = ModuleContext::new(&mut world);
+    assert!(!ctx.send_event(RoundStarted(1)));
+}
+
+#[test]
+fn spawn_creates_entity_with_bundle() {
+    let mut world = World::new();
+    let mut ctx = ModuleContext::new(&mut world);
+
+    let entity = ctx.spawn((Marker("duck"), Transform::default()));
+
+    assert_eq!(world.get::<Marker>(entity), Some(&Marker("duck")));
+    assert!(world.get::<Transform>(entity).is_some());
+}
@@ -0,0 +1,55 @@
+#![cfg(not(target_arch = "wasm32"))]
+
+use platform_api::{AppState, MODULES_DIR_ENV, discover_modules_in, modules_dir};
+use std::fs;
+use std::path::Path;
+
+fn write_manifest(dir: &Path, id: &str) {
+    let module = dir.join(id);
+    fs::create_dir_all(&module).unwrap();
+    fs::write(
+        module.join("module.toml"),
+        format!(
+            r#"id = "{id}"
+name = "Custom"
+version = "1.0.0"
+author = "Test"
+state = "DuckHunt"
+capabilities = ["LOBBY_PAD"]
+icon_path = "modules/{id}/icon.png"
+"#
+        ),
+    )
+    .unwrap();
+}
+
+#[test]
+fn discovers_modules_in_custom_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    write_manifest(dir.path(), "custom");
+
+    let modules = discover_modules_in(dir.path());
+    assert_eq!(modules.len(), 1);
+    assert_eq!(modules[0].id, "custom");
+    assert_eq!(modules[0].state, AppState::DuckHunt);
+    assert_eq!(
+        modules[0].icon_path.as_deref(),
+        Some("modules/custom/icon.png")
+    );
+}
+
+#[test]
+fn missing_directory_has_no_modules() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(discover_modules_in(&dir.path().join("absent")).is_empty());
+}
+
+#[test]
+fn env_var_overrides_modules_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    // SAFETY: no other test in this binary touches the environment.
+    unsafe { std::env::set_var(MODULES_DIR_ENV, dir.path()) };
+    assert_eq!(modules_dir(), dir.path());
+    unsafe { std::env::remove_var(MODULES_DIR_ENV) };
+    assert!(modules_dir().ends_with("assets/modules"));
+}
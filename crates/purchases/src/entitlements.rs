@@ -0,0 +1,227 @@
+//! Pluggable storage for granted entitlements.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseConnection, DbErr};
+
+use crate::{Catalog, UserId};
+
+#[derive(Debug)]
+pub enum GrantError {
+    /// The SKU isn't in the catalog.
+    UnknownSku(String),
+    Db(DbErr),
+}
+
+impl fmt::Display for GrantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSku(sku) => write!(f, "unknown sku {sku}"),
+            Self::Db(err) => write!(f, "entitlement storage failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GrantError {}
+
+/// Where entitlements live. The in-memory backend suits tests and offline
+/// servers; [`DbEntitlements`] persists through the purchases tables.
+#[async_trait]
+pub trait EntitlementBackend: Send + Sync {
+    /// Grant `sku_id` until `expires_at`, or permanently if `None`.
+    async fn grant_until(
+        &self,
+        user_id: UserId,
+        sku_id: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), DbErr>;
+
+    async fn revoke(&self, user_id: UserId, sku_id: &str) -> Result<(), DbErr>;
+
+    /// SKUs the user holds right now; expired grants are left out.
+    async fn list(&self, user_id: UserId) -> Result<Vec<String>, DbErr>;
+
+    async fn grant(&self, user_id: UserId, sku_id: &str) -> Result<(), DbErr> {
+        self.grant_until(user_id, sku_id, None).await
+    }
+
+    /// Grant `sku_id` only if it is a real item in `catalog`.
+    async fn grant_if_valid(
+        &self,
+        catalog: &Catalog,
+        user_id: UserId,
+        sku_id: &str,
+    ) -> Result<(), GrantError> {
+        if catalog.get(sku_id).is_none() {
+            return Err(GrantError::UnknownSku(sku_id.to_string()));
+        }
+        self.grant(user_id, sku_id).await.map_err(GrantError::Db)
+    }
+
+    async fn has(&self, user_id: UserId, sku_id: &str) -> Result<bool, DbErr> {
+        Ok(self.list(user_id).await?.iter().any(|sku| sku == sku_id))
+    }
+}
+
+/// Each granted SKU with its expiry, if any.
+type Grants = BTreeMap<String, Option<DateTime<Utc>>>;
+
+#[derive(Default)]
+pub struct MemoryEntitlements {
+    grants: Mutex<HashMap<UserId, Grants>>,
+}
+
+impl MemoryEntitlements {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl EntitlementBackend for MemoryEntitlements {
+    async fn grant_until(
+        &self,
+        user_id: UserId,
+        sku_id: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), DbErr> {
+        self.grants
+            .lock()
+            .unwrap()
+            .entry(user_id)
+            .or_default()
+            .insert(sku_id.to_string(), expires_at);
+        Ok(())
+    }
+
+    async fn revoke(&self, user_id: UserId, sku_id: &str) -> Result<(), DbErr> {
+        if let Some(skus) = self.grants.lock().unwrap().get_mut(&user_id) {
+            skus.remove(sku_id);
+        }
+        Ok(())
+    }
+
+    async fn list(&self, user_id: UserId) -> Result<Vec<String>, DbErr> {
+        let now = Utc::now();
+        Ok(self
+            .grants
+            .lock()
+            .unwrap()
+            .get(&user_id)
+            .map(|skus| {
+                skus.iter()
+                    .filter(|(_, expires_at)| expires_at.is_none_or(|at| at > now))
+                    .map(|(sku, _)| sku.clone())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+/// Entitlements stored with [`crate::grant_entitlement_until`],
+/// [`crate::revoke_entitlement`] and [`crate::list_entitlements`].
+#[derive(Clone)]
+pub struct DbEntitlements {
+    db: DatabaseConnection,
+}
+
+impl DbEntitlements {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl EntitlementBackend for DbEntitlements {
+    async fn grant_until(
+        &self,
+        user_id: UserId,
+        sku_id: &str,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<(), DbErr> {
+        crate::grant_entitlement_until(&self.db, user_id, sku_id, expires_at).await
+    }
+
+    async fn revoke(&self, user_id: UserId, sku_id: &str) -> Result<(), DbErr> {
+        crate::revoke_entitlement(&self.db, user_id, sku_id).await
+    }
+
+    async fn list(&self, user_id: UserId) -> Result<Vec<String>, DbErr> {
+        crate::list_entitlements(&self.db, &user_id.to_string()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, Database};
+
+    async fn exercise(backend: &dyn EntitlementBackend) {
+        let user = UserId::new_v4();
+        assert!(!backend.has(user, "basic").await.unwrap());
+        backend.grant(user, "basic").await.unwrap();
+        backend.grant(user, "basic").await.unwrap();
+        assert!(backend.has(user, "basic").await.unwrap());
+        assert!(!backend.has(user, "premium").await.unwrap());
+        assert_eq!(backend.list(user).await.unwrap(), vec!["basic".to_string()]);
+        assert!(backend.list(UserId::new_v4()).await.unwrap().is_empty());
+
+        backend.revoke(user, "basic").await.unwrap();
+        assert!(!backend.has(user, "basic").await.unwrap());
+
+        let past = Utc::now() - chrono::Duration::hours(1);
+        let future = Utc::now() + chrono::Duration::hours(1);
+        backend
+            .grant_until(user, "expired", Some(past))
+            .await
+            .unwrap();
+        backend
+            .grant_until(user, "pass", Some(future))
+            .await
+            .unwrap();
+        assert!(!backend.has(user, "expired").await.unwrap());
+        assert!(backend.has(user, "pass").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn memory_backend_grants_and_lists() {
+        exercise(&MemoryEntitlements::new()).await;
+    }
+
+    #[tokio::test]
+    async fn grant_if_valid_rejects_unknown_sku() {
+        let catalog = Catalog::new(vec![crate::Sku {
+            id: "basic".into(),
+            price_cents: 1000,
+        }]);
+        let backend = MemoryEntitlements::new();
+        let user = UserId::new_v4();
+        backend
+            .grant_if_valid(&catalog, user, "basic")
+            .await
+            .unwrap();
+        assert!(matches!(
+            backend.grant_if_valid(&catalog, user, "free_everything").await,
+            Err(GrantError::UnknownSku(sku)) if sku == "free_everything"
+        ));
+        assert_eq!(backend.list(user).await.unwrap(), vec!["basic".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn db_backend_grants_and_lists() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared(
+            "CREATE TABLE entitlements (player_id TEXT NOT NULL, sku TEXT NOT NULL, \
+             granted_at TEXT NOT NULL, expires_at TEXT, PRIMARY KEY (player_id, sku));",
+        )
+        .await
+        .unwrap();
+        exercise(&DbEntitlements::new(db)).await;
+    }
+}
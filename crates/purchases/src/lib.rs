@@ -1,30 +1,83 @@
 use chrono::{DateTime, Utc};
 use sea_orm::{
-    ActiveValue::Set, DatabaseConnection, QueryFilter, TransactionError, TransactionTrait,
-    entity::prelude::*, sea_query::OnConflict,
+    ActiveValue::Set, Condition, DatabaseConnection, QueryFilter, QuerySelect, TransactionError,
+    TransactionTrait, entity::prelude::*, sea_query::OnConflict,
 };
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
 use uuid::Uuid;
 pub use uuid::Uuid as UserId;
 
+pub mod entitlements;
+pub mod webhook;
+
+pub use entitlements::{DbEntitlements, EntitlementBackend, GrantError, MemoryEntitlements};
+pub use webhook::{HmacWebhookVerifier, WebhookError};
+
 type DateTimeUtc = DateTime<Utc>;
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Sku {
     pub id: String,
     pub price_cents: u32,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Catalog {
     skus: Vec<Sku>,
 }
 
+#[derive(Debug)]
+pub enum CatalogError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    /// Two items share this id.
+    DuplicateSku(String),
+    /// This item has a zero price.
+    ZeroPrice(String),
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read catalog: {err}"),
+            Self::Parse(err) => write!(f, "invalid catalog JSON: {err}"),
+            Self::DuplicateSku(id) => write!(f, "duplicate sku {id} in catalog"),
+            Self::ZeroPrice(id) => write!(f, "sku {id} has a zero price"),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
 impl Catalog {
     pub fn new(skus: Vec<Sku>) -> Self {
         Self { skus }
     }
 
+    /// Parse a JSON array of [`Sku`]s, rejecting duplicate ids and free items.
+    pub fn from_json(json: &str) -> Result<Self, CatalogError> {
+        let skus: Vec<Sku> = serde_json::from_str(json).map_err(CatalogError::Parse)?;
+        let mut seen = HashSet::new();
+        for sku in &skus {
+            if !seen.insert(sku.id.as_str()) {
+                return Err(CatalogError::DuplicateSku(sku.id.clone()));
+            }
+            if sku.price_cents == 0 {
+                return Err(CatalogError::ZeroPrice(sku.id.clone()));
+            }
+        }
+        Ok(Self { skus })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CatalogError> {
+        Self::from_json(&std::fs::read_to_string(path).map_err(CatalogError::Io)?)
+    }
+
     pub fn get(&self, id: &str) -> Option<&Sku> {
         self.skus.iter().find(|s| s.id == id)
     }
@@ -34,34 +87,131 @@ impl Catalog {
     }
 }
 
+/// Currency that catalog `price_cents` are denominated in.
+pub const BASE_CURRENCY: &str = "USD";
+
+/// Exchange rates from [`BASE_CURRENCY`], keyed by ISO 4217 code.
+#[derive(Clone, Default)]
+pub struct FxRates {
+    rates: HashMap<String, f64>,
+}
+
+impl FxRates {
+    pub fn new(rates: HashMap<String, f64>) -> Self {
+        Self {
+            rates: rates
+                .into_iter()
+                .map(|(code, rate)| (code.to_ascii_uppercase(), rate))
+                .collect(),
+        }
+    }
+
+    pub fn rate(&self, currency: &str) -> Option<f64> {
+        let currency = currency.to_ascii_uppercase();
+        if currency == BASE_CURRENCY {
+            return Some(1.0);
+        }
+        self.rates.get(&currency).copied()
+    }
+
+    /// Convert a base-currency amount, or `None` if the currency is unknown.
+    pub fn convert(&self, price_cents: u32, currency: &str) -> Option<u32> {
+        self.rate(currency)
+            .map(|rate| (f64::from(price_cents) * rate).round() as u32)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct EntitlementList {
     pub entitlements: Vec<String>,
 }
 
-pub fn initiate_purchase(_user: &str, sku: &str) -> String {
-    format!("session_{sku}")
+/// How long a checkout session stays open before it must be restarted.
+pub const CHECKOUT_SESSION_TTL: chrono::Duration = chrono::Duration::minutes(30);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckoutSession {
+    pub id: String,
+    pub expires_at: DateTimeUtc,
+}
+
+/// Open a checkout session for `sku` and record who opened it, so the
+/// completion webhook can be matched against it. There is no hosted payment
+/// provider yet, so the session only carries a fresh id and its expiry.
+pub async fn initiate_purchase(
+    db: &DatabaseConnection,
+    user_id: UserId,
+    sku: &str,
+) -> Result<CheckoutSession, DbErr> {
+    let session = CheckoutSession {
+        id: Uuid::new_v4().to_string(),
+        expires_at: Utc::now() + CHECKOUT_SESSION_TTL,
+    };
+    db::checkout_sessions::ActiveModel {
+        id: Set(session.id.clone()),
+        player_id: Set(user_id.to_string()),
+        sku: Set(sku.to_string()),
+        expires_at: Set(session.expires_at),
+    }
+    .insert(db)
+    .await?;
+    Ok(session)
+}
+
+/// Whether `session_id` was opened by [`initiate_purchase`] for this user
+/// and sku. Expiry isn't checked: a provider may redeliver the completion
+/// webhook after the session would have closed.
+pub async fn checkout_matches(
+    db: &DatabaseConnection,
+    session_id: &str,
+    user_id: UserId,
+    sku: &str,
+) -> Result<bool, DbErr> {
+    let session = db::checkout_sessions::Entity::find_by_id(session_id.to_string())
+        .one(db)
+        .await?;
+    Ok(session.is_some_and(|s| s.player_id == user_id.to_string() && s.sku == sku))
 }
 
+/// Record a sale of `sku` at its current catalog price.
+///
+/// `idempotency_key` identifies the sale upstream, e.g. a checkout session
+/// id. Repeating a key records nothing and returns the original purchase id,
+/// so a webhook delivered twice is only counted once.
 pub async fn create_purchase(
     db: &DatabaseConnection,
     user_id: UserId,
-    sku_id: &str,
+    sku: &Sku,
+    idempotency_key: &str,
 ) -> Result<Uuid, DbErr> {
-    let sku = sku_id.to_string();
-    let id = Uuid::new_v4();
+    let sku = sku.clone();
+    let key = idempotency_key.to_string();
     db.transaction(move |txn| {
         let sku = sku.clone();
-        let id = id;
+        let key = key.clone();
         Box::pin(async move {
             let purchase = db::purchases::ActiveModel {
-                id: Set(id),
+                id: Set(Uuid::new_v4()),
                 player_id: Set(user_id.to_string()),
-                sku: Set(sku),
+                sku: Set(sku.id),
                 created_at: Set(Utc::now()),
+                price_cents: Set(i64::from(sku.price_cents)),
+                idempotency_key: Set(Some(key.clone())),
             };
-            db::purchases::Entity::insert(purchase).exec(txn).await?;
-            Ok(id)
+            db::purchases::Entity::insert(purchase)
+                .on_conflict(
+                    OnConflict::column(db::purchases::Column::IdempotencyKey)
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .exec_without_returning(txn)
+                .await?;
+            let purchase = db::purchases::Entity::find()
+                .filter(db::purchases::Column::IdempotencyKey.eq(key))
+                .one(txn)
+                .await?
+                .ok_or(DbErr::RecordNotFound("purchase".into()))?;
+            Ok(purchase.id)
         })
     })
     .await
@@ -70,10 +220,35 @@ pub async fn create_purchase(
     })
 }
 
+/// Sum of every recorded sale, in [`BASE_CURRENCY`] cents.
+pub async fn total_revenue_cents(db: &DatabaseConnection) -> Result<i64, DbErr> {
+    let total = db::purchases::Entity::find()
+        .select_only()
+        .column_as(
+            Expr::cust("CAST(COALESCE(SUM(price_cents), 0) AS BIGINT)"),
+            "total",
+        )
+        .into_tuple::<i64>()
+        .one(db)
+        .await?;
+    Ok(total.unwrap_or_default())
+}
+
 pub async fn grant_entitlement(
     db: &DatabaseConnection,
     user_id: UserId,
     sku_id: &str,
+) -> Result<(), DbErr> {
+    grant_entitlement_until(db, user_id, sku_id, None).await
+}
+
+/// Grant `sku_id` until `expires_at`, or permanently if `None`. Re-granting
+/// an existing entitlement replaces its expiry.
+pub async fn grant_entitlement_until(
+    db: &DatabaseConnection,
+    user_id: UserId,
+    sku_id: &str,
+    expires_at: Option<DateTimeUtc>,
 ) -> Result<(), DbErr> {
     let sku = sku_id.to_string();
     db.transaction(move |txn| {
@@ -83,6 +258,7 @@ pub async fn grant_entitlement(
                 player_id: Set(user_id.to_string()),
                 sku: Set(sku),
                 granted_at: Set(Utc::now()),
+                expires_at: Set(expires_at),
             };
             db::entitlements::Entity::insert(ent)
                 .on_conflict(
@@ -90,10 +266,10 @@ pub async fn grant_entitlement(
                         db::entitlements::Column::PlayerId,
                         db::entitlements::Column::Sku,
                     ])
-                    .do_nothing()
+                    .update_column(db::entitlements::Column::ExpiresAt)
                     .to_owned(),
                 )
-                .exec(txn)
+                .exec_without_returning(txn)
                 .await?;
             Ok(())
         })
@@ -104,12 +280,31 @@ pub async fn grant_entitlement(
     })
 }
 
+pub async fn revoke_entitlement(
+    db: &DatabaseConnection,
+    user_id: UserId,
+    sku_id: &str,
+) -> Result<(), DbErr> {
+    db::entitlements::Entity::delete_many()
+        .filter(db::entitlements::Column::PlayerId.eq(user_id.to_string()))
+        .filter(db::entitlements::Column::Sku.eq(sku_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// SKUs the user currently holds; expired entitlements are left out.
 pub async fn list_entitlements(
     db: &DatabaseConnection,
     user_id: &str,
 ) -> Result<Vec<String>, DbErr> {
     let rows = db::entitlements::Entity::find()
         .filter(db::entitlements::Column::PlayerId.eq(user_id))
+        .filter(
+            Condition::any()
+                .add(db::entitlements::Column::ExpiresAt.is_null())
+                .add(db::entitlements::Column::ExpiresAt.gt(Utc::now())),
+        )
         .all(db)
         .await?;
     Ok(rows.into_iter().map(|e| e.sku).collect())
@@ -118,6 +313,25 @@ pub async fn list_entitlements(
 mod db {
     use super::*;
 
+    pub mod checkout_sessions {
+        use super::*;
+
+        #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+        #[sea_orm(table_name = "checkout_sessions")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub id: String,
+            pub player_id: String,
+            pub sku: String,
+            pub expires_at: DateTimeUtc,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
     pub mod purchases {
         use super::*;
 
@@ -129,6 +343,8 @@ mod db {
             pub player_id: String,
             pub sku: String,
             pub created_at: DateTimeUtc,
+            pub price_cents: i64,
+            pub idempotency_key: Option<String>,
         }
 
         #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -148,6 +364,7 @@ mod db {
             #[sea_orm(primary_key, auto_increment = false)]
             pub sku: String,
             pub granted_at: DateTimeUtc,
+            pub expires_at: Option<DateTimeUtc>,
         }
 
         #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -156,3 +373,117 @@ mod db {
         impl ActiveModelBehavior for ActiveModel {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::ConnectionTrait;
+
+    #[tokio::test]
+    async fn checkout_session_has_id_and_future_expiry() {
+        let db = purchases_db().await;
+        let user = UserId::new_v4();
+        let before = Utc::now();
+        let session = initiate_purchase(&db, user, "basic").await.unwrap();
+        assert!(!session.id.is_empty());
+        assert!(session.expires_at >= before + CHECKOUT_SESSION_TTL);
+        let other = initiate_purchase(&db, user, "basic").await.unwrap();
+        assert_ne!(other.id, session.id);
+    }
+
+    #[tokio::test]
+    async fn checkout_matches_only_the_opening_user_and_sku() {
+        let db = purchases_db().await;
+        let user = UserId::new_v4();
+        let session = initiate_purchase(&db, user, "basic").await.unwrap();
+        assert!(
+            checkout_matches(&db, &session.id, user, "basic")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !checkout_matches(&db, &session.id, user, "deluxe")
+                .await
+                .unwrap()
+        );
+        let stranger = UserId::new_v4();
+        assert!(
+            !checkout_matches(&db, &session.id, stranger, "basic")
+                .await
+                .unwrap()
+        );
+        assert!(
+            !checkout_matches(&db, "cs_unknown", user, "basic")
+                .await
+                .unwrap()
+        );
+    }
+
+    async fn purchases_db() -> DatabaseConnection {
+        let db = sea_orm::Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared(
+            "CREATE TABLE purchases (id BLOB PRIMARY KEY, player_id TEXT NOT NULL, \
+             sku TEXT NOT NULL, created_at TEXT NOT NULL, price_cents INTEGER NOT NULL, \
+             idempotency_key TEXT UNIQUE); \
+             CREATE TABLE checkout_sessions (id TEXT PRIMARY KEY, player_id TEXT NOT NULL, \
+             sku TEXT NOT NULL, expires_at TEXT NOT NULL);",
+        )
+        .await
+        .unwrap();
+        db
+    }
+
+    fn basic() -> Sku {
+        Sku {
+            id: "basic".into(),
+            price_cents: 1000,
+        }
+    }
+
+    #[test]
+    fn catalog_json_is_validated() {
+        let catalog = Catalog::from_json(
+            r#"[{"id":"basic","price_cents":1000},{"id":"deluxe","price_cents":2500}]"#,
+        )
+        .unwrap();
+        assert_eq!(catalog.all().len(), 2);
+        assert_eq!(catalog.get("deluxe").unwrap().price_cents, 2500);
+
+        assert!(matches!(
+            Catalog::from_json(r#"[{"id":"basic","price_cents":1},{"id":"basic","price_cents":2}]"#),
+            Err(CatalogError::DuplicateSku(id)) if id == "basic"
+        ));
+        assert!(matches!(
+            Catalog::from_json(r#"[{"id":"free","price_cents":0}]"#),
+            Err(CatalogError::ZeroPrice(id)) if id == "free"
+        ));
+        assert!(matches!(
+            Catalog::from_json("{}"),
+            Err(CatalogError::Parse(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn revenue_sums_prices_at_time_of_sale() {
+        let db = purchases_db().await;
+        assert_eq!(total_revenue_cents(&db).await.unwrap(), 0);
+
+        let mut sku = basic();
+        let user = UserId::new_v4();
+        create_purchase(&db, user, &sku, "cs_1").await.unwrap();
+        sku.price_cents = 1500;
+        create_purchase(&db, user, &sku, "cs_2").await.unwrap();
+        assert_eq!(total_revenue_cents(&db).await.unwrap(), 2500);
+    }
+
+    #[tokio::test]
+    async fn repeated_idempotency_key_returns_original_purchase() {
+        let db = purchases_db().await;
+        let user = UserId::new_v4();
+        let first = create_purchase(&db, user, &basic(), "cs_1").await.unwrap();
+        let second = create_purchase(&db, user, &basic(), "cs_1").await.unwrap();
+        assert_eq!(first, second);
+        let rows = db::purchases::Entity::find().count(&db).await.unwrap();
+        assert_eq!(rows, 1);
+    }
+}
@@ -0,0 +1,204 @@
+//! Verification for payment webhooks that sign the whole raw body with
+//! HMAC-SHA256 and send the hex digest in a provider-specific header.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookError {
+    /// The signature header was absent.
+    MissingSignature,
+    /// The header wasn't a hex digest.
+    MalformedSignature,
+    /// The digest doesn't match the body.
+    InvalidSignature,
+    /// The timestamp wasn't unix seconds.
+    MalformedTimestamp,
+    /// The timestamp is outside the replay tolerance.
+    StaleTimestamp,
+}
+
+/// How far a signed timestamp may drift from now before it is rejected.
+pub const DEFAULT_WEBHOOK_TOLERANCE: Duration = Duration::from_secs(5 * 60);
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSignature => write!(f, "missing webhook signature"),
+            Self::MalformedSignature => write!(f, "malformed webhook signature"),
+            Self::InvalidSignature => write!(f, "webhook signature mismatch"),
+            Self::MalformedTimestamp => write!(f, "malformed webhook timestamp"),
+            Self::StaleTimestamp => write!(f, "webhook timestamp outside tolerance"),
+        }
+    }
+}
+
+impl std::error::Error for WebhookError {}
+
+/// Verifies webhooks from providers that send `hex(hmac_sha256(secret, body))`
+/// in a configurable header, optionally prefixed with `sha256=`.
+#[derive(Clone)]
+pub struct HmacWebhookVerifier {
+    header: String,
+    secret: Vec<u8>,
+    tolerance: Duration,
+}
+
+impl HmacWebhookVerifier {
+    pub fn new(header: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self::new_with_tolerance(header, secret, DEFAULT_WEBHOOK_TOLERANCE)
+    }
+
+    pub fn new_with_tolerance(
+        header: impl Into<String>,
+        secret: impl Into<Vec<u8>>,
+        tolerance: Duration,
+    ) -> Self {
+        Self {
+            header: header.into(),
+            secret: secret.into(),
+            tolerance,
+        }
+    }
+
+    /// Header the provider puts the signature in.
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// Hex digest a provider would send for `body`.
+    pub fn sign(&self, body: &[u8]) -> String {
+        hex::encode(self.mac(body).finalize().into_bytes())
+    }
+
+    /// Hex digest a provider would send for `body` signed at `timestamp`.
+    pub fn sign_timestamped(&self, timestamp: &str, body: &[u8]) -> String {
+        self.sign(&timestamped(timestamp, body))
+    }
+
+    /// Check `signature`, the value of [`Self::header`], against the raw
+    /// request body. The comparison is constant-time.
+    pub fn verify_webhook(&self, signature: Option<&str>, body: &[u8]) -> Result<(), WebhookError> {
+        let signature = signature.ok_or(WebhookError::MissingSignature)?.trim();
+        let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+        let expected = hex::decode(signature).map_err(|_| WebhookError::MalformedSignature)?;
+        self.mac(body)
+            .verify_slice(&expected)
+            .map_err(|_| WebhookError::InvalidSignature)
+    }
+
+    /// Check a signature over `{timestamp}.{body}`, rejecting timestamps more
+    /// than the tolerance away from now so captured webhooks can't be replayed.
+    pub fn verify_timestamped(
+        &self,
+        signature: Option<&str>,
+        timestamp: &str,
+        body: &[u8],
+    ) -> Result<(), WebhookError> {
+        self.verify_timestamped_at(signature, timestamp, body, SystemTime::now())
+    }
+
+    fn verify_timestamped_at(
+        &self,
+        signature: Option<&str>,
+        timestamp: &str,
+        body: &[u8],
+        now: SystemTime,
+    ) -> Result<(), WebhookError> {
+        let secs: u64 = timestamp
+            .trim()
+            .parse()
+            .map_err(|_| WebhookError::MalformedTimestamp)?;
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if now.abs_diff(secs) > self.tolerance.as_secs() {
+            return Err(WebhookError::StaleTimestamp);
+        }
+        self.verify_webhook(signature, &timestamped(timestamp.trim(), body))
+    }
+
+    fn mac(&self, body: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(body);
+        mac
+    }
+}
+
+fn timestamped(timestamp: &str, body: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(timestamp.len() + 1 + body.len());
+    payload.extend_from_slice(timestamp.as_bytes());
+    payload.push(b'.');
+    payload.extend_from_slice(body);
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "whsec_test";
+    const BODY: &[u8] = br#"{"sku":"basic","user":"u1"}"#;
+    const SIGNATURE: &str = "cc6e8ab80b712e213f3dde495579eba2e0167686f08b400852d851905ad04bbf";
+
+    fn verifier() -> HmacWebhookVerifier {
+        HmacWebhookVerifier::new("X-Signature", SECRET)
+    }
+
+    #[test]
+    fn accepts_known_signature() {
+        let verifier = verifier();
+        assert_eq!(verifier.sign(BODY), SIGNATURE);
+        assert_eq!(verifier.verify_webhook(Some(SIGNATURE), BODY), Ok(()));
+        let prefixed = format!("sha256={SIGNATURE}");
+        assert_eq!(verifier.verify_webhook(Some(&prefixed), BODY), Ok(()));
+    }
+
+    #[test]
+    fn rejects_tampered_payload() {
+        let verifier = verifier();
+        let tampered = br#"{"sku":"basic","user":"u2"}"#;
+        assert_eq!(
+            verifier.verify_webhook(Some(SIGNATURE), tampered),
+            Err(WebhookError::InvalidSignature)
+        );
+        assert_eq!(
+            verifier.verify_webhook(Some("not-hex"), BODY),
+            Err(WebhookError::MalformedSignature)
+        );
+        assert_eq!(
+            verifier.verify_webhook(None, BODY),
+            Err(WebhookError::MissingSignature)
+        );
+    }
+
+    #[test]
+    fn timestamped_webhook_respects_tolerance() {
+        let verifier = verifier();
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let sign = |t: &str| verifier.sign_timestamped(t, BODY);
+
+        let fresh = "1699999900";
+        assert_eq!(
+            verifier.verify_timestamped_at(Some(&sign(fresh)), fresh, BODY, now),
+            Ok(())
+        );
+
+        let stale = "1699999000";
+        assert_eq!(
+            verifier.verify_timestamped_at(Some(&sign(stale)), stale, BODY, now),
+            Err(WebhookError::StaleTimestamp)
+        );
+
+        assert_eq!(
+            verifier.verify_timestamped_at(Some(&sign("soon")), "soon", BODY, now),
+            Err(WebhookError::MalformedTimestamp)
+        );
+    }
+}
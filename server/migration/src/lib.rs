@@ -4,6 +4,15 @@ mod m0001_init;
 mod m0002_add_analytics_event_id;
 mod m0003_create_leaderboard_tables;
 mod m0004_email_otps;
+mod m0005_room_state;
+mod m0006_entitlement_expiry;
+mod m0007_purchase_price;
+mod m0008_purchase_idempotency;
+mod m0009_mail_outbox_html;
+mod m0010_sessions;
+mod m0011_run_trimmed_points;
+mod m0012_checkout_sessions;
+mod m0013_mail_outbox_attempts;
 
 pub struct Migrator;
 
@@ -15,6 +24,15 @@ impl MigratorTrait for Migrator {
             Box::new(m0002_add_analytics_event_id::Migration),
             Box::new(m0003_create_leaderboard_tables::Migration),
             Box::new(m0004_email_otps::Migration),
+            Box::new(m0005_room_state::Migration),
+            Box::new(m0006_entitlement_expiry::Migration),
+            Box::new(m0007_purchase_price::Migration),
+            Box::new(m0008_purchase_idempotency::Migration),
+            Box::new(m0009_mail_outbox_html::Migration),
+            Box::new(m0010_sessions::Migration),
+            Box::new(m0011_run_trimmed_points::Migration),
+            Box::new(m0012_checkout_sessions::Migration),
+            Box::new(m0013_mail_outbox_attempts::Migration),
         ]
     }
 }
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RoomState::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RoomState::RoomId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(RoomState::PlayerIds).json().not_null())
+                    .col(ColumnDef::new(RoomState::Scores).json().not_null())
+                    .col(ColumnDef::new(RoomState::Frame).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(RoomState::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RoomState::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum RoomState {
+    Table,
+    RoomId,
+    PlayerIds,
+    Scores,
+    Frame,
+    UpdatedAt,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Purchases made before this migration have no recorded price.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Purchases::Table)
+                    .add_column(
+                        ColumnDef::new(Purchases::PriceCents)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Purchases::Table)
+                    .drop_column(Purchases::PriceCents)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Purchases {
+    Table,
+    PriceCents,
+}
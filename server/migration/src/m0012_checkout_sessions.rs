@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CheckoutSessions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CheckoutSessions::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CheckoutSessions::PlayerId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(CheckoutSessions::Sku).string().not_null())
+                    .col(
+                        ColumnDef::new(CheckoutSessions::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CheckoutSessions::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum CheckoutSessions {
+    Table,
+    Id,
+    PlayerId,
+    Sku,
+    ExpiresAt,
+}
@@ -6,3 +6,45 @@ fn migrator_contains_email_otps() {
     let names: Vec<&str> = migrations.iter().map(|m| m.name()).collect();
     assert!(names.contains(&"m0004_email_otps"));
 }
+
+#[test]
+fn migrator_contains_room_state() {
+    let migrations = Migrator::migrations();
+    let names: Vec<&str> = migrations.iter().map(|m| m.name()).collect();
+    assert!(names.contains(&"m0005_room_state"));
+}
+
+#[test]
+fn migrator_contains_entitlement_expiry() {
+    let migrations = Migrator::migrations();
+    let names: Vec<&str> = migrations.iter().map(|m| m.name()).collect();
+    assert!(names.contains(&"m0006_entitlement_expiry"));
+}
+
+#[test]
+fn migrator_contains_purchase_price() {
+    let migrations = Migrator::migrations();
+    let names: Vec<&str> = migrations.iter().map(|m| m.name()).collect();
+    assert!(names.contains(&"m0007_purchase_price"));
+}
+
+#[test]
+fn migrator_contains_purchase_idempotency() {
+    let migrations = Migrator::migrations();
+    let names: Vec<&str> = migrations.iter().map(|m| m.name()).collect();
+    assert!(names.contains(&"m0008_purchase_idempotency"));
+}
+
+#[test]
+fn migrator_contains_mail_outbox_html() {
+    let migrations = Migrator::migrations();
+    let names: Vec<&str> = migrations.iter().map(|m| m.name()).collect();
+    assert!(names.contains(&"m0009_mail_outbox_html"));
+}
+
+#[test]
+fn migrator_contains_sessions() {
+    let migrations = Migrator::migrations();
+    let names: Vec<&str> = migrations.iter().map(|m| m.name()).collect();
+    assert!(names.contains(&"m0010_sessions"));
+}
@@ -13,7 +13,7 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use crate::{AppState, otp_store};
+use crate::{AppState, csrf, otp_store, session_store};
 
 const REQUEST_COOLDOWN: Duration = Duration::from_secs(60);
 const OTP_TTL: Duration = Duration::from_secs(300);
@@ -72,7 +72,7 @@ async fn request_handler(
         tracing::error!("failed to insert OTP: {e}");
         return StatusCode::INTERNAL_SERVER_ERROR;
     }
-    let _ = state.email.send_otp_code(&body.email, &code_str);
+    let _ = state.email.send_otp_code(&body.email, &code_str).await;
     StatusCode::OK
 }
 
@@ -94,10 +94,29 @@ async fn verify_handler(
                     )
                         .into_response();
                 }
-                let token = Uuid::new_v4().to_string();
+                let id = Uuid::new_v4();
+                let expires_at = session_store::next_expiry();
+                if let Err(e) =
+                    session_store::insert_session(&state.db, id, &email_hash, expires_at).await
+                {
+                    tracing::error!("failed to store session: {e}");
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(VerifyResponse {
+                            token: String::new(),
+                        }),
+                    )
+                        .into_response();
+                }
+                let token = id.to_string();
                 let mut headers = HeaderMap::new();
-                let cookie = format!("session={}; Path=/; Secure; HttpOnly; SameSite=Lax", token);
+                let cookie = format!(
+                    "session={}; Path=/; Max-Age={}; Secure; HttpOnly; SameSite=Lax",
+                    token,
+                    session_store::SESSION_TTL.as_secs()
+                );
                 headers.insert(SET_COOKIE, HeaderValue::from_str(&cookie).unwrap());
+                csrf::issue(&mut headers, session_store::SESSION_TTL.as_secs(), true, "Lax");
                 return (headers, Json(VerifyResponse { token })).into_response();
             }
         }
@@ -1,8 +1,9 @@
-use axum::{Json, extract::Extension};
+use axum::{Json, extract::Extension, http::HeaderMap};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use std::collections::HashMap;
 
-use crate::{IceServerConfig, ResolvedConfig};
+use crate::{IceServerConfig, ResolvedConfig, session_from_cookie};
 
 /// Public configuration returned to clients.
 #[derive(Serialize)]
@@ -27,6 +28,9 @@ pub struct ConfigResponse {
     pub feature_flags: HashMap<String, bool>,
     /// ICE servers used for establishing peer connections
     pub ice_servers: Vec<IceServerConfig>,
+    /// When generated TURN credentials in `ice_servers` stop working
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ice_servers_expires_at: Option<DateTime<Utc>>,
     /// Whether COOP/COEP headers are enabled
     #[serde(default)]
     pub enable_coop_coep: bool,
@@ -35,8 +39,58 @@ pub struct ConfigResponse {
     pub enable_sw: bool,
 }
 
+/// ICE servers to hand to a client, with TURN credentials generated for its
+/// session when a TURN secret is configured.
+///
+/// Without a secret the only TURN credentials are the static ones, which
+/// must not reach anonymous clients, so TURN urls are left out.
+fn client_ice_servers(
+    cfg: &ResolvedConfig,
+    headers: &HeaderMap,
+) -> (Vec<IceServerConfig>, Option<DateTime<Utc>>) {
+    let Some(turn) = &cfg.turn else {
+        let servers = cfg
+            .ice_servers
+            .iter()
+            .filter_map(|server| {
+                let urls: Vec<String> = server
+                    .urls
+                    .iter()
+                    .filter(|url| !url.starts_with("turn"))
+                    .cloned()
+                    .collect();
+                (!urls.is_empty()).then_some(IceServerConfig {
+                    urls,
+                    username: None,
+                    credential: None,
+                })
+            })
+            .collect();
+        return (servers, None);
+    };
+    let user = session_from_cookie(headers).map_or_else(|| "anonymous".into(), |id| id.to_string());
+    let creds = turn.credentials(&user, Utc::now());
+    let servers = cfg
+        .ice_servers
+        .iter()
+        .cloned()
+        .map(|mut server| {
+            if server.urls.iter().any(|url| url.starts_with("turn")) {
+                server.username = Some(creds.username.clone());
+                server.credential = Some(creds.credential.clone());
+            }
+            server
+        })
+        .collect();
+    (servers, Some(creds.expires_at))
+}
+
 /// HTTP handler that returns public configuration as JSON.
-pub async fn get_config(Extension(cfg): Extension<ResolvedConfig>) -> Json<ConfigResponse> {
+pub async fn get_config(
+    Extension(cfg): Extension<ResolvedConfig>,
+    headers: HeaderMap,
+) -> Json<ConfigResponse> {
+    let (ice_servers, ice_servers_expires_at) = client_ice_servers(&cfg, &headers);
     let cfg = ConfigResponse {
         signal_url: cfg.signaling_ws_url.clone(),
         api_base_url: cfg.public_base_url.clone(),
@@ -45,7 +99,8 @@ pub async fn get_config(Extension(cfg): Extension<ResolvedConfig>) -> Json<Confi
         analytics_local: cfg.analytics_local,
         posthog_url: cfg.posthog_url.clone(),
         feature_flags: cfg.feature_flags.clone(),
-        ice_servers: cfg.ice_servers.clone(),
+        ice_servers,
+        ice_servers_expires_at,
         enable_coop_coep: cfg.enable_coop_coep,
         enable_sw: cfg.enable_sw,
     };
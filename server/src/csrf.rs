@@ -0,0 +1,72 @@
+//! Double-submit CSRF protection for cookie-authenticated requests.
+//!
+//! Whenever a session cookie is issued, a random token is issued alongside it
+//! in a `csrf` cookie that scripts can read. Unsafe requests carrying the
+//! `session` cookie must echo that token in the [`CSRF_HEADER`] header; a
+//! cross-site form or script can make the browser send the cookie but cannot
+//! read it to fill in the header.
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header::SET_COOKIE},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use uuid::Uuid;
+
+use crate::cookie_value;
+
+/// Cookie holding the CSRF token.
+pub const CSRF_COOKIE: &str = "csrf";
+/// Header unsafe requests must copy the CSRF cookie into.
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+
+/// Routes authenticated by other means, or that issue the token themselves.
+const EXEMPT_PATHS: &[&str] = &["/auth/guest", "/store/webhook"];
+
+/// Append a `Set-Cookie` header carrying a fresh CSRF token.
+///
+/// The cookie mirrors the session cookie's lifetime and attributes, except
+/// that it is not `HttpOnly` so the client can read it.
+pub fn issue(headers: &mut HeaderMap, max_age: u64, secure: bool, same_site: &str) {
+    let cookie = format!(
+        "{CSRF_COOKIE}={}; Path=/; Max-Age={max_age};{} SameSite={same_site}",
+        Uuid::new_v4().simple(),
+        if secure { " Secure;" } else { "" },
+    );
+    match HeaderValue::from_str(&cookie) {
+        Ok(value) => {
+            headers.append(SET_COOKIE, value);
+        }
+        Err(e) => {
+            tracing::error!("failed to create CSRF cookie header: {e}");
+        }
+    }
+}
+
+/// Whether `headers` carry a CSRF header matching the CSRF cookie.
+fn token_matches(headers: &HeaderMap) -> bool {
+    let header = headers.get(CSRF_HEADER).and_then(|v| v.to_str().ok());
+    match (header, cookie_value(headers, CSRF_COOKIE)) {
+        (Some(header), Some(cookie)) => !cookie.is_empty() && header == cookie,
+        _ => false,
+    }
+}
+
+/// Reject unsafe requests that ride on the `session` cookie without a valid
+/// CSRF token with `403 Forbidden`.
+///
+/// Requests without a session cookie are left alone: they are authenticated
+/// by headers a cross-site page cannot set, if at all.
+pub async fn require_csrf_token(request: Request, next: Next) -> Response {
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE
+    );
+    let exempt = EXEMPT_PATHS.contains(&request.uri().path());
+    let headers = request.headers();
+    if !safe && !exempt && cookie_value(headers, "session").is_some() && !token_matches(headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(request).await
+}
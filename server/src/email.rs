@@ -1,31 +1,48 @@
 //! Email utilities and rate limiting.
 //!
-//! A background task periodically purges expired entries from the rate-limit
-//! map. The task runs on the Tokio runtime and its [`JoinHandle`] is stored so
-//! it can be aborted during shutdown if necessary. The cleanup task is
-//! terminated when [`EmailService`] is dropped.
+//! Each [`EmailService`] owns its rate-limit map, so services with different
+//! windows never share state. A background task periodically purges expired
+//! entries from the map. The task runs on the Tokio runtime and its
+//! [`JoinHandle`] is stored so it can be aborted; it is terminated when the
+//! [`EmailService`] is dropped.
+//!
+//! Services built with [`EmailService::with_outbox`] persist each message to
+//! the `mail_outbox` table instead of holding it in memory. A worker delivers
+//! pending rows, deleting them once sent, so mail queued before a restart is
+//! still delivered afterwards. A row that keeps failing is retried on later
+//! passes until it has failed [`OUTBOX_MAX_ATTEMPTS`] times.
 
 use anyhow::{anyhow, Result};
+use chrono::Utc;
 use clap::{Args, ValueEnum};
 use lettre::address::AddressError;
+use lettre::message::MultiPart;
 use lettre::transport::smtp::{
     authentication::Credentials,
     client::{Tls, TlsParameters},
 };
 use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
 use once_cell::sync::Lazy;
+use prometheus::core::Collector;
 use prometheus::{
     register_gauge, register_int_counter, register_int_gauge_vec, Gauge, IntCounter, IntGaugeVec,
 };
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+};
 use serde::Serialize;
 use std::collections::HashMap;
 use std::fmt;
-use std::sync::{atomic::{AtomicUsize, Ordering}, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use thiserror::Error;
 use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::sync::Notify;
 use tokio::task::JoinHandle;
 
+use crate::entities::mail_outbox;
+
 // -- Configuration ---------------------------------------------------------
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, ValueEnum)]
@@ -92,6 +109,26 @@ pub struct SmtpConfig {
     pub user: Option<String>,
     #[arg(long = "smtp-pass", env = "ARENA_SMTP_PASS")]
     pub pass: Option<String>,
+    /// Minimum time between two emails to the same recipient; 0 disables the limit.
+    #[arg(
+        long = "smtp-rate-limit-ms",
+        env = "ARENA_SMTP_RATE_LIMIT_MS",
+        default_value_t = 60000
+    )]
+    pub rate_limit: u64,
+    /// Directory of `{name}.txt` files overriding the built-in email copy.
+    #[arg(long = "smtp-templates-dir", env = "ARENA_SMTP_TEMPLATES_DIR")]
+    pub templates_dir: Option<PathBuf>,
+    /// Send attempts per message before it is dead-lettered.
+    #[arg(long = "smtp-retries", env = "ARENA_SMTP_RETRIES", default_value_t = 5)]
+    pub retries: u32,
+    /// Delay before the first retry; doubles after each failed attempt.
+    #[arg(
+        long = "smtp-retry-base-ms",
+        env = "ARENA_SMTP_RETRY_BASE_MS",
+        default_value_t = 1000
+    )]
+    pub retry_base_ms: u64,
 }
 
 impl Default for SmtpConfig {
@@ -105,6 +142,10 @@ impl Default for SmtpConfig {
             timeout: 10000,
             user: None,
             pass: None,
+            rate_limit: 60000,
+            templates_dir: None,
+            retries: MAX_RETRIES,
+            retry_base_ms: RETRY_BASE.as_millis() as u64,
         }
     }
 }
@@ -123,29 +164,265 @@ impl SmtpConfig {
     }
 }
 
+// -- Templates ------------------------------------------------------------
+
+/// Emails sent by [`EmailService`], each with a built-in subject and body.
+///
+/// Copy is written with `{placeholder}` markers that are filled in when the
+/// template is rendered; unknown placeholders are left untouched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EmailTemplate {
+    /// Placeholder: `{password}`.
+    RegistrationPassword,
+    /// Placeholder: `{link}`.
+    VerificationLink,
+    /// Placeholder: `{code}`.
+    OtpCode,
+    /// Placeholder: `{link}`.
+    PasswordReset,
+    Test,
+}
+
+impl EmailTemplate {
+    pub const ALL: [EmailTemplate; 5] = [
+        EmailTemplate::RegistrationPassword,
+        EmailTemplate::VerificationLink,
+        EmailTemplate::OtpCode,
+        EmailTemplate::PasswordReset,
+        EmailTemplate::Test,
+    ];
+
+    /// File stem used when loading an override from a template directory.
+    pub fn name(self) -> &'static str {
+        match self {
+            EmailTemplate::RegistrationPassword => "registration_password",
+            EmailTemplate::VerificationLink => "verification_link",
+            EmailTemplate::OtpCode => "otp_code",
+            EmailTemplate::PasswordReset => "password_reset",
+            EmailTemplate::Test => "test",
+        }
+    }
+
+    fn default_copy(self) -> (&'static str, &'static str) {
+        match self {
+            EmailTemplate::RegistrationPassword => (
+                "Registration Password",
+                "Your registration password is: {password}",
+            ),
+            EmailTemplate::VerificationLink => (
+                "Verify Your Account",
+                "Click the following link to verify your account: {link}",
+            ),
+            EmailTemplate::OtpCode => ("Your OTP Code", "Your one-time passcode is: {code}"),
+            EmailTemplate::PasswordReset => (
+                "Password Reset",
+                "Reset your password using the following link: {link}",
+            ),
+            EmailTemplate::Test => ("Test email", "Arena test message"),
+        }
+    }
+
+    fn default_html(self) -> Option<&'static str> {
+        match self {
+            EmailTemplate::VerificationLink => Some(
+                "<p>Click the following link to verify your account:</p>\n\
+                 <p><a href=\"{link}\">{link}</a></p>",
+            ),
+            EmailTemplate::OtpCode => {
+                Some("<p>Your one-time passcode is:</p>\n<p><strong>{code}</strong></p>")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Subject and body produced by [`EmailTemplates::render`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub body: String,
+    /// HTML alternative to `body`, if the template has one.
+    pub html: Option<String>,
+}
+
+/// Operator overrides for [`EmailTemplate`] copy.
+///
+/// Templates without an override render their built-in default. Overriding
+/// the plain text without also providing HTML drops the built-in HTML, so
+/// readers never see stale default copy.
+#[derive(Clone, Debug, Default)]
+pub struct EmailTemplates {
+    overrides: HashMap<EmailTemplate, (String, String)>,
+    html: HashMap<EmailTemplate, String>,
+}
+
+impl EmailTemplates {
+    /// Load overrides from `dir`, reading `{name}.txt` and `{name}.html` for
+    /// each template.
+    ///
+    /// The first line of a `.txt` file is the subject and the remaining lines
+    /// are the body. A `.html` file holds the HTML body. Missing files keep the
+    /// built-in default.
+    pub fn load_dir(dir: &Path) -> std::io::Result<Self> {
+        let mut templates = Self::default();
+        for template in EmailTemplate::ALL {
+            let path = dir.join(format!("{}.txt", template.name()));
+            if let Some(text) = read_optional(&path)? {
+                let (subject, body) = text.split_once('\n').unwrap_or((&text, ""));
+                templates.set(template, subject.trim_end(), body.trim_end());
+            }
+            let path = dir.join(format!("{}.html", template.name()));
+            if let Some(html) = read_optional(&path)? {
+                templates.set_html(template, html.trim_end());
+            }
+        }
+        Ok(templates)
+    }
+
+    /// Override the subject and body for `template`.
+    pub fn set(&mut self, template: EmailTemplate, subject: &str, body: &str) {
+        self.overrides
+            .insert(template, (subject.to_string(), body.to_string()));
+    }
+
+    /// Override the HTML body for `template`.
+    pub fn set_html(&mut self, template: EmailTemplate, html: &str) {
+        self.html.insert(template, html.to_string());
+    }
+
+    /// Render `template`, replacing each `{key}` with its value from `vars`.
+    ///
+    /// Values are HTML-escaped when substituted into the HTML body.
+    pub fn render(&self, template: EmailTemplate, vars: &[(&str, &str)]) -> RenderedEmail {
+        let (subject, body) = match self.overrides.get(&template) {
+            Some((subject, body)) => (subject.as_str(), body.as_str()),
+            None => template.default_copy(),
+        };
+        let html = match self.html.get(&template) {
+            Some(html) => Some(html.as_str()),
+            None if self.overrides.contains_key(&template) => None,
+            None => template.default_html(),
+        };
+        let escaped: Vec<(&str, String)> = vars.iter().map(|(k, v)| (*k, escape_html(v))).collect();
+        let escaped: Vec<(&str, &str)> = escaped.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        RenderedEmail {
+            subject: substitute(subject, vars),
+            body: substitute(body, vars),
+            html: html.map(|html| substitute(html, &escaped)),
+        }
+    }
+}
+
+fn read_optional(path: &Path) -> std::io::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn substitute(text: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let key = &after[..end];
+            vars.iter().find(|(k, _)| *k == key).map(|(_, v)| (*v, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
 // -- Rate limiting --------------------------------------------------------
 
-static RATE_LIMITS: Lazy<Mutex<HashMap<String, Instant>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
-static ACTIVE_SERVICES: AtomicUsize = AtomicUsize::new(0);
-static CLEANUP_TASK: Lazy<Mutex<Option<JoinHandle<()>>>> =
-    Lazy::new(|| Mutex::new(None));
+/// Per-recipient send limit owned by a single [`EmailService`].
+struct RateLimiter {
+    window: Duration,
+    last_sent: Arc<Mutex<HashMap<String, Instant>>>,
+    cleanup: Option<JoinHandle<()>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter whose expired entries are purged once per `window`.
+    fn new(window: Duration) -> Self {
+        let last_sent = Arc::new(Mutex::new(HashMap::new()));
+        let cleanup = (!window.is_zero()).then(|| spawn_cleanup(last_sent.clone(), window));
+        Self {
+            window,
+            last_sent,
+            cleanup,
+        }
+    }
+
+    fn allowed(&self, to: &str) -> Result<bool, EmailError> {
+        if self.window.is_zero() {
+            return Ok(true);
+        }
+        let mut map = self.last_sent.lock().map_err(|_| EmailError::LockPoisoned)?;
+        let now = Instant::now();
+        let allowed = match map.get(to) {
+            Some(last) if now.duration_since(*last) < self.window => false,
+            _ => {
+                map.insert(to.to_string(), now);
+                true
+            }
+        };
+        Ok(allowed)
+    }
+}
+
+impl Drop for RateLimiter {
+    fn drop(&mut self) {
+        if let Some(handle) = self.cleanup.take() {
+            handle.abort();
+        }
+    }
+}
 
-fn spawn_cleanup() -> JoinHandle<()> {
+fn spawn_cleanup(
+    last_sent: Arc<Mutex<HashMap<String, Instant>>>,
+    window: Duration,
+) -> JoinHandle<()> {
     tokio::spawn(async move {
         loop {
-            tokio::time::sleep(CLEANUP_INTERVAL).await;
+            tokio::time::sleep(window).await;
             let now = Instant::now();
-            let mut map = match RATE_LIMITS.lock() {
+            let mut map = match last_sent.lock() {
                 Ok(m) => m,
                 Err(poison) => poison.into_inner(),
             };
-            map.retain(|_, &mut instant| now.duration_since(instant) < RATE_LIMIT);
+            map.retain(|_, &mut instant| now.duration_since(instant) < window);
         }
     })
 }
-const RATE_LIMIT: Duration = Duration::from_secs(60);
-const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
 
 static EMAIL_QUEUED: Lazy<IntCounter> =
     Lazy::new(|| register_int_counter!("email_queued_total", "Emails queued").unwrap());
@@ -166,6 +443,36 @@ const MAX_RETRIES: u32 = 5;
 const RETRY_BASE: Duration = Duration::from_millis(1);
 #[cfg(not(test))]
 const RETRY_BASE: Duration = Duration::from_millis(1000);
+// each delay is stretched by a random fraction up to this much
+const RETRY_JITTER: f64 = 0.5;
+
+/// How often and how patiently a message is retried.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RetryPolicy {
+    attempts: u32,
+    base: Duration,
+}
+
+impl RetryPolicy {
+    fn from_config(config: &SmtpConfig) -> Self {
+        Self {
+            // Always try at least once.
+            attempts: config.retries.max(1),
+            base: Duration::from_millis(config.retry_base_ms),
+        }
+    }
+}
+
+/// Add up to [`RETRY_JITTER`] of `delay` so failures at the same moment don't
+/// retry in lockstep.
+fn jittered(delay: Duration) -> Duration {
+    delay + delay.mul_f64(rand::random::<f64>() * RETRY_JITTER)
+}
+
+// how often the outbox worker looks for rows it was not woken for
+const OUTBOX_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Worker passes a failing outbox row gets before it is dead-lettered.
+const OUTBOX_MAX_ATTEMPTS: i32 = 5;
 
 #[derive(Debug, Error)]
 pub enum EmailError {
@@ -179,16 +486,67 @@ pub enum EmailError {
     Build(lettre::error::Error),
     #[error("lock poisoned")]
     LockPoisoned,
+    #[error("{0}")]
+    Template(std::io::Error),
+    #[error("{0}")]
+    Outbox(DbErr),
 }
 
 pub struct EmailService {
     from: String,
-    sender: UnboundedSender<Message>,
+    queue: Queue,
+    rate_limit: RateLimiter,
+    templates: EmailTemplates,
+}
+
+/// Where queued mail waits for delivery.
+enum Queue {
+    /// In-memory channel drained by a background task.
+    Channel(UnboundedSender<Message>),
+    Outbox(Outbox),
+}
+
+/// Database-backed queue used instead of the in-memory channel.
+struct Outbox {
+    db: DatabaseConnection,
+    wake: Arc<Notify>,
+    worker: JoinHandle<()>,
 }
 
 impl EmailService {
     pub fn new(config: SmtpConfig) -> Result<Self, EmailError> {
+        let transport = Self::smtp_transport(&config)?;
+        let templates = Self::load_templates(&config)?;
+        Ok(Self::new_with_transport(&config, transport).with_templates(templates))
+    }
+
+    /// Build a service that queues mail in the `mail_outbox` table so it
+    /// survives a restart. Rows left pending by a previous run are delivered
+    /// as soon as the worker starts.
+    pub fn with_outbox(config: SmtpConfig, db: DatabaseConnection) -> Result<Self, EmailError> {
+        let transport = Self::smtp_transport(&config)?;
+        let templates = Self::load_templates(&config)?;
+        Ok(Self::new_with_outbox_transport(&config, transport, db).with_templates(templates))
+    }
+
+    fn load_templates(config: &SmtpConfig) -> Result<EmailTemplates, EmailError> {
+        match &config.templates_dir {
+            Some(dir) => EmailTemplates::load_dir(dir).map_err(EmailError::Template),
+            None => Ok(EmailTemplates::default()),
+        }
+    }
+
+    /// Replace the copy used by the `send_*` helpers.
+    pub fn with_templates(mut self, templates: EmailTemplates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    fn smtp_transport(
+        config: &SmtpConfig,
+    ) -> Result<AsyncSmtpTransport<Tokio1Executor>, EmailError> {
         let config = config
+            .clone()
             .validate()
             .map_err(|e| EmailError::Smtp(e.to_string()))?;
 
@@ -217,170 +575,352 @@ impl EmailService {
             builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
         }
 
-        let transport = builder.build();
-        Ok(Self::new_with_transport(config.from, transport))
+        Ok(builder.build())
     }
 
-    pub(crate) fn new_with_transport<T>(from: String, transport: T) -> Self
+    pub(crate) fn new_with_transport<T>(config: &SmtpConfig, transport: T) -> Self
     where
         T: AsyncTransport + Clone + Send + Sync + 'static,
         T::Error: std::fmt::Display,
     {
-        // Start periodic cleanup once
-        if ACTIVE_SERVICES.fetch_add(1, Ordering::SeqCst) == 0 {
-            let handle = spawn_cleanup();
-            let mut guard = CLEANUP_TASK.lock().unwrap_or_else(|e| e.into_inner());
-            *guard = Some(handle);
-        }
+        let retry = RetryPolicy::from_config(config);
         let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
         tokio::spawn(async move {
             while let Some(msg) = rx.recv().await {
-                let mailer = transport.clone();
-                let start = Instant::now();
-                let res = send_with_retry(|| {
-                    let mailer = mailer.clone();
-                    let msg = msg.clone();
-                    async move {
-                        mailer
-                            .send(msg)
-                            .await
-                            .map(|_| ())
-                            .map_err(|e| e.to_string())
-                    }
-                })
-                .await;
-                let latency = start.elapsed().as_secs_f64();
-                EMAIL_LAST_LATENCY.set(latency);
-                match res {
-                    Ok(()) => {
-                        EMAIL_SENT.inc();
-                        EMAIL_LAST_ERROR.reset();
-                    }
-                    Err(e) => {
-                        EMAIL_FAILED.inc();
-                        EMAIL_LAST_ERROR.reset();
-                        EMAIL_LAST_ERROR.with_label_values(&[&e]).set(1);
-                        tracing::error!("dead-letter to {:?}: {e}", msg.envelope().to());
-                    }
+                if let Err(e) = deliver(&transport, &msg, retry).await {
+                    tracing::error!("dead-letter to {:?}: {e}", msg.envelope().to());
                 }
             }
         });
-        Self { from, sender: tx }
+        Self::with_queue(config, Queue::Channel(tx))
     }
 
-    fn allowed(to: &str) -> Result<bool, EmailError> {
-        let mut map = RATE_LIMITS.lock().map_err(|_| EmailError::LockPoisoned)?;
-        let now = Instant::now();
-        let allowed = match map.get(to) {
-            Some(last) if now.duration_since(*last) < RATE_LIMIT => false,
-            _ => {
-                map.insert(to.to_string(), now);
-                true
-            }
-        };
-        Ok(allowed)
+    pub(crate) fn new_with_outbox_transport<T>(
+        config: &SmtpConfig,
+        transport: T,
+        db: DatabaseConnection,
+    ) -> Self
+    where
+        T: AsyncTransport + Clone + Send + Sync + 'static,
+        T::Error: std::fmt::Display,
+    {
+        let wake = Arc::new(Notify::new());
+        let from = config.from.clone();
+        let retry = RetryPolicy::from_config(config);
+        let worker = tokio::spawn(run_outbox(db.clone(), from, transport, retry, wake.clone()));
+        Self::with_queue(config, Queue::Outbox(Outbox { db, wake, worker }))
     }
 
-    pub fn queue_mail(&self, email: Message) {
-        EMAIL_QUEUED.inc();
-        if self.sender.send(email).is_err() {
-            tracing::warn!("email queue disconnected");
+    fn with_queue(config: &SmtpConfig, queue: Queue) -> Self {
+        Self {
+            from: config.from.clone(),
+            queue,
+            rate_limit: RateLimiter::new(Duration::from_millis(config.rate_limit)),
+            templates: EmailTemplates::default(),
         }
     }
 
-    pub(crate) fn send_mail(&self, to: &str, subject: &str, body: &str) -> Result<(), EmailError> {
-        if !Self::allowed(to)? {
+    /// Whether queued mail is persisted to the `mail_outbox` table.
+    pub fn has_outbox(&self) -> bool {
+        matches!(self.queue, Queue::Outbox(_))
+    }
+
+    /// Queue a message to `to`. When `html` is given the message is sent as
+    /// `multipart/alternative` with `body` as the plain-text fallback.
+    ///
+    /// With an outbox this returns once the row is written, or the error if
+    /// it couldn't be.
+    pub(crate) async fn send_mail(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+        html: Option<&str>,
+    ) -> Result<(), EmailError> {
+        if !self.rate_limit.allowed(to)? {
             return Err(EmailError::RateLimited);
         }
 
-        let email = Message::builder()
-            .from(self.from.parse().map_err(EmailError::Address)?)
-            .to(to.parse().map_err(EmailError::Address)?)
-            .subject(subject)
-            .body(body.to_string())
-            .map_err(EmailError::Build)?;
+        let email = build_message(&self.from, to, subject, body, html)?;
 
-        self.queue_mail(email);
+        match &self.queue {
+            Queue::Outbox(outbox) => outbox
+                .persist(to, subject, body, html)
+                .await
+                .map_err(EmailError::Outbox)?,
+            Queue::Channel(sender) => {
+                EMAIL_QUEUED.inc();
+                if sender.send(email).is_err() {
+                    tracing::warn!("email queue disconnected");
+                }
+            }
+        }
         Ok(())
     }
 
-    pub fn send_registration_password(&self, to: &str, password: &str) -> Result<(), EmailError> {
-        let subject = "Registration Password";
-        let body = format!("Your registration password is: {}", password);
-        self.send_mail(to, subject, &body)
+    async fn send_template(
+        &self,
+        to: &str,
+        template: EmailTemplate,
+        vars: &[(&str, &str)],
+    ) -> Result<(), EmailError> {
+        let email = self.templates.render(template, vars);
+        self.send_mail(to, &email.subject, &email.body, email.html.as_deref())
+            .await
+    }
+
+    pub async fn send_registration_password(
+        &self,
+        to: &str,
+        password: &str,
+    ) -> Result<(), EmailError> {
+        self.send_template(
+            to,
+            EmailTemplate::RegistrationPassword,
+            &[("password", password)],
+        )
+        .await
     }
 
     #[allow(dead_code)]
-    pub fn send_verification_link(&self, to: &str, link: &str) -> Result<(), EmailError> {
-        let subject = "Verify Your Account";
-        let body = format!("Click the following link to verify your account: {}", link);
-        self.send_mail(to, subject, &body)
+    pub async fn send_verification_link(&self, to: &str, link: &str) -> Result<(), EmailError> {
+        self.send_template(to, EmailTemplate::VerificationLink, &[("link", link)])
+            .await
     }
 
     #[allow(dead_code)]
-    pub fn send_otp_code(&self, to: &str, code: &str) -> Result<(), EmailError> {
-        let subject = "Your OTP Code";
-        let body = format!("Your one-time passcode is: {}", code);
-        self.send_mail(to, subject, &body)
+    pub async fn send_otp_code(&self, to: &str, code: &str) -> Result<(), EmailError> {
+        self.send_template(to, EmailTemplate::OtpCode, &[("code", code)])
+            .await
     }
 
     #[allow(dead_code)]
-    pub fn send_password_reset(&self, to: &str, link: &str) -> Result<(), EmailError> {
-        let subject = "Password Reset";
-        let body = format!("Reset your password using the following link: {}", link);
-        self.send_mail(to, subject, &body)
+    pub async fn send_password_reset(&self, to: &str, link: &str) -> Result<(), EmailError> {
+        self.send_template(to, EmailTemplate::PasswordReset, &[("link", link)])
+            .await
     }
 
-    pub fn send_test(&self, to: &str) -> Result<(), EmailError> {
-        self.send_mail(to, "Test email", "Arena test message")
+    pub async fn send_test(&self, to: &str) -> Result<(), EmailError> {
+        self.send_template(to, EmailTemplate::Test, &[]).await
     }
 
     pub fn from_address(&self) -> &str {
         &self.from
     }
 
+    /// Snapshot of the email metrics for health checks.
+    ///
+    /// The counters are process-wide, so every service reports the same totals.
+    pub fn status(&self) -> EmailStatus {
+        let last_error = EMAIL_LAST_ERROR
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric())
+            .find(|metric| metric.get_gauge().get_value() > 0.0)
+            .and_then(|metric| metric.get_label().first())
+            .map(|label| label.get_value().to_string());
+        EmailStatus {
+            queued: EMAIL_QUEUED.get(),
+            sent: EMAIL_SENT.get(),
+            failed: EMAIL_FAILED.get(),
+            last_latency_secs: EMAIL_LAST_LATENCY.get(),
+            last_error,
+        }
+    }
+}
+
+/// Delivery totals and the outcome of the most recent send.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EmailStatus {
+    pub queued: u64,
+    pub sent: u64,
+    pub failed: u64,
+    pub last_latency_secs: f64,
+    /// Error from the last send, or `None` if it succeeded.
+    pub last_error: Option<String>,
+}
+
+impl Outbox {
+    async fn persist(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+        html: Option<&str>,
+    ) -> Result<(), DbErr> {
+        let row = mail_outbox::ActiveModel {
+            recipient: Set(to.to_string()),
+            subject: Set(subject.to_string()),
+            body: Set(body.to_string()),
+            html: Set(html.map(str::to_string)),
+            created_at: Set(Utc::now()),
+            sent_at: Set(None),
+            error: Set(None),
+            attempts: Set(0),
+            ..Default::default()
+        };
+        mail_outbox::Entity::insert(row).exec(&self.db).await?;
+        EMAIL_QUEUED.inc();
+        self.wake.notify_one();
+        Ok(())
+    }
 }
 
-impl Drop for EmailService {
+impl Drop for Outbox {
     fn drop(&mut self) {
-        if ACTIVE_SERVICES.fetch_sub(1, Ordering::SeqCst) == 1 {
-            let mut guard = CLEANUP_TASK.lock().unwrap_or_else(|e| e.into_inner());
-            if let Some(handle) = guard.take() {
-                handle.abort();
+        self.worker.abort();
+    }
+}
+
+/// Send `msg` with retries, recording the outcome in the email metrics.
+async fn deliver<T>(transport: &T, msg: &Message, retry: RetryPolicy) -> Result<(), String>
+where
+    T: AsyncTransport + Clone + Send + Sync + 'static,
+    T::Error: std::fmt::Display,
+{
+    let start = Instant::now();
+    let res = send_with_retry(retry, || {
+        let mailer = transport.clone();
+        let msg = msg.clone();
+        async move {
+            mailer
+                .send(msg)
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        }
+    })
+    .await;
+    EMAIL_LAST_LATENCY.set(start.elapsed().as_secs_f64());
+    EMAIL_LAST_ERROR.reset();
+    match &res {
+        Ok(()) => EMAIL_SENT.inc(),
+        Err(e) => {
+            EMAIL_FAILED.inc();
+            EMAIL_LAST_ERROR.with_label_values(&[e.as_str()]).set(1);
+        }
+    }
+    res
+}
+
+async fn run_outbox<T>(
+    db: DatabaseConnection,
+    from: String,
+    transport: T,
+    retry: RetryPolicy,
+    wake: Arc<Notify>,
+) where
+    T: AsyncTransport + Clone + Send + Sync + 'static,
+    T::Error: std::fmt::Display,
+{
+    loop {
+        if let Err(e) = deliver_outbox(&db, &from, &transport, retry).await {
+            tracing::error!("mail outbox delivery failed: {e}");
+        }
+        tokio::select! {
+            _ = wake.notified() => {}
+            _ = tokio::time::sleep(OUTBOX_POLL_INTERVAL) => {}
+        }
+    }
+}
+
+/// Deliver every pending outbox row. Sent rows are deleted. A failed row is
+/// tried again on later passes until it has failed [`OUTBOX_MAX_ATTEMPTS`]
+/// times, then keeps its error so it is not retried forever.
+async fn deliver_outbox<T>(
+    db: &DatabaseConnection,
+    from: &str,
+    transport: &T,
+    retry: RetryPolicy,
+) -> Result<(), DbErr>
+where
+    T: AsyncTransport + Clone + Send + Sync + 'static,
+    T::Error: std::fmt::Display,
+{
+    let pending = mail_outbox::Entity::find()
+        .filter(mail_outbox::Column::SentAt.is_null())
+        .filter(mail_outbox::Column::Error.is_null())
+        .order_by_asc(mail_outbox::Column::Id)
+        .all(db)
+        .await?;
+    for row in pending {
+        let html = row.html.as_deref();
+        // A row that doesn't build into a message never will, so it isn't retried.
+        let (res, permanent) =
+            match build_message(from, &row.recipient, &row.subject, &row.body, html) {
+                Ok(msg) => (deliver(transport, &msg, retry).await, false),
+                Err(e) => (Err(e.to_string()), true),
+            };
+        match res {
+            Ok(()) => {
+                mail_outbox::Entity::delete_by_id(row.id).exec(db).await?;
+            }
+            Err(e) => {
+                let attempts = row.attempts + 1;
+                let dead = permanent || attempts >= OUTBOX_MAX_ATTEMPTS;
+                if dead {
+                    tracing::error!("dead-letter to {}: {e}", row.recipient);
+                } else {
+                    tracing::warn!(
+                        "mail to {} failed ({attempts}/{OUTBOX_MAX_ATTEMPTS}): {e}",
+                        row.recipient
+                    );
+                }
+                let mut active: mail_outbox::ActiveModel = row.into();
+                active.attempts = Set(attempts);
+                active.error = Set(dead.then_some(e));
+                mail_outbox::Entity::update(active).exec(db).await?;
             }
         }
     }
+    Ok(())
 }
 
-#[cfg(test)]
-fn cleanup_task_exists() -> bool {
-    let guard = CLEANUP_TASK.lock().unwrap_or_else(|e| e.into_inner());
-    guard.is_some()
+fn build_message(
+    from: &str,
+    to: &str,
+    subject: &str,
+    body: &str,
+    html: Option<&str>,
+) -> Result<Message, EmailError> {
+    let builder = Message::builder()
+        .from(from.parse().map_err(EmailError::Address)?)
+        .to(to.parse().map_err(EmailError::Address)?)
+        .subject(subject);
+    match html {
+        Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+            body.to_string(),
+            html.to_string(),
+        )),
+        None => builder.body(body.to_string()),
+    }
+    .map_err(EmailError::Build)
 }
 
-async fn send_with_retry<F, Fut, E>(mut send: F) -> Result<(), E>
+async fn send_with_retry<F, Fut, E>(retry: RetryPolicy, mut send: F) -> Result<(), E>
 where
     F: FnMut() -> Fut,
     Fut: std::future::Future<Output = Result<(), E>>,
     E: std::fmt::Display,
 {
-    let mut delay = RETRY_BASE;
+    let mut backoff = retry.base;
     let mut last_err = None;
-    for _ in 0..MAX_RETRIES {
+    for _ in 0..retry.attempts {
         match send().await {
             Ok(_) => return Ok(()),
             Err(e) => {
+                let delay = jittered(backoff);
                 tracing::warn!(
                     "failed to send email: {e}; retrying in {}ms",
                     delay.as_millis()
                 );
                 last_err = Some(e);
                 tokio::time::sleep(delay).await;
-                delay *= 2;
+                backoff *= 2;
             }
         }
     }
-    tracing::warn!("giving up after {MAX_RETRIES} attempts");
+    tracing::warn!("giving up after {} attempts", retry.attempts);
     Err(last_err.expect("no error recorded"))
 }
 
@@ -393,17 +933,6 @@ mod tests {
     use std::error::Error as _;
     use std::sync::atomic::{AtomicUsize, Ordering};
 
-    fn clear_limits() {
-        let mut map = match RATE_LIMITS.lock() {
-            Ok(guard) => guard,
-            Err(poison) => {
-                RATE_LIMITS.clear_poison();
-                poison.into_inner()
-            }
-        };
-        map.clear();
-    }
-
     fn smtp_cfg() -> SmtpConfig {
         SmtpConfig {
             host: "localhost".into(),
@@ -413,49 +942,81 @@ mod tests {
         }
     }
 
-    #[test]
+    /// Sends once, so a failing stub is reported well within the tests' wait.
+    fn stub_cfg(rate_limit: u64) -> SmtpConfig {
+        SmtpConfig {
+            from: "noreply@example.com".into(),
+            rate_limit,
+            retries: 1,
+            ..smtp_cfg()
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limiting() {
+        let limiter = RateLimiter::new(Duration::from_secs(60));
+        assert!(limiter.allowed("a@example.com").unwrap());
+        assert!(!limiter.allowed("a@example.com").unwrap());
+        assert!(limiter.allowed("b@example.com").unwrap());
+    }
+
+    #[tokio::test]
+    async fn zero_window_disables_rate_limiting() {
+        let limiter = RateLimiter::new(Duration::ZERO);
+        assert!(limiter.cleanup.is_none());
+        assert!(limiter.allowed("a@example.com").unwrap());
+        assert!(limiter.allowed("a@example.com").unwrap());
+    }
+
+    #[tokio::test]
     #[serial]
-    fn rate_limiting() {
-        clear_limits();
-        assert!(EmailService::allowed("a@example.com").unwrap());
-        assert!(!EmailService::allowed("a@example.com").unwrap());
+    async fn services_rate_limit_independently() {
+        let transport = lettre::transport::stub::AsyncStubTransport::new_ok();
+        let short = EmailService::new_with_transport(&stub_cfg(20), transport.clone());
+        let long = EmailService::new_with_transport(&stub_cfg(60000), transport);
+
+        short.send_test("a@example.com").await.unwrap();
+        long.send_test("a@example.com").await.unwrap();
+        assert!(matches!(short.send_test("a@example.com").await, Err(EmailError::RateLimited)));
+        assert!(matches!(long.send_test("a@example.com").await, Err(EmailError::RateLimited)));
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(short.send_test("a@example.com").await.is_ok());
+        assert!(matches!(long.send_test("a@example.com").await, Err(EmailError::RateLimited)));
     }
 
     #[tokio::test]
     #[serial]
     async fn invalid_address() {
-        clear_limits();
         let mut cfg = smtp_cfg();
         cfg.from = "noreply@example.com".into();
         let svc = EmailService::new(cfg).unwrap();
-        match svc.send_test("not-an-email") {
+        match svc.send_test("not-an-email").await {
             Err(EmailError::Address(_)) => {}
             _ => panic!("expected address error"),
         }
     }
 
-    #[test]
-    #[ignore]
-    fn lock_poisoned() {
-        clear_limits();
-        let _ = std::thread::spawn(|| {
-            let _guard = RATE_LIMITS.lock().unwrap();
+    #[tokio::test]
+    async fn lock_poisoned() {
+        let limiter = RateLimiter::new(Duration::from_secs(60));
+        let map = limiter.last_sent.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = map.lock().unwrap();
             panic!();
         })
         .join();
-        let err = EmailService::allowed("b@example.com").unwrap_err();
+        let err = limiter.allowed("b@example.com").unwrap_err();
         assert!(matches!(err, EmailError::LockPoisoned));
         assert!(err.source().is_none());
-        let mut guard = RATE_LIMITS.lock().unwrap_or_else(|e| e.into_inner());
-        guard.clear();
-        RATE_LIMITS.clear_poison();
     }
 
     #[tokio::test]
     #[serial]
     async fn retries_on_failure() {
         let attempts = AtomicUsize::new(0);
-        let res = send_with_retry(|| {
+        let retry = RetryPolicy::from_config(&smtp_cfg());
+        let res = send_with_retry(retry, || {
             let n = attempts.fetch_add(1, Ordering::SeqCst);
             async move {
                 if n < 2 {
@@ -470,6 +1031,40 @@ mod tests {
         assert_eq!(attempts.load(Ordering::SeqCst), 3);
     }
 
+    #[tokio::test]
+    async fn retries_honor_configured_max() {
+        let attempts = AtomicUsize::new(0);
+        let cfg = SmtpConfig {
+            retries: 2,
+            ..smtp_cfg()
+        };
+        let res = send_with_retry(RetryPolicy::from_config(&cfg), || {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async { Err::<(), _>("fail") }
+        })
+        .await;
+        assert_eq!(res, Err("fail"));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn zero_retries_still_sends_once() {
+        let cfg = SmtpConfig {
+            retries: 0,
+            ..smtp_cfg()
+        };
+        assert_eq!(RetryPolicy::from_config(&cfg).attempts, 1);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let base = Duration::from_millis(100);
+        let delays: Vec<Duration> = (0..100).map(|_| jittered(base)).collect();
+        let max = base.mul_f64(1.0 + RETRY_JITTER);
+        assert!(delays.iter().all(|d| *d >= base && *d <= max));
+        assert!(delays.iter().any(|d| *d != delays[0]));
+    }
+
     #[tokio::test]
     #[serial]
     async fn metrics_counters_increment() {
@@ -480,8 +1075,8 @@ mod tests {
         EMAIL_LAST_LATENCY.set(0.0);
 
         let transport = lettre::transport::stub::AsyncStubTransport::new_ok();
-        let svc = EmailService::new_with_transport("noreply@example.com".into(), transport);
-        svc.send_test("ok@example.com").unwrap();
+        let svc = EmailService::new_with_transport(&stub_cfg(60000), transport);
+        svc.send_test("ok@example.com").await.unwrap();
         tokio::time::sleep(Duration::from_millis(50)).await;
         assert_eq!(EMAIL_QUEUED.get(), 1);
         assert_eq!(EMAIL_SENT.get(), 1);
@@ -492,8 +1087,8 @@ mod tests {
         EMAIL_FAILED.reset();
         EMAIL_LAST_ERROR.reset();
         let transport = lettre::transport::stub::AsyncStubTransport::new_error();
-        let svc = EmailService::new_with_transport("noreply@example.com".into(), transport);
-        svc.send_test("fail@example.com").unwrap();
+        let svc = EmailService::new_with_transport(&stub_cfg(60000), transport);
+        svc.send_test("fail@example.com").await.unwrap();
         tokio::time::sleep(Duration::from_millis(50)).await;
         assert_eq!(EMAIL_QUEUED.get(), 1);
         assert_eq!(EMAIL_SENT.get(), 0);
@@ -504,29 +1099,201 @@ mod tests {
         assert_eq!(gauge.get(), 1);
     }
 
+    fn reset_metrics() {
+        EMAIL_QUEUED.reset();
+        EMAIL_SENT.reset();
+        EMAIL_FAILED.reset();
+        EMAIL_LAST_ERROR.reset();
+        EMAIL_LAST_LATENCY.set(0.0);
+    }
+
     #[tokio::test]
     #[serial]
-    async fn cleanup_task_lifecycle() {
-        clear_limits();
-        assert!(!cleanup_task_exists());
+    async fn status_reports_sent_email() {
+        reset_metrics();
+        let transport = lettre::transport::stub::AsyncStubTransport::new_ok();
+        let svc = EmailService::new_with_transport(&stub_cfg(60000), transport);
+        svc.send_test("ok@example.com").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let status = svc.status();
+        assert_eq!((status.queued, status.sent, status.failed), (1, 1, 0));
+        assert!(status.last_latency_secs >= 0.0);
+        assert_eq!(status.last_error, None);
+    }
 
+    #[tokio::test]
+    #[serial]
+    async fn status_reports_last_error() {
+        reset_metrics();
+        let transport = lettre::transport::stub::AsyncStubTransport::new_error();
+        let svc = EmailService::new_with_transport(&stub_cfg(60000), transport);
+        svc.send_test("fail@example.com").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let status = svc.status();
+        assert_eq!((status.queued, status.sent, status.failed), (1, 0, 1));
+        assert_eq!(status.last_error.as_deref(), Some("stub error"));
+    }
+
+    async fn outbox_db(recipient: &str) -> DatabaseConnection {
+        use sea_orm::{ConnectionTrait, Database};
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared(
+            "CREATE TABLE mail_outbox (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             recipient TEXT NOT NULL, subject TEXT NOT NULL, body TEXT NOT NULL, \
+             html TEXT, created_at TEXT NOT NULL, sent_at TEXT, error TEXT, \
+             attempts INTEGER NOT NULL DEFAULT 0);",
+        )
+        .await
+        .unwrap();
+        let row = mail_outbox::ActiveModel {
+            recipient: Set(recipient.into()),
+            subject: Set("Test email".into()),
+            body: Set("Arena test message".into()),
+            html: Set(None),
+            created_at: Set(Utc::now()),
+            sent_at: Set(None),
+            error: Set(None),
+            attempts: Set(0),
+            ..Default::default()
+        };
+        mail_outbox::Entity::insert(row).exec(&db).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn outbox_worker_delivers_pending_rows() {
+        let db = outbox_db("ok@example.com").await;
         let transport = lettre::transport::stub::AsyncStubTransport::new_ok();
-        let svc1 = EmailService::new_with_transport("noreply@example.com".into(), transport.clone());
-        assert!(cleanup_task_exists());
+        let svc = EmailService::new_with_outbox_transport(&stub_cfg(60000), transport, db.clone());
+        assert!(svc.has_outbox());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let rows = mail_outbox::Entity::find().all(&db).await.unwrap();
+        assert!(rows.is_empty());
+    }
 
-        {
-            let svc2 = EmailService::new_with_transport("noreply@example.com".into(), transport.clone());
-            assert!(cleanup_task_exists());
-            drop(svc2);
+    #[tokio::test]
+    #[serial]
+    async fn outbox_retries_failed_rows_then_marks_them() {
+        let db = outbox_db("fail@example.com").await;
+        let transport = lettre::transport::stub::AsyncStubTransport::new_error();
+        let retry = RetryPolicy::from_config(&stub_cfg(0));
+        for pass in 1..=OUTBOX_MAX_ATTEMPTS {
+            deliver_outbox(&db, "noreply@example.com", &transport, retry)
+                .await
+                .unwrap();
+            let rows = mail_outbox::Entity::find().all(&db).await.unwrap();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].attempts, pass);
+            assert!(rows[0].sent_at.is_none());
+            // Only the last allowed pass gives up on the row.
+            let expected = (pass == OUTBOX_MAX_ATTEMPTS).then_some("stub error");
+            assert_eq!(rows[0].error.as_deref(), expected);
         }
 
-        assert!(cleanup_task_exists());
-        drop(svc1);
-        assert!(!cleanup_task_exists());
+        deliver_outbox(&db, "noreply@example.com", &transport, retry)
+            .await
+            .unwrap();
+        let rows = mail_outbox::Entity::find().all(&db).await.unwrap();
+        assert_eq!(rows[0].attempts, OUTBOX_MAX_ATTEMPTS);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn outbox_write_failure_is_returned() {
+        // No `mail_outbox` table, so the insert fails.
+        let db = crate::test_state::memory_db().await;
+        let transport = lettre::transport::stub::AsyncStubTransport::new_ok();
+        let svc = EmailService::new_with_outbox_transport(&stub_cfg(60000), transport, db);
+        assert!(matches!(
+            svc.send_test("a@example.com").await,
+            Err(EmailError::Outbox(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn cleanup_purges_expired_entries() {
+        let limiter = RateLimiter::new(Duration::from_millis(20));
+        assert!(limiter.allowed("a@example.com").unwrap());
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(limiter.last_sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn cleanup_task_stops_with_service() {
+        let transport = lettre::transport::stub::AsyncStubTransport::new_ok();
+        let svc = EmailService::new_with_transport(&stub_cfg(60000), transport);
+        let map = svc.rate_limit.last_sent.clone();
+        assert_eq!(Arc::strong_count(&map), 3);
+
+        drop(svc);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(Arc::strong_count(&map), 1);
+    }
+
+    #[test]
+    fn template_renders_placeholders() {
+        let mut templates = EmailTemplates::default();
+        templates.set(EmailTemplate::OtpCode, "Code {code}", "Hi {name}, use {code}. {unknown}");
+        let vars = [("code", "123456"), ("name", "Ann")];
+        let email = templates.render(EmailTemplate::OtpCode, &vars);
+        assert_eq!(email.subject, "Code 123456");
+        assert_eq!(email.body, "Hi Ann, use 123456. {unknown}");
+    }
+
+    #[test]
+    fn templates_fall_back_to_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("otp_code.txt"), "Sign in\nYour code: {code}\n").unwrap();
+        let templates = EmailTemplates::load_dir(dir.path()).unwrap();
+
+        let otp = templates.render(EmailTemplate::OtpCode, &[("code", "42")]);
+        assert_eq!(otp.subject, "Sign in");
+        assert_eq!(otp.body, "Your code: 42");
+
+        let reset = templates.render(EmailTemplate::PasswordReset, &[("link", "https://x/r")]);
+        assert_eq!(reset.subject, "Password Reset");
+        assert_eq!(
+            reset.body,
+            "Reset your password using the following link: https://x/r"
+        );
+    }
+
+    #[test]
+    fn html_message_has_both_parts() {
+        let msg = build_message(
+            "noreply@example.com",
+            "a@example.com",
+            "Your OTP Code",
+            "Your code is 123456",
+            Some("<p>Your code is <strong>123456</strong></p>"),
+        )
+        .unwrap();
+        let raw = String::from_utf8(msg.formatted()).unwrap();
+        assert!(raw.contains("multipart/alternative"));
+        assert!(raw.contains("Content-Type: text/plain"));
+        assert!(raw.contains("Content-Type: text/html"));
+        assert!(raw.contains("Your code is 123456"));
+        assert!(raw.contains("<p>Your code is <strong>123456</strong></p>"));
+    }
+
+    #[test]
+    fn otp_template_renders_escaped_html() {
+        let email = EmailTemplates::default().render(EmailTemplate::OtpCode, &[("code", "<42>")]);
+        assert_eq!(email.body, "Your one-time passcode is: <42>");
+        assert_eq!(
+            email.html.as_deref(),
+            Some("<p>Your one-time passcode is:</p>\n<p><strong>&lt;42&gt;</strong></p>")
+        );
+    }
 
-        let svc3 = EmailService::new_with_transport("noreply@example.com".into(), transport);
-        assert!(cleanup_task_exists());
-        drop(svc3);
-        assert!(!cleanup_task_exists());
+    #[test]
+    fn text_override_drops_default_html() {
+        let mut templates = EmailTemplates::default();
+        templates.set(EmailTemplate::OtpCode, "Sign in", "Code: {code}");
+        assert_eq!(templates.render(EmailTemplate::OtpCode, &[]).html, None);
     }
 }
@@ -102,6 +102,7 @@ pub mod entitlements {
         #[sea_orm(primary_key, auto_increment = false)]
         pub sku: String,
         pub granted_at: DateTimeUtc,
+        pub expires_at: Option<DateTimeUtc>,
     }
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
     pub enum Relation {}
@@ -118,6 +119,8 @@ pub mod purchases {
         pub player_id: String,
         pub sku: String,
         pub created_at: DateTimeUtc,
+        pub price_cents: i64,
+        pub idempotency_key: Option<String>,
     }
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
     pub enum Relation {}
@@ -183,9 +186,11 @@ pub mod mail_outbox {
         pub recipient: String,
         pub subject: String,
         pub body: String,
+        pub html: Option<String>,
         pub created_at: DateTimeUtc,
         pub sent_at: Option<DateTimeUtc>,
         pub error: Option<String>,
+        pub attempts: i32,
     }
     #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
     pub enum Relation {}
@@ -19,6 +19,7 @@ use uuid::Uuid;
 use crate::{
     email::EmailService,
     entities::{jobs, mail_outbox, nodes},
+    otp_store,
 };
 
 const MAX_ATTEMPTS: i32 = 5;
@@ -26,6 +27,9 @@ const MAX_ATTEMPTS: i32 = 5;
 const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 const LEADER_TIMEOUT: ChronoDuration = ChronoDuration::seconds(15);
 
+/// Default time between purges of expired email OTPs.
+pub const DEFAULT_OTP_PURGE_INTERVAL: Duration = Duration::from_secs(300);
+
 /// Run the background job runner.
 pub async fn run(db: DatabaseConnection, email: Arc<EmailService>) {
     let node_id = Uuid::new_v4();
@@ -49,6 +53,27 @@ pub async fn run(db: DatabaseConnection, email: Arc<EmailService>) {
     }
 }
 
+/// Periodically delete email OTPs whose `expires_at` has passed.
+///
+/// Deleting expired rows is idempotent, so every node runs this rather than
+/// only the leader.
+pub async fn run_otp_purge(db: DatabaseConnection, every: Duration) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match purge_expired_otps(&db).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("purged {n} expired email OTPs"),
+            Err(e) => tracing::error!("OTP purge failed: {e}"),
+        }
+    }
+}
+
+/// Delete expired email OTPs once, returning how many were removed.
+pub async fn purge_expired_otps(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    otp_store::delete_expired(db, Utc::now()).await
+}
+
 async fn heartbeat(db: &DatabaseConnection, id: Uuid, region: &str) -> Result<(), DbErr> {
     let model = nodes::ActiveModel {
         id: Set(id),
@@ -134,13 +159,21 @@ async fn handle(
 ) -> anyhow::Result<()> {
     match job.kind.as_str() {
         "send_mail" => {
+            // The outbox worker delivers rows itself; sending here would duplicate them.
+            if email.has_outbox() {
+                return Ok(());
+            }
             let id: i64 = job.payload.parse()?;
             let mail = mail_outbox::Entity::find_by_id(id)
                 .one(db)
                 .await?
                 .ok_or_else(|| anyhow::anyhow!("mail_outbox {id} not found"))?;
             let mut active: mail_outbox::ActiveModel = mail.clone().into();
-            match email.send_mail(&mail.recipient, &mail.subject, &mail.body) {
+            let html = mail.html.as_deref();
+            match email
+                .send_mail(&mail.recipient, &mail.subject, &mail.body, html)
+                .await
+            {
                 Ok(_) => {
                     active.sent_at = Set(Some(Utc::now()));
                     active.error = Set(None);
@@ -161,3 +194,32 @@ async fn handle(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, Database};
+
+    #[tokio::test]
+    async fn purge_removes_only_expired_otps() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared(
+            "CREATE TABLE email_otps (email_hash TEXT PRIMARY KEY, code TEXT NOT NULL, \
+             expires_at TEXT NOT NULL);",
+        )
+        .await
+        .unwrap();
+        let now = Utc::now();
+        otp_store::insert_otp(&db, "expired", "111111", now - ChronoDuration::minutes(1))
+            .await
+            .unwrap();
+        otp_store::insert_otp(&db, "fresh", "222222", now + ChronoDuration::minutes(5))
+            .await
+            .unwrap();
+
+        assert_eq!(purge_expired_otps(&db).await.unwrap(), 1);
+        assert!(otp_store::fetch_otp(&db, "expired").await.unwrap().is_none());
+        let (code, _) = otp_store::fetch_otp(&db, "fresh").await.unwrap().unwrap();
+        assert_eq!(code, "222222");
+    }
+}
@@ -2,37 +2,52 @@ use std::sync::Arc;
 
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::{
+    body::Body,
+    middleware,
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use base64::{engine::general_purpose, Engine as _};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use tokio_util::io::ReaderStream;
+use tower_http::decompression::RequestDecompressionLayer;
 use uuid::Uuid;
 
 use ::leaderboard::{
     models::{LeaderboardWindow, Run, Score},
-    LeaderboardService,
+    LeaderboardService, TrimmedReplay,
 };
 use analytics::Event as AnalyticsEvent;
 
-use crate::AppState;
+use crate::{AppState, require_admin_token};
+
+pub fn routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    // Moderation changes what everyone sees, so it takes the admin token.
+    let moderation = Router::new()
+        .route("/:id/run/:run_id/verify", post(post_verify))
+        .route("/:id/run/:run_id/flag", post(post_flag))
+        .route_layer(middleware::from_fn_with_state(state, require_admin_token));
 
-pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/:id", get(get_scores))
         .route("/:id/ws", get(ws_scores))
+        .route("/:id/rank/:player", get(get_rank))
         .route("/:id/run", post(post_run))
         .route("/:id/run/:run_id/replay", get(get_replay))
-        .route("/:id/run/:run_id/verify", post(post_verify))
+        .merge(moderation)
+        // Large replays may be uploaded with `Content-Encoding: gzip` or `deflate`.
+        .layer(RequestDecompressionLayer::new())
 }
 
 #[derive(Deserialize)]
 struct WindowQuery {
     window: Option<LeaderboardWindow>,
+    offset: Option<u64>,
+    limit: Option<u64>,
 }
 
 async fn get_scores(
@@ -41,10 +56,30 @@ async fn get_scores(
     State(state): State<Arc<AppState>>,
 ) -> Json<Vec<Score>> {
     let window = q.window.unwrap_or(LeaderboardWindow::AllTime);
-    let scores = state.leaderboard.get_scores(id, window).await;
+    let scores = state
+        .leaderboard
+        .get_scores_paged(id, window, q.offset.unwrap_or(0), q.limit.unwrap_or(u64::MAX))
+        .await;
     Json(scores)
 }
 
+#[derive(Serialize)]
+struct RankResponse {
+    rank: u64,
+}
+
+async fn get_rank(
+    Path((id, player)): Path<(Uuid, Uuid)>,
+    Query(q): Query<WindowQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<RankResponse>, StatusCode> {
+    let window = q.window.unwrap_or(LeaderboardWindow::AllTime);
+    match state.leaderboard.get_rank(id, window, player).await {
+        Some(rank) => Ok(Json(RankResponse { rank })),
+        None => Err(StatusCode::NOT_FOUND),
+    }
+}
+
 #[derive(Deserialize)]
 struct SubmitRun {
     player_id: Uuid,
@@ -82,6 +117,10 @@ async fn post_run(
         return StatusCode::BAD_REQUEST;
     }
 
+    // Implausible scores are stored flagged and stay hidden until `/verify`
+    // re-scores the replay to the same points or `/flag` clears the flag.
+    let suspicious = state.leaderboard.is_implausible(payload.points);
+
     let run = Run {
         id: run_id,
         leaderboard: id,
@@ -100,50 +139,116 @@ async fn post_run(
         created_at: Utc::now(),
         window: LeaderboardWindow::AllTime,
     };
-    if state
+    let outcome = match state
         .leaderboard
         .submit_score(id, score, run, replay_bytes)
         .await
-        .is_err()
     {
-        return StatusCode::INTERNAL_SERVER_ERROR;
-    }
+        Ok(outcome) => outcome,
+        Err(err) if err.kind() == std::io::ErrorKind::InvalidInput => {
+            return StatusCode::PAYLOAD_TOO_LARGE;
+        }
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR,
+    };
     state.analytics.dispatch(AnalyticsEvent::LeaderboardSubmit);
+    if outcome.replay_trimmed {
+        state.analytics.dispatch(AnalyticsEvent::ReplayTrimmed);
+    }
+    if suspicious {
+        state
+            .analytics
+            .dispatch(AnalyticsEvent::RunVerificationFailed);
+        return StatusCode::ACCEPTED;
+    }
     StatusCode::CREATED
 }
 
 async fn get_replay(
     Path((_id, run_id)): Path<(Uuid, Uuid)>,
     State(state): State<Arc<AppState>>,
-) -> Result<Vec<u8>, StatusCode> {
-    if let Some(data) = state.leaderboard.get_replay(run_id).await {
-        Ok(data)
-    } else {
-        Err(StatusCode::NOT_FOUND)
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let Some(replay) = state.leaderboard.open_replay(run_id).await else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    if !replay.gzip {
+        return Ok(stream_replay(replay.file));
+    }
+    if accepts_gzip(&headers) {
+        let encoding = [(header::CONTENT_ENCODING, "gzip")];
+        return Ok((encoding, stream_replay(replay.file)).into_response());
     }
+    // Clients that can't inflate get the replay decompressed in memory.
+    state
+        .leaderboard
+        .get_replay(run_id)
+        .await
+        .map(IntoResponse::into_response)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+fn stream_replay(file: tokio::fs::File) -> Response {
+    (
+        [(header::CONTENT_TYPE, "application/octet-stream")],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response()
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',')
+                .any(|enc| enc.split(';').next().map(str::trim) == Some("gzip"))
+        })
 }
 
 async fn post_verify(
     Path((_id, run_id)): Path<(Uuid, Uuid)>,
     State(state): State<Arc<AppState>>,
 ) -> StatusCode {
-    if state.leaderboard.verify_run(run_id).await {
+    if state.leaderboard.verify_run(run_id, verify_score).await {
         StatusCode::OK
     } else {
         StatusCode::NOT_FOUND
     }
 }
 
+#[derive(Deserialize)]
+struct FlagRun {
+    flagged: bool,
+}
+
+async fn post_flag(
+    Path((_id, run_id)): Path<(Uuid, Uuid)>,
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<FlagRun>,
+) -> StatusCode {
+    match state.leaderboard.flag_run(run_id, payload.flagged).await {
+        Ok(()) => StatusCode::OK,
+        Err(_) => StatusCode::NOT_FOUND,
+    }
+}
+
+#[derive(Deserialize)]
+struct WsQuery {
+    window: Option<LeaderboardWindow>,
+    /// `seq` of the last snapshot the client saw before reconnecting.
+    since: Option<u64>,
+}
+
 async fn ws_scores(
     Path(id): Path<Uuid>,
-    Query(q): Query<WindowQuery>,
+    Query(q): Query<WsQuery>,
     State(state): State<Arc<AppState>>,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
     let service = state.leaderboard.clone();
     let window = q.window.unwrap_or(LeaderboardWindow::AllTime);
     ws.on_upgrade(move |socket| async move {
-        handle_ws(socket, id, window, service).await;
+        handle_ws(socket, id, window, q.since, service).await;
     })
 }
 
@@ -151,14 +256,33 @@ async fn handle_ws(
     mut socket: WebSocket,
     id: Uuid,
     window: LeaderboardWindow,
+    since: Option<u64>,
     service: LeaderboardService,
 ) {
+    // Subscribe first so nothing broadcast while catching up is lost;
+    // `last_seq` drops the overlap.
     let mut rx = service.subscribe();
-    if let Ok(json) = serde_json::to_string(&service.get_scores(id, window).await) {
-        let _ = socket.send(Message::Text(json)).await;
+    let mut last_seq = since.unwrap_or(0);
+    match since.and_then(|since| service.snapshots_since(id, since)) {
+        Some(missed) => {
+            for snapshot in missed.into_iter().filter(|s| s.window == window) {
+                last_seq = snapshot.seq;
+                if let Ok(json) = serde_json::to_string(&snapshot) {
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        None => {
+            last_seq = 0;
+            if let Ok(json) = serde_json::to_string(&service.get_scores(id, window).await) {
+                let _ = socket.send(Message::Text(json)).await;
+            }
+        }
     }
     while let Ok(snapshot) = rx.recv().await {
-        if snapshot.leaderboard != id || snapshot.window != window {
+        if snapshot.leaderboard != id || snapshot.window != window || snapshot.seq <= last_seq {
             continue;
         }
         if let Ok(json) = serde_json::to_string(&snapshot) {
@@ -180,8 +304,41 @@ struct Replay {
     events: Vec<Event>,
 }
 
+/// Drop a replay's oldest events until it encodes to at most `max_bytes`.
+pub(crate) fn trim_replay_events(replay: &[u8], max_bytes: usize) -> Option<TrimmedReplay> {
+    let Replay { events } = postcard::from_bytes(replay).ok()?;
+    // A `Replay` encodes exactly like its event sequence, so slices of
+    // `events` can be sized without rebuilding the struct.
+    let fits = |start: usize| {
+        postcard::to_allocvec(&events[start..]).is_ok_and(|bytes| bytes.len() <= max_bytes)
+    };
+    if !fits(events.len()) {
+        return None;
+    }
+    let (mut lo, mut hi) = (0, events.len());
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if fits(mid) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    let dropped_points = events[..lo]
+        .iter()
+        .filter(|e| matches!(e, Event::Hit))
+        .count();
+    Some(TrimmedReplay {
+        replay: postcard::to_allocvec(&events[lo..]).ok()?,
+        dropped_points: dropped_points as i32,
+    })
+}
+
 fn verify_score(replay: &[u8]) -> Option<i32> {
-    let replay: Replay = postcard::from_bytes(replay).ok()?;
+    let (replay, rest): (Replay, _) = postcard::take_from_bytes(replay).ok()?;
+    if !rest.is_empty() {
+        return None;
+    }
     let mut points = 0;
     for event in replay.events {
         if let Event::Hit = event {
@@ -223,58 +380,14 @@ mod verify_score_tests {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        email::{EmailService, SmtpConfig},
-        room,
-    };
-    use analytics::Analytics;
     use axum::extract::{Path, State};
     use axum::Json;
     use leaderboard::models::LeaderboardWindow;
-    use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase};
-    use purchases::{Catalog, Sku};
-    use std::path::PathBuf;
-
-    fn smtp_cfg() -> SmtpConfig {
-        SmtpConfig {
-            host: "localhost".into(),
-            from: "arena@localhost".into(),
-            port: Some(25),
-            ..Default::default()
-        }
-    }
-
-    async fn leaderboard_service() -> (::leaderboard::LeaderboardService, DatabaseConnection) {
-        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
-        let service = ::leaderboard::LeaderboardService::with_db(
-            db.clone(),
-            PathBuf::from("replays"),
-        )
-        .await
-        .unwrap();
-        (service, db)
-    }
 
     #[tokio::test]
     #[ignore]
     async fn post_run_rejects_malformed_base64() {
-        let cfg = smtp_cfg();
-        let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-        let (leaderboard, db) = leaderboard_service().await;
-        let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-        let state = Arc::new(AppState {
-            email,
-            rooms,
-            smtp: cfg,
-            analytics: Analytics::new(true, None, None, None),
-            leaderboard: leaderboard.clone(),
-            catalog: Catalog::new(vec![Sku {
-                id: "basic".into(),
-                price_cents: 1000,
-            }]),
-            db,
-            email_salt: "salt".into(),
-        });
+        let state = Arc::new(AppState::builder().build().await);
 
         let leaderboard_id = Uuid::new_v4();
         let payload = SubmitRun {
@@ -295,20 +408,7 @@ mod tests {
     #[tokio::test]
     #[ignore]
     async fn post_run_accepts_valid_payload() {
-        let cfg = smtp_cfg();
-        let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-        let (leaderboard, db) = leaderboard_service().await;
-        let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-        let state = Arc::new(AppState {
-            email,
-            rooms,
-            smtp: cfg,
-            analytics: Analytics::new(true, None, None, None),
-            leaderboard: leaderboard.clone(),
-            catalog: Catalog::new(vec![]),
-            db,
-            email_salt: "salt".into(),
-        });
+        let state = Arc::new(AppState::builder().build().await);
 
         let leaderboard_id = Uuid::new_v4();
         let replay = general_purpose::STANDARD.encode(42i32.to_le_bytes());
@@ -335,20 +435,7 @@ mod tests {
     #[tokio::test]
     #[ignore]
     async fn post_run_rejects_oversized_payload() {
-        let cfg = smtp_cfg();
-        let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-        let (leaderboard, db) = leaderboard_service().await;
-        let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-        let state = Arc::new(AppState {
-            email,
-            rooms,
-            smtp: cfg,
-            analytics: Analytics::new(true, None, None, None),
-            leaderboard: leaderboard.clone(),
-            catalog: Catalog::new(vec![]),
-            db,
-            email_salt: "salt".into(),
-        });
+        let state = Arc::new(AppState::builder().build().await);
 
         let leaderboard_id = Uuid::new_v4();
         let bytes = vec![0u8; super::MAX_REPLAY_SIZE + 1];
@@ -371,20 +458,7 @@ mod tests {
     #[tokio::test]
     #[ignore]
     async fn post_run_rejects_invalid_score() {
-        let cfg = smtp_cfg();
-        let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-        let (leaderboard, db) = leaderboard_service().await;
-        let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-        let state = Arc::new(AppState {
-            email,
-            rooms,
-            smtp: cfg,
-            analytics: Analytics::new(true, None, None, None),
-            leaderboard: leaderboard.clone(),
-            catalog: Catalog::new(vec![]),
-            db,
-            email_salt: "salt".into(),
-        });
+        let state = Arc::new(AppState::builder().build().await);
 
         let leaderboard_id = Uuid::new_v4();
         let mut bytes = 41i32.to_le_bytes().to_vec();
@@ -408,20 +482,7 @@ mod tests {
     #[tokio::test]
     #[ignore]
     async fn verify_endpoint_marks_score_verified() {
-        let cfg = smtp_cfg();
-        let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-        let (leaderboard, db) = leaderboard_service().await;
-        let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-        let state = Arc::new(AppState {
-            email,
-            rooms,
-            smtp: cfg,
-            analytics: Analytics::new(true, None, None, None),
-            leaderboard: leaderboard.clone(),
-            catalog: Catalog::new(vec![]),
-            db,
-            email_salt: "salt".into(),
-        });
+        let state = Arc::new(AppState::builder().build().await);
 
         let leaderboard_id = Uuid::new_v4();
         let player = Uuid::new_v4();
@@ -449,4 +510,289 @@ mod tests {
             .await;
         assert!(scores[0].verified);
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn post_run_accepts_gzip_body() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use flate2::{Compression, write::GzEncoder};
+        use std::io::Write;
+        use tower::ServiceExt;
+
+        let state = Arc::new(AppState::builder().build().await);
+        let app = Router::new()
+            .nest("/leaderboard", routes(state.clone()))
+            .with_state(state.clone());
+
+        let replay = Replay {
+            events: vec![Event::Hit, Event::Hit, Event::Miss],
+        };
+        let replay = general_purpose::STANDARD.encode(postcard::to_allocvec(&replay).unwrap());
+        let body = serde_json::json!({
+            "player_id": Uuid::new_v4(),
+            "points": 2,
+            "replay": replay,
+        })
+        .to_string();
+
+        let plain_id = Uuid::new_v4();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post(format!("/leaderboard/{plain_id}/run"))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.clone()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let gzip_id = Uuid::new_v4();
+        let response = app
+            .oneshot(
+                Request::post(format!("/leaderboard/{gzip_id}/run"))
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .body(Body::from(gzipped))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let plain = state
+            .leaderboard
+            .get_scores(plain_id, LeaderboardWindow::AllTime)
+            .await;
+        let gzip = state
+            .leaderboard
+            .get_scores(gzip_id, LeaderboardWindow::AllTime)
+            .await;
+        assert_eq!(plain.len(), 1);
+        assert_eq!(gzip.len(), 1);
+        assert_eq!(plain[0].points, gzip[0].points);
+    }
+
+    #[tokio::test]
+    async fn plausible_score_stays_unflagged() {
+        let mut state = AppState::builder()
+            .db(crate::test_state::leaderboard_db().await)
+            .build()
+            .await;
+        state.leaderboard = state.leaderboard.clone().with_max_points(Some(100));
+        let state = Arc::new(state);
+
+        let leaderboard_id = Uuid::new_v4();
+        let replay = Replay {
+            events: vec![Event::Hit, Event::Hit],
+        };
+        let payload = SubmitRun {
+            player_id: Uuid::new_v4(),
+            points: 2,
+            replay: general_purpose::STANDARD.encode(postcard::to_allocvec(&replay).unwrap()),
+        };
+
+        let status = post_run(Path(leaderboard_id), State(state.clone()), Json(payload)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let scores = state
+            .leaderboard
+            .get_scores(leaderboard_id, LeaderboardWindow::AllTime)
+            .await;
+        assert_eq!(scores.len(), 1);
+        assert!(
+            !state
+                .analytics
+                .events()
+                .contains(&AnalyticsEvent::RunVerificationFailed)
+        );
+    }
+
+    #[tokio::test]
+    async fn implausible_score_is_auto_flagged() {
+        let mut state = AppState::builder()
+            .db(crate::test_state::leaderboard_db().await)
+            .build()
+            .await;
+        state.leaderboard = state.leaderboard.clone().with_max_points(Some(1));
+        let state = Arc::new(state);
+
+        let leaderboard_id = Uuid::new_v4();
+        let replay = Replay {
+            events: vec![Event::Hit, Event::Hit, Event::Hit],
+        };
+        let payload = SubmitRun {
+            player_id: Uuid::new_v4(),
+            points: 3,
+            replay: general_purpose::STANDARD.encode(postcard::to_allocvec(&replay).unwrap()),
+        };
+
+        let status = post_run(Path(leaderboard_id), State(state.clone()), Json(payload)).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert!(
+            state
+                .leaderboard
+                .get_scores(leaderboard_id, LeaderboardWindow::AllTime)
+                .await
+                .is_empty()
+        );
+        assert!(
+            state
+                .analytics
+                .events()
+                .contains(&AnalyticsEvent::RunVerificationFailed)
+        );
+    }
+
+    #[tokio::test]
+    async fn over_cap_replay_is_trimmed_to_bound() {
+        let state = AppState::builder().build().await;
+        let service = state.leaderboard.with_max_replay_bytes(Some(4));
+
+        let events = vec![Event::Hit, Event::Miss, Event::Hit, Event::Miss, Event::Hit];
+        let mut replay = postcard::to_allocvec(&Replay { events }).unwrap();
+        // The dropped leading hit is reported so verification can add it back.
+        assert_eq!(service.trim_replay(&mut replay).unwrap(), Some(1));
+        assert!(replay.len() <= 4);
+        // The final three events survive and still decode and score.
+        let trimmed: Replay = postcard::from_bytes(&replay).unwrap();
+        assert!(matches!(
+            trimmed.events[..],
+            [Event::Hit, Event::Miss, Event::Hit]
+        ));
+        assert_eq!(verify_score(&replay), Some(2));
+    }
+
+    #[tokio::test]
+    async fn over_cap_replay_is_rejected_if_it_cannot_be_trimmed() {
+        let state = AppState::builder().build().await;
+        let service = state.leaderboard.with_max_replay_bytes(Some(4));
+
+        // One event with an unknown variant doesn't decode.
+        let mut garbage = vec![1, 7, 7, 7, 7, 7];
+        assert!(service.trim_replay(&mut garbage).is_err());
+        let mut replay = postcard::to_allocvec(&Replay {
+            events: vec![Event::Hit],
+        })
+        .unwrap();
+        let service = service.with_max_replay_bytes(Some(0));
+        assert!(service.trim_replay(&mut replay).is_err());
+    }
+
+    #[tokio::test]
+    async fn under_cap_replay_is_stored_verbatim() {
+        let state = AppState::builder().build().await;
+        let service = state.leaderboard.with_max_replay_bytes(Some(16));
+
+        let mut replay: Vec<u8> = (0..10).collect();
+        assert_eq!(service.trim_replay(&mut replay).unwrap(), None);
+        assert_eq!(replay, (0..10).collect::<Vec<u8>>());
+    }
+
+    #[tokio::test]
+    async fn trimmed_replay_is_recorded_in_analytics() {
+        let mut state = AppState::builder()
+            .db(crate::test_state::leaderboard_db().await)
+            .build()
+            .await;
+        state.leaderboard = state.leaderboard.clone().with_max_replay_bytes(Some(1));
+        let state = Arc::new(state);
+
+        let replay = Replay {
+            events: vec![Event::Hit, Event::Hit],
+        };
+        let payload = SubmitRun {
+            player_id: Uuid::new_v4(),
+            points: 2,
+            replay: general_purpose::STANDARD.encode(postcard::to_allocvec(&replay).unwrap()),
+        };
+
+        let status = post_run(Path(Uuid::new_v4()), State(state.clone()), Json(payload)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(
+            state
+                .analytics
+                .events()
+                .contains(&AnalyticsEvent::ReplayTrimmed)
+        );
+    }
+
+    #[tokio::test]
+    async fn trimmed_flagged_run_verifies_and_shows_again() {
+        use sea_orm::EntityTrait;
+
+        let mut state = AppState::builder()
+            .db(crate::test_state::leaderboard_db().await)
+            .build()
+            .await;
+        state.leaderboard = state
+            .leaderboard
+            .clone()
+            .with_max_points(Some(1))
+            .with_max_replay_bytes(Some(2));
+        let state = Arc::new(state);
+
+        let leaderboard_id = Uuid::new_v4();
+        let replay = Replay {
+            events: vec![Event::Hit, Event::Hit, Event::Hit],
+        };
+        let payload = SubmitRun {
+            player_id: Uuid::new_v4(),
+            points: 3,
+            replay: general_purpose::STANDARD.encode(postcard::to_allocvec(&replay).unwrap()),
+        };
+        let status = post_run(Path(leaderboard_id), State(state.clone()), Json(payload)).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let run_id = ::leaderboard::db::runs::Entity::find()
+            .one(&state.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .id;
+
+        // Only the last hit is stored, yet the run still verifies at 3.
+        let status = post_verify(Path((leaderboard_id, run_id)), State(state.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        let scores = state
+            .leaderboard
+            .get_scores(leaderboard_id, LeaderboardWindow::AllTime)
+            .await;
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].points, 3);
+        assert!(scores[0].verified);
+    }
+
+    #[tokio::test]
+    async fn moderation_requires_admin_token() {
+        use axum::body::Body;
+        use axum::http::{Request, header};
+        use tower::ServiceExt;
+
+        let state = Arc::new(AppState::builder().admin_token("hunter2").build().await);
+        let app = Router::new()
+            .nest("/leaderboard", routes(state.clone()))
+            .with_state(state);
+
+        let run = format!("/leaderboard/{}/run/{}", Uuid::new_v4(), Uuid::new_v4());
+        for (uri, body) in [
+            (format!("{run}/flag"), r#"{"flagged":false}"#),
+            (format!("{run}/verify"), ""),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::post(uri)
+                        .header(header::CONTENT_TYPE, "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
 }
@@ -2,34 +2,45 @@ use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc};
 
 use anyhow::{Result, anyhow};
 
-use crate::email::{EmailService, SmtpConfig, StartTls};
+use crate::email::{EmailService, EmailStatus, SmtpConfig, StartTls};
 use analytics::{Analytics, Event};
 use axum::{
     Extension, Router,
+    body::Bytes,
     extract::{
-        Json, Path, Query, State,
+        Json, Path, Query, Request, State,
         ws::{Message, WebSocket, WebSocketUpgrade},
     },
     http::{
         HeaderMap, HeaderName, HeaderValue, StatusCode,
-        header::{CACHE_CONTROL, SET_COOKIE},
+        header::{AUTHORIZATION, CACHE_CONTROL, COOKIE, SET_COOKIE, WWW_AUTHENTICATE},
     },
-    response::IntoResponse,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::{get, get_service, post},
 };
 use clap::Parser;
 use email_address::EmailAddress;
 use migration::{Migrator, MigratorTrait};
 use net::server::ServerConnector;
-use purchases::{Catalog, EntitlementList, Sku, UserId};
+use purchases::{
+    BASE_CURRENCY, Catalog, CheckoutSession, DbEntitlements, EntitlementBackend, EntitlementList,
+    FxRates, GrantError, HmacWebhookVerifier, Sku, UserId,
+};
 use sea_orm::{ActiveModelTrait, ActiveValue::Set, Database, DatabaseConnection};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use storage::connect as connect_db;
+use tokio::sync::{mpsc, watch};
+use webrtc::ice_transport::ice_candidate::{RTCIceCandidate, RTCIceCandidateInit};
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::peer_connection::sdp::sdp_type::RTCSdpType;
 use webrtc::peer_connection::sdp::session_description::RTCSessionDescription;
 
 mod auth;
 mod config;
+mod csrf;
 mod email;
 mod entities;
 mod jobs;
@@ -37,9 +48,16 @@ mod leaderboard;
 mod otp_store;
 mod players;
 mod room;
+mod room_store;
+mod session_store;
 mod shard;
+mod turn;
 #[cfg(test)]
 mod test_logger;
+#[cfg(test)]
+mod test_state;
+#[cfg(test)]
+mod tests;
 use prometheus::{Encoder, TextEncoder};
 use tower_http::{services::ServeDir, set_header::SetResponseHeaderLayer};
 
@@ -67,6 +85,14 @@ struct Cli {
     analytics_opt_out: bool,
     #[arg(long, env = "ARENA_LOG_LEVEL")]
     log_level: Option<String>,
+    /// Shared secret payment webhooks are signed with; `/store/webhook` is
+    /// disabled without it.
+    #[arg(long, env = "ARENA_STORE_WEBHOOK_SECRET")]
+    store_webhook_secret: Option<String>,
+    /// Bearer token `/admin` requests must present; every admin request is
+    /// rejected without it.
+    #[arg(long, env = "ARENA_ADMIN_TOKEN")]
+    admin_token: Option<String>,
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -82,6 +108,9 @@ struct Config {
     /// Maximum number of database connections (default 20).
     #[arg(long, env = "ARENA_DB_MAX_CONNS")]
     db_max_conns: Option<u32>,
+    /// Maximum number of rooms running at once (default 64).
+    #[arg(long, env = "ARENA_MAX_ROOMS")]
+    max_rooms: Option<usize>,
     #[arg(long, env = "ARENA_MIGRATE_ON_START", default_value_t = false)]
     migrate_on_start: bool,
     #[arg(long, env = "ARENA_ENABLE_COOP_COEP", default_value_t = false)]
@@ -98,10 +127,32 @@ struct Config {
     csp: Option<String>,
     #[arg(long, env = "ARENA_RTC_ICE_SERVERS_JSON")]
     rtc_ice_servers_json: Option<String>,
+    /// Secret shared with the TURN server; when set, clients get short-lived
+    /// TURN credentials instead of the configured ones.
+    #[arg(long, env = "ARENA_TURN_SECRET")]
+    turn_secret: Option<String>,
+    /// Seconds generated TURN credentials stay valid (default 3600).
+    #[arg(long, env = "ARENA_TURN_CREDENTIAL_TTL_SECS")]
+    turn_credential_ttl_secs: Option<u64>,
     #[arg(long, env = "ARENA_METRICS_ADDR")]
     metrics_addr: Option<SocketAddr>,
     #[arg(long, env = "ARENA_EMAIL_SALT")]
     email_salt: Option<String>,
+    /// Seconds a disconnected player's room slot is kept for resume (default 10).
+    #[arg(long, env = "ARENA_ROOM_DISCONNECT_GRACE_SECS")]
+    room_disconnect_grace_secs: Option<u64>,
+    /// Milliseconds between checkpoints of room scores (default 250).
+    #[arg(long, env = "ARENA_ROOM_CHECKPOINT_INTERVAL_MS")]
+    room_checkpoint_interval_ms: Option<u64>,
+    /// Seconds between purges of expired email OTPs (default 300).
+    #[arg(long, env = "ARENA_OTP_PURGE_INTERVAL_SECS")]
+    otp_purge_interval_secs: Option<u64>,
+    /// JSON object of exchange rates from USD, e.g. `{"EUR": 0.92}`.
+    #[arg(long, env = "ARENA_STORE_FX_RATES_JSON")]
+    store_fx_rates_json: Option<String>,
+    /// JSON array of store items, e.g. `[{"id": "basic", "price_cents": 1000}]`.
+    #[arg(long, env = "ARENA_CATALOG_PATH")]
+    catalog_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -117,6 +168,42 @@ pub struct IceServerConfig {
     pub credential: Option<String>,
 }
 
+/// Why an [`IceServerConfig`] from `ARENA_RTC_ICE_SERVERS_JSON` was rejected.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum IceServerError {
+    #[error("urls must not be empty")]
+    NoUrls,
+    #[error("unsupported scheme in {0:?}; expected stun:, stuns:, turn: or turns:")]
+    UnsupportedScheme(String),
+    #[error("TURN url {0:?} requires a username and credential")]
+    MissingTurnCredentials(String),
+}
+
+impl IceServerConfig {
+    /// Check every url uses an ICE scheme and that TURN servers carry the
+    /// credentials clients need to allocate a relay.
+    pub fn validate(&self) -> Result<(), IceServerError> {
+        if self.urls.is_empty() {
+            return Err(IceServerError::NoUrls);
+        }
+        let has_credentials = [&self.username, &self.credential]
+            .iter()
+            .all(|v| v.as_deref().is_some_and(|v| !v.is_empty()));
+        for url in &self.urls {
+            let scheme = url.split_once(':').map(|(scheme, _)| scheme);
+            match scheme {
+                Some("stun" | "stuns") => {}
+                Some("turn" | "turns") if has_credentials => {}
+                Some("turn" | "turns") => {
+                    return Err(IceServerError::MissingTurnCredentials(url.clone()));
+                }
+                _ => return Err(IceServerError::UnsupportedScheme(url.clone())),
+            }
+        }
+        Ok(())
+    }
+}
+
 fn deserialize_urls<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
 where
     D: serde::de::Deserializer<'de>,
@@ -149,6 +236,8 @@ pub struct ResolvedConfig {
     pub db_url: String,
     /// Maximum number of database connections. Defaults to 20.
     pub db_max_conns: u32,
+    /// Maximum number of rooms running at once.
+    pub max_rooms: usize,
     pub migrate_on_start: bool,
     pub enable_coop_coep: bool,
     pub static_dir: PathBuf,
@@ -157,6 +246,8 @@ pub struct ResolvedConfig {
     pub enable_sw: bool,
     pub csp: Option<String>,
     pub ice_servers: Vec<IceServerConfig>,
+    /// Mints per-session credentials for the TURN servers in `ice_servers`.
+    pub turn: Option<turn::TurnSecret>,
     pub feature_flags: HashMap<String, bool>,
     pub metrics_addr: Option<SocketAddr>,
     pub analytics_enabled: bool,
@@ -165,17 +256,88 @@ pub struct ResolvedConfig {
     pub posthog_url: Option<String>,
     pub analytics_otlp_endpoint: Option<SocketAddr>,
     pub email_salt: String,
+    /// How long a disconnected room slot is kept before its score is dropped.
+    pub room_disconnect_grace: std::time::Duration,
+    /// How often changed room scores are checkpointed to the database.
+    pub room_checkpoint_interval: std::time::Duration,
+    /// How often expired email OTPs are deleted.
+    pub otp_purge_interval: std::time::Duration,
+    /// Exchange rates from the catalog base currency used by `/store`.
+    pub fx_rates: HashMap<String, f64>,
+    /// Items sold by `/store`.
+    pub catalog: Catalog,
+}
+
+/// Every problem found while resolving [`Config`], reported together.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid configuration: {}", .problems.join("; "))]
+pub struct ConfigError {
+    pub problems: Vec<String>,
 }
 
 impl Config {
     fn resolve(self) -> Result<ResolvedConfig> {
-        let ice_servers = self
-            .rtc_ice_servers_json
-            .ok_or_else(|| anyhow!("invalid ARENA_RTC_ICE_SERVERS_JSON: missing"))
-            .and_then(|json| {
-                serde_json::from_str::<Vec<IceServerConfig>>(&json)
-                    .map_err(|e| anyhow!("invalid ARENA_RTC_ICE_SERVERS_JSON: {e}"))
-            })?;
+        fn require<T>(problems: &mut Vec<String>, value: Option<T>, var: &str) -> Option<T> {
+            if value.is_none() {
+                problems.push(format!("{var} not set"));
+            }
+            value
+        }
+
+        let mut problems = Vec::new();
+        let bind_addr = require(&mut problems, self.bind_addr, "ARENA_BIND_ADDR");
+        let public_base_url = require(&mut problems, self.public_base_url, "ARENA_PUBLIC_BASE_URL");
+        let signaling_ws_url =
+            require(&mut problems, self.signaling_ws_url, "ARENA_SIGNALING_WS_URL");
+        let db_url = require(&mut problems, self.db_url, "ARENA_DB_URL");
+        let static_dir = require(&mut problems, self.static_dir, "ARENA_STATIC_DIR");
+        let assets_dir = require(&mut problems, self.assets_dir, "ARENA_ASSETS_DIR");
+        let email_salt = require(&mut problems, self.email_salt, "ARENA_EMAIL_SALT");
+        let turn = self.turn_secret.map(|secret| {
+            let ttl = self
+                .turn_credential_ttl_secs
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(turn::DEFAULT_TURN_CREDENTIAL_TTL);
+            turn::TurnSecret::new(secret, ttl)
+        });
+        let ice_servers = match self.rtc_ice_servers_json {
+            Some(json) => match serde_json::from_str::<Vec<IceServerConfig>>(&json) {
+                Ok(servers) => {
+                    let before = problems.len();
+                    for (i, server) in servers.iter().enumerate() {
+                        match server.validate() {
+                            Ok(()) => {}
+                            // Credentials are generated per session instead.
+                            Err(IceServerError::MissingTurnCredentials(_)) if turn.is_some() => {}
+                            Err(e) => problems.push(format!(
+                                "invalid ARENA_RTC_ICE_SERVERS_JSON: server {i}: {e}"
+                            )),
+                        }
+                    }
+                    (problems.len() == before).then_some(servers)
+                }
+                Err(e) => {
+                    problems.push(format!("invalid ARENA_RTC_ICE_SERVERS_JSON: {e}"));
+                    None
+                }
+            },
+            None => {
+                problems.push("invalid ARENA_RTC_ICE_SERVERS_JSON: missing".into());
+                None
+            }
+        };
+        let fx_rates = match self.store_fx_rates_json {
+            Some(json) => serde_json::from_str::<HashMap<String, f64>>(&json)
+                .map_err(|e| problems.push(format!("invalid ARENA_STORE_FX_RATES_JSON: {e}")))
+                .ok(),
+            None => Some(HashMap::new()),
+        };
+        let catalog = match &self.catalog_path {
+            Some(path) => Catalog::load(path)
+                .map_err(|e| problems.push(format!("invalid ARENA_CATALOG_PATH: {e}")))
+                .ok(),
+            None => Some(default_catalog()),
+        };
         let feature_flags = std::env::vars()
             .filter_map(|(k, v)| {
                 k.strip_prefix("ARENA_FEATURE_").map(|name| {
@@ -186,30 +348,26 @@ impl Config {
             })
             .collect();
 
+        if !problems.is_empty() {
+            return Err(ConfigError { problems }.into());
+        }
+        // Every `None` above recorded a problem, so these are all `Some`.
         Ok(ResolvedConfig {
-            bind_addr: self
-                .bind_addr
-                .ok_or_else(|| anyhow!("ARENA_BIND_ADDR not set"))?,
-            public_base_url: self
-                .public_base_url
-                .ok_or_else(|| anyhow!("ARENA_PUBLIC_BASE_URL not set"))?,
-            signaling_ws_url: self
-                .signaling_ws_url
-                .ok_or_else(|| anyhow!("ARENA_SIGNALING_WS_URL not set"))?,
-            db_url: self.db_url.ok_or_else(|| anyhow!("ARENA_DB_URL not set"))?,
+            bind_addr: bind_addr.unwrap(),
+            public_base_url: public_base_url.unwrap(),
+            signaling_ws_url: signaling_ws_url.unwrap(),
+            db_url: db_url.unwrap(),
             db_max_conns: self.db_max_conns.unwrap_or(DEFAULT_DB_MAX_CONNS),
+            max_rooms: self.max_rooms.unwrap_or(room::DEFAULT_MAX_ROOMS),
             migrate_on_start: self.migrate_on_start,
             enable_coop_coep: self.enable_coop_coep,
-            static_dir: self
-                .static_dir
-                .ok_or_else(|| anyhow!("ARENA_STATIC_DIR not set"))?,
-            assets_dir: self
-                .assets_dir
-                .ok_or_else(|| anyhow!("ARENA_ASSETS_DIR not set"))?,
+            static_dir: static_dir.unwrap(),
+            assets_dir: assets_dir.unwrap(),
             replays_dir: self.replays_dir.unwrap_or_else(|| PathBuf::from("replays")),
             enable_sw: self.enable_sw,
             csp: self.csp,
-            ice_servers,
+            ice_servers: ice_servers.unwrap(),
+            turn,
             feature_flags,
             metrics_addr: self.metrics_addr,
             analytics_enabled: false,
@@ -217,13 +375,33 @@ impl Config {
             analytics_local: false,
             posthog_url: None,
             analytics_otlp_endpoint: None,
-            email_salt: self
-                .email_salt
-                .ok_or_else(|| anyhow!("ARENA_EMAIL_SALT not set"))?,
+            email_salt: email_salt.unwrap(),
+            room_disconnect_grace: self
+                .room_disconnect_grace_secs
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(room::DEFAULT_DISCONNECT_GRACE),
+            room_checkpoint_interval: self
+                .room_checkpoint_interval_ms
+                .map(std::time::Duration::from_millis)
+                .unwrap_or(room::DEFAULT_CHECKPOINT_INTERVAL),
+            otp_purge_interval: self
+                .otp_purge_interval_secs
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(jobs::DEFAULT_OTP_PURGE_INTERVAL),
+            fx_rates: fx_rates.unwrap(),
+            catalog: catalog.unwrap(),
         })
     }
 }
 
+/// Catalog used when `ARENA_CATALOG_PATH` is unset.
+fn default_catalog() -> Catalog {
+    Catalog::new(vec![Sku {
+        id: "basic".to_string(),
+        price_cents: 1000,
+    }])
+}
+
 #[derive(Clone)]
 pub(crate) struct AppState {
     email: Arc<EmailService>,
@@ -232,30 +410,208 @@ pub(crate) struct AppState {
     analytics: Analytics,
     leaderboard: ::leaderboard::LeaderboardService,
     catalog: Catalog,
+    fx_rates: FxRates,
     db: DatabaseConnection,
     email_salt: String,
+    store_webhook: Option<HmacWebhookVerifier>,
+    admin_token: Option<String>,
+}
+
+/// Header `/store/webhook` reads the body signature from.
+const STORE_WEBHOOK_SIGNATURE_HEADER: &str = "X-Arena-Signature";
+/// Header carrying the unix time `/store/webhook` signatures cover.
+const STORE_WEBHOOK_TIMESTAMP_HEADER: &str = "X-Arena-Timestamp";
+
+/// Value of the cookie called `name`, searching every `Cookie` header.
+fn cookie_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then_some(value)
+        })
+}
+
+/// Guest session id from the `session` cookie set by `guest_handler`.
+fn session_from_cookie(headers: &HeaderMap) -> Option<uuid::Uuid> {
+    cookie_value(headers, "session").and_then(|id| id.parse().ok())
+}
+
+/// Reject requests whose `session` cookie or `X-Session` header names an
+/// unknown or expired session.
+///
+/// Requests carrying neither pass through unchanged.
+async fn require_live_session(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    for id in [session_from_cookie(headers), session_user(headers)]
+        .into_iter()
+        .flatten()
+    {
+        match session_store::is_live(&state.db, id).await {
+            Ok(true) => {}
+            Ok(false) => return StatusCode::UNAUTHORIZED.into_response(),
+            Err(e) => {
+                tracing::error!("failed to load session: {e}");
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    }
+    next.run(request).await
+}
+
+/// Reject requests without an `Authorization: Bearer` header matching the
+/// configured admin token.
+async fn require_admin_token(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let authorized = match (&state.admin_token, presented) {
+        // Comparing digests keeps the time taken independent of how much of
+        // the token matched.
+        (Some(expected), Some(presented)) => {
+            Sha256::digest(expected.as_bytes()) == Sha256::digest(presented.as_bytes())
+        }
+        _ => false,
+    };
+    if !authorized {
+        return (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, "Bearer")]).into_response();
+    }
+    next.run(request).await
+}
+
+/// Routes nested under `/admin`, all guarded by [`require_admin_token`].
+fn admin_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/mail/test", post(mail_test_handler))
+        .route("/mail/config", get(mail_config_handler))
+        .route_layer(middleware::from_fn_with_state(state, require_admin_token))
 }
 
-async fn ws_handler(State(state): State<Arc<AppState>>, ws: WebSocketUpgrade) -> impl IntoResponse {
-    state.analytics.dispatch(Event::WsConnected);
-    state.analytics.dispatch(Event::SessionStart);
+/// Record a new connection, attributed to the guest session if there is one.
+fn dispatch_session_start(analytics: &Analytics, headers: &HeaderMap) {
+    let session = session_from_cookie(headers);
+    let player = session.map(|id| id.to_string());
+    analytics.dispatch_with_context(Event::WsConnected, player.clone(), session);
+    analytics.dispatch_with_context(Event::SessionStart, player, session);
+}
+
+async fn ws_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    dispatch_session_start(&state.analytics, &headers);
     ws.on_upgrade(|socket| async move {
         handle_socket(socket).await;
     })
 }
 
+#[derive(Deserialize)]
+struct SignalParams {
+    /// Room to join, e.g. a module id; peers share state only within a
+    /// room.
+    room: Option<room::RoomId>,
+}
+
 async fn signal_ws_handler(
     State(state): State<Arc<AppState>>,
+    Query(params): Query<SignalParams>,
+    headers: HeaderMap,
     ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    state.analytics.dispatch(Event::WsConnected);
-    state.analytics.dispatch(Event::SessionStart);
+) -> Response {
+    let room_id = params
+        .room
+        .unwrap_or_else(|| room::DEFAULT_ROOM.to_string());
+    if !state.rooms.is_known_room(&room_id) {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    dispatch_session_start(&state.analytics, &headers);
+    // Sessions identify the player across reconnects so they can resume
+    // their slot in the room.
+    let player = session_from_cookie(&headers).or_else(|| session_user(&headers));
     ws.on_upgrade(move |socket| async move {
-        handle_signal_socket(state, socket).await;
+        handle_signal_socket(state, socket, room_id, player).await;
     })
+    .into_response()
+}
+
+/// JSON messages exchanged on `/signal` after the SDP answer.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum SignalMessage {
+    /// A trickled ICE candidate, in the browser's `RTCIceCandidateInit` shape.
+    Candidate(RTCIceCandidateInit),
+}
+
+/// Trickle ICE candidates both ways until the client closes the socket.
+///
+/// Clients that put every candidate in their SDP offer never send anything
+/// here and can ignore the server's candidate messages.
+async fn exchange_candidates(
+    socket: &mut WebSocket,
+    pc: &RTCPeerConnection,
+    local: &mut mpsc::UnboundedReceiver<RTCIceCandidateInit>,
+) {
+    use serde_json::json;
+
+    loop {
+        tokio::select! {
+            Some(candidate) = local.recv() => {
+                let Ok(text) = serde_json::to_string(&SignalMessage::Candidate(candidate)) else {
+                    continue;
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = match serde_json::from_str::<SignalMessage>(&text) {
+                        Ok(SignalMessage::Candidate(candidate)) => {
+                            match pc.add_ice_candidate(candidate.clone()).await {
+                                Ok(()) => continue,
+                                Err(e) => {
+                                    tracing::warn!("invalid ICE candidate: {e}");
+                                    json!({
+                                        "error": "invalid ICE candidate",
+                                        "candidate": candidate.candidate,
+                                    })
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!("unexpected signaling message: {e}");
+                            json!({ "error": "invalid signaling message" })
+                        }
+                    };
+                    let _ = socket.send(Message::Text(reply.to_string())).await;
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
 }
 
-async fn handle_signal_socket(state: Arc<AppState>, mut socket: WebSocket) {
+async fn handle_signal_socket(
+    state: Arc<AppState>,
+    mut socket: WebSocket,
+    room_id: room::RoomId,
+    player: Option<uuid::Uuid>,
+) {
     use axum::extract::ws::CloseFrame;
     use serde_json::json;
 
@@ -263,10 +619,22 @@ async fn handle_signal_socket(state: Arc<AppState>, mut socket: WebSocket) {
         match msg {
             Ok(Message::Text(sdp)) => match ServerConnector::new().await {
                 Ok(connector) => {
+                    // Owned up front: the connector itself moves into the room
+                    // while signaling keeps driving the peer connection.
+                    let pc: Arc<RTCPeerConnection> = Arc::clone(&connector.pc);
+                    // Registered before the answer so no gathered candidate
+                    // is missed; they are sent once the answer is out.
+                    let (candidate_tx, mut candidate_rx) = mpsc::unbounded_channel();
+                    pc.on_ice_candidate(Box::new(move |candidate: Option<RTCIceCandidate>| {
+                        if let Some(init) = candidate.and_then(|c| c.to_json().ok()) {
+                            let _ = candidate_tx.send(init);
+                        }
+                        Box::pin(async {})
+                    }));
                     let mut offer = RTCSessionDescription::default();
                     offer.sdp_type = RTCSdpType::Offer;
                     offer.sdp = sdp;
-                    if let Err(e) = connector.pc.set_remote_description(offer).await {
+                    if let Err(e) = pc.set_remote_description(offer).await {
                         tracing::warn!("invalid SDP offer: {e}");
                         let _ = socket
                             .send(Message::Text(
@@ -282,14 +650,9 @@ async fn handle_signal_socket(state: Arc<AppState>, mut socket: WebSocket) {
                         return;
                     }
 
-                    match connector.pc.create_answer(None).await {
+                    match pc.create_answer(None).await {
                         Ok(answer) => {
-                            if connector
-                                .pc
-                                .set_local_description(answer.clone())
-                                .await
-                                .is_err()
-                            {
+                            if pc.set_local_description(answer.clone()).await.is_err() {
                                 tracing::warn!("failed to set local description");
                                 let _ = socket
                                     .send(Message::Close(Some(CloseFrame {
@@ -301,7 +664,40 @@ async fn handle_signal_socket(state: Arc<AppState>, mut socket: WebSocket) {
                             }
 
                             let _ = socket.send(Message::Text(answer.sdp.clone())).await;
-                            state.rooms.add_peer(connector).await;
+                            let snapshot_tx = connector.snapshot_tx.clone();
+                            let (pc_state_tx, mut pc_state_rx) =
+                                watch::channel(RTCPeerConnectionState::New);
+                            pc.on_peer_connection_state_change(Box::new(move |pc_state| {
+                                let _ = pc_state_tx.send(pc_state);
+                                Box::pin(async {})
+                            }));
+                            if let Err(e) = state.rooms.add_peer(&room_id, player, connector).await
+                            {
+                                tracing::warn!("refused peer for room {room_id}: {e}");
+                                let _ = socket
+                                    .send(Message::Close(Some(CloseFrame {
+                                        code: 1013,
+                                        reason: "room unavailable".into(),
+                                    })))
+                                    .await;
+                                let _ = pc.close().await;
+                                return;
+                            }
+                            exchange_candidates(&mut socket, &pc, &mut candidate_rx).await;
+                            // The data channels don't need signaling once ICE
+                            // is done, so the slot outlives the socket. Only a
+                            // dead connection starts its grace window.
+                            let _ = pc_state_rx
+                                .wait_for(|pc_state| {
+                                    matches!(
+                                        pc_state,
+                                        RTCPeerConnectionState::Failed
+                                            | RTCPeerConnectionState::Closed
+                                    )
+                                })
+                                .await;
+                            state.rooms.disconnect_peer(&room_id, &snapshot_tx).await;
+                            let _ = pc.close().await;
                         }
                         Err(e) => {
                             tracing::warn!("failed to create answer: {e}");
@@ -398,6 +794,7 @@ struct RedactedSmtpConfig {
     timeout: u64,
     user: Option<String>,
     pass: Option<String>,
+    rate_limit: u64,
 }
 
 impl From<&SmtpConfig> for RedactedSmtpConfig {
@@ -411,6 +808,7 @@ impl From<&SmtpConfig> for RedactedSmtpConfig {
             timeout: cfg.timeout,
             user: cfg.user.clone(),
             pass: cfg.pass.as_ref().map(|_| "***".into()),
+            rate_limit: cfg.rate_limit,
         }
     }
 }
@@ -432,7 +830,7 @@ async fn mail_test_handler(
         tracing::warn!("invalid test email address: {to}");
         false
     } else {
-        match state.email.send_test(&to) {
+        match state.email.send_test(&to).await {
             Ok(()) => {
                 state.analytics.dispatch(Event::MailTestQueued);
                 true
@@ -447,17 +845,51 @@ async fn mail_test_handler(
     Json(MailTestResponse { queued })
 }
 
+#[derive(Deserialize)]
+struct StoreParams {
+    currency: Option<String>,
+}
+
+#[derive(Serialize)]
+struct StoreItem {
+    #[serde(flatten)]
+    sku: Sku,
+    currency: String,
+    /// Price in `currency`; equals `price_cents` for the base currency.
+    converted_price_cents: u32,
+}
+
 #[derive(Serialize)]
 struct StoreResponse {
-    items: Vec<Sku>,
+    items: Vec<StoreItem>,
 }
 
-async fn store_handler(State(state): State<Arc<AppState>>) -> Json<StoreResponse> {
+async fn store_handler(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<StoreParams>,
+) -> Json<StoreResponse> {
     state.analytics.dispatch(Event::StoreViewed);
     state.analytics.dispatch(Event::StoreOpen);
-    Json(StoreResponse {
-        items: state.catalog.all().to_vec(),
-    })
+    // Unknown currencies fall back to the base price rather than failing.
+    let currency = params
+        .currency
+        .map(|c| c.to_ascii_uppercase())
+        .filter(|c| state.fx_rates.rate(c).is_some())
+        .unwrap_or_else(|| BASE_CURRENCY.to_string());
+    let items = state
+        .catalog
+        .all()
+        .iter()
+        .map(|sku| StoreItem {
+            sku: sku.clone(),
+            converted_price_cents: state
+                .fx_rates
+                .convert(sku.price_cents, &currency)
+                .unwrap_or(sku.price_cents),
+            currency: currency.clone(),
+        })
+        .collect();
+    Json(StoreResponse { items })
 }
 
 #[derive(Deserialize)]
@@ -465,21 +897,111 @@ struct ClaimRequest {
     sku: String,
 }
 
+fn session_user(headers: &HeaderMap) -> Option<UserId> {
+    headers
+        .get("X-Session")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| UserId::parse_str(s).ok())
+}
+
+async fn store_checkout_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<ClaimRequest>,
+) -> Result<Json<CheckoutSession>, StatusCode> {
+    let user = session_user(&headers).ok_or(StatusCode::UNAUTHORIZED)?;
+    let sku = state.catalog.get(&req.sku).ok_or(StatusCode::BAD_REQUEST)?;
+    let session = purchases::initiate_purchase(&state.db, user, &sku.id)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to open checkout session: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    state.analytics.dispatch(Event::PurchaseInitiated);
+    Ok(Json(session))
+}
+
 async fn store_claim_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Json(req): Json<ClaimRequest>,
 ) -> StatusCode {
-    let user = match headers
-        .get("X-Session")
-        .and_then(|v| v.to_str().ok())
-        .and_then(|s| UserId::parse_str(s).ok())
+    let Some(user) = session_user(&headers) else {
+        return StatusCode::UNAUTHORIZED;
+    };
+
+    let entitlements = DbEntitlements::new(state.db.clone());
+    match entitlements
+        .grant_if_valid(&state.catalog, user, &req.sku)
+        .await
     {
-        Some(u) => u,
-        None => return StatusCode::UNAUTHORIZED,
+        Ok(()) => {
+            state.analytics.dispatch(Event::EntitlementGranted);
+            StatusCode::OK
+        }
+        Err(GrantError::UnknownSku(_)) => StatusCode::BAD_REQUEST,
+        Err(GrantError::Db(e)) => {
+            tracing::error!("failed to grant entitlement: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Completed checkout reported by the payment provider.
+#[derive(Deserialize)]
+struct StoreWebhook {
+    session_id: String,
+    user: UserId,
+    sku: String,
+}
+
+async fn store_webhook_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    let Some(verifier) = &state.store_webhook else {
+        return StatusCode::NOT_FOUND;
     };
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let timestamp = header(STORE_WEBHOOK_TIMESTAMP_HEADER).unwrap_or_default();
+    if let Err(e) = verifier.verify_timestamped(header(verifier.header()), timestamp, &body) {
+        tracing::warn!("rejected store webhook: {e}");
+        return StatusCode::BAD_REQUEST;
+    }
+    let Ok(webhook) = serde_json::from_slice::<StoreWebhook>(&body) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    let Some(sku) = state.catalog.get(&webhook.sku) else {
+        return StatusCode::BAD_REQUEST;
+    };
+    // Only sessions this server opened count, for the user and sku they
+    // were opened with.
+    match purchases::checkout_matches(&state.db, &webhook.session_id, webhook.user, &sku.id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::warn!("rejected store webhook for unknown checkout session");
+            return StatusCode::BAD_REQUEST;
+        }
+        Err(e) => {
+            tracing::error!("failed to look up checkout session: {e}");
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    }
 
-    let _ = purchases::grant_entitlement(&state.db, user, &req.sku).await;
+    // Checkout session ids are unique per checkout, so keying on them records
+    // each sale once however often its webhook is delivered.
+    if let Err(e) =
+        purchases::create_purchase(&state.db, webhook.user, sku, &webhook.session_id).await
+    {
+        tracing::error!("failed to record purchase: {e}");
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+    state.analytics.dispatch(Event::PurchaseSucceeded);
+    if let Err(e) = purchases::grant_entitlement(&state.db, webhook.user, &sku.id).await {
+        tracing::error!("failed to grant entitlement: {e}");
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
     state.analytics.dispatch(Event::EntitlementGranted);
     StatusCode::OK
 }
@@ -518,23 +1040,18 @@ struct GuestResponse {
     user_id: String,
 }
 
-async fn guest_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let id = uuid::Uuid::new_v4();
-    let active = players::ActiveModel {
-        id: Set(id.to_string()),
-        handle: Set(String::new()),
-        region: Set(None),
-        created_at: Set(chrono::Utc::now()),
-    };
-    let _ = active.insert(&state.db).await;
+/// Headers setting the `session` cookie for `id`, valid for [`session_store::SESSION_TTL`],
+/// and a matching CSRF token.
+fn session_cookie_headers(id: uuid::Uuid) -> HeaderMap {
     let mut headers = HeaderMap::new();
     let same_site = std::env::var("ARENA_COOKIE_SAME_SITE").unwrap_or_else(|_| "Strict".into());
     let secure = std::env::var("ARENA_COOKIE_SECURE")
         .map(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
         .unwrap_or(true);
     let cookie = format!(
-        "session={}; Path=/; HttpOnly;{} SameSite={}",
+        "session={}; Path=/; Max-Age={}; HttpOnly;{} SameSite={}",
         id,
+        session_store::SESSION_TTL.as_secs(),
         if secure { " Secure;" } else { "" },
         same_site
     );
@@ -546,12 +1063,120 @@ async fn guest_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse
             tracing::error!("failed to create session cookie header: {e}");
         }
     }
+    csrf::issue(
+        &mut headers,
+        session_store::SESSION_TTL.as_secs(),
+        secure,
+        &same_site,
+    );
+    headers
+}
+
+async fn guest_handler(State(state): State<Arc<AppState>>) -> Response {
+    let id = uuid::Uuid::new_v4();
+    let active = players::ActiveModel {
+        id: Set(id.to_string()),
+        handle: Set(String::new()),
+        region: Set(None),
+        created_at: Set(chrono::Utc::now()),
+    };
+    let _ = active.insert(&state.db).await;
+    let expires_at = session_store::next_expiry();
+    if let Err(e) = session_store::insert_session(&state.db, id, &id.to_string(), expires_at).await
+    {
+        tracing::error!("failed to store guest session: {e}");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
     (
-        headers,
+        session_cookie_headers(id),
         Json(GuestResponse {
             user_id: id.to_string(),
         }),
     )
+        .into_response()
+}
+
+/// Extend a live session by [`session_store::SESSION_TTL`] and reissue its cookie.
+async fn session_refresh_handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(id) = session_from_cookie(&headers) else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let refreshed = match session_store::is_live(&state.db, id).await {
+        Ok(true) => {
+            session_store::extend_session(&state.db, id, session_store::next_expiry()).await
+        }
+        Ok(false) => return StatusCode::UNAUTHORIZED.into_response(),
+        Err(e) => Err(e),
+    };
+    match refreshed {
+        Ok(()) => (session_cookie_headers(id), StatusCode::OK).into_response(),
+        Err(e) => {
+            tracing::error!("failed to refresh session: {e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Outcome of the `/healthz` database probe.
+#[derive(Serialize)]
+struct DbHealth {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Outcome of the most recent analytics flush.
+#[derive(Serialize)]
+struct AnalyticsHealth {
+    last_flush_error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    ok: bool,
+    db: DbHealth,
+    email: EmailStatus,
+    analytics: AnalyticsHealth,
+    rooms: usize,
+}
+
+/// Report subsystem health for load balancers and operators.
+///
+/// Only the database decides `ok` and the status code (`200` or `503`):
+/// email and analytics failures are reported but do not take the instance
+/// out of rotation, since they are retried in the background.
+async fn healthz_handler(State(state): State<Arc<AppState>>) -> Response {
+    let db = match state.db.ping().await {
+        Ok(()) => DbHealth {
+            ok: true,
+            error: None,
+        },
+        Err(e) => {
+            tracing::warn!("health check database ping failed: {e}");
+            DbHealth {
+                ok: false,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    let health = HealthResponse {
+        ok: db.ok,
+        db,
+        email: state.email.status(),
+        analytics: AnalyticsHealth {
+            last_flush_error: state.analytics.last_flush_error(),
+        },
+        rooms: state.rooms.room_count().await,
+    };
+    let status = if health.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(health)).into_response()
 }
 
 async fn shutdown_signal() {
@@ -582,6 +1207,8 @@ async fn setup(
     cfg: &ResolvedConfig,
     smtp: SmtpConfig,
     posthog_key: Option<String>,
+    store_webhook_secret: Option<String>,
+    admin_token: Option<String>,
 ) -> Result<AppState> {
     if cfg.migrate_on_start {
         let migration_db = Database::connect(&cfg.db_url).await?;
@@ -595,19 +1222,23 @@ async fn setup(
 
     let leaderboard = ::leaderboard::LeaderboardService::new(&cfg.db_url, cfg.replays_dir.clone())
         .await
-        .map_err(|e| anyhow!(e))?;
+        .map_err(|e| anyhow!(e))?
+        .with_replay_trimmer(leaderboard::trim_replay_events);
+    let db = connect_db(&cfg.db_url, cfg.db_max_conns).await?;
     let registry = Arc::new(shard::MemoryShardRegistry::new());
+    // Each installed module gets a room alongside the default one.
+    let modules = platform_api::discover_modules_in(&cfg.assets_dir.join("modules"));
     let rooms = room::RoomManager::with_registry(
         leaderboard.clone(),
         registry,
         "shard1".into(),
         cfg.signaling_ws_url.clone(),
-    );
-    let catalog = Catalog::new(vec![Sku {
-        id: "basic".to_string(),
-        price_cents: 1000,
-    }]);
-    let db = connect_db(&cfg.db_url, cfg.db_max_conns).await?;
+    )
+    .with_db(db.clone())
+    .with_checkpoint_interval(cfg.room_checkpoint_interval)
+    .with_rooms(modules.into_iter().map(|module| module.id))
+    .with_max_rooms(cfg.max_rooms);
+    rooms.set_disconnect_grace(cfg.room_disconnect_grace).await;
     let analytics = Analytics::new(
         cfg.analytics_enabled && !cfg.analytics_opt_out,
         Some(db.clone()),
@@ -620,9 +1251,13 @@ async fn setup(
         smtp,
         analytics,
         leaderboard,
-        catalog,
+        catalog: cfg.catalog.clone(),
+        fx_rates: FxRates::new(cfg.fx_rates.clone()),
         db,
         email_salt: cfg.email_salt.clone(),
+        store_webhook: store_webhook_secret
+            .map(|secret| HmacWebhookVerifier::new(STORE_WEBHOOK_SIGNATURE_HEADER, secret)),
+        admin_token,
     })
 }
 
@@ -636,6 +1271,8 @@ async fn run(cli: Cli) -> Result<()> {
         analytics_otlp_endpoint,
         analytics_opt_out,
         log_level: _,
+        store_webhook_secret,
+        admin_token,
     } = cli;
     if let Some(url) = &posthog_url {
         unsafe {
@@ -655,9 +1292,19 @@ async fn run(cli: Cli) -> Result<()> {
         }
     }
     tracing::info!("Using config: {:?}", config);
-    let state = Arc::new(setup(&config, smtp, posthog_key.clone()).await?);
+    let state = Arc::new(
+        setup(
+            &config,
+            smtp,
+            posthog_key.clone(),
+            store_webhook_secret,
+            admin_token,
+        )
+        .await?,
+    );
 
     tokio::spawn(jobs::run(state.db.clone(), state.email.clone()));
+    tokio::spawn(jobs::run_otp_purge(state.db.clone(), config.otp_purge_interval));
 
     let assets_service = get_service(ServeDir::new(&config.assets_dir)).layer(
         SetResponseHeaderLayer::if_not_present(
@@ -666,20 +1313,32 @@ async fn run(cli: Cli) -> Result<()> {
         ),
     );
 
+    // Routes acting on behalf of the cookie's session refuse expired ones.
+    let session_routes = Router::new()
+        .route("/ws", get(ws_handler))
+        .route("/signal", get(signal_ws_handler))
+        .route("/store/checkout", post(store_checkout_handler))
+        .route("/store/claim", post(store_claim_handler))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_live_session,
+        ));
+
     let mut app = Router::new()
         .nest("/auth", auth::routes())
         .route("/auth/guest", post(guest_handler))
-        .route("/ws", get(ws_handler))
-        .route("/signal", get(signal_ws_handler))
+        .route("/auth/session/refresh", post(session_refresh_handler))
+        .merge(session_routes)
         .route("/config.json", get(config::get_config))
+        .route("/healthz", get(healthz_handler))
         .route("/store", get(store_handler))
-        .route("/store/claim", post(store_claim_handler))
+        .route("/store/webhook", post(store_webhook_handler))
         .route("/entitlements/:user", get(entitlements_handler))
-        .route("/admin/mail/test", post(mail_test_handler))
-        .route("/admin/mail/config", get(mail_config_handler))
-        .nest("/leaderboard", leaderboard::routes())
+        .nest("/admin", admin_routes(state.clone()))
+        .nest("/leaderboard", leaderboard::routes(state.clone()))
         .nest_service("/assets", assets_service)
-        .fallback_service(ServeDir::new(&config.static_dir));
+        .fallback_service(ServeDir::new(&config.static_dir))
+        .layer(middleware::from_fn(csrf::require_csrf_token));
 
     if config.enable_coop_coep {
         app = app
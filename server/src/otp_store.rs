@@ -38,6 +38,16 @@ pub async fn delete_otp(db: &DatabaseConnection, email_hash: &str) -> Result<(),
     Ok(())
 }
 
+/// Delete every OTP that expired before `now`, returning how many were removed.
+pub async fn delete_expired(db: &DatabaseConnection, now: DateTime<Utc>) -> Result<u64, DbErr> {
+    let now: DateTimeWithTimeZone = now.into();
+    let res = email_otps::Entity::delete_many()
+        .filter(email_otps::Column::ExpiresAt.lt(now))
+        .exec(db)
+        .await?;
+    Ok(res.rows_affected)
+}
+
 mod email_otps {
     use sea_orm::entity::prelude::*;
 
@@ -1,7 +1,9 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::Duration as StdDuration;
 
 use tokio::sync::{Mutex, mpsc::Receiver, mpsc::Sender, mpsc::error::TrySendError};
+use tokio::task::JoinHandle;
 use tokio::time::{self, Duration};
 
 use once_cell::sync::Lazy;
@@ -14,19 +16,62 @@ use ::leaderboard::{
 use analytics::{Analytics, Event};
 use chrono::Utc;
 use duck_hunt_server::server::{
-    DuckState, Server as DuckServer, replicate, spawn_duck, validate_hit,
+    DEFAULT_MAX_REWIND, Server as DuckServer, replicate, spawn_duck, validate_hit,
 };
 use glam::Vec3;
-use net::message::{InputFrame, ServerMessage, Snapshot, delta_compress};
+use net::message::{EntityMask, InputFrame, ServerMessage, Snapshot, delta_compress};
 use net::server::ServerConnector;
+use sea_orm::DatabaseConnection;
 use serde::{Deserialize, Serialize};
 #[cfg(test)]
 use std::sync::atomic::{AtomicBool, Ordering};
 use uuid::Uuid;
 
+use crate::room_store::{self, RoomState};
+
 #[cfg(test)]
 static FORCE_SERIALIZATION_ERROR: AtomicBool = AtomicBool::new(false);
 
+/// How long a disconnected slot is kept before its score is dropped.
+pub const DEFAULT_DISCONNECT_GRACE: StdDuration = StdDuration::from_secs(10);
+
+/// Rooms a shard runs at once unless configured otherwise.
+pub const DEFAULT_MAX_ROOMS: usize = 64;
+
+/// Snapshots queued per connector while its channel is full.
+pub const DEFAULT_SNAPSHOT_CAPACITY: usize = 32;
+
+/// How often a room's in-flight scores are written to the `room_state` table
+/// unless configured otherwise.
+pub const DEFAULT_CHECKPOINT_INTERVAL: StdDuration = StdDuration::from_millis(250);
+
+/// Simulation ticks per second.
+const TICK_RATE: u32 = 60;
+/// Shots a single connector may fire per second of ticks; the rest are
+/// dropped.
+const MAX_SHOTS_PER_SECOND: u32 = 10;
+
+/// Input for up to this many frames ago is still applied.
+const INPUT_LATE_FRAMES: i32 = 2;
+/// Input for up to this many frames ahead is held until its frame.
+const INPUT_EARLY_FRAMES: i32 = 2;
+
+static DROPPED_INPUT_FRAMES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "dropped_input_frames_total",
+        "Number of input frames dropped for falling outside the accepted window"
+    )
+    .unwrap()
+});
+
+static RATE_LIMITED_SHOTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "rate_limited_shots_total",
+        "Number of shots dropped for exceeding the per-player fire rate"
+    )
+    .unwrap()
+});
+
 static SNAPSHOT_CHANNEL_FULL: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
         "snapshot_channel_full_total",
@@ -35,13 +80,66 @@ static SNAPSHOT_CHANNEL_FULL: Lazy<IntCounter> = Lazy::new(|| {
     .unwrap()
 });
 
+/// How a connector's interest mask is interpreted when filtering snapshots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InterestMode {
+    /// The mask lists the entities to deliver.
+    #[default]
+    Inclusive,
+    /// The mask lists the entities to ignore.
+    Exclusive,
+}
+
+impl InterestMode {
+    /// Returns `true` if a change touching `diff_mask` should be sent.
+    fn wants(self, interest_mask: &EntityMask, diff_mask: &EntityMask) -> bool {
+        match self {
+            InterestMode::Inclusive => interest_mask.intersects(diff_mask),
+            InterestMode::Exclusive => !diff_mask.is_subset(interest_mask),
+        }
+    }
+}
+
 struct ConnectorHandle {
     input_rx: Receiver<InputFrame>,
     snapshot_tx: Sender<ServerMessage>,
-    /// Bitmask describing which updates this client is interested in.
-    interest_mask: u64,
+    /// Which score slots this client is interested in.
+    interest_mask: EntityMask,
+    /// Whether `interest_mask` selects or excludes entities.
+    interest_mode: InterestMode,
     /// Receives interest mask updates from the network layer.
-    interest_rx: Receiver<u64>,
+    interest_rx: Receiver<EntityMask>,
+    /// Signalled when the client announces it is leaving.
+    disconnect_rx: Receiver<()>,
+    /// Frames that arrived slightly early, applied once their frame comes.
+    pending_inputs: Vec<InputFrame>,
+    shot_limiter: ShotLimiter,
+    /// Snapshots waiting for room in `snapshot_tx`, oldest first.
+    backlog: VecDeque<ServerMessage>,
+    /// Set when the snapshot channel closes; the slot is evicted once the
+    /// room's grace window elapses without a resume.
+    disconnected_at: Option<std::time::Instant>,
+}
+
+/// Counts a connector's shots over one-second windows of ticks.
+#[derive(Default)]
+struct ShotLimiter {
+    window_start: u32,
+    shots: u32,
+}
+
+impl ShotLimiter {
+    /// Record a shot fired on `frame`; returns `false` once the connector is
+    /// over [`MAX_SHOTS_PER_SECOND`] for the current window, which starts at
+    /// the first shot after the previous one ended.
+    fn allow(&mut self, frame: u32) -> bool {
+        if self.shots == 0 || frame.wrapping_sub(self.window_start) >= TICK_RATE {
+            self.window_start = frame;
+            self.shots = 0;
+        }
+        self.shots += 1;
+        self.shots <= MAX_SHOTS_PER_SECOND
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -54,6 +152,7 @@ struct Shot {
 pub const LEADERBOARD_ID: Uuid = Uuid::from_u128(0);
 
 struct Room {
+    id: RoomId,
     connectors: Vec<ConnectorHandle>,
     last_snapshot: Option<Snapshot>,
     frame: u32,
@@ -64,15 +163,44 @@ struct Room {
     leaderboard_id: Uuid,
     start_time: std::time::Instant,
     analytics: Analytics,
+    disconnect_grace: StdDuration,
+    snapshot_capacity: usize,
+    /// Where checkpoints are written; `None` disables checkpointing.
+    db: Option<DatabaseConnection>,
+    /// State as of the last checkpoint, so unchanged state isn't rewritten.
+    last_checkpoint: Option<RoomState>,
+}
+
+/// Room state waiting to be written, taken under the room lock and saved
+/// after it is released.
+struct Checkpoint {
+    db: DatabaseConnection,
+    room_id: RoomId,
+    state: RoomState,
+}
+
+impl Checkpoint {
+    async fn save(self) {
+        if let Err(err) = room_store::save_room_state(&self.db, &self.room_id, &self.state).await {
+            tracing::warn!("failed to checkpoint room {}: {err}", self.room_id);
+        }
+    }
 }
 
 impl Room {
-    fn new(leaderboard: LeaderboardService) -> Self {
+    /// Create a room, restoring the last checkpoint for `id` if one exists.
+    /// Restoration is best-effort: a missing table or row starts empty.
+    async fn new(
+        leaderboard: LeaderboardService,
+        id: &str,
+        db: Option<DatabaseConnection>,
+    ) -> Self {
         let analytics = Analytics::new(false, None, None, None);
         let mut server = DuckServer {
             latency: StdDuration::from_secs(0),
             ducks: Vec::new(),
             snapshot_txs: Vec::new(),
+            max_rewind: DEFAULT_MAX_REWIND,
         };
         spawn_duck(
             &mut server,
@@ -80,7 +208,8 @@ impl Room {
             Vec3::new(1.0, 0.0, 0.0),
         );
         analytics.dispatch(Event::Respawn);
-        Self {
+        let mut room = Self {
+            id: id.to_string(),
             connectors: Vec::new(),
             last_snapshot: None,
             frame: 0,
@@ -91,25 +220,116 @@ impl Room {
             leaderboard_id: LEADERBOARD_ID,
             start_time: std::time::Instant::now(),
             analytics,
+            disconnect_grace: DEFAULT_DISCONNECT_GRACE,
+            snapshot_capacity: DEFAULT_SNAPSHOT_CAPACITY,
+            db,
+            last_checkpoint: None,
+        };
+        if let Some(ref db) = room.db {
+            match room_store::load_room_state(db, id).await {
+                Ok(Some(state)) => room.restore(state),
+                Ok(None) => {}
+                Err(err) => tracing::warn!("failed to restore room {id}: {err}"),
+            }
         }
+        room
     }
 
-    fn add_connector(&mut self, connector: ServerConnector) -> usize {
+    /// Refill scores from a checkpoint. Each restored player gets a
+    /// disconnected slot so a reconnecting client can resume it within the
+    /// grace window.
+    fn restore(&mut self, state: RoomState) {
+        let now = std::time::Instant::now();
+        self.frame = state.frame;
+        self.last_checkpoint = Some(state.clone());
+        for (player_id, score) in state.player_ids.into_iter().zip(state.scores) {
+            let (_input_tx, input_rx) = tokio::sync::mpsc::channel(1);
+            let (snapshot_tx, _snapshot_rx) = tokio::sync::mpsc::channel(1);
+            let (_interest_tx, interest_rx) = tokio::sync::mpsc::channel(1);
+            let (_disconnect_tx, disconnect_rx) = tokio::sync::mpsc::channel(1);
+            self.connectors.push(ConnectorHandle {
+                input_rx,
+                snapshot_tx,
+                interest_mask: EntityMask::all(),
+                interest_mode: InterestMode::Inclusive,
+                interest_rx,
+                disconnect_rx,
+                pending_inputs: Vec::new(),
+                shot_limiter: ShotLimiter::default(),
+                backlog: VecDeque::new(),
+                disconnected_at: Some(now),
+            });
+            self.scores.push(score);
+            self.player_ids.push(player_id);
+        }
+    }
+
+    /// The room's scores and frame to write to the database, or `None` if
+    /// checkpointing is off or no player or score changed since the last
+    /// checkpoint. The frame advances every tick, so it alone doesn't
+    /// warrant a write.
+    fn take_checkpoint(&mut self) -> Option<Checkpoint> {
+        let db = self.db.clone()?;
+        let unchanged = self.last_checkpoint.as_ref().is_some_and(|last| {
+            last.player_ids == self.player_ids && last.scores == self.scores
+        });
+        if unchanged {
+            return None;
+        }
+        let state = RoomState {
+            player_ids: self.player_ids.clone(),
+            scores: self.scores.clone(),
+            frame: self.frame,
+        };
+        self.last_checkpoint = Some(state.clone());
+        Some(Checkpoint {
+            db,
+            room_id: self.id.clone(),
+            state,
+        })
+    }
+
+    /// Seat `player_id`'s connector, resuming their slot and score if they
+    /// still have one. Anonymous peers always get a new slot.
+    fn join(&mut self, player_id: Option<Uuid>, connector: ServerConnector) -> usize {
+        let slot = player_id
+            .and_then(|id| self.player_ids.iter().position(|p| *p == id))
+            .filter(|&index| index < self.connectors.len());
+        let Some(index) = slot else {
+            return self.add_connector(player_id.unwrap_or_else(Uuid::new_v4), connector);
+        };
+        // The new connection supersedes the old one even if the old one
+        // hasn't been noticed as gone yet.
+        self.connectors[index]
+            .disconnected_at
+            .get_or_insert_with(std::time::Instant::now);
+        self.resume_connector(index, connector);
+        index
+    }
+
+    fn add_connector(&mut self, player_id: Uuid, connector: ServerConnector) -> usize {
         let ServerConnector {
             input_rx,
             snapshot_tx,
             interest_rx,
+            disconnect_rx,
             ..
         } = connector;
         self.duck_server.snapshot_txs.push(snapshot_tx.clone());
         self.connectors.push(ConnectorHandle {
             input_rx,
             snapshot_tx,
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx,
+            disconnect_rx,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         self.scores.push(0);
-        self.player_ids.push(Uuid::new_v4());
+        self.player_ids.push(player_id);
         let ducks = self.duck_server.ducks.clone();
         for duck in &ducks {
             replicate(&self.duck_server, duck);
@@ -117,28 +337,125 @@ impl Room {
         self.connectors.len() - 1
     }
 
-    fn set_interest(&mut self, index: usize, mask: u64) {
+    /// Reattach a connector to a slot that is pending eviction, keeping its
+    /// score. Returns `false` if the slot is unknown or still connected.
+    fn resume_connector(&mut self, index: usize, connector: ServerConnector) -> bool {
+        let ServerConnector {
+            input_rx,
+            snapshot_tx,
+            interest_rx,
+            disconnect_rx,
+            ..
+        } = connector;
+        self.resume_handle(
+            index,
+            ConnectorHandle {
+                input_rx,
+                snapshot_tx,
+                interest_mask: EntityMask::all(),
+                interest_mode: InterestMode::Inclusive,
+                interest_rx,
+                disconnect_rx,
+                pending_inputs: Vec::new(),
+                shot_limiter: ShotLimiter::default(),
+                backlog: VecDeque::new(),
+                disconnected_at: None,
+            },
+        )
+    }
+
+    fn resume_handle(&mut self, index: usize, handle: ConnectorHandle) -> bool {
+        match self.connectors.get(index) {
+            Some(conn) if conn.disconnected_at.is_some() => {}
+            _ => return false,
+        }
+        // The resumed client has no base for deltas, so start it from the
+        // most recent full snapshot.
+        if let Some(ref snapshot) = self.last_snapshot {
+            let _ = handle
+                .snapshot_tx
+                .try_send(ServerMessage::Baseline(snapshot.clone()));
+        }
+        let stale = &self.connectors[index].snapshot_tx;
+        self.duck_server
+            .snapshot_txs
+            .retain(|tx| !tx.same_channel(stale));
+        self.duck_server.snapshot_txs.push(handle.snapshot_tx.clone());
+        self.connectors[index] = handle;
+        let ducks = self.duck_server.ducks.clone();
+        for duck in &ducks {
+            replicate(&self.duck_server, duck);
+        }
+        true
+    }
+
+    /// A room with no peers and no scores left to submit.
+    fn is_empty(&self) -> bool {
+        self.connectors.is_empty() && self.scores.is_empty()
+    }
+
+    fn set_disconnect_grace(&mut self, grace: StdDuration) {
+        self.disconnect_grace = grace;
+    }
+
+    fn set_snapshot_capacity(&mut self, capacity: usize) {
+        self.snapshot_capacity = capacity;
+    }
+
+    fn set_interest(&mut self, index: usize, mask: EntityMask) {
         if let Some(conn) = self.connectors.get_mut(index) {
             conn.interest_mask = mask;
         }
     }
 
+    fn set_interest_mode(&mut self, index: usize, mode: InterestMode) {
+        if let Some(conn) = self.connectors.get_mut(index) {
+            conn.interest_mode = mode;
+        }
+    }
+
     async fn tick(&mut self) {
         self.frame = self.frame.wrapping_add(1);
         // Consume all pending input frames.
+        let mut left = Vec::new();
         for (i, conn) in self.connectors.iter_mut().enumerate() {
+            if conn.disconnect_rx.try_recv().is_ok() {
+                left.push(i);
+            }
             while let Ok(mask) = conn.interest_rx.try_recv() {
                 conn.interest_mask = mask;
             }
+            let mut frames = std::mem::take(&mut conn.pending_inputs);
             while let Ok(frame) = conn.input_rx.try_recv() {
-                if frame.frame != self.frame {
+                frames.push(frame);
+            }
+            for frame in frames {
+                // Signed distance behind the current frame, robust to wrap-around.
+                let age = self.frame.wrapping_sub(frame.frame) as i32;
+                if !(-INPUT_EARLY_FRAMES..=INPUT_LATE_FRAMES).contains(&age) {
+                    DROPPED_INPUT_FRAMES.inc();
+                    continue;
+                }
+                if age < 0 {
+                    conn.pending_inputs.push(frame);
                     continue;
                 }
                 if let Ok(shot) = postcard::from_bytes::<Shot>(&frame.data) {
+                    if !conn.shot_limiter.allow(self.frame) {
+                        RATE_LIMITED_SHOTS.inc();
+                        // Report once per window rather than per dropped shot.
+                        if conn.shot_limiter.shots == MAX_SHOTS_PER_SECOND + 1 {
+                            let player = self.player_ids.get(i).copied().unwrap_or_default();
+                            self.analytics.dispatch(Event::Error {
+                                message: format!("shot rate limit exceeded by player {player}"),
+                            });
+                        }
+                        continue;
+                    }
                     let origin = Vec3::from_array(shot.origin);
                     let direction = Vec3::from_array(shot.direction);
                     self.analytics.dispatch(Event::ShotFired);
-                    if validate_hit(
+                    if let Some(points) = validate_hit(
                         &self.duck_server,
                         origin,
                         direction,
@@ -149,13 +466,19 @@ impl Room {
                         self.analytics.dispatch(Event::Death);
                         self.analytics.dispatch(Event::CurrencyEarned);
                         if let Some(score) = self.scores.get_mut(i) {
-                            *score += 1;
+                            *score += points;
                         }
                     }
                 }
             }
         }
 
+        // Peers that said goodbye are freed now rather than lingering until
+        // their channel closes.
+        for i in left.into_iter().rev() {
+            self.remove_slot(i);
+        }
+
         // Build a snapshot of the world containing player scores.
         #[cfg(test)]
         if FORCE_SERIALIZATION_ERROR.load(Ordering::Relaxed) {
@@ -174,58 +497,78 @@ impl Room {
             data,
         };
 
-        let mut diff_mask = 0u64;
+        let every_slot = || EntityMask::from_indices(0..self.scores.len());
+        let mut diff_mask = EntityMask::none();
         let msg = if let Some(ref base) = self.last_snapshot {
             match delta_compress(base, &snapshot) {
                 Ok(delta) => {
                     if let Ok(prev_scores) = postcard::from_bytes::<Vec<u32>>(&base.data) {
-                        for (i, (prev, curr)) in
-                            prev_scores.iter().zip(&self.scores).enumerate().take(64)
-                        {
+                        for (i, (prev, curr)) in prev_scores.iter().zip(&self.scores).enumerate() {
                             if prev != curr {
-                                diff_mask |= 1 << i;
+                                diff_mask.insert(i);
                             }
                         }
                     } else {
-                        diff_mask = (1u64 << self.scores.len().min(64)) - 1;
+                        diff_mask = every_slot();
                     }
                     ServerMessage::Delta(delta)
                 }
                 Err(_) => {
-                    diff_mask = (1u64 << self.scores.len().min(64)) - 1;
+                    diff_mask = every_slot();
                     ServerMessage::Baseline(snapshot.clone())
                 }
             }
         } else {
-            diff_mask = (1u64 << self.scores.len().min(64)) - 1;
+            diff_mask = every_slot();
             ServerMessage::Baseline(snapshot.clone())
         };
 
+        // Never await a slow client here: full channels queue into the
+        // connector's backlog so the tick keeps pace for everyone else.
         let mut closed = Vec::new();
-        let diff_masks = vec![diff_mask; self.connectors.len()];
-        for (i, (conn, &diff_mask)) in self.connectors.iter().zip(diff_masks.iter()).enumerate() {
-            if conn.interest_mask & diff_mask == 0 {
+        for (i, conn) in self.connectors.iter_mut().enumerate() {
+            if conn.disconnected_at.is_some() {
                 continue;
             }
-            if let Err(err) = conn.snapshot_tx.try_send(msg.clone()) {
-                match err {
-                    TrySendError::Full(msg) => {
+            if conn.interest_mode.wants(&conn.interest_mask, &diff_mask) {
+                if conn.backlog.len() >= self.snapshot_capacity {
+                    // Queued deltas are useless once one is dropped, so
+                    // replace them all with a fresh baseline.
+                    tracing::warn!("snapshot backlog full; resyncing client with a baseline");
+                    conn.backlog.clear();
+                    conn.backlog.push_back(ServerMessage::Baseline(snapshot.clone()));
+                } else {
+                    conn.backlog.push_back(msg.clone());
+                }
+            }
+            while let Some(queued) = conn.backlog.pop_front() {
+                match conn.snapshot_tx.try_send(queued) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(queued)) => {
                         SNAPSHOT_CHANNEL_FULL.inc();
-                        tracing::warn!("snapshot channel full; falling back to send");
-                        let _ = conn.snapshot_tx.send(msg).await;
+                        tracing::warn!("snapshot channel full; queueing snapshot");
+                        conn.backlog.push_front(queued);
+                        break;
                     }
-                    TrySendError::Closed(_) => {
+                    Err(TrySendError::Closed(_)) => {
                         tracing::warn!("snapshot channel closed");
+                        conn.backlog.clear();
                         closed.push(i);
                     }
                 }
             }
         }
 
-        for i in closed.into_iter().rev() {
-            self.connectors.remove(i);
-            if i < self.scores.len() {
-                self.scores.remove(i);
+        let now = std::time::Instant::now();
+        for i in closed {
+            self.connectors[i].disconnected_at = Some(now);
+        }
+        for i in (0..self.connectors.len()).rev() {
+            let expired = self.connectors[i]
+                .disconnected_at
+                .is_some_and(|at| now.duration_since(at) >= self.disconnect_grace);
+            if expired {
+                self.remove_slot(i);
             }
         }
 
@@ -243,6 +586,37 @@ impl Room {
         }
     }
 
+    /// Mark the slot whose snapshots go to `snapshot_tx` as disconnected, so
+    /// it is evicted once the grace window passes without a resume. Returns
+    /// `false` if no connected slot uses it, e.g. because the peer has since
+    /// resumed on a new connector.
+    fn disconnect_connector(&mut self, snapshot_tx: &Sender<ServerMessage>) -> bool {
+        let conn = self.connectors.iter_mut().find(|conn| {
+            conn.disconnected_at.is_none() && conn.snapshot_tx.same_channel(snapshot_tx)
+        });
+        match conn {
+            Some(conn) => {
+                conn.disconnected_at = Some(std::time::Instant::now());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop a connector along with its score and player id.
+    fn remove_slot(&mut self, index: usize) {
+        let conn = self.connectors.remove(index);
+        self.duck_server
+            .snapshot_txs
+            .retain(|tx| !tx.same_channel(&conn.snapshot_tx));
+        if index < self.scores.len() {
+            self.scores.remove(index);
+        }
+        if index < self.player_ids.len() {
+            self.player_ids.remove(index);
+        }
+    }
+
     async fn submit_scores(&mut self) {
         let leaderboard = self.leaderboard.clone();
         let leaderboard_id = self.leaderboard_id;
@@ -279,11 +653,49 @@ impl Room {
     }
 }
 
+/// Names an independent room; peers in different rooms never share state.
+pub type RoomId = String;
+
+/// Room joined when the signaling request doesn't name one.
+pub const DEFAULT_ROOM: &str = "default";
+
+/// Why a peer couldn't join a room.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum JoinError {
+    #[error("unknown room")]
+    UnknownRoom,
+    #[error("too many rooms running")]
+    TooManyRooms,
+}
+
+/// Settings applied to every room, including ones created later.
+#[derive(Clone, Copy)]
+struct RoomSettings {
+    disconnect_grace: StdDuration,
+    snapshot_capacity: usize,
+}
+
+struct RoomEntry {
+    room: Arc<Mutex<Room>>,
+    /// Tick and round loops, aborted when the room is torn down.
+    tasks: Vec<JoinHandle<()>>,
+}
+
+type Rooms = Arc<Mutex<HashMap<RoomId, RoomEntry>>>;
+
 #[derive(Clone)]
 pub struct RoomManager {
-    room: Arc<Mutex<Room>>,
+    rooms: Rooms,
+    settings: Arc<std::sync::Mutex<RoomSettings>>,
+    leaderboard: LeaderboardService,
     registry: Arc<dyn crate::shard::ShardRegistry>,
     shard_id: String,
+    db: Option<DatabaseConnection>,
+    checkpoint_interval: StdDuration,
+    /// Room ids peers may join; anything else is refused rather than
+    /// starting a room for it.
+    known_rooms: Arc<HashSet<RoomId>>,
+    max_rooms: usize,
 }
 
 impl RoomManager {
@@ -298,48 +710,212 @@ impl RoomManager {
         shard_id: String,
         addr: String,
     ) -> Self {
-        let room = Arc::new(Mutex::new(Room::new(leaderboard)));
+        let rooms: Rooms = Arc::default();
         registry.register(crate::shard::ShardInfo::new(shard_id.clone(), addr, 0));
-        let tick_room = Arc::clone(&room);
+        let heartbeat_rooms = Arc::clone(&rooms);
+        let heartbeat_registry = Arc::clone(&registry);
+        let heartbeat_id = shard_id.clone();
         tokio::spawn(async move {
-            let mut interval = time::interval(Duration::from_secs_f64(1.0 / 60.0));
+            let mut interval = time::interval(Duration::from_secs(5));
             loop {
                 interval.tick().await;
-                tick_room.lock().await.tick().await;
+                let load = peer_count(&heartbeat_rooms).await;
+                heartbeat_registry.heartbeat(&heartbeat_id, load);
+            }
+        });
+        Self {
+            rooms,
+            settings: Arc::new(std::sync::Mutex::new(RoomSettings {
+                disconnect_grace: DEFAULT_DISCONNECT_GRACE,
+                snapshot_capacity: DEFAULT_SNAPSHOT_CAPACITY,
+            })),
+            leaderboard,
+            registry,
+            shard_id,
+            db: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            known_rooms: Arc::new(HashSet::from([DEFAULT_ROOM.to_string()])),
+            max_rooms: DEFAULT_MAX_ROOMS,
+        }
+    }
+
+    /// Checkpoint room state to `db` and restore it when a room restarts.
+    pub fn with_db(mut self, db: DatabaseConnection) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// Checkpoint rooms started from now on every `interval` instead of
+    /// [`DEFAULT_CHECKPOINT_INTERVAL`].
+    pub fn with_checkpoint_interval(mut self, interval: StdDuration) -> Self {
+        self.checkpoint_interval = interval;
+        self
+    }
+
+    /// Also accept peers into `rooms`, e.g. one per installed module.
+    /// [`DEFAULT_ROOM`] is always accepted.
+    pub fn with_rooms(mut self, rooms: impl IntoIterator<Item = RoomId>) -> Self {
+        Arc::make_mut(&mut self.known_rooms).extend(rooms);
+        self
+    }
+
+    /// Refuse joins that would start more than `max_rooms` rooms at once.
+    pub fn with_max_rooms(mut self, max_rooms: usize) -> Self {
+        self.max_rooms = max_rooms;
+        self
+    }
+
+    /// Whether peers may join `room_id`.
+    pub fn is_known_room(&self, room_id: &str) -> bool {
+        self.known_rooms.contains(room_id)
+    }
+
+    /// Start a room and its tick, round and checkpoint loops. Every room
+    /// submits to [`LEADERBOARD_ID`]: room ids come from clients, so they
+    /// must not choose where scores go.
+    async fn spawn_room(&self, room_id: &str) -> RoomEntry {
+        let mut room = Room::new(self.leaderboard.clone(), room_id, self.db.clone()).await;
+        let settings = *self.settings.lock().unwrap();
+        room.set_disconnect_grace(settings.disconnect_grace);
+        room.set_snapshot_capacity(settings.snapshot_capacity);
+        let room = Arc::new(Mutex::new(room));
+
+        let tick_room = Arc::clone(&room);
+        let tick_rooms = Arc::clone(&self.rooms);
+        let tick_id = room_id.to_string();
+        let tick = tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs_f64(1.0 / TICK_RATE as f64));
+            loop {
+                interval.tick().await;
+                let empty = {
+                    let mut room = tick_room.lock().await;
+                    room.tick().await;
+                    room.is_empty()
+                };
+                if empty && remove_if_empty(&tick_rooms, &tick_id, &tick_room).await {
+                    break;
+                }
             }
         });
         let round_room = Arc::clone(&room);
-        tokio::spawn(async move {
+        let round = tokio::spawn(async move {
             let mut interval = time::interval(Duration::from_secs(1));
             loop {
                 interval.tick().await;
                 round_room.lock().await.submit_scores().await;
             }
         });
-        let heartbeat_room = Arc::clone(&room);
-        let heartbeat_registry = Arc::clone(&registry);
-        let heartbeat_id = shard_id.clone();
-        tokio::spawn(async move {
-            let mut interval = time::interval(Duration::from_secs(5));
+        let checkpoint_room = Arc::clone(&room);
+        let checkpoint_interval = self.checkpoint_interval;
+        let checkpoint = tokio::spawn(async move {
+            let mut interval = time::interval(checkpoint_interval);
             loop {
                 interval.tick().await;
-                let load = heartbeat_room.lock().await.connectors.len();
-                heartbeat_registry.heartbeat(&heartbeat_id, load);
+                // Write without the room lock so a slow database doesn't
+                // stall the tick.
+                let pending = checkpoint_room.lock().await.take_checkpoint();
+                if let Some(pending) = pending {
+                    pending.save().await;
+                }
             }
         });
-        Self {
+        RoomEntry {
             room,
-            registry,
-            shard_id,
+            tasks: vec![tick, round, checkpoint],
+        }
+    }
+
+    /// The room named `room_id`, started on first use.
+    async fn room_or_spawn(
+        &self,
+        rooms: &mut HashMap<RoomId, RoomEntry>,
+        room_id: &str,
+    ) -> Arc<Mutex<Room>> {
+        if !rooms.contains_key(room_id) {
+            let entry = self.spawn_room(room_id).await;
+            rooms.insert(room_id.to_string(), entry);
+        }
+        Arc::clone(&rooms[room_id].room)
+    }
+
+    async fn existing_room(&self, room_id: &str) -> Option<Arc<Mutex<Room>>> {
+        let rooms = self.rooms.lock().await;
+        rooms.get(room_id).map(|entry| Arc::clone(&entry.room))
+    }
+
+    /// Join `connector` to `room_id` as `player_id`, starting the room if
+    /// needed. A player who still has a slot, e.g. one reconnecting within
+    /// the disconnect grace window, resumes it with their score. Returns the
+    /// peer's slot within the room.
+    ///
+    /// Only [known](Self::is_known_room) rooms can be joined, and a join
+    /// that would start a room beyond the room limit is refused.
+    pub async fn add_peer(
+        &self,
+        room_id: &str,
+        player_id: Option<Uuid>,
+        connector: ServerConnector,
+    ) -> Result<usize, JoinError> {
+        if !self.is_known_room(room_id) {
+            return Err(JoinError::UnknownRoom);
+        }
+        // Hold the map lock so the room can't be torn down before the peer
+        // is in it.
+        let mut rooms = self.rooms.lock().await;
+        if !rooms.contains_key(room_id) && rooms.len() >= self.max_rooms {
+            return Err(JoinError::TooManyRooms);
         }
+        let room = self.room_or_spawn(&mut rooms, room_id).await;
+        Ok(room.lock().await.join(player_id, connector))
     }
 
-    pub async fn add_peer(&self, connector: ServerConnector) -> usize {
-        self.room.lock().await.add_connector(connector)
+    pub async fn set_interest(&self, room_id: &str, index: usize, mask: EntityMask) {
+        if let Some(room) = self.existing_room(room_id).await {
+            room.lock().await.set_interest(index, mask);
+        }
+    }
+
+    pub async fn set_interest_mode(&self, room_id: &str, index: usize, mode: InterestMode) {
+        if let Some(room) = self.existing_room(room_id).await {
+            room.lock().await.set_interest_mode(index, mode);
+        }
+    }
+
+    /// Mark the peer whose snapshots go to `snapshot_tx` as disconnected,
+    /// e.g. once its peer connection has failed. The slot keeps its score for
+    /// the disconnect grace window so the player can resume it, and is then
+    /// evicted on a tick. Returns whether a connected peer was found.
+    pub async fn disconnect_peer(
+        &self,
+        room_id: &str,
+        snapshot_tx: &Sender<ServerMessage>,
+    ) -> bool {
+        match self.existing_room(room_id).await {
+            Some(room) => room.lock().await.disconnect_connector(snapshot_tx),
+            None => false,
+        }
     }
 
-    pub async fn set_interest(&self, index: usize, mask: u64) {
-        self.room.lock().await.set_interest(index, mask);
+    /// Set how long disconnected slots are kept before being evicted.
+    pub async fn set_disconnect_grace(&self, grace: StdDuration) {
+        self.settings.lock().unwrap().disconnect_grace = grace;
+        for entry in self.rooms.lock().await.values() {
+            entry.room.lock().await.set_disconnect_grace(grace);
+        }
+    }
+
+    /// Set how many snapshots are queued for a peer whose channel is full
+    /// before its backlog is replaced with a fresh baseline.
+    pub async fn set_snapshot_capacity(&self, capacity: usize) {
+        self.settings.lock().unwrap().snapshot_capacity = capacity;
+        for entry in self.rooms.lock().await.values() {
+            entry.room.lock().await.set_snapshot_capacity(capacity);
+        }
+    }
+
+    /// Number of rooms currently running.
+    pub async fn room_count(&self) -> usize {
+        self.rooms.lock().await.len()
     }
 
     pub fn select_shard(&self) -> Option<crate::shard::ShardInfo> {
@@ -347,39 +923,131 @@ impl RoomManager {
     }
 }
 
+/// Peers across all rooms, reported as the shard's load.
+async fn peer_count(rooms: &Mutex<HashMap<RoomId, RoomEntry>>) -> usize {
+    let rooms = rooms.lock().await;
+    let mut count = 0;
+    for entry in rooms.values() {
+        count += entry.room.lock().await.connectors.len();
+    }
+    count
+}
+
+/// Remove `room` and stop its loops if it has no peers left. Returns `true`
+/// once the room is gone.
+async fn remove_if_empty(
+    rooms: &Mutex<HashMap<RoomId, RoomEntry>>,
+    room_id: &str,
+    room: &Arc<Mutex<Room>>,
+) -> bool {
+    let mut rooms = rooms.lock().await;
+    match rooms.get(room_id) {
+        Some(entry) if Arc::ptr_eq(&entry.room, room) => {}
+        _ => return true,
+    }
+    // A peer may have joined since the tick that saw the room empty.
+    let pending = {
+        let mut room = room.lock().await;
+        if !room.is_empty() {
+            return false;
+        }
+        room.take_checkpoint()
+    };
+    if let Some(entry) = rooms.remove(room_id) {
+        for task in entry.tasks {
+            task.abort();
+        }
+    }
+    drop(rooms);
+    // Clear the checkpoint so a later room with this id starts fresh. The
+    // write runs on its own task: it must not hold up other rooms on the map
+    // lock, and the calling tick task may have just been aborted.
+    if let Some(pending) = pending {
+        tokio::spawn(pending.save());
+    }
+    true
+}
+
 #[cfg(test)]
 impl RoomManager {
     pub async fn push_score(&self, score: u32) {
-        let mut room = self.room.lock().await;
+        let mut rooms = self.rooms.lock().await;
+        let room = self.room_or_spawn(&mut rooms, DEFAULT_ROOM).await;
+        let mut room = room.lock().await;
         room.player_ids.push(Uuid::new_v4());
         room.scores.push(score);
     }
+
+    async fn add_handle(&self, room_id: &str, handle: ConnectorHandle) {
+        let mut rooms = self.rooms.lock().await;
+        let room = self.room_or_spawn(&mut rooms, room_id).await;
+        let mut room = room.lock().await;
+        room.connectors.push(handle);
+        room.scores.push(0);
+        room.player_ids.push(Uuid::new_v4());
+    }
+
+    async fn set_score(&self, room_id: &str, index: usize, score: u32) {
+        if let Some(room) = self.existing_room(room_id).await {
+            room.lock().await.scores[index] = score;
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_logger::{init, INIT, LOGGER};
+    use duck_hunt_server::server::DuckState;
     use tracing::level_filters::LevelFilter;
     use net::message::apply_delta;
     use serial_test::serial;
     use std::path::PathBuf;
     use std::sync::atomic::Ordering;
     use tokio::sync::mpsc;
-    use migration::{Migrator, MigratorTrait, sea_orm::Database};
+    use sea_orm::ConnectionTrait;
 
+    /// A room on an in-memory database, with checkpointing off.
     async fn test_room() -> Room {
-        let db = Database::connect("127.0.0.1:9042").await.unwrap();
-        Migrator::up(&db, None).await.unwrap();
-        let leaderboard =
-            ::leaderboard::LeaderboardService::new("127.0.0.1:9042", PathBuf::from("replays"))
-                .await
-                .unwrap();
-        Room::new(leaderboard)
+        let db = crate::test_state::memory_db().await;
+        let leaderboard = LeaderboardService::with_db(db, PathBuf::from("replays"))
+            .await
+            .unwrap();
+        Room::new(leaderboard, DEFAULT_ROOM, None).await
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn restores_checkpointed_scores() {
+        let db = crate::test_state::memory_db().await;
+        db.execute_unprepared(
+            "CREATE TABLE room_state (room_id TEXT PRIMARY KEY, player_ids TEXT NOT NULL, \
+             scores TEXT NOT NULL, frame BIGINT NOT NULL, updated_at TEXT NOT NULL)",
+        )
+        .await
+        .unwrap();
+        let leaderboard = LeaderboardService::with_db(db.clone(), PathBuf::from("replays"))
+            .await
+            .unwrap();
+        let room_id = Uuid::new_v4().to_string();
+        let players = vec![Uuid::new_v4(), Uuid::new_v4()];
+
+        let mut room = Room::new(leaderboard.clone(), &room_id, Some(db.clone())).await;
+        room.player_ids = players.clone();
+        room.scores = vec![3, 7];
+        room.frame = 42;
+        room.take_checkpoint().unwrap().save().await;
+
+        let restored = Room::new(leaderboard, &room_id, Some(db)).await;
+        assert_eq!(restored.player_ids, players);
+        assert_eq!(restored.scores, vec![3, 7]);
+        assert_eq!(restored.frame, 42);
+        // Restored players wait in disconnected slots for a resume.
+        assert_eq!(restored.connectors.len(), 2);
+        assert!(restored.connectors.iter().all(|c| c.disconnected_at.is_some()));
     }
 
     #[tokio::test]
-    #[ignore]
     #[serial]
     async fn updates_snapshot_after_delta() {
         let mut room = test_room().await;
@@ -391,8 +1059,14 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx,
             snapshot_tx,
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         room.scores.push(0);
 
@@ -424,7 +1098,6 @@ mod tests {
     }
 
     #[tokio::test]
-    #[ignore]
     #[serial]
     async fn multiplayer_scoring() {
         let mut room = test_room().await;
@@ -434,8 +1107,14 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx: rx1,
             snapshot_tx: snap_tx1,
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx: i1rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         let (tx2, rx2) = mpsc::channel(1);
         let (_i2tx, i2rx) = mpsc::channel(1);
@@ -443,8 +1122,14 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx: rx2,
             snapshot_tx: snap_tx2,
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx: i2rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         room.scores.push(0);
         room.scores.push(0);
@@ -515,7 +1200,6 @@ mod tests {
     }
 
     #[tokio::test]
-    #[ignore]
     #[serial]
     async fn selective_updates_to_multiple_clients() {
         let mut room = test_room().await;
@@ -526,8 +1210,14 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx: rx1,
             snapshot_tx: snap_tx1,
-            interest_mask: 1,
+            interest_mask: EntityMask::from(1),
+            interest_mode: InterestMode::Inclusive,
             interest_rx: i1rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         let (_tx2, rx2) = mpsc::channel(1);
         let (_i2tx, i2rx) = mpsc::channel(1);
@@ -535,8 +1225,14 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx: rx2,
             snapshot_tx: snap_tx2,
-            interest_mask: 1 << 1,
+            interest_mask: EntityMask::from(1 << 1),
+            interest_mode: InterestMode::Inclusive,
             interest_rx: i2rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         room.scores.push(0);
         room.scores.push(0);
@@ -569,7 +1265,6 @@ mod tests {
     }
 
     #[tokio::test]
-    #[ignore]
     #[serial]
     async fn serialization_error_logged_and_skips_snapshot() {
         INIT.call_once(|| init(LevelFilter::ERROR));
@@ -584,8 +1279,14 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx,
             snapshot_tx,
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
 
         room.tick().await;
@@ -602,7 +1303,6 @@ mod tests {
     }
 
     #[tokio::test]
-    #[ignore]
     #[serial]
     async fn logs_warning_when_channel_full() {
         INIT.call_once(|| init(LevelFilter::WARN));
@@ -612,12 +1312,18 @@ mod tests {
         let mut room = test_room().await;
         let (_input_tx, input_rx) = mpsc::channel(1);
         let (_interest_tx, interest_rx) = mpsc::channel(1);
-        let (snapshot_tx, snapshot_rx) = mpsc::channel(1);
+        let (snapshot_tx, mut snapshot_rx) = mpsc::channel(1);
         room.connectors.push(ConnectorHandle {
             input_rx,
             snapshot_tx,
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         room.scores.push(0);
 
@@ -626,27 +1332,33 @@ mod tests {
 
         room.scores[0] = 1;
 
-        // Drain baseline and future delta to allow fallback send to complete.
-        let drain = tokio::spawn(async move {
-            let mut rx = snapshot_rx;
-            let _ = rx.recv().await;
-            let _ = rx.recv().await;
-        });
-
-        // Second tick encounters a full channel and logs a warning.
+        // Second tick encounters a full channel, logs a warning and queues
+        // the delta instead of waiting.
         room.tick().await;
-        drain.await.unwrap();
+        {
+            let logs = LOGGER.messages.lock().unwrap();
+            assert!(
+                logs.iter().any(|msg| msg.contains("snapshot channel full")),
+                "expected warning not found: {:?}",
+                *logs
+            );
+        }
+        assert_eq!(room.connectors[0].backlog.len(), 1);
 
-        let logs = LOGGER.messages.lock().unwrap();
-        assert!(
-            logs.iter().any(|msg| msg.contains("snapshot channel full")),
-            "expected warning not found: {:?}",
-            *logs
-        );
+        // Once the client catches up the queued delta is delivered.
+        assert!(matches!(
+            snapshot_rx.try_recv().unwrap(),
+            ServerMessage::Baseline(_)
+        ));
+        room.tick().await;
+        assert!(matches!(
+            snapshot_rx.try_recv().unwrap(),
+            ServerMessage::Delta(_)
+        ));
+        assert!(room.connectors[0].backlog.is_empty());
     }
 
     #[tokio::test]
-    #[ignore]
     async fn duck_spawn_and_position_updates() {
         let mut room = test_room().await;
         let (_input_tx, input_rx) = mpsc::channel(1);
@@ -655,8 +1367,14 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx,
             snapshot_tx: snapshot_tx.clone(),
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         room.scores.push(0);
         room.duck_server.snapshot_txs.push(snapshot_tx);
@@ -692,7 +1410,6 @@ mod tests {
     }
 
     #[tokio::test]
-    #[ignore]
     async fn removes_closed_connectors() {
         let mut room = test_room().await;
         let (_input_tx, input_rx) = mpsc::channel(1);
@@ -702,19 +1419,115 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx,
             snapshot_tx,
-            interest_mask: u64::MAX,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         room.scores.push(0);
+        room.set_disconnect_grace(StdDuration::ZERO);
+
+        room.tick().await;
+
+        assert!(room.connectors.is_empty());
+        assert!(room.scores.is_empty());
+    }
+
+    #[tokio::test]
+    async fn resume_within_grace_preserves_score() {
+        let mut room = test_room().await;
+        room.set_disconnect_grace(StdDuration::from_secs(60));
+        let (_input_tx, input_rx) = mpsc::channel(1);
+        let (_interest_tx, interest_rx) = mpsc::channel(1);
+        let (snapshot_tx, snapshot_rx) = mpsc::channel(1);
+        drop(snapshot_rx);
+        room.connectors.push(ConnectorHandle {
+            input_rx,
+            snapshot_tx,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
+            interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
+        });
+        room.scores.push(5);
+        room.player_ids.push(Uuid::new_v4());
+
+        room.tick().await;
+        assert_eq!(room.connectors.len(), 1);
+        assert!(room.connectors[0].disconnected_at.is_some());
+        assert_eq!(room.scores, vec![5]);
+
+        let (_input_tx, input_rx) = mpsc::channel(1);
+        let (_interest_tx, interest_rx) = mpsc::channel(1);
+        let (snapshot_tx, mut snapshot_rx) = mpsc::channel(8);
+        assert!(room.resume_handle(
+            0,
+            ConnectorHandle {
+                input_rx,
+                snapshot_tx,
+                interest_mask: EntityMask::all(),
+                interest_mode: InterestMode::Inclusive,
+                interest_rx,
+                disconnect_rx: mpsc::channel(1).1,
+                pending_inputs: Vec::new(),
+                shot_limiter: ShotLimiter::default(),
+                backlog: VecDeque::new(),
+                disconnected_at: None,
+            },
+        ));
+        assert!(matches!(
+            snapshot_rx.try_recv().unwrap(),
+            ServerMessage::Baseline(_)
+        ));
 
         room.tick().await;
+        assert_eq!(room.connectors.len(), 1);
+        assert!(room.connectors[0].disconnected_at.is_none());
+        assert_eq!(room.scores, vec![5]);
+    }
 
+    #[tokio::test]
+    async fn grace_timeout_removes_slot() {
+        let mut room = test_room().await;
+        room.set_disconnect_grace(StdDuration::from_millis(10));
+        let (_input_tx, input_rx) = mpsc::channel(1);
+        let (_interest_tx, interest_rx) = mpsc::channel(1);
+        let (snapshot_tx, snapshot_rx) = mpsc::channel(1);
+        drop(snapshot_rx);
+        room.connectors.push(ConnectorHandle {
+            input_rx,
+            snapshot_tx,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
+            interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
+        });
+        room.scores.push(5);
+        room.player_ids.push(Uuid::new_v4());
+
+        room.tick().await;
+        assert_eq!(room.scores, vec![5]);
+
+        tokio::time::sleep(StdDuration::from_millis(20)).await;
+        room.tick().await;
         assert!(room.connectors.is_empty());
         assert!(room.scores.is_empty());
+        assert!(room.player_ids.is_empty());
     }
 
     #[tokio::test]
-    #[ignore]
     #[serial]
     async fn set_interest_updates_mask() {
         let mut room = test_room().await;
@@ -724,15 +1537,21 @@ mod tests {
         room.connectors.push(ConnectorHandle {
             input_rx,
             snapshot_tx,
-            interest_mask: 0,
+            interest_mask: EntityMask::none(),
+            interest_mode: InterestMode::Inclusive,
             interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
         });
         room.scores.push(0);
 
         room.tick().await; // baseline exists but not sent due to zero interest
         assert!(snapshot_rx.try_recv().is_err());
 
-        room.set_interest(0, 1);
+        room.set_interest(0, EntityMask::from(1));
         room.scores[0] = 1;
         room.tick().await;
         assert!(matches!(
@@ -740,4 +1559,457 @@ mod tests {
             ServerMessage::Delta(_)
         ));
     }
+
+    fn push_masked_connector(
+        room: &mut Room,
+        mask: EntityMask,
+        mode: InterestMode,
+    ) -> mpsc::Receiver<ServerMessage> {
+        let (_input_tx, input_rx) = mpsc::channel(1);
+        let (_interest_tx, interest_rx) = mpsc::channel(1);
+        let (snapshot_tx, snapshot_rx) = mpsc::channel(8);
+        room.connectors.push(ConnectorHandle {
+            input_rx,
+            snapshot_tx,
+            interest_mask: mask,
+            interest_mode: mode,
+            interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
+        });
+        snapshot_rx
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn inclusive_mode_delivers_only_listed_entities() {
+        let mut room = test_room().await;
+        let mut rx =
+            push_masked_connector(&mut room, EntityMask::from(1 << 1), InterestMode::Inclusive);
+        room.scores.extend([0, 0, 0]);
+
+        room.tick().await; // baseline
+        assert!(matches!(rx.try_recv().unwrap(), ServerMessage::Baseline(_)));
+
+        room.scores[0] = 1;
+        room.scores[2] = 1;
+        room.tick().await;
+        assert!(rx.try_recv().is_err());
+
+        room.scores[1] = 1;
+        room.tick().await;
+        assert!(matches!(rx.try_recv().unwrap(), ServerMessage::Delta(_)));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn exclusive_mode_delivers_all_but_listed_entities() {
+        let mut room = test_room().await;
+        let mut rx =
+            push_masked_connector(&mut room, EntityMask::from(1 << 1), InterestMode::Exclusive);
+        room.scores.extend([0, 0, 0]);
+
+        room.tick().await; // baseline
+        assert!(matches!(rx.try_recv().unwrap(), ServerMessage::Baseline(_)));
+
+        room.scores[1] = 1;
+        room.tick().await;
+        assert!(rx.try_recv().is_err());
+
+        room.scores[0] = 1;
+        room.tick().await;
+        assert!(matches!(rx.try_recv().unwrap(), ServerMessage::Delta(_)));
+
+        room.scores[2] = 1;
+        room.tick().await;
+        assert!(matches!(rx.try_recv().unwrap(), ServerMessage::Delta(_)));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn selective_updates_beyond_64_slots() {
+        let mut room = test_room().await;
+        let mut receivers: Vec<_> = (0..100)
+            .map(|i| {
+                push_masked_connector(
+                    &mut room,
+                    EntityMask::from_indices([i]),
+                    InterestMode::Inclusive,
+                )
+            })
+            .collect();
+        room.scores.resize(100, 0);
+
+        room.tick().await; // baseline
+        for rx in &mut receivers {
+            assert!(matches!(rx.try_recv().unwrap(), ServerMessage::Baseline(_)));
+        }
+
+        room.scores[70] = 1;
+        room.tick().await;
+        for (i, rx) in receivers.iter_mut().enumerate() {
+            if i == 70 {
+                assert!(matches!(rx.try_recv().unwrap(), ServerMessage::Delta(_)));
+            } else {
+                assert!(rx.try_recv().is_err(), "client {i} got an unrelated delta");
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn disconnect_message_frees_slot() {
+        let mut room = test_room().await;
+        let mut disconnect_txs = Vec::new();
+        let mut snapshot_rxs = Vec::new();
+        for score in [4, 9] {
+            let (_input_tx, input_rx) = mpsc::channel(1);
+            let (_interest_tx, interest_rx) = mpsc::channel(1);
+            let (disconnect_tx, disconnect_rx) = mpsc::channel(1);
+            let (snapshot_tx, snapshot_rx) = mpsc::channel(8);
+            room.connectors.push(ConnectorHandle {
+                input_rx,
+                snapshot_tx,
+                interest_mask: EntityMask::all(),
+                interest_mode: InterestMode::Inclusive,
+                interest_rx,
+                disconnect_rx,
+                pending_inputs: Vec::new(),
+                shot_limiter: ShotLimiter::default(),
+                backlog: VecDeque::new(),
+                disconnected_at: None,
+            });
+            room.scores.push(score);
+            room.player_ids.push(Uuid::new_v4());
+            disconnect_txs.push(disconnect_tx);
+            snapshot_rxs.push(snapshot_rx);
+        }
+        let remaining = room.player_ids[1];
+
+        disconnect_txs[0].send(()).await.unwrap();
+        room.tick().await;
+
+        // Removed on the same tick, even though its channel is still open.
+        assert_eq!(room.connectors.len(), 1);
+        assert_eq!(room.scores, vec![9]);
+        assert_eq!(room.player_ids, vec![remaining]);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn input_frames_outside_window_are_dropped() {
+        let mut room = test_room().await;
+        let (input_tx, input_rx) = mpsc::channel(8);
+        let (_interest_tx, interest_rx) = mpsc::channel(1);
+        let (snapshot_tx, _snapshot_rx) = mpsc::channel(8);
+        room.connectors.push(ConnectorHandle {
+            input_rx,
+            snapshot_tx,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
+            interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
+        });
+        room.scores.push(0);
+        room.tick().await;
+        room.tick().await;
+
+        let shot = postcard::to_allocvec(&Shot {
+            origin: [0.0, 0.0, 0.0],
+            direction: [0.0, 0.0, 1.0],
+            time: 0.0,
+        })
+        .unwrap();
+        let current = room.frame + 1;
+        for frame in [current, current - 1, current + 5, current + 1] {
+            input_tx
+                .send(InputFrame {
+                    frame,
+                    data: shot.clone(),
+                })
+                .await
+                .unwrap();
+        }
+        let dropped = DROPPED_INPUT_FRAMES.get();
+
+        room.tick().await;
+        assert_eq!(room.scores, vec![2]);
+        assert_eq!(DROPPED_INPUT_FRAMES.get(), dropped + 1);
+        assert_eq!(room.connectors[0].pending_inputs.len(), 1);
+
+        // The early frame is applied on its own tick.
+        room.tick().await;
+        assert_eq!(room.scores, vec![3]);
+        assert!(room.connectors[0].pending_inputs.is_empty());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn shot_flood_is_rate_limited() {
+        let mut room = test_room().await;
+        let (input_tx, input_rx) = mpsc::channel(32);
+        let (_interest_tx, interest_rx) = mpsc::channel(1);
+        let (snapshot_tx, _snapshot_rx) = mpsc::channel(8);
+        room.connectors.push(ConnectorHandle {
+            input_rx,
+            snapshot_tx,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
+            interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
+        });
+        room.scores.push(0);
+        room.player_ids.push(Uuid::new_v4());
+
+        let shot = postcard::to_allocvec(&Shot {
+            origin: [0.0, 0.0, 0.0],
+            direction: [0.0, 0.0, 1.0],
+            time: 0.0,
+        })
+        .unwrap();
+        for _ in 0..30 {
+            input_tx
+                .send(InputFrame {
+                    frame: room.frame + 1,
+                    data: shot.clone(),
+                })
+                .await
+                .unwrap();
+        }
+        let limited = RATE_LIMITED_SHOTS.get();
+
+        room.tick().await;
+        assert_eq!(room.scores, vec![MAX_SHOTS_PER_SECOND]);
+        assert_eq!(RATE_LIMITED_SHOTS.get(), limited + 20);
+    }
+
+    #[test]
+    fn shot_limiter_resets_each_second() {
+        let mut limiter = ShotLimiter::default();
+        let allowed = (0..MAX_SHOTS_PER_SECOND + 5)
+            .filter(|_| limiter.allow(1))
+            .count();
+        assert_eq!(allowed, MAX_SHOTS_PER_SECOND as usize);
+        assert!(!limiter.allow(TICK_RATE));
+        assert!(limiter.allow(1 + TICK_RATE));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn slow_consumer_does_not_block_others() {
+        let mut room = test_room().await;
+        room.set_snapshot_capacity(2);
+        let (_slow_input_tx, input_rx) = mpsc::channel(1);
+        let (_slow_interest_tx, interest_rx) = mpsc::channel(1);
+        let (snapshot_tx, mut slow_rx) = mpsc::channel(1);
+        room.connectors.push(ConnectorHandle {
+            input_rx,
+            snapshot_tx,
+            interest_mask: EntityMask::all(),
+            interest_mode: InterestMode::Inclusive,
+            interest_rx,
+            disconnect_rx: mpsc::channel(1).1,
+            pending_inputs: Vec::new(),
+            shot_limiter: ShotLimiter::default(),
+            backlog: VecDeque::new(),
+            disconnected_at: None,
+        });
+        let mut fast_rx =
+            push_masked_connector(&mut room, EntityMask::all(), InterestMode::Inclusive);
+        room.scores.extend([0, 0]);
+
+        for score in 0..5 {
+            room.scores[1] = score;
+            tokio::time::timeout(StdDuration::from_secs(1), room.tick())
+                .await
+                .expect("tick blocked on a slow consumer");
+            assert!(fast_rx.try_recv().is_ok());
+            assert!(room.connectors[0].backlog.len() <= 2);
+        }
+
+        // The slow client resyncs from a baseline rather than stale deltas.
+        assert!(matches!(slow_rx.try_recv().unwrap(), ServerMessage::Baseline(_)));
+        room.scores[1] = 10;
+        room.tick().await;
+        assert!(matches!(slow_rx.try_recv().unwrap(), ServerMessage::Baseline(_)));
+    }
+
+    fn baseline(message: ServerMessage) -> (Snapshot, Vec<u32>) {
+        match message {
+            ServerMessage::Baseline(snapshot) => {
+                let scores = postcard::from_bytes(&snapshot.data).unwrap();
+                (snapshot, scores)
+            }
+            other => panic!("expected baseline, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn peers_in_different_rooms_do_not_share_scores() {
+        let room = test_room().await;
+        let manager = RoomManager::new(room.leaderboard, "local".into(), "localhost".into());
+        let mut receivers = Vec::new();
+        for room_id in ["a", "b"] {
+            let (_input_tx, input_rx) = mpsc::channel(1);
+            let (_interest_tx, interest_rx) = mpsc::channel(1);
+            let (snapshot_tx, snapshot_rx) = mpsc::channel(64);
+            manager
+                .add_handle(
+                    room_id,
+                    ConnectorHandle {
+                        input_rx,
+                        snapshot_tx,
+                        interest_mask: EntityMask::all(),
+                        interest_mode: InterestMode::Inclusive,
+                        interest_rx,
+                        disconnect_rx: mpsc::channel(1).1,
+                        pending_inputs: Vec::new(),
+                        shot_limiter: ShotLimiter::default(),
+                        backlog: VecDeque::new(),
+                        disconnected_at: None,
+                    },
+                )
+                .await;
+            receivers.push(snapshot_rx);
+        }
+        assert_eq!(manager.room_count().await, 2);
+
+        let mut a_rx = receivers.remove(0);
+        let mut b_rx = receivers.remove(0);
+        let (a_base, a_scores) = baseline(a_rx.recv().await.unwrap());
+        assert_eq!(a_scores, vec![0]);
+        assert_eq!(baseline(b_rx.recv().await.unwrap()).1, vec![0]);
+
+        manager.set_score("a", 0, 5).await;
+        let a_delta = match a_rx.recv().await.unwrap() {
+            ServerMessage::Delta(delta) => delta,
+            other => panic!("expected delta, got {:?}", other),
+        };
+        let a_snapshot = apply_delta(&a_base, &a_delta).unwrap();
+        let a_scores: Vec<u32> = postcard::from_bytes(&a_snapshot.data).unwrap();
+        assert_eq!(a_scores, vec![5]);
+        tokio::time::sleep(StdDuration::from_millis(100)).await;
+        // Room b's only slot never changed, so it gets no deltas at all.
+        assert!(b_rx.try_recv().is_err());
+    }
+
+    /// A manager whose rooms need no database and accept peers into `a`
+    /// and `b`.
+    async fn memory_manager() -> RoomManager {
+        let db = crate::test_state::memory_db().await;
+        let leaderboard = LeaderboardService::with_db(db, PathBuf::from("replays"))
+            .await
+            .unwrap();
+        RoomManager::new(leaderboard, "local".into(), "localhost".into())
+            .with_rooms(["a".to_string(), "b".to_string()])
+    }
+
+    #[tokio::test]
+    async fn disconnected_peer_resumes_within_grace_then_is_evicted() {
+        let manager = memory_manager().await;
+        manager.set_disconnect_grace(StdDuration::from_secs(60)).await;
+        let player = Uuid::new_v4();
+        let connector = ServerConnector::new().await.unwrap();
+        let snapshot_tx = connector.snapshot_tx.clone();
+        manager.add_peer("a", Some(player), connector).await.unwrap();
+        manager.set_score("a", 0, 5).await;
+
+        assert!(manager.disconnect_peer("a", &snapshot_tx).await);
+        assert!(!manager.disconnect_peer("a", &snapshot_tx).await);
+        // The slot waits out the grace window instead of being dropped.
+        assert_eq!(peer_count(&manager.rooms).await, 1);
+
+        let connector = ServerConnector::new().await.unwrap();
+        let snapshot_tx = connector.snapshot_tx.clone();
+        assert_eq!(manager.add_peer("a", Some(player), connector).await, Ok(0));
+        {
+            let room = manager.existing_room("a").await.unwrap();
+            let room = room.lock().await;
+            assert_eq!(room.scores, vec![5]);
+            assert!(room.connectors[0].disconnected_at.is_none());
+        }
+
+        manager.set_disconnect_grace(StdDuration::ZERO).await;
+        assert!(manager.disconnect_peer("a", &snapshot_tx).await);
+        // The evicted slot empties the room, which stops its loops.
+        time::timeout(StdDuration::from_secs(1), async {
+            while manager.room_count().await > 0 {
+                time::sleep(StdDuration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("room was not torn down");
+    }
+
+    #[tokio::test]
+    async fn rejoining_player_resumes_slot_and_score() {
+        let manager = memory_manager().await;
+        let player = Uuid::new_v4();
+        let first = ServerConnector::new().await.unwrap();
+        assert_eq!(manager.add_peer("a", Some(player), first).await, Ok(0));
+        manager.set_score("a", 0, 5).await;
+
+        let second = ServerConnector::new().await.unwrap();
+        let snapshot_tx = second.snapshot_tx.clone();
+        assert_eq!(manager.add_peer("a", Some(player), second).await, Ok(0));
+        let anonymous = ServerConnector::new().await.unwrap();
+        assert_eq!(manager.add_peer("a", None, anonymous).await, Ok(1));
+
+        let room = manager.existing_room("a").await.unwrap();
+        let room = room.lock().await;
+        assert_eq!(room.scores[0], 5);
+        assert_eq!(room.player_ids[0], player);
+        assert!(room.connectors[0].snapshot_tx.same_channel(&snapshot_tx));
+        assert!(room.connectors[0].disconnected_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn joins_limited_to_known_rooms_and_room_cap() {
+        let manager = memory_manager().await.with_max_rooms(1);
+        let join = |room_id: &'static str| {
+            let manager = manager.clone();
+            async move {
+                let connector = ServerConnector::new().await.unwrap();
+                manager.add_peer(room_id, None, connector).await
+            }
+        };
+
+        assert_eq!(join("nope").await, Err(JoinError::UnknownRoom));
+        assert_eq!(join("a").await, Ok(0));
+        assert_eq!(join("b").await, Err(JoinError::TooManyRooms));
+        // Joining a room that is already running doesn't count against it.
+        assert_eq!(join("a").await, Ok(1));
+        assert_eq!(manager.room_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn checkpoint_skips_unchanged_scores() {
+        let db = crate::test_state::memory_db().await;
+        let leaderboard = LeaderboardService::with_db(db.clone(), PathBuf::from("replays"))
+            .await
+            .unwrap();
+        let mut room = Room::new(leaderboard, "a", Some(db)).await;
+        room.player_ids.push(Uuid::new_v4());
+        room.scores.push(3);
+
+        assert!(room.take_checkpoint().is_some());
+        room.frame += 15;
+        assert!(room.take_checkpoint().is_none());
+        room.scores[0] = 4;
+        let pending = room.take_checkpoint().expect("changed score is checkpointed");
+        assert_eq!(pending.state.scores, vec![4]);
+        assert_eq!(pending.state.frame, room.frame);
+    }
 }
@@ -0,0 +1,83 @@
+use chrono::Utc;
+use sea_orm::{ActiveValue::Set, DbErr, entity::prelude::*, sea_query::OnConflict};
+
+/// In-flight state of a room, checkpointed so a restart doesn't lose the
+/// scores accumulated since the last leaderboard submission.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoomState {
+    pub player_ids: Vec<Uuid>,
+    pub scores: Vec<u32>,
+    pub frame: u32,
+}
+
+pub async fn save_room_state(
+    db: &DatabaseConnection,
+    room_id: &str,
+    state: &RoomState,
+) -> Result<(), DbErr> {
+    let player_ids =
+        serde_json::to_value(&state.player_ids).map_err(|e| DbErr::Json(e.to_string()))?;
+    let scores = serde_json::to_value(&state.scores).map_err(|e| DbErr::Json(e.to_string()))?;
+    let active = room_state::ActiveModel {
+        room_id: Set(room_id.to_owned()),
+        player_ids: Set(player_ids),
+        scores: Set(scores),
+        frame: Set(state.frame as i64),
+        updated_at: Set(Utc::now().into()),
+    };
+    room_state::Entity::insert(active)
+        .on_conflict(
+            OnConflict::column(room_state::Column::RoomId)
+                .update_columns([
+                    room_state::Column::PlayerIds,
+                    room_state::Column::Scores,
+                    room_state::Column::Frame,
+                    room_state::Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn load_room_state(
+    db: &DatabaseConnection,
+    room_id: &str,
+) -> Result<Option<RoomState>, DbErr> {
+    let Some(row) = room_state::Entity::find_by_id(room_id.to_owned())
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let player_ids: Vec<Uuid> =
+        serde_json::from_value(row.player_ids).map_err(|e| DbErr::Json(e.to_string()))?;
+    let scores: Vec<u32> =
+        serde_json::from_value(row.scores).map_err(|e| DbErr::Json(e.to_string()))?;
+    Ok(Some(RoomState {
+        player_ids,
+        scores,
+        frame: row.frame as u32,
+    }))
+}
+
+mod room_state {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "room_state")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub room_id: String,
+        pub player_ids: Json,
+        pub scores: Json,
+        pub frame: i64,
+        pub updated_at: DateTimeWithTimeZone,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveValue::Set, DbErr, QueryFilter, entity::prelude::*, sea_query::Expr};
+
+/// How long a session lasts before it must be refreshed.
+pub const SESSION_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Expiry for a session created or refreshed now.
+pub fn next_expiry() -> DateTime<Utc> {
+    Utc::now() + chrono::Duration::from_std(SESSION_TTL).unwrap()
+}
+
+/// Whether `id` names a session that has not yet expired.
+pub async fn is_live(db: &DatabaseConnection, id: Uuid) -> Result<bool, DbErr> {
+    Ok(fetch_expiry(db, id)
+        .await?
+        .is_some_and(|expires_at| expires_at > Utc::now()))
+}
+
+/// Record a session for `player_id` that lasts until `expires_at`.
+pub async fn insert_session(
+    db: &DatabaseConnection,
+    id: Uuid,
+    player_id: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), DbErr> {
+    let active = sessions::ActiveModel {
+        id: Set(id),
+        player_id: Set(player_id.to_owned()),
+        created_at: Set(Utc::now().into()),
+        expires_at: Set(expires_at.into()),
+    };
+    sessions::Entity::insert(active).exec(db).await?;
+    Ok(())
+}
+
+/// When the session expires, or `None` if there is no such session.
+pub async fn fetch_expiry(
+    db: &DatabaseConnection,
+    id: Uuid,
+) -> Result<Option<DateTime<Utc>>, DbErr> {
+    let row = sessions::Entity::find_by_id(id).one(db).await?;
+    Ok(row.map(|row| row.expires_at.into()))
+}
+
+/// Move the session's expiry to `expires_at`.
+pub async fn extend_session(
+    db: &DatabaseConnection,
+    id: Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<(), DbErr> {
+    let expires_at: DateTimeWithTimeZone = expires_at.into();
+    sessions::Entity::update_many()
+        .col_expr(sessions::Column::ExpiresAt, Expr::value(expires_at))
+        .filter(sessions::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+mod sessions {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "sessions")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub id: Uuid,
+        pub player_id: String,
+        pub created_at: DateTimeWithTimeZone,
+        pub expires_at: DateTimeWithTimeZone,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
@@ -0,0 +1,141 @@
+#![cfg(test)]
+
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use analytics::Analytics;
+use purchases::{Catalog, FxRates, HmacWebhookVerifier, Sku};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
+
+use crate::{
+    AppState,
+    email::{EmailService, SmtpConfig},
+    leaderboard, room,
+};
+
+/// Builds an [`AppState`] for tests without running `setup`.
+///
+/// Every field has a default backed by in-memory services, so tests only
+/// override what they exercise.
+pub struct AppStateBuilder {
+    smtp: SmtpConfig,
+    analytics: Analytics,
+    catalog: Catalog,
+    fx_rates: HashMap<String, f64>,
+    email_salt: String,
+    db: Option<DatabaseConnection>,
+    store_webhook: Option<HmacWebhookVerifier>,
+    admin_token: Option<String>,
+}
+
+impl Default for AppStateBuilder {
+    fn default() -> Self {
+        Self {
+            smtp: SmtpConfig {
+                host: "localhost".into(),
+                from: "arena@localhost".into(),
+                port: Some(25),
+                ..Default::default()
+            },
+            analytics: Analytics::new(true, None, None, None),
+            catalog: Catalog::new(vec![Sku {
+                id: "basic".into(),
+                price_cents: 1000,
+            }]),
+            fx_rates: HashMap::new(),
+            email_salt: "salt".into(),
+            db: None,
+            store_webhook: None,
+            admin_token: None,
+        }
+    }
+}
+
+impl AppStateBuilder {
+    pub fn smtp(mut self, smtp: SmtpConfig) -> Self {
+        self.smtp = smtp;
+        self
+    }
+
+    pub fn catalog(mut self, catalog: Catalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    pub fn fx_rates(mut self, fx_rates: HashMap<String, f64>) -> Self {
+        self.fx_rates = fx_rates;
+        self
+    }
+
+    /// Use `db` instead of an empty in-memory database, e.g. one with tables.
+    pub fn db(mut self, db: DatabaseConnection) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    pub fn store_webhook(mut self, verifier: HmacWebhookVerifier) -> Self {
+        self.store_webhook = Some(verifier);
+        self
+    }
+
+    pub fn admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    pub async fn build(self) -> AppState {
+        let db = match self.db {
+            Some(db) => db,
+            None => memory_db().await,
+        };
+        let email = Arc::new(EmailService::new(self.smtp.clone()).unwrap());
+        let leaderboard =
+            ::leaderboard::LeaderboardService::with_db(db.clone(), PathBuf::from("replays"))
+                .await
+                .unwrap()
+                .with_replay_trimmer(leaderboard::trim_replay_events);
+        let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
+        AppState {
+            email,
+            rooms,
+            smtp: self.smtp,
+            analytics: self.analytics,
+            leaderboard,
+            catalog: self.catalog,
+            fx_rates: FxRates::new(self.fx_rates),
+            db,
+            email_salt: self.email_salt,
+            store_webhook: self.store_webhook,
+            admin_token: self.admin_token,
+        }
+    }
+}
+
+impl AppState {
+    pub fn builder() -> AppStateBuilder {
+        AppStateBuilder::default()
+    }
+}
+
+/// An in-memory SQLite database without tables, so every query fails like an
+/// unreachable database would unless a test creates what it needs.
+pub async fn memory_db() -> DatabaseConnection {
+    Database::connect("sqlite::memory:").await.unwrap()
+}
+
+/// An in-memory SQLite database with the leaderboard's `runs` and `scores`
+/// tables.
+pub async fn leaderboard_db() -> DatabaseConnection {
+    let db = memory_db().await;
+    db.execute_unprepared(
+        "CREATE TABLE runs (id BLOB PRIMARY KEY, leaderboard BLOB NOT NULL, \
+         player_id TEXT NOT NULL, replay_path TEXT NOT NULL, created_at TEXT NOT NULL, \
+         flagged BOOLEAN NOT NULL, replay_index INTEGER NOT NULL, \
+         trimmed_points INTEGER NOT NULL); \
+         CREATE TABLE scores (id BLOB PRIMARY KEY, run BLOB NOT NULL, leaderboard BLOB NOT NULL, \
+         player_id TEXT NOT NULL, points INTEGER NOT NULL, created_at TEXT NOT NULL, \
+         verified BOOLEAN NOT NULL);",
+    )
+    .await
+    .unwrap();
+    db
+}
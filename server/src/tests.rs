@@ -1,12 +1,10 @@
 use super::*;
-use analytics::{Analytics, Event};
 use axum::body::Body;
 use axum::extract::{Extension, Json, Query, State};
 use axum::http::Request;
 use futures_util::{SinkExt, StreamExt};
 use serial_test::serial;
 use std::{collections::HashMap, env};
-use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase};
 use tokio_tungstenite::tungstenite::Message;
 use tower::ServiceExt;
 use webrtc::api::APIBuilder;
@@ -19,17 +17,6 @@ use tracing::level_filters::LevelFilter;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-async fn leaderboard_service() -> (::leaderboard::LeaderboardService, DatabaseConnection) {
-    let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
-    let service = ::leaderboard::LeaderboardService::with_db(
-        db.clone(),
-        PathBuf::from("replays"),
-    )
-    .await
-    .unwrap();
-    (service, db)
-}
-
 fn smtp_cfg() -> SmtpConfig {
     SmtpConfig {
         host: "localhost".into(),
@@ -39,19 +26,14 @@ fn smtp_cfg() -> SmtpConfig {
     }
 }
 
-#[tokio::test]
-#[ignore]
-async fn setup_succeeds_without_env_vars() {
-    unsafe {
-        env::remove_var("DATABASE_URL");
-    }
-
-    let cfg = ResolvedConfig {
+fn resolved_config() -> ResolvedConfig {
+    ResolvedConfig {
         bind_addr: "127.0.0.1:3000".parse().unwrap(),
         public_base_url: "http://localhost".into(),
         signaling_ws_url: "ws://127.0.0.1".into(),
         db_url: "127.0.0.1:9042".into(),
         db_max_conns: 1,
+        max_rooms: room::DEFAULT_MAX_ROOMS,
         migrate_on_start: false,
         enable_coop_coep: false,
         static_dir: PathBuf::from("static"),
@@ -60,6 +42,7 @@ async fn setup_succeeds_without_env_vars() {
         enable_sw: false,
         csp: None,
         ice_servers: Vec::new(),
+        turn: None,
         feature_flags: HashMap::new(),
         metrics_addr: None,
         analytics_enabled: false,
@@ -68,11 +51,33 @@ async fn setup_succeeds_without_env_vars() {
         posthog_url: None,
         analytics_otlp_endpoint: None,
         email_salt: "salt".into(),
-    };
-    assert!(setup(&cfg, smtp_cfg(), None).await.is_ok());
+        room_disconnect_grace: room::DEFAULT_DISCONNECT_GRACE,
+        room_checkpoint_interval: room::DEFAULT_CHECKPOINT_INTERVAL,
+        otp_purge_interval: jobs::DEFAULT_OTP_PURGE_INTERVAL,
+        fx_rates: HashMap::new(),
+        catalog: default_catalog(),
+    }
+}
+
+#[tokio::test]
+#[ignore]
+async fn setup_succeeds_without_env_vars() {
+    unsafe {
+        env::remove_var("DATABASE_URL");
+    }
+
+    let cfg = resolved_config();
+    assert!(setup(&cfg, smtp_cfg(), None, None, None).await.is_ok());
+}
+
+/// Parse `args` after the SMTP flags every command line needs.
+fn cli_with_smtp<'a>(args: impl IntoIterator<Item = &'a str>) -> Cli {
+    let smtp = ["prog", "--smtp-host", "localhost", "--smtp-from", "arena@localhost"];
+    Cli::try_parse_from(smtp.into_iter().chain(args)).unwrap()
 }
 
 #[test]
+#[serial]
 fn cli_overrides_env() {
     unsafe {
         env::set_var("ARENA_SMTP_HOST", "envhost");
@@ -88,6 +93,7 @@ fn cli_overrides_env() {
 }
 
 #[test]
+#[serial]
 fn env_used_when_no_cli() {
     unsafe {
         env::set_var("ARENA_SMTP_HOST", "envhost");
@@ -105,13 +111,14 @@ fn env_used_when_no_cli() {
 }
 
 #[test]
+#[serial]
 fn missing_bind_addr_errors() {
     unsafe {
         env::remove_var("ARENA_BIND_ADDR");
         env::set_var("ARENA_RTC_ICE_SERVERS_JSON", "[]");
         env::set_var("ARENA_EMAIL_SALT", "salt");
     }
-    let cli = Cli::try_parse_from(["prog"]).unwrap();
+    let cli = cli_with_smtp([]);
     assert!(cli.config.clone().resolve().is_err());
     unsafe {
         env::remove_var("ARENA_RTC_ICE_SERVERS_JSON");
@@ -120,6 +127,95 @@ fn missing_bind_addr_errors() {
 }
 
 #[test]
+#[serial]
+fn resolve_reports_every_problem() {
+    unsafe {
+        env::remove_var("ARENA_BIND_ADDR");
+        env::remove_var("ARENA_DB_URL");
+        env::remove_var("ARENA_EMAIL_SALT");
+        env::set_var("ARENA_RTC_ICE_SERVERS_JSON", "not json");
+    }
+    let cli = cli_with_smtp([]);
+    let err = cli.config.resolve().unwrap_err();
+    let problems = &err.downcast_ref::<ConfigError>().unwrap().problems;
+    assert!(problems.len() >= 4);
+    let message = err.to_string();
+    for var in [
+        "ARENA_BIND_ADDR",
+        "ARENA_DB_URL",
+        "ARENA_EMAIL_SALT",
+        "ARENA_RTC_ICE_SERVERS_JSON",
+    ] {
+        assert!(message.contains(var), "{var} missing from: {message}");
+    }
+    unsafe {
+        env::remove_var("ARENA_RTC_ICE_SERVERS_JSON");
+    }
+}
+
+fn ice_server(json: &str) -> IceServerConfig {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn ice_server_validation_accepts_stun_and_turn_with_credentials() {
+    ice_server(r#"{"urls":"stun:stun.example.com:3478"}"#)
+        .validate()
+        .unwrap();
+    ice_server(
+        r#"{"urls":["turn:turn.example.com","turns:turn.example.com:5349"],
+            "username":"arena","credential":"secret"}"#,
+    )
+    .validate()
+    .unwrap();
+}
+
+#[test]
+fn ice_server_validation_rejects_empty_urls() {
+    assert_eq!(
+        ice_server(r#"{"urls":[]}"#).validate(),
+        Err(IceServerError::NoUrls)
+    );
+}
+
+#[test]
+fn ice_server_validation_rejects_turn_without_credentials() {
+    assert_eq!(
+        ice_server(r#"{"urls":"turn:turn.example.com","username":"arena"}"#).validate(),
+        Err(IceServerError::MissingTurnCredentials(
+            "turn:turn.example.com".into()
+        ))
+    );
+    assert_eq!(
+        ice_server(r#"{"urls":"http://turn.example.com"}"#).validate(),
+        Err(IceServerError::UnsupportedScheme(
+            "http://turn.example.com".into()
+        ))
+    );
+}
+
+#[test]
+#[serial]
+fn resolve_rejects_invalid_ice_servers() {
+    unsafe {
+        env::set_var(
+            "ARENA_RTC_ICE_SERVERS_JSON",
+            r#"[{"urls":"stun:ok"},{"urls":"turns:relay"}]"#,
+        );
+    }
+    let cli = cli_with_smtp([]);
+    let message = cli.config.resolve().unwrap_err().to_string();
+    assert!(
+        message.contains("ARENA_RTC_ICE_SERVERS_JSON: server 1: TURN url"),
+        "{message}"
+    );
+    unsafe {
+        env::remove_var("ARENA_RTC_ICE_SERVERS_JSON");
+    }
+}
+
+#[test]
+#[serial]
 fn invalid_starttls_cli_value_errors() {
     unsafe {
         env::set_var("ARENA_SMTP_HOST", "envhost");
@@ -133,6 +229,7 @@ fn invalid_starttls_cli_value_errors() {
 }
 
 #[test]
+#[serial]
 fn invalid_starttls_env_value_errors() {
     unsafe {
         env::set_var("ARENA_SMTP_HOST", "envhost");
@@ -148,6 +245,7 @@ fn invalid_starttls_env_value_errors() {
 }
 
 #[tokio::test]
+#[serial]
 async fn config_json_respects_cli_overrides() {
     unsafe {
         env::set_var("ARENA_BIND_ADDR", "127.0.0.1:3000");
@@ -161,8 +259,7 @@ async fn config_json_respects_cli_overrides() {
         env::set_var("ARENA_RTC_ICE_SERVERS_JSON", "[]");
         env::set_var("ARENA_EMAIL_SALT", "salt");
     }
-    let cli = Cli::try_parse_from([
-        "prog",
+    let cli = cli_with_smtp([
         "--posthog-key",
         "cli_key",
         "--posthog-url",
@@ -171,8 +268,7 @@ async fn config_json_respects_cli_overrides() {
         "--analytics-local",
         "--analytics-otlp-endpoint",
         "127.0.0.1:4317",
-    ])
-    .unwrap();
+    ]);
     let mut cfg = cli.config.resolve().unwrap();
     cfg.analytics_enabled =
         cli.analytics_local || cli.posthog_key.is_some() || cli.analytics_otlp_endpoint.is_some();
@@ -180,7 +276,7 @@ async fn config_json_respects_cli_overrides() {
     cfg.analytics_local = cli.analytics_local;
     cfg.posthog_url = cli.posthog_url.clone();
     cfg.analytics_otlp_endpoint = cli.analytics_otlp_endpoint;
-    let Json(resp) = config::get_config(Extension(cfg)).await;
+    let Json(resp) = config::get_config(Extension(cfg), HeaderMap::new()).await;
     assert!(resp.analytics_enabled);
     assert!(resp.analytics_opt_out);
     assert!(resp.analytics_local);
@@ -199,25 +295,82 @@ async fn config_json_respects_cli_overrides() {
     }
 }
 
+#[tokio::test]
+async fn config_json_serves_ice_servers_without_static_turn_credentials() {
+    let cfg = ResolvedConfig {
+        ice_servers: vec![
+            ice_server(r#"{"urls":"stun:stun.example.com"}"#),
+            ice_server(r#"{"urls":"turn:turn.example.com","username":"u","credential":"c"}"#),
+            ice_server(
+                r#"{"urls":["stun:mixed.example.com","turns:mixed.example.com"],
+                    "username":"u","credential":"c"}"#,
+            ),
+        ],
+        ..resolved_config()
+    };
+    let app = Router::new()
+        .route("/config.json", get(config::get_config))
+        .layer(Extension(cfg));
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/config.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        json["ice_servers"],
+        serde_json::json!([
+            {"urls": ["stun:stun.example.com"], "username": null, "credential": null},
+            {"urls": ["stun:mixed.example.com"], "username": null, "credential": null},
+        ])
+    );
+    assert!(!String::from_utf8_lossy(&body).contains(r#""c""#));
+}
+
+#[tokio::test]
+async fn config_json_generates_turn_credentials_per_session() {
+    let cfg = ResolvedConfig {
+        ice_servers: vec![
+            ice_server(r#"{"urls":"stun:stun.example.com"}"#),
+            ice_server(r#"{"urls":"turns:turn.example.com"}"#),
+        ],
+        turn: Some(turn::TurnSecret::new(
+            "north",
+            std::time::Duration::from_secs(600),
+        )),
+        ..resolved_config()
+    };
+    let session = uuid::Uuid::new_v4();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::COOKIE,
+        HeaderValue::from_str(&format!("session={session}")).unwrap(),
+    );
+
+    let Json(resp) = config::get_config(Extension(cfg), headers).await;
+    let expires_at = resp.ice_servers_expires_at.unwrap();
+    assert!(expires_at > chrono::Utc::now());
+    assert_eq!(resp.ice_servers[0].username, None);
+    let turn = &resp.ice_servers[1];
+    assert_eq!(
+        turn.username.as_deref(),
+        Some(format!("{}:{session}", expires_at.timestamp()).as_str())
+    );
+    assert!(turn.credential.as_deref().is_some_and(|c| !c.is_empty()));
+}
+
 #[tokio::test]
 async fn websocket_signaling_completes_handshake() {
-    let cfg = smtp_cfg();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg,
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().build().await);
 
     let app = Router::new()
         .route("/signal", get(signal_ws_handler))
@@ -253,29 +406,95 @@ async fn websocket_signaling_completes_handshake() {
     assert!(pc.remote_description().await.is_some());
 }
 
+#[tokio::test]
+async fn websocket_signaling_rejects_unknown_room() {
+    let state = Arc::new(AppState::builder().build().await);
+    let app = Router::new()
+        .route("/signal", get(signal_ws_handler))
+        .with_state(state.clone());
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let url = format!("ws://{addr}/signal?room=00000000-0000-0000-0000-000000000001");
+    match tokio_tungstenite::connect_async(url).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        other => panic!("expected 400, got {other:?}"),
+    }
+    assert_eq!(state.rooms.room_count().await, 0);
+}
+
+#[tokio::test]
+async fn websocket_signaling_accepts_trickled_candidates() {
+    let state = Arc::new(AppState::builder().build().await);
+
+    let app = Router::new()
+        .route("/signal", get(signal_ws_handler))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+
+    let mut m = MediaEngine::default();
+    m.register_default_codecs().unwrap();
+    let api = APIBuilder::new().with_media_engine(m).build();
+    let pc = api
+        .new_peer_connection(RTCConfiguration::default())
+        .await
+        .unwrap();
+    let _dc = pc.create_data_channel("data", None).await.unwrap();
+    let offer = pc.create_offer(None).await.unwrap();
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/signal", addr))
+        .await
+        .unwrap();
+    ws.send(Message::Text(offer.sdp)).await.unwrap();
+    let answer = ws.next().await.expect("no answer").unwrap();
+    assert!(answer.into_text().unwrap().starts_with("v=0"));
+
+    let candidate = serde_json::json!({
+        "type": "candidate",
+        "candidate": "candidate:1 1 udp 2130706431 192.0.2.1 50000 typ host",
+        "sdpMid": "0",
+        "sdpMLineIndex": 0,
+    });
+    ws.send(Message::Text(candidate.to_string())).await.unwrap();
+    let bogus = serde_json::json!({ "type": "candidate", "candidate": "bogus" });
+    ws.send(Message::Text(bogus.to_string())).await.unwrap();
+
+    // The server handles messages in order, so the first error must be for
+    // the bogus candidate; its own trickled candidates may arrive in between.
+    let error = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let msg = ws.next().await.expect("socket closed").unwrap();
+            let json: serde_json::Value = serde_json::from_str(&msg.into_text().unwrap()).unwrap();
+            if json["type"] == "candidate" {
+                continue;
+            }
+            break json;
+        }
+    })
+    .await
+    .expect("no error reply");
+    assert_eq!(error["error"], "invalid ICE candidate");
+    assert_eq!(error["candidate"], "bogus");
+}
+
 #[tokio::test]
 #[serial]
 async fn websocket_signaling_invalid_sdp_logs_and_closes() {
     INIT.call_once(|| init(LevelFilter::WARN));
     LOGGER.messages.lock().unwrap().clear();
 
-    let cfg = smtp_cfg();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg,
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().build().await);
 
     let app = Router::new()
         .route("/signal", get(signal_ws_handler))
@@ -309,23 +528,7 @@ async fn websocket_signaling_unexpected_binary_logs_and_closes() {
     INIT.call_once(|| init(LevelFilter::WARN));
     LOGGER.messages.lock().unwrap().clear();
 
-    let cfg = smtp_cfg();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg,
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().build().await);
 
     let app = Router::new()
         .route("/signal", get(signal_ws_handler))
@@ -359,23 +562,7 @@ async fn websocket_logs_unexpected_messages_and_closes() {
     INIT.call_once(|| init(LevelFilter::WARN));
     LOGGER.messages.lock().unwrap().clear();
 
-    let cfg = smtp_cfg();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg,
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().build().await);
 
     let app = Router::new()
         .route("/ws", get(ws_handler))
@@ -404,22 +591,7 @@ async fn websocket_logs_unexpected_messages_and_closes() {
 async fn mail_test_defaults_to_from_address() {
     let mut cfg = smtp_cfg();
     cfg.from = "default@example.com".into();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg.clone(),
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().smtp(cfg).build().await);
 
     assert_eq!(
         mail_test_handler(State(state.clone()), None, None).await.0,
@@ -436,22 +608,7 @@ async fn mail_test_defaults_to_from_address() {
 async fn mail_test_accepts_user_address_query() {
     let mut cfg = smtp_cfg();
     cfg.from = "query@example.com".into();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg.clone(),
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().smtp(cfg).build().await);
 
     assert_eq!(
         mail_test_handler(State(state.clone()), None, None).await.0,
@@ -475,22 +632,7 @@ async fn mail_test_accepts_user_address_query() {
 async fn mail_test_accepts_user_address_body() {
     let mut cfg = smtp_cfg();
     cfg.from = "body@example.com".into();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg.clone(),
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().smtp(cfg).build().await);
 
     assert_eq!(
         mail_test_handler(State(state.clone()), None, None).await.0,
@@ -513,22 +655,7 @@ async fn mail_test_accepts_user_address_body() {
 async fn mail_config_redacts_password() {
     let mut cfg = smtp_cfg();
     cfg.pass = Some("secret".into());
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg.clone(),
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().smtp(cfg).build().await);
 
     let Json(redacted) = mail_config_handler(State(state)).await;
     assert_eq!(redacted.pass, Some("***".into()));
@@ -537,33 +664,17 @@ async fn mail_config_redacts_password() {
 
 #[tokio::test]
 async fn admin_mail_config_route() {
-    let cfg = smtp_cfg();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    let state = Arc::new(AppState {
-        email,
-        rooms,
-        smtp: cfg,
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let state = Arc::new(AppState::builder().admin_token("hunter2").build().await);
 
     let app = Router::new()
-        .route("/admin/mail/config", get(mail_config_handler))
-        .route("/admin/mail/test", post(mail_test_handler))
+        .nest("/admin", admin_routes(state.clone()))
         .with_state(state);
 
     let response = app
         .oneshot(
             Request::builder()
                 .uri("/admin/mail/config")
+                .header(axum::http::header::AUTHORIZATION, "Bearer hunter2")
                 .body(Body::empty())
                 .unwrap(),
         )
@@ -573,32 +684,53 @@ async fn admin_mail_config_route() {
     assert_eq!(response.status(), StatusCode::OK);
 }
 
+#[tokio::test]
+async fn admin_routes_reject_missing_or_wrong_token() {
+    for (configured, presented) in [
+        (Some("hunter2"), None),
+        (Some("hunter2"), Some("Bearer hunter3")),
+        (Some("hunter2"), Some("hunter2")),
+        (None, Some("Bearer ")),
+    ] {
+        let mut builder = AppState::builder();
+        if let Some(token) = configured {
+            builder = builder.admin_token(token);
+        }
+        let state = Arc::new(builder.build().await);
+        let app = Router::new()
+            .nest("/admin", admin_routes(state.clone()))
+            .with_state(state);
+
+        for (method, uri) in [("GET", "/admin/mail/config"), ("POST", "/admin/mail/test")] {
+            let mut request = Request::builder().method(method).uri(uri);
+            if let Some(presented) = presented {
+                request = request.header(axum::http::header::AUTHORIZATION, presented);
+            }
+            let response = app
+                .clone()
+                .oneshot(request.body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::UNAUTHORIZED,
+                "{method} {uri} with {presented:?}"
+            );
+        }
+    }
+}
+
 #[tokio::test]
 async fn round_scores_appear_in_leaderboard() {
     use ::leaderboard::models::Score;
     use std::time::Duration;
 
-    let cfg = smtp_cfg();
-    let email = Arc::new(EmailService::new(cfg.clone()).unwrap());
-    let (leaderboard, db) = leaderboard_service().await;
-    let rooms = room::RoomManager::new(leaderboard.clone(), "local".into(), "localhost".into());
-    rooms.push_score(7).await;
-    let state = Arc::new(AppState {
-        email,
-        rooms: rooms.clone(),
-        smtp: cfg,
-        analytics: Analytics::new(true, None, None, None),
-        leaderboard: leaderboard.clone(),
-        catalog: Catalog::new(vec![Sku {
-            id: "basic".into(),
-            price_cents: 1000,
-        }]),
-        db,
-        email_salt: "salt".into(),
-    });
+    let db = crate::test_state::leaderboard_db().await;
+    let state = Arc::new(AppState::builder().db(db).build().await);
+    state.rooms.push_score(7).await;
 
     let app = Router::new()
-        .nest("/leaderboard", crate::leaderboard::routes())
+        .nest("/leaderboard", crate::leaderboard::routes(state.clone()))
         .with_state(state);
 
     tokio::time::sleep(Duration::from_secs(2)).await;
@@ -618,3 +750,614 @@ async fn round_scores_appear_in_leaderboard() {
     let scores: Vec<Score> = serde_json::from_slice(&body).unwrap();
     assert!(scores.iter().any(|s| s.points == 7));
 }
+
+#[tokio::test]
+async fn builder_state_serves_store_catalog() {
+    let catalog = Catalog::new(vec![Sku {
+        id: "deluxe".into(),
+        price_cents: 500,
+    }]);
+    let state = Arc::new(AppState::builder().catalog(catalog).build().await);
+    let app = Router::new()
+        .route("/store", get(store_handler))
+        .with_state(state);
+
+    let response = app
+        .oneshot(Request::builder().uri("/store").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["items"][0]["id"], "deluxe");
+}
+
+async fn claim(state: Arc<AppState>, sku: &str) -> StatusCode {
+    let app = Router::new()
+        .route("/store/claim", post(store_claim_handler))
+        .with_state(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/store/claim")
+                .header("X-Session", UserId::new_v4().to_string())
+                .header("Content-Type", "application/json")
+                .body(Body::from(format!(r#"{{"sku":"{sku}"}}"#)))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    response.status()
+}
+
+#[tokio::test]
+async fn store_claim_rejects_unknown_sku() {
+    let state = Arc::new(AppState::builder().build().await);
+    assert_eq!(
+        claim(state.clone(), "free_everything").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert!(state.analytics.events().is_empty());
+}
+
+#[tokio::test]
+async fn store_claim_reports_database_failure() {
+    // No entitlements table, so the grant itself fails.
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    let state = Arc::new(AppState::builder().db(db).build().await);
+    assert_eq!(
+        claim(state.clone(), "basic").await,
+        StatusCode::INTERNAL_SERVER_ERROR
+    );
+    assert!(state.analytics.events().is_empty());
+}
+
+#[tokio::test]
+async fn store_checkout_returns_session_and_reports() {
+    let state = Arc::new(AppState::builder().db(purchases_db().await).build().await);
+    let app = Router::new()
+        .route("/store/checkout", post(store_checkout_handler))
+        .with_state(state.clone());
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/store/checkout")
+                .header("X-Session", UserId::new_v4().to_string())
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"sku":"basic"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["id"].as_str().is_some_and(|id| !id.is_empty()));
+    assert!(json.get("url").is_none());
+    assert_eq!(state.analytics.events(), vec![Event::PurchaseInitiated]);
+}
+
+const WEBHOOK_USER: &str = "0b6f8a4e-3c1d-4f55-9a2e-7d1c9b0e5f21";
+
+fn webhook_body(session_id: &str, user: &str) -> String {
+    format!(r#"{{"session_id":"{session_id}","user":"{user}","sku":"basic"}}"#)
+}
+
+fn webhook_request(body: String, timestamp: i64, signature: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/store/webhook")
+        .header(STORE_WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+        .header(STORE_WEBHOOK_SIGNATURE_HEADER, signature)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+/// `body` signed by `verifier` as of `age` ago.
+fn signed_webhook(
+    verifier: &HmacWebhookVerifier,
+    body: String,
+    age: chrono::Duration,
+) -> Request<Body> {
+    let timestamp = (chrono::Utc::now() - age).timestamp();
+    let signature = verifier.sign_timestamped(&timestamp.to_string(), body.as_bytes());
+    webhook_request(body, timestamp, &signature)
+}
+
+fn webhook_verifier() -> HmacWebhookVerifier {
+    HmacWebhookVerifier::new(STORE_WEBHOOK_SIGNATURE_HEADER, "whsec_test")
+}
+
+async fn purchases_db() -> DatabaseConnection {
+    use sea_orm::ConnectionTrait;
+
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared(
+        "CREATE TABLE purchases (id BLOB PRIMARY KEY, player_id TEXT NOT NULL, \
+         sku TEXT NOT NULL, created_at TEXT NOT NULL, price_cents INTEGER NOT NULL, \
+         idempotency_key TEXT UNIQUE); \
+         CREATE TABLE entitlements (player_id TEXT NOT NULL, sku TEXT NOT NULL, \
+         granted_at TEXT NOT NULL, expires_at TEXT, PRIMARY KEY (player_id, sku)); \
+         CREATE TABLE checkout_sessions (id TEXT PRIMARY KEY, player_id TEXT NOT NULL, \
+         sku TEXT NOT NULL, expires_at TEXT NOT NULL);",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+async fn webhook_state() -> Arc<AppState> {
+    Arc::new(
+        AppState::builder()
+            .db(purchases_db().await)
+            .store_webhook(webhook_verifier())
+            .build()
+            .await,
+    )
+}
+
+#[tokio::test]
+async fn store_webhook_grants_verified_purchase() {
+    let state = webhook_state().await;
+    let app = Router::new()
+        .route("/store/webhook", post(store_webhook_handler))
+        .with_state(state.clone());
+
+    let user = UserId::parse_str(WEBHOOK_USER).unwrap();
+    let session = purchases::initiate_purchase(&state.db, user, "basic")
+        .await
+        .unwrap();
+    let body = webhook_body(&session.id, WEBHOOK_USER);
+    let request = signed_webhook(&webhook_verifier(), body, chrono::Duration::zero());
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        state.analytics.events(),
+        vec![Event::PurchaseSucceeded, Event::EntitlementGranted]
+    );
+    let entitlements = purchases::list_entitlements(&state.db, WEBHOOK_USER)
+        .await
+        .unwrap();
+    assert_eq!(entitlements, vec!["basic".to_string()]);
+}
+
+#[tokio::test]
+async fn store_webhook_rejects_bad_signature() {
+    let state = Arc::new(
+        AppState::builder()
+            .store_webhook(webhook_verifier())
+            .build()
+            .await,
+    );
+    let app = Router::new()
+        .route("/store/webhook", post(store_webhook_handler))
+        .with_state(state.clone());
+
+    let body = webhook_body("cs_1", WEBHOOK_USER);
+    let request = webhook_request(body, chrono::Utc::now().timestamp(), &"0".repeat(64));
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(state.analytics.events().is_empty());
+}
+
+#[tokio::test]
+async fn store_webhook_rejects_stale_timestamp() {
+    let state = webhook_state().await;
+    let app = Router::new()
+        .route("/store/webhook", post(store_webhook_handler))
+        .with_state(state.clone());
+
+    let body = webhook_body("cs_1", WEBHOOK_USER);
+    let request = signed_webhook(&webhook_verifier(), body, chrono::Duration::hours(1));
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(state.analytics.events().is_empty());
+    assert_eq!(purchases::total_revenue_cents(&state.db).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn store_webhook_rejects_unknown_or_mismatched_session() {
+    let state = webhook_state().await;
+    let app = Router::new()
+        .route("/store/webhook", post(store_webhook_handler))
+        .with_state(state.clone());
+
+    let opener = UserId::new_v4();
+    let session = purchases::initiate_purchase(&state.db, opener, "basic")
+        .await
+        .unwrap();
+    for body in [
+        webhook_body("cs_unknown", WEBHOOK_USER),
+        webhook_body(&session.id, WEBHOOK_USER),
+    ] {
+        let request = signed_webhook(&webhook_verifier(), body, chrono::Duration::zero());
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    assert!(state.analytics.events().is_empty());
+    assert_eq!(purchases::total_revenue_cents(&state.db).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn store_webhook_records_each_buyer_of_a_sku() {
+    let state = webhook_state().await;
+    let app = Router::new()
+        .route("/store/checkout", post(store_checkout_handler))
+        .route("/store/webhook", post(store_webhook_handler))
+        .with_state(state.clone());
+
+    for user in [UserId::new_v4(), UserId::new_v4()] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/store/checkout")
+                    .header("X-Session", user.to_string())
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(r#"{"sku":"basic"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let session: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let body = webhook_body(session["id"].as_str().unwrap(), &user.to_string());
+        let request = signed_webhook(&webhook_verifier(), body, chrono::Duration::zero());
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert_eq!(purchases::total_revenue_cents(&state.db).await.unwrap(), 2000);
+}
+
+async fn store_json(state: AppState, uri: &str) -> serde_json::Value {
+    let app = Router::new()
+        .route("/store", get(store_handler))
+        .with_state(Arc::new(state));
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn store_serves_catalog_loaded_from_file() {
+    let path = env::temp_dir().join(format!("arena-catalog-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        r#"[{"id":"basic","price_cents":1000},{"id":"deluxe","price_cents":2500}]"#,
+    )
+    .unwrap();
+    let catalog = Catalog::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let state = AppState::builder().catalog(catalog).build().await;
+    let json = store_json(state, "/store").await;
+    let ids: Vec<&str> = json["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["basic", "deluxe"]);
+}
+
+#[tokio::test]
+async fn store_converts_prices_to_known_currency() {
+    let state = AppState::builder()
+        .fx_rates(HashMap::from([("EUR".to_string(), 0.92)]))
+        .build()
+        .await;
+    let json = store_json(state, "/store?currency=eur").await;
+    assert_eq!(json["items"][0]["currency"], "EUR");
+    assert_eq!(json["items"][0]["price_cents"], 1000);
+    assert_eq!(json["items"][0]["converted_price_cents"], 920);
+}
+
+#[tokio::test]
+async fn store_falls_back_to_base_for_unknown_currency() {
+    let state = AppState::builder().build().await;
+    let json = store_json(state, "/store?currency=XYZ").await;
+    assert_eq!(json["items"][0]["currency"], "USD");
+    assert_eq!(json["items"][0]["price_cents"], 1000);
+    assert_eq!(json["items"][0]["converted_price_cents"], 1000);
+}
+
+#[test]
+fn session_cookie_is_parsed_among_others() {
+    let id = uuid::Uuid::new_v4();
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        axum::http::header::COOKIE,
+        HeaderValue::from_str(&format!("theme=dark; session={id}")).unwrap(),
+    );
+    assert_eq!(session_from_cookie(&headers), Some(id));
+    assert_eq!(session_from_cookie(&HeaderMap::new()), None);
+}
+
+async fn sessions_state() -> Arc<AppState> {
+    use sea_orm::ConnectionTrait;
+
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared(
+        "CREATE TABLE sessions (id BLOB PRIMARY KEY, player_id TEXT NOT NULL, \
+         created_at TEXT NOT NULL, expires_at TEXT NOT NULL);",
+    )
+    .await
+    .unwrap();
+    Arc::new(AppState::builder().db(db).build().await)
+}
+
+fn session_request(method: &str, uri: &str, id: Option<uuid::Uuid>) -> Request<Body> {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(id) = id {
+        request = request.header(axum::http::header::COOKIE, format!("session={id}"));
+    }
+    request.body(Body::empty()).unwrap()
+}
+
+fn x_session_request(id: uuid::Uuid) -> Request<Body> {
+    Request::builder()
+        .uri("/guarded")
+        .header("X-Session", id.to_string())
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn session_guarded_app(state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/guarded", get(|| async { StatusCode::OK }))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_live_session,
+        ))
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn guest_session_cookie_expires() {
+    let state = sessions_state().await;
+    let app = Router::new()
+        .route("/auth/guest", post(guest_handler))
+        .with_state(state.clone());
+
+    let response = app
+        .oneshot(session_request("POST", "/auth/guest", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response.headers()[SET_COOKIE].to_str().unwrap();
+    assert!(cookie.contains(&format!(
+        "Max-Age={}",
+        session_store::SESSION_TTL.as_secs()
+    )));
+    assert!(cookie.contains("HttpOnly"));
+    let id = session_from_cookie(&HeaderMap::from_iter([(
+        axum::http::header::COOKIE,
+        HeaderValue::from_str(cookie).unwrap(),
+    )]))
+    .unwrap();
+    assert!(session_store::is_live(&state.db, id).await.unwrap());
+}
+
+#[tokio::test]
+async fn expired_session_is_rejected() {
+    let state = sessions_state().await;
+    let live = uuid::Uuid::new_v4();
+    let expired = uuid::Uuid::new_v4();
+    let now = chrono::Utc::now();
+    session_store::insert_session(&state.db, live, "live", now + chrono::Duration::hours(1))
+        .await
+        .unwrap();
+    session_store::insert_session(&state.db, expired, "old", now - chrono::Duration::hours(1))
+        .await
+        .unwrap();
+    let app = session_guarded_app(state);
+
+    for (id, status) in [
+        (Some(expired), StatusCode::UNAUTHORIZED),
+        (Some(uuid::Uuid::new_v4()), StatusCode::UNAUTHORIZED),
+        (Some(live), StatusCode::OK),
+        (None, StatusCode::OK),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(session_request("GET", "/guarded", id))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "session {id:?}");
+    }
+}
+
+#[tokio::test]
+async fn expired_x_session_is_rejected() {
+    let state = sessions_state().await;
+    let live = uuid::Uuid::new_v4();
+    let expired = uuid::Uuid::new_v4();
+    let now = chrono::Utc::now();
+    session_store::insert_session(&state.db, live, "live", now + chrono::Duration::hours(1))
+        .await
+        .unwrap();
+    session_store::insert_session(&state.db, expired, "old", now - chrono::Duration::hours(1))
+        .await
+        .unwrap();
+    let app = session_guarded_app(state);
+
+    for (id, status) in [
+        (expired, StatusCode::UNAUTHORIZED),
+        (uuid::Uuid::new_v4(), StatusCode::UNAUTHORIZED),
+        (live, StatusCode::OK),
+    ] {
+        let response = app.clone().oneshot(x_session_request(id)).await.unwrap();
+        assert_eq!(response.status(), status, "session {id}");
+    }
+}
+
+#[tokio::test]
+async fn session_refresh_extends_expiry() {
+    let state = sessions_state().await;
+    let id = uuid::Uuid::new_v4();
+    let soon = chrono::Utc::now() + chrono::Duration::minutes(1);
+    session_store::insert_session(&state.db, id, "player", soon)
+        .await
+        .unwrap();
+    let app = Router::new()
+        .route("/auth/session/refresh", post(session_refresh_handler))
+        .with_state(state.clone());
+
+    let response = app
+        .clone()
+        .oneshot(session_request("POST", "/auth/session/refresh", Some(id)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().contains_key(SET_COOKIE));
+    let expires_at = session_store::fetch_expiry(&state.db, id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(expires_at > soon + chrono::Duration::days(6));
+
+    let response = app
+        .oneshot(session_request("POST", "/auth/session/refresh", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+fn csrf_app() -> Router {
+    Router::new()
+        .route("/store/claim", post(|| async { StatusCode::OK }))
+        .route("/store/webhook", post(|| async { StatusCode::OK }))
+        .layer(middleware::from_fn(csrf::require_csrf_token))
+}
+
+fn csrf_request(uri: &str, cookie: Option<&str>, token: Option<&str>) -> Request<Body> {
+    let mut request = Request::builder().method("POST").uri(uri);
+    if let Some(cookie) = cookie {
+        request = request.header(axum::http::header::COOKIE, cookie);
+    }
+    if let Some(token) = token {
+        request = request.header(csrf::CSRF_HEADER, token);
+    }
+    request.body(Body::empty()).unwrap()
+}
+
+#[tokio::test]
+async fn csrf_token_required_with_session_cookie() {
+    let session = format!("session={}; csrf=abc123", uuid::Uuid::new_v4());
+    for (cookie, token, status) in [
+        (Some(session.as_str()), None, StatusCode::FORBIDDEN),
+        (Some(session.as_str()), Some("wrong"), StatusCode::FORBIDDEN),
+        (Some("session=x"), Some(""), StatusCode::FORBIDDEN),
+        (Some(session.as_str()), Some("abc123"), StatusCode::OK),
+        (None, None, StatusCode::OK),
+    ] {
+        let response = csrf_app()
+            .oneshot(csrf_request("/store/claim", cookie, token))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), status, "cookie {cookie:?} token {token:?}");
+    }
+}
+
+#[tokio::test]
+async fn csrf_exempts_webhook_and_safe_methods() {
+    let cookie = format!("session={}", uuid::Uuid::new_v4());
+    let response = csrf_app()
+        .oneshot(csrf_request("/store/webhook", Some(&cookie), None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let app = Router::new()
+        .route("/store", get(|| async { StatusCode::OK }))
+        .layer(middleware::from_fn(csrf::require_csrf_token));
+    let response = app
+        .oneshot(session_request("GET", "/store", Some(uuid::Uuid::new_v4())))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn guest_session_issues_csrf_cookie() {
+    let state = sessions_state().await;
+    let app = Router::new()
+        .route("/auth/guest", post(guest_handler))
+        .with_state(state);
+
+    let response = app
+        .oneshot(session_request("POST", "/auth/guest", None))
+        .await
+        .unwrap();
+    let csrf = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with("csrf="))
+        .expect("csrf cookie");
+    assert!(!csrf.contains("HttpOnly"));
+}
+
+async fn healthz(state: AppState) -> (StatusCode, serde_json::Value) {
+    let app = Router::new()
+        .route("/healthz", get(healthz_handler))
+        .with_state(Arc::new(state));
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/healthz")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn healthz_reports_subsystems() {
+    let (status, json) = healthz(AppState::builder().build().await).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json["ok"], true);
+    assert_eq!(json["db"]["ok"], true);
+    assert!(json["email"]["queued"].is_u64());
+    assert!(json["email"].get("last_error").is_some());
+    assert!(json["analytics"].get("last_flush_error").is_some());
+    assert_eq!(json["rooms"], 0);
+}
+
+#[tokio::test]
+async fn healthz_fails_without_database() {
+    let state = AppState::builder()
+        .db(DatabaseConnection::Disconnected)
+        .build()
+        .await;
+    let (status, json) = healthz(state).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(json["ok"], false);
+    assert_eq!(json["db"]["ok"], false);
+    assert!(json["db"]["error"].is_string());
+}
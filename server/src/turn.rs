@@ -0,0 +1,91 @@
+use std::{fmt, time::Duration};
+
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha1::Sha1;
+
+/// How long generated TURN credentials stay valid by default.
+pub const DEFAULT_TURN_CREDENTIAL_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Secret shared with the TURN server (coturn's `static-auth-secret`), used
+/// to mint time-limited credentials instead of handing out static ones.
+#[derive(Clone)]
+pub struct TurnSecret {
+    secret: String,
+    ttl: Duration,
+}
+
+impl fmt::Debug for TurnSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TurnSecret")
+            .field("secret", &"***")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+/// Credentials for one user, accepted by the TURN server until `expires_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TurnCredentials {
+    pub username: String,
+    pub credential: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl TurnSecret {
+    pub fn new(secret: impl Into<String>, ttl: Duration) -> Self {
+        Self {
+            secret: secret.into(),
+            ttl,
+        }
+    }
+
+    /// Credentials for `user` valid for the configured TTL from `now`.
+    ///
+    /// Follows the TURN REST API scheme: the username is
+    /// `<expiry unix timestamp>:<user>` and the credential is the base64
+    /// HMAC-SHA1 of the username keyed with the shared secret, so the TURN
+    /// server can check both without calling back.
+    pub fn credentials(&self, user: &str, now: DateTime<Utc>) -> TurnCredentials {
+        let expires_at = now + chrono::Duration::from_std(self.ttl).unwrap_or_default();
+        let username = format!("{}:{user}", expires_at.timestamp());
+        let mut mac = Hmac::<Sha1>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(username.as_bytes());
+        TurnCredentials {
+            credential: general_purpose::STANDARD.encode(mac.finalize().into_bytes()),
+            username,
+            expires_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn username_encodes_future_expiry() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let creds = TurnSecret::new("north", Duration::from_secs(3600)).credentials("alice", now);
+        assert_eq!(creds.username, "1700003600:alice");
+        assert_eq!(creds.expires_at.timestamp(), 1_700_003_600);
+        assert!(creds.expires_at > now);
+    }
+
+    #[test]
+    fn credential_is_hmac_of_username() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let creds = TurnSecret::new("north", Duration::from_secs(3600)).credentials("alice", now);
+        // base64(HMAC-SHA1("north", "1700003600:alice")), computed independently.
+        assert_eq!(creds.credential, "wjwSXO2ch1B6VaLTLMy2Avn5O9o=");
+    }
+
+    #[test]
+    fn debug_redacts_secret() {
+        let secret = TurnSecret::new("north", DEFAULT_TURN_CREDENTIAL_TTL);
+        assert!(!format!("{secret:?}").contains("north"));
+    }
+}